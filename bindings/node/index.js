@@ -0,0 +1,160 @@
+// Node.js bindings of jwtk, built with napi-rs:
+//
+//     npm install
+//     npm run build
+//
+//     const jwtk = require("jwtk");
+//     const key = jwtk.Key.generate("ES256", "k1");
+//     const token = key.sign({ sub: "alice" });
+//     jwtk.verifyWithKey(token, key.publicJwk()).claims.sub; // "alice"
+//
+// Calls are synchronous, and don't block on I/O, except for
+// JwksVerifier.verify, which returns a promise.
+
+"use strict";
+
+const native = require("./jwtk.node");
+
+const codes = {
+  OK: 0,
+  INVALID_ARGUMENT: 1,
+  INVALID_TOKEN: 2,
+  VERIFICATION_FAILED: 3,
+  EXPIRED: 4,
+  NOT_YET_VALID: 5,
+  NO_KEY: 6,
+  INVALID_KEY: 7,
+  PANIC: 98,
+  OTHER: 99,
+};
+
+// A jwtk error. `code` is one of `codes`.
+class JwtkError extends Error {
+  constructor(code, message) {
+    const name = Object.keys(codes).find((k) => codes[k] === code) || "OTHER";
+    super(message || `jwtk: ${name}`);
+    this.code = code;
+  }
+}
+
+// The native errors start with the name of their code.
+function jwtkError(e) {
+  const match = /^([A-Z_]+): /.exec(e.message);
+  if (!match || !(match[1] in codes)) {
+    return e;
+  }
+  return new JwtkError(codes[match[1]], `jwtk: ${e.message}`);
+}
+
+function call(f) {
+  try {
+    return f();
+  } catch (e) {
+    throw jwtkError(e);
+  }
+}
+
+function json(v) {
+  return typeof v === "string" ? v : JSON.stringify(v);
+}
+
+// A private key, PEM or a private JWK. Tokens get the key id `kid`, or
+// else the `kid` of the JWK, if any.
+class Key {
+  constructor(privateKey, kid) {
+    this.inner = privateKey instanceof native.Key ? privateKey : call(() => new native.Key(json(privateKey), kid));
+  }
+
+  // A new key for `alg`, e.g. "ES256".
+  static generate(alg, kid) {
+    return new Key(call(() => native.Key.generate(alg, kid)));
+  }
+
+  get kid() {
+    return this.inner.kid;
+  }
+
+  // Sign the `claims` object.
+  sign(claims) {
+    return call(() => this.inner.sign(JSON.stringify(claims)));
+  }
+
+  // The public JWK, with the key id.
+  publicJwk() {
+    return JSON.parse(call(() => this.inner.publicJwk()));
+  }
+
+  privateKeyPem() {
+    return call(() => this.inner.privateKeyPem());
+  }
+}
+
+// Verifies tokens with a public key or a JWK Set, parsed once.
+class Verifier {
+  constructor(inner) {
+    this.inner = inner;
+  }
+
+  // A PEM or JWK public key.
+  static fromKey(publicKey) {
+    return new Verifier(call(() => native.Verifier.fromKey(json(publicKey))));
+  }
+
+  static fromJwks(jwks) {
+    return new Verifier(call(() => native.Verifier.fromJwks(json(jwks))));
+  }
+
+  // Verify `token`, and check `exp` and `nbf`. Returns `{ header, claims }`.
+  verify(token) {
+    return JSON.parse(call(() => this.inner.verify(token)));
+  }
+}
+
+// Verifies tokens with the JWK Set at `url`, like RemoteJwksVerifier: the
+// set is fetched and parsed once, cached for `cacheDuration` milliseconds,
+// and refetched early when a token has an unknown key id.
+class JwksVerifier {
+  constructor(url, { cacheDuration = 5 * 60 * 1000 } = {}) {
+    this.inner = new native.JwksVerifier(url, cacheDuration);
+  }
+
+  async verify(token) {
+    try {
+      return JSON.parse(await this.inner.verify(token));
+    } catch (e) {
+      throw jwtkError(e);
+    }
+  }
+}
+
+// Sign the `claims` object with a PEM or private JWK key.
+function sign(privateKey, claims) {
+  return new Key(privateKey).sign(claims);
+}
+
+// Verify `token` with a JWK Set, see `Verifier.verify`.
+function verify(token, jwks) {
+  return Verifier.fromJwks(jwks).verify(token);
+}
+
+// Verify `token` with a PEM or JWK public key, see `Verifier.verify`.
+function verifyWithKey(token, publicKey) {
+  return Verifier.fromKey(publicKey).verify(token);
+}
+
+// The public JWK of a PEM or private JWK key.
+function publicJwk(privateKey) {
+  return new Key(privateKey).publicJwk();
+}
+
+module.exports = {
+  codes,
+  JwtkError,
+  Key,
+  Verifier,
+  JwksVerifier,
+  sign,
+  verify,
+  verifyWithKey,
+  publicJwk,
+};
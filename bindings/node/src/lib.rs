@@ -0,0 +1,182 @@
+//! Node.js bindings of jwtk, built with [napi-rs](https://napi.rs), see
+//! `index.js` for the JavaScript API.
+//!
+//! Claims, keys and results are passed as JSON strings. Errors are
+//! `Error`s whose message starts with the name of an error code of
+//! `index.js`, e.g. `EXPIRED: token expired`.
+
+use std::{sync::Arc, time::Duration};
+
+use jwtk::{
+    jwk::{Jwk, JwkSet, JwkSetVerifier, RemoteJwksVerifier},
+    rsa::RsaAlgorithm,
+    sign as sign_token, verify as verify_token, Error, HeaderAndClaims, PublicKeyToJwk,
+    SomePrivateKey, SomePublicKey,
+};
+use napi::Result;
+use napi_derive::napi;
+use serde_json::{json, Map, Value};
+
+/// The error of `e`, grouped like the error codes of the C ABI.
+fn error(e: Error) -> napi::Error {
+    let code = match e {
+        Error::InvalidToken | Error::Decode(_) | Error::SerdeJson(_) | Error::TooLarge => {
+            "INVALID_TOKEN"
+        }
+        Error::VerificationError | Error::AlgMismatch => "VERIFICATION_FAILED",
+        Error::Expired => "EXPIRED",
+        Error::Before => "NOT_YET_VALID",
+        Error::NoKid | Error::NoKey => "NO_KEY",
+        Error::UnsupportedOrInvalidKey | Error::InvalidPoint => "INVALID_KEY",
+        _ => "OTHER",
+    };
+    napi::Error::from_reason(format!("{}: {}", code, e))
+}
+
+fn invalid_argument(e: impl std::fmt::Display) -> napi::Error {
+    napi::Error::from_reason(format!("INVALID_ARGUMENT: {}", e))
+}
+
+fn invalid_key(e: impl std::fmt::Display) -> napi::Error {
+    napi::Error::from_reason(format!("INVALID_KEY: {}", e))
+}
+
+fn public_key(key: &str) -> Result<SomePublicKey> {
+    match serde_json::from_str::<Jwk>(key) {
+        Ok(jwk) => jwk.to_verification_key(),
+        Err(_) => SomePublicKey::from_pem(key.as_bytes()),
+    }
+    .map_err(error)
+}
+
+fn verified_json(verified: jwtk::Result<HeaderAndClaims<Map<String, Value>>>) -> Result<String> {
+    let verified = verified.map_err(error)?;
+    Ok(json!({
+        "header": verified.header(),
+        "claims": verified.claims(),
+    })
+    .to_string())
+}
+
+/// A private key, PEM or a private JWK. RSA keys without an algorithm use
+/// RS256. Tokens get the key id `kid`, or else the `kid` of the JWK, if any.
+#[napi]
+pub struct Key {
+    key: SomePrivateKey,
+    kid: Option<String>,
+}
+
+#[napi]
+impl Key {
+    #[napi(constructor)]
+    pub fn new(key: String, kid: Option<String>) -> Result<Self> {
+        let kid = kid.or_else(|| serde_json::from_str::<Jwk>(&key).ok()?.kid);
+        let key = SomePrivateKey::from_text(&key, RsaAlgorithm::RS256).map_err(error)?;
+        Ok(Self { key, kid })
+    }
+
+    /// A new key for `alg`, e.g. `"ES256"`.
+    #[napi(factory)]
+    pub fn generate(alg: String, kid: Option<String>) -> Result<Self> {
+        let key = SomePrivateKey::generate(&alg).map_err(error)?;
+        Ok(Self { key, kid })
+    }
+
+    #[napi(getter)]
+    pub fn kid(&self) -> Option<String> {
+        self.kid.clone()
+    }
+
+    /// Sign `claims_json`, a JSON object.
+    #[napi]
+    pub fn sign(&self, claims_json: String) -> Result<String> {
+        let mut token = HeaderAndClaims::<Map<String, Value>>::new_dynamic();
+        if let Some(kid) = &self.kid {
+            token.set_kid(kid.as_str());
+        }
+        *token.claims_mut() = serde_json::from_str(&claims_json).map_err(invalid_argument)?;
+        sign_token(&mut token, &self.key).map_err(error)
+    }
+
+    /// The public JWK, with the key id.
+    #[napi]
+    pub fn public_jwk(&self) -> Result<String> {
+        let mut jwk = self.key.public_key_to_jwk().map_err(error)?;
+        jwk.kid = self.kid.clone();
+        Ok(json!(jwk).to_string())
+    }
+
+    /// The private key as PKCS #8 PEM.
+    #[napi]
+    pub fn private_key_pem(&self) -> Result<String> {
+        self.key.private_key_to_pem_pkcs8().map_err(error)
+    }
+}
+
+enum Keys {
+    Key(SomePublicKey),
+    Jwks(JwkSetVerifier),
+}
+
+/// Verifies tokens with a public key or a JWK Set, parsed once.
+#[napi]
+pub struct Verifier {
+    keys: Keys,
+}
+
+#[napi]
+impl Verifier {
+    /// A PEM or JWK public key.
+    #[napi(factory)]
+    pub fn from_key(key: String) -> Result<Self> {
+        Ok(Self {
+            keys: Keys::Key(public_key(&key)?),
+        })
+    }
+
+    /// A JWK Set.
+    #[napi(factory)]
+    pub fn from_jwks(jwks_json: String) -> Result<Self> {
+        let jwks: JwkSet = serde_json::from_str(&jwks_json).map_err(invalid_key)?;
+        Ok(Self {
+            keys: Keys::Jwks(jwks.verifier()),
+        })
+    }
+
+    /// Verify `token`, and check `exp` and `nbf`. Returns
+    /// `{"header": {...}, "claims": {...}}`.
+    #[napi]
+    pub fn verify(&self, token: String) -> Result<String> {
+        verified_json(match &self.keys {
+            Keys::Key(k) => verify_token(&token, k),
+            Keys::Jwks(jwks) => jwks.verify(&token),
+        })
+    }
+}
+
+/// Verifies tokens with the JWK Set at `url`, cached for
+/// `cache_duration_ms` and refetched early for unknown key ids, see
+/// `RemoteJwksVerifier`.
+#[napi]
+pub struct JwksVerifier {
+    verifier: Arc<RemoteJwksVerifier>,
+}
+
+#[napi]
+impl JwksVerifier {
+    #[napi(constructor)]
+    pub fn new(url: String, cache_duration_ms: Option<u32>) -> Self {
+        let cache_duration =
+            Duration::from_millis(cache_duration_ms.unwrap_or(5 * 60 * 1000).into());
+        Self {
+            verifier: Arc::new(RemoteJwksVerifier::new(url, None, cache_duration)),
+        }
+    }
+
+    /// Verify `token`, see `Verifier.verify`, fetching the JWK Set if needed.
+    #[napi]
+    pub async fn verify(&self, token: String) -> Result<String> {
+        let verifier = self.verifier.clone();
+        verified_json(verifier.verify(&token).await)
+    }
+}
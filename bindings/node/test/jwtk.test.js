@@ -0,0 +1,89 @@
+"use strict";
+
+const assert = require("node:assert");
+const http = require("node:http");
+const test = require("node:test");
+
+const jwtk = require("..");
+
+test("sign and verify", () => {
+  const key = jwtk.Key.generate("ES256", "k1");
+  assert.strictEqual(key.kid, "k1");
+  const token = key.sign({ sub: "alice" });
+
+  const verified = jwtk.verifyWithKey(token, key.publicJwk());
+  assert.strictEqual(verified.header.kid, "k1");
+  assert.strictEqual(verified.claims.sub, "alice");
+
+  const jwks = { keys: [key.publicJwk()] };
+  assert.strictEqual(jwtk.verify(token, jwks).claims.sub, "alice");
+
+  const pem = key.privateKeyPem();
+  assert.strictEqual(jwtk.sign(pem, { sub: "bob" }).split(".").length, 3);
+  assert.deepStrictEqual(jwtk.publicJwk(pem).x, key.publicJwk().x);
+});
+
+test("verifier is reused", () => {
+  const key = jwtk.Key.generate("EdDSA", "k1");
+  const verifier = jwtk.Verifier.fromJwks({ keys: [key.publicJwk()] });
+  for (const sub of ["alice", "bob"]) {
+    assert.strictEqual(verifier.verify(key.sign({ sub })).claims.sub, sub);
+  }
+});
+
+test("error codes", () => {
+  const key = jwtk.Key.generate("ES256", "k1");
+  const verifier = jwtk.Verifier.fromKey(key.publicJwk());
+  const now = Math.floor(Date.now() / 1000);
+
+  const cases = [
+    ["a.b.c", jwtk.codes.INVALID_TOKEN],
+    [jwtk.Key.generate("ES256", "k1").sign({}), jwtk.codes.VERIFICATION_FAILED],
+    [key.sign({ exp: now - 3600 }), jwtk.codes.EXPIRED],
+    [key.sign({ nbf: now + 3600 }), jwtk.codes.NOT_YET_VALID],
+  ];
+  for (const [token, code] of cases) {
+    assert.throws(
+      () => verifier.verify(token),
+      (e) => e instanceof jwtk.JwtkError && e.code === code,
+    );
+  }
+
+  const other = jwtk.Key.generate("ES256", "k2").sign({});
+  assert.throws(
+    () => jwtk.verify(other, { keys: [key.publicJwk()] }),
+    (e) => e instanceof jwtk.JwtkError && e.code === jwtk.codes.NO_KEY,
+  );
+  assert.throws(
+    () => new jwtk.Key("not a key"),
+    (e) => e instanceof jwtk.JwtkError && e.code === jwtk.codes.INVALID_KEY,
+  );
+});
+
+test("JWKS verifier fetches the set once", async () => {
+  const key = jwtk.Key.generate("ES256", "k1");
+  const body = JSON.stringify({ keys: [key.publicJwk()] });
+  let fetches = 0;
+  const server = http.createServer((req, res) => {
+    fetches += 1;
+    res.setHeader("content-type", "application/json");
+    res.end(body);
+  });
+  await new Promise((resolve) => server.listen(0, "127.0.0.1", resolve));
+  try {
+    const url = `http://127.0.0.1:${server.address().port}/jwks`;
+    const verifier = new jwtk.JwksVerifier(url);
+    for (const sub of ["alice", "bob", "carol"]) {
+      const verified = await verifier.verify(key.sign({ sub }));
+      assert.strictEqual(verified.claims.sub, sub);
+    }
+    assert.strictEqual(fetches, 1);
+
+    await assert.rejects(
+      verifier.verify(jwtk.Key.generate("ES256", "k1").sign({})),
+      (e) => e instanceof jwtk.JwtkError && e.code === jwtk.codes.VERIFICATION_FAILED,
+    );
+  } finally {
+    server.close();
+  }
+});
@@ -0,0 +1,297 @@
+//! Python bindings of jwtk, built with [maturin](https://www.maturin.rs):
+//!
+//! ```sh
+//! cd bindings/python
+//! maturin develop
+//! ```
+
+use std::time::Duration;
+
+use jwtk::{
+    jwk::{Jwk, JwkSet, JwkSetVerifier, RemoteJwksVerifier},
+    rsa::RsaAlgorithm,
+    sign as sign_token, verify as verify_token, Error, HeaderAndClaims, PublicKeyToJwk,
+    SomePrivateKey, SomePublicKey,
+};
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+use serde_json::{json, Map, Value};
+
+create_exception!(jwtk, JwtkError, PyException, "A jwtk error.");
+create_exception!(
+    jwtk,
+    InvalidTokenError,
+    JwtkError,
+    "The token is malformed."
+);
+create_exception!(
+    jwtk,
+    VerificationError,
+    JwtkError,
+    "The signature or a claim is invalid."
+);
+create_exception!(jwtk, ExpiredError, JwtkError, "The token has expired.");
+create_exception!(
+    jwtk,
+    NotYetValidError,
+    JwtkError,
+    "The token is not valid yet."
+);
+create_exception!(jwtk, NoKeyError, JwtkError, "No key matches the token.");
+create_exception!(jwtk, InvalidKeyError, JwtkError, "The key is invalid.");
+
+/// The Python exception of `e`, grouped like the error codes of the C ABI.
+fn error(e: Error) -> PyErr {
+    let message = e.to_string();
+    match e {
+        Error::InvalidToken | Error::Decode(_) | Error::SerdeJson(_) | Error::TooLarge => {
+            InvalidTokenError::new_err(message)
+        }
+        Error::Expired => ExpiredError::new_err(message),
+        Error::Before => NotYetValidError::new_err(message),
+        Error::NoKid | Error::NoKey => NoKeyError::new_err(message),
+        Error::UnsupportedOrInvalidKey | Error::InvalidPoint => InvalidKeyError::new_err(message),
+        Error::VerificationError | Error::AlgMismatch => VerificationError::new_err(message),
+        _ => JwtkError::new_err(message),
+    }
+}
+
+/// `obj` as JSON: a `str` as is, anything else through `json.dumps`.
+fn to_json(obj: &Bound<'_, PyAny>) -> PyResult<String> {
+    if let Ok(s) = obj.extract::<String>() {
+        return Ok(s);
+    }
+    obj.py()
+        .import_bound("json")?
+        .call_method1("dumps", (obj,))?
+        .extract()
+}
+
+fn from_json(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(py
+        .import_bound("json")?
+        .call_method1("loads", (value.to_string(),))?
+        .unbind())
+}
+
+fn public_key(key: &Bound<'_, PyAny>) -> PyResult<SomePublicKey> {
+    let key = to_json(key)?;
+    match serde_json::from_str::<Jwk>(&key) {
+        Ok(jwk) => jwk.to_verification_key(),
+        Err(_) => SomePublicKey::from_pem(key.as_bytes()),
+    }
+    .map_err(error)
+}
+
+fn jwks(jwks: &Bound<'_, PyAny>) -> PyResult<JwkSetVerifier> {
+    let jwks: JwkSet = serde_json::from_str(&to_json(jwks)?)
+        .map_err(|e| InvalidKeyError::new_err(e.to_string()))?;
+    Ok(jwks.verifier())
+}
+
+fn sign_claims(
+    k: &SomePrivateKey,
+    kid: Option<&str>,
+    claims: &Bound<'_, PyAny>,
+) -> PyResult<String> {
+    let mut token = HeaderAndClaims::<Map<String, Value>>::new_dynamic();
+    if let Some(kid) = kid {
+        token.set_kid(kid);
+    }
+    *token.claims_mut() = serde_json::from_str(&to_json(claims)?)
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+    sign_token(&mut token, k).map_err(error)
+}
+
+fn verified(py: Python<'_>, verified: HeaderAndClaims<Map<String, Value>>) -> PyResult<PyObject> {
+    from_json(
+        py,
+        &json!({
+            "header": verified.header(),
+            "claims": verified.claims(),
+        }),
+    )
+}
+
+/// A private key, PEM or a private JWK as a `str` or `dict`. RSA keys
+/// without an algorithm use RS256. Tokens get the key id `kid`, or else
+/// the `kid` of the JWK, if any.
+#[pyclass(frozen, module = "jwtk")]
+struct Key {
+    key: SomePrivateKey,
+    kid: Option<String>,
+}
+
+#[pymethods]
+impl Key {
+    #[new]
+    #[pyo3(signature = (key, kid = None))]
+    fn new(key: &Bound<'_, PyAny>, kid: Option<String>) -> PyResult<Self> {
+        let text = to_json(key)?;
+        let kid = kid.or_else(|| serde_json::from_str::<Jwk>(&text).ok()?.kid);
+        let key = SomePrivateKey::from_text(&text, RsaAlgorithm::RS256).map_err(error)?;
+        Ok(Self { key, kid })
+    }
+
+    /// A new key for `alg`, e.g. `"ES256"`.
+    #[staticmethod]
+    #[pyo3(signature = (alg, kid = None))]
+    fn generate(alg: &str, kid: Option<String>) -> PyResult<Self> {
+        let key = SomePrivateKey::generate(alg).map_err(error)?;
+        Ok(Self { key, kid })
+    }
+
+    #[getter]
+    fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    /// Sign the `claims` dict.
+    fn sign(&self, claims: &Bound<'_, PyAny>) -> PyResult<String> {
+        sign_claims(&self.key, self.kid.as_deref(), claims)
+    }
+
+    /// The public JWK, as a `dict`, with the key id.
+    fn public_jwk(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let mut jwk = self.key.public_key_to_jwk().map_err(error)?;
+        jwk.kid = self.kid.clone();
+        from_json(py, &json!(jwk))
+    }
+
+    /// The private key as PKCS #8 PEM.
+    fn private_key_pem(&self) -> PyResult<String> {
+        self.key.private_key_to_pem_pkcs8().map_err(error)
+    }
+}
+
+enum Keys {
+    Key(SomePublicKey),
+    Jwks(JwkSetVerifier),
+}
+
+/// Verifies tokens with a public key or a JWK Set, parsed once.
+#[pyclass(frozen, module = "jwtk")]
+struct Verifier(Keys);
+
+#[pymethods]
+impl Verifier {
+    /// A PEM or JWK public key.
+    #[staticmethod]
+    fn from_key(key: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Verifier(Keys::Key(public_key(key)?)))
+    }
+
+    /// A JWK Set, as a `str` or `dict`.
+    #[staticmethod]
+    fn from_jwks(jwks_json: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Verifier(Keys::Jwks(jwks(jwks_json)?)))
+    }
+
+    /// Verify `token`, and check `exp` and `nbf`. Returns
+    /// `{"header": ..., "claims": ...}`.
+    fn verify(&self, py: Python<'_>, token: &str) -> PyResult<PyObject> {
+        let result = py.allow_threads(|| match &self.0 {
+            Keys::Key(k) => verify_token::<Map<String, Value>>(token, k),
+            Keys::Jwks(jwks) => jwks.verify::<Map<String, Value>>(token),
+        });
+        verified(py, result.map_err(error)?)
+    }
+}
+
+/// Verifies tokens with the JWK Set at `url`, cached for `cache_duration`
+/// seconds and refetched early for unknown key ids, see
+/// `RemoteJwksVerifier`.
+#[pyclass(frozen, module = "jwtk")]
+struct JwksVerifier {
+    runtime: tokio::runtime::Runtime,
+    verifier: RemoteJwksVerifier,
+}
+
+#[pymethods]
+impl JwksVerifier {
+    #[new]
+    #[pyo3(signature = (url, cache_duration = 300.0))]
+    fn new(url: String, cache_duration: f64) -> PyResult<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let cache_duration = Duration::try_from_secs_f64(cache_duration)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        Ok(Self {
+            runtime,
+            verifier: RemoteJwksVerifier::new(url, None, cache_duration),
+        })
+    }
+
+    /// Verify `token`, see `Verifier.verify`. Blocks while the JWK Set is
+    /// fetched, without holding the GIL.
+    fn verify(&self, py: Python<'_>, token: &str) -> PyResult<PyObject> {
+        let result = py.allow_threads(|| {
+            self.runtime
+                .block_on(self.verifier.verify::<Map<String, Value>>(token))
+        });
+        verified(py, result.map_err(error)?)
+    }
+}
+
+/// Sign the `claims` dict with `private_key`, see `Key`.
+#[pyfunction]
+fn sign(private_key: &Bound<'_, PyAny>, claims: &Bound<'_, PyAny>) -> PyResult<String> {
+    Key::new(private_key, None)?.sign(claims)
+}
+
+/// Verify `token` with a JWK Set, see `Verifier.verify`.
+#[pyfunction]
+fn verify(py: Python<'_>, token: &str, jwks: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    Verifier::from_jwks(jwks)?.verify(py, token)
+}
+
+/// Verify `token` with a PEM or JWK public key, see `Verifier.verify`.
+#[pyfunction]
+fn verify_with_key(
+    py: Python<'_>,
+    token: &str,
+    public_key: &Bound<'_, PyAny>,
+) -> PyResult<PyObject> {
+    Verifier::from_key(public_key)?.verify(py, token)
+}
+
+/// The public JWK, as a `dict`, of a PEM or private JWK key.
+#[pyfunction]
+fn public_jwk(py: Python<'_>, private_key: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    Key::new(private_key, None)?.public_jwk(py)
+}
+
+/// Python bindings of jwtk. Tokens are signed and verified with the same code,
+/// and so the same semantics, as in Rust.
+///
+/// >>> import jwtk
+/// >>> key = jwtk.Key.generate("ES256")
+/// >>> token = key.sign({"sub": "alice"})
+/// >>> jwtk.verify_with_key(token, key.public_jwk())["claims"]["sub"]
+/// 'alice'
+#[pymodule]
+#[pyo3(name = "jwtk")]
+fn jwtk_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("JwtkError", py.get_type_bound::<JwtkError>())?;
+    m.add(
+        "InvalidTokenError",
+        py.get_type_bound::<InvalidTokenError>(),
+    )?;
+    m.add(
+        "VerificationError",
+        py.get_type_bound::<VerificationError>(),
+    )?;
+    m.add("ExpiredError", py.get_type_bound::<ExpiredError>())?;
+    m.add("NotYetValidError", py.get_type_bound::<NotYetValidError>())?;
+    m.add("NoKeyError", py.get_type_bound::<NoKeyError>())?;
+    m.add("InvalidKeyError", py.get_type_bound::<InvalidKeyError>())?;
+    m.add_class::<Key>()?;
+    m.add_class::<Verifier>()?;
+    m.add_class::<JwksVerifier>()?;
+    m.add_function(wrap_pyfunction!(sign, m)?)?;
+    m.add_function(wrap_pyfunction!(verify, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_with_key, m)?)?;
+    m.add_function(wrap_pyfunction!(public_jwk, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+import http.server
+import json
+import threading
+
+import pytest
+
+import jwtk
+
+# RFC 7515 Appendix A.3, expired since 2011.
+ES256_JWK = {
+    "kty": "EC",
+    "crv": "P-256",
+    "x": "f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU",
+    "y": "x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0",
+}
+ES256_TOKEN = (
+    "eyJhbGciOiJFUzI1NiJ9"
+    ".eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ"
+    ".DtEhU3ljbEg8L38VWAfUAqOyKAM6-Xx-F4GawxaepmXFCgfTjDxw5djxLa8ISlSApmWQxfKTUJqPP3-Kg6NU1Q"
+)
+
+
+def test_sign_and_verify():
+    key = jwtk.Key.generate("ES256")
+    token = key.sign({"sub": "alice", "roles": ["admin"]})
+    verified = jwtk.verify_with_key(token, key.public_jwk())
+    assert verified["header"]["alg"] == "ES256"
+    assert verified["claims"] == {"sub": "alice", "roles": ["admin"]}
+
+    # PEM keys, and the module level functions.
+    pem = key.private_key_pem()
+    token = jwtk.sign(pem, {"sub": "bob"})
+    assert jwtk.verify_with_key(token, jwtk.public_jwk(pem))["claims"]["sub"] == "bob"
+
+
+def test_verifier():
+    key = jwtk.Key(jwtk.Key.generate("EdDSA").private_key_pem(), kid="k1")
+    assert key.kid == "k1"
+    verifier = jwtk.Verifier.from_jwks({"keys": [key.public_jwk()]})
+    token = key.sign({"sub": "alice"})
+    assert jwtk.verify_with_key(token, key.public_jwk())["header"]["kid"] == "k1"
+    assert verifier.verify(token)["claims"]["sub"] == "alice"
+    assert jwtk.verify(token, json.dumps({"keys": [key.public_jwk()]}))["claims"]["sub"] == "alice"
+
+    other = jwtk.Key.generate("EdDSA", kid="k2")
+    with pytest.raises(jwtk.NoKeyError):
+        verifier.verify(other.sign({"sub": "alice"}))
+    with pytest.raises(jwtk.VerificationError):
+        jwtk.Verifier.from_key(other.public_jwk()).verify(token)
+
+
+def test_errors():
+    with pytest.raises(jwtk.ExpiredError):
+        jwtk.verify_with_key(ES256_TOKEN, ES256_JWK)
+    with pytest.raises(jwtk.InvalidTokenError):
+        jwtk.verify_with_key("a.b", ES256_JWK)
+    with pytest.raises(jwtk.InvalidKeyError):
+        jwtk.Key("not a key")
+    assert issubclass(jwtk.ExpiredError, jwtk.JwtkError)
+
+
+def test_jwks_verifier():
+    key = jwtk.Key.generate("ES256", kid="k1")
+    jwks = json.dumps({"keys": [key.public_jwk()]}).encode()
+    requests = []
+
+    class Handler(http.server.BaseHTTPRequestHandler):
+        def do_GET(self):
+            requests.append(self.path)
+            self.send_response(200)
+            self.send_header("content-type", "application/json")
+            self.send_header("content-length", str(len(jwks)))
+            self.end_headers()
+            self.wfile.write(jwks)
+
+        def log_message(self, *args):
+            pass
+
+    server = http.server.HTTPServer(("127.0.0.1", 0), Handler)
+    threading.Thread(target=server.serve_forever, daemon=True).start()
+    try:
+        verifier = jwtk.JwksVerifier("http://127.0.0.1:%d/jwks" % server.server_port)
+        for sub in ["alice", "bob"]:
+            assert verifier.verify(key.sign({"sub": sub}))["claims"]["sub"] == sub
+        # Fetched once, and cached.
+        assert requests == ["/jwks"]
+        with pytest.raises(jwtk.NoKeyError):
+            verifier.verify(jwtk.Key.generate("ES256", kid="k2").sign({}))
+    finally:
+        server.shutdown()
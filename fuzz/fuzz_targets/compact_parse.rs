@@ -0,0 +1,44 @@
+//! Feeds arbitrary input through the compact serialization parser.
+//!
+//! The input is used both as a raw token, and split on `|` into segments
+//! whose first two parts are base64url encoded first, so that the JSON
+//! parser sees arbitrary headers and claims too.
+
+#![no_main]
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jwtk::{
+    decode_without_verify,
+    hmac::{HmacAlgorithm, HmacKey},
+    jwk::JwkSet,
+    verify, verify_only,
+};
+use libfuzzer_sys::fuzz_target;
+use serde_json::{Map, Value};
+
+type Claims = Map<String, Value>;
+
+fn parse(token: &str) {
+    let k = HmacKey::from_bytes(b"key", HmacAlgorithm::HS256);
+    let _ = decode_without_verify::<Claims>(token);
+    let _ = verify::<Claims>(token, &k);
+    let _ = verify_only::<Claims>(token, &k);
+    let _ = JwkSet { keys: vec![] }.verifier().verify::<Claims>(token);
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(token) = std::str::from_utf8(data) {
+        parse(token);
+    }
+
+    let mut segments = data.splitn(3, |b| *b == b'|');
+    let header = segments.next().unwrap_or_default();
+    let payload = segments.next().unwrap_or_default();
+    let sig = String::from_utf8_lossy(segments.next().unwrap_or_default());
+    parse(&format!(
+        "{}.{}.{}",
+        URL_SAFE_NO_PAD.encode(header),
+        URL_SAFE_NO_PAD.encode(payload),
+        sig
+    ));
+});
@@ -0,0 +1,182 @@
+/* C interface of jwtk, built with the `ffi` feature. See src/ffi.rs. */
+
+#ifndef JWTK_H
+#define JWTK_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define JWTK_OK 0
+
+#define JWTK_INVALID_ARGUMENT 1
+
+#define JWTK_INVALID_TOKEN 2
+
+#define JWTK_VERIFICATION_FAILED 3
+
+#define JWTK_EXPIRED 4
+
+#define JWTK_NOT_YET_VALID 5
+
+#define JWTK_NO_KEY 6
+
+#define JWTK_INVALID_KEY 7
+
+#define JWTK_PANIC 98
+
+#define JWTK_OTHER 99
+
+/*
+ A private key, see [`jwtk_key_new`].
+ */
+typedef struct JwtkKey JwtkKey;
+
+/*
+ A public key or JWK Set, see [`jwtk_verifier_from_key`] and
+ [`jwtk_verifier_from_jwks`].
+ */
+typedef struct JwtkVerifier JwtkVerifier;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Sign `claims_json`, a JSON object, with `private_key`, PEM or a private
+ JWK. RSA keys without an algorithm use RS256.
+
+ # Safety
+
+ Arguments must be valid NUL terminated strings, and `out_token` must be
+ valid for writes.
+ */
+int jwtk_sign(const char *private_key, const char *claims_json, char **out_token);
+
+/*
+ Verify `token` with the keys in `jwks_json`, a JWK Set, and check `exp`
+ and `nbf`. On success the output is `{"header": {...}, "claims": {...}}`.
+
+ # Safety
+
+ Arguments must be valid NUL terminated strings, and `out_json` must be
+ valid for writes.
+ */
+int jwtk_verify(const char *token, const char *jwks_json, char **out_json);
+
+/*
+ Verify `token` with a single public key, PEM or JWK, see [`jwtk_verify`].
+
+ # Safety
+
+ Arguments must be valid NUL terminated strings, and `out_json` must be
+ valid for writes.
+ */
+int jwtk_verify_with_key(const char *token, const char *public_key, char **out_json);
+
+/*
+ The public JWK of `private_key`, PEM or a private JWK.
+
+ # Safety
+
+ `private_key` must be a valid NUL terminated string, and `out_jwk` must
+ be valid for writes.
+ */
+int jwtk_public_jwk(const char *private_key, char **out_jwk);
+
+/*
+ Free a string returned by one of the functions above. `NULL` is ignored.
+
+ # Safety
+
+ `s` must have been returned by jwtk and not freed yet.
+ */
+void jwtk_string_free(char *s);
+
+/*
+ Parse `private_key`, PEM or a private JWK, into a handle to free with
+ [`jwtk_key_free`].
+
+ # Safety
+
+ `private_key` must be a valid NUL terminated string, and `out_key` must
+ be valid for writes.
+ */
+int jwtk_key_new(const char *private_key, struct JwtkKey **out_key);
+
+/*
+ Sign `claims_json` with `key`, see [`jwtk_sign`].
+
+ # Safety
+
+ `key` must be a live handle, `claims_json` a valid NUL terminated string,
+ and `out_token` must be valid for writes.
+ */
+int jwtk_key_sign(const struct JwtkKey *key, const char *claims_json, char **out_token);
+
+/*
+ The public JWK of `key`.
+
+ # Safety
+
+ `key` must be a live handle, and `out_jwk` must be valid for writes.
+ */
+int jwtk_key_public_jwk(const struct JwtkKey *key, char **out_jwk);
+
+/*
+ Free `key`. `NULL` is ignored.
+
+ # Safety
+
+ `key` must have been returned by [`jwtk_key_new`] and not freed yet.
+ */
+void jwtk_key_free(struct JwtkKey *key);
+
+/*
+ A verifier of the keys in `jwks_json`, a JWK Set, to free with
+ [`jwtk_verifier_free`].
+
+ # Safety
+
+ `jwks_json` must be a valid NUL terminated string, and `out_verifier`
+ must be valid for writes.
+ */
+int jwtk_verifier_from_jwks(const char *jwks_json, struct JwtkVerifier **out_verifier);
+
+/*
+ A verifier of a single public key, PEM or JWK, to free with
+ [`jwtk_verifier_free`].
+
+ # Safety
+
+ `public_key` must be a valid NUL terminated string, and `out_verifier`
+ must be valid for writes.
+ */
+int jwtk_verifier_from_key(const char *public_key, struct JwtkVerifier **out_verifier);
+
+/*
+ Verify `token` with `verifier`, see [`jwtk_verify`].
+
+ # Safety
+
+ `verifier` must be a live handle, `token` a valid NUL terminated string,
+ and `out_json` must be valid for writes.
+ */
+int jwtk_verifier_verify(const struct JwtkVerifier *verifier, const char *token, char **out_json);
+
+/*
+ Free `verifier`. `NULL` is ignored.
+
+ # Safety
+
+ `verifier` must have been returned by jwtk and not freed yet.
+ */
+void jwtk_verifier_free(struct JwtkVerifier *verifier);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* JWTK_H */
@@ -0,0 +1,125 @@
+//! Actor and delegation claims, RFC 8693 section 4.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+use crate::Claims;
+
+/// A party in the `act` (actor) or `may_act` (authorized actor) claim.
+///
+/// In `act`, a nested `act` is the actor that acted before this one, so a
+/// delegation chain reads from the current actor outwards. `may_act` only
+/// identifies a party and normally has no nested `act`.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: Option<String>,
+    pub iss: Option<String>,
+    pub act: Option<Box<Actor>>,
+
+    /// Other identity claims, e.g. `client_id`.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Actor {
+    #[inline]
+    pub fn new(sub: impl Into<String>) -> Self {
+        Self {
+            sub: Some(sub.into()),
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    pub fn with_iss(mut self, iss: impl Into<String>) -> Self {
+        self.iss = Some(iss.into());
+        self
+    }
+
+    /// Set the prior actor.
+    #[inline]
+    pub fn with_act(mut self, act: Actor) -> Self {
+        self.act = Some(Box::new(act));
+        self
+    }
+
+    /// This actor followed by the prior actors, most recent first.
+    pub fn chain(&self) -> impl Iterator<Item = &Actor> {
+        std::iter::successors(Some(self), |a| a.act.as_deref())
+    }
+
+    /// Whether `self` and `other` identify the same party, i.e. have the same
+    /// `sub` and `iss`. Nested actors and other claims are ignored.
+    pub fn is_same_party(&self, other: &Actor) -> bool {
+        self.sub == other.sub && self.iss == other.iss
+    }
+}
+
+impl<ExtraClaims> Claims<ExtraClaims> {
+    /// The current actor followed by the prior actors, most recent first.
+    /// Empty if there is no `act` claim.
+    pub fn actors(&self) -> impl Iterator<Item = &Actor> {
+        self.act.iter().flat_map(|a| a.chain())
+    }
+
+    /// Number of delegation steps, i.e. the length of the `act` chain.
+    pub fn delegation_depth(&self) -> usize {
+        self.actors().count()
+    }
+
+    /// Whether `actor` is the party in the `may_act` claim. `false` if there
+    /// is no `may_act` claim.
+    pub fn may_act_as(&self, actor: &Actor) -> bool {
+        self.may_act
+            .as_ref()
+            .is_some_and(|may_act| may_act.is_same_party(actor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::HeaderAndClaims;
+
+    use super::*;
+
+    #[test]
+    fn delegation_chain() -> crate::Result<()> {
+        let claims: Claims<Map<String, Value>> = serde_json::from_value(json!({
+            "sub": "user@example.com",
+            "act": {
+                "sub": "https://service16.example.com",
+                "act": {
+                    "sub": "https://service77.example.com",
+                    "iss": "https://issuer.example.com",
+                }
+            },
+            "may_act": { "sub": "admin@example.com" },
+        }))?;
+        let subs: Vec<_> = claims.actors().map(|a| a.sub.as_deref().unwrap()).collect();
+        assert_eq!(
+            subs,
+            [
+                "https://service16.example.com",
+                "https://service77.example.com"
+            ]
+        );
+        assert_eq!(claims.delegation_depth(), 2);
+        assert!(claims.may_act_as(&Actor::new("admin@example.com")));
+        assert!(!claims.may_act_as(&Actor::new("admin@example.com").with_iss("x")));
+        assert!(claims.extra.is_empty());
+
+        let mut claims = HeaderAndClaims::new_dynamic();
+        assert_eq!(claims.claims().delegation_depth(), 0);
+        claims.set_act(Actor::new("b").with_act(Actor::new("a").with_iss("i")));
+        assert_eq!(
+            serde_json::to_value(claims.claims())?,
+            json!({ "act": { "sub": "b", "act": { "sub": "a", "iss": "i" } } })
+        );
+        Ok(())
+    }
+}
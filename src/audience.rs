@@ -0,0 +1,177 @@
+//! Tokens for several audiences, with restrictions per audience.
+//!
+//! The [`RESOURCES_CLAIM`] maps audiences to what the token allows there,
+//! like the `resource` parameter of RFC 8707 narrows an access token to a
+//! resource server. Audiences without an entry are unrestricted.
+//!
+//! ```
+//! # use jwtk::{audience::Restrictions, HeaderAndClaims};
+//! let mut token = HeaderAndClaims::new_dynamic();
+//! token
+//!     .set_sub("alice")
+//!     .add_restricted_aud("https://billing.example.com", Restrictions::scope("invoices:read"))
+//!     .add_aud("https://profile.example.com");
+//!
+//! let claims = token.claims();
+//! claims.check_audience_scope("https://billing.example.com", &["invoices:read"])?;
+//! assert!(claims
+//!     .check_audience_scope("https://billing.example.com", &["invoices:write"])
+//!     .is_err());
+//! claims.check_audience_scope("https://profile.example.com", &["profile:write"])?;
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+use crate::{Claims, Error, HeaderAndClaims, OneOrMany, Result};
+
+/// The claim with the [`Restrictions`] of the token, by audience.
+pub const RESOURCES_CLAIM: &str = "resources";
+
+/// What a token allows at one of its audiences.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Restrictions {
+    /// Space separated scopes.
+    pub scope: Option<String>,
+
+    /// Other restrictions, for the audience to check.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Restrictions {
+    #[inline]
+    pub fn scope(scope: impl Into<String>) -> Self {
+        Self {
+            scope: Some(scope.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Whether the space separated `scope` contains `scope`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|s| s.split(' ').any(|x| x == scope))
+    }
+
+    fn into_value(self) -> Value {
+        let mut v = self.extra;
+        if let Some(scope) = self.scope {
+            v.insert("scope".into(), scope.into());
+        }
+        Value::Object(v)
+    }
+}
+
+impl HeaderAndClaims<Map<String, Value>> {
+    /// Add `aud` to the audiences, with `restrictions` in the
+    /// [`RESOURCES_CLAIM`].
+    pub fn add_restricted_aud(
+        &mut self,
+        aud: impl Into<String>,
+        restrictions: Restrictions,
+    ) -> &mut Self {
+        let aud = aud.into();
+        let resources = self
+            .claims
+            .extra
+            .entry(RESOURCES_CLAIM)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(resources) = resources {
+            resources.insert(aud.clone(), restrictions.into_value());
+        }
+        self.add_aud(aud)
+    }
+}
+
+impl Claims<Map<String, Value>> {
+    /// The restrictions of the token at `aud`, `None` if there are none.
+    ///
+    /// Returns `Error::AudMismatch` if `aud` is not an audience of the token,
+    /// and `Error::InvalidToken` if the restrictions are malformed.
+    pub fn restrictions_for(&self, aud: &str) -> Result<Option<Restrictions>> {
+        let found = match &self.aud {
+            OneOrMany::One(a) => a == aud,
+            OneOrMany::Vec(auds) => auds.iter().any(|a| a == aud),
+        };
+        if !found {
+            return Err(Error::AudMismatch);
+        }
+        match self.extra.get(RESOURCES_CLAIM) {
+            None => Ok(None),
+            Some(Value::Object(resources)) => resources
+                .get(aud)
+                .map(|r| Restrictions::deserialize(r).map_err(|_| Error::InvalidToken))
+                .transpose(),
+            Some(_) => Err(Error::InvalidToken),
+        }
+    }
+
+    /// Check that `aud` is an audience of the token, and that its
+    /// restrictions, if any, allow all of `scopes`.
+    pub fn check_audience_scope(&self, aud: &str, scopes: &[&str]) -> Result<()> {
+        match self.restrictions_for(aud)? {
+            Some(r) if !scopes.iter().all(|s| r.has_scope(s)) => Err(Error::InsufficientScope),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn restricted_audiences() -> Result<()> {
+        let mut token = HeaderAndClaims::new_dynamic();
+        let mut reports = Restrictions::scope("read export");
+        reports.extra.insert("max_rows".into(), 1000.into());
+        token
+            .add_aud("a")
+            .add_restricted_aud("b", Restrictions::scope("read"))
+            .add_restricted_aud("c", reports);
+        assert_eq!(
+            serde_json::to_value(token.claims())?,
+            json!({
+                "aud": ["a", "b", "c"],
+                "resources": {
+                    "b": { "scope": "read" },
+                    "c": { "scope": "read export", "max_rows": 1000 },
+                },
+            })
+        );
+
+        let claims: Claims<Map<String, Value>> =
+            serde_json::from_value(serde_json::to_value(token.claims())?)?;
+        assert_eq!(claims.restrictions_for("a")?, None);
+        assert_eq!(
+            claims.restrictions_for("c")?.unwrap().extra["max_rows"],
+            1000
+        );
+        assert!(matches!(
+            claims.restrictions_for("d"),
+            Err(Error::AudMismatch)
+        ));
+        claims.check_audience_scope("a", &["write"])?;
+        claims.check_audience_scope("c", &["read", "export"])?;
+        assert!(matches!(
+            claims.check_audience_scope("b", &["read", "export"]),
+            Err(Error::InsufficientScope)
+        ));
+
+        let claims: Claims<Map<String, Value>> =
+            serde_json::from_value(json!({ "aud": "a", "resources": { "a": "read" } }))?;
+        assert!(matches!(
+            claims.restrictions_for("a"),
+            Err(Error::InvalidToken)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+//! Authenticate bearer tokens that are either JWTs or opaque.
+//!
+//! [`Authenticator`] verifies JWTs locally with a JWK Set and sends anything
+//! else to an OAuth 2.0 token introspection (RFC 7662) endpoint. Either way
+//! the result is the same claims type.
+
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use openssl::hash::{hash, MessageDigest};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    decode_header,
+    jwk::{JwkSetVerifier, RemoteJwksVerifier},
+    split_token, Claims, Error, Result, ValidationOptions,
+};
+
+/// Where the claims of an authenticated token come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSource {
+    /// The token is a JWT and was verified locally.
+    Jwt,
+    /// The token was introspected.
+    Introspection,
+}
+
+/// An authenticated token.
+#[derive(Debug, Clone)]
+pub struct Authenticated {
+    pub source: TokenSource,
+    /// The JWT claims, or the introspection response without `active`.
+    pub claims: Claims<Map<String, Value>>,
+}
+
+/// An OAuth 2.0 token introspection endpoint.
+#[derive(Debug, Clone)]
+pub struct Introspection {
+    url: String,
+    client: reqwest::Client,
+    credentials: Option<(String, String)>,
+}
+
+impl Introspection {
+    pub fn new(url: String, client: Option<reqwest::Client>) -> Self {
+        Self {
+            url,
+            client: client.unwrap_or_default(),
+            credentials: None,
+        }
+    }
+
+    /// Authenticate to the endpoint with HTTP basic authentication.
+    pub fn set_client_credentials(&mut self, client_id: String, client_secret: String) {
+        self.credentials = Some((client_id, client_secret));
+    }
+
+    /// Introspect `token`. Inactive tokens are an [`Error::Inactive`].
+    pub async fn introspect(&self, token: &str) -> Result<Claims<Map<String, Value>>> {
+        #[derive(Deserialize)]
+        struct Response {
+            active: bool,
+            #[serde(flatten)]
+            claims: Claims<Map<String, Value>>,
+        }
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("accept", "application/json")
+            .form(&[("token", token)]);
+        if let Some((id, secret)) = &self.credentials {
+            request = request.basic_auth(id, Some(secret));
+        }
+        let response: Response = request.send().await?.error_for_status()?.json().await?;
+        if !response.active {
+            return Err(Error::Inactive);
+        }
+        Ok(response.claims)
+    }
+}
+
+enum JwtVerifier {
+    Local(Box<JwkSetVerifier>),
+    Remote(Box<RemoteJwksVerifier>),
+}
+
+/// Introspection results by SHA-256 of the token, with expiry.
+type Cache = HashMap<[u8; 32], (Instant, Claims<Map<String, Value>>)>;
+
+/// Verifies JWTs locally and introspects opaque tokens.
+///
+/// A token is treated as a JWT if it has three segments and a valid header.
+/// JWTs that fail verification are rejected, not introspected. Successful
+/// introspections are cached for the configured TTL, but never beyond `exp`.
+pub struct Authenticator {
+    jwt: JwtVerifier,
+    introspection: Introspection,
+    options: ValidationOptions,
+    cache_ttl: Duration,
+    cache_capacity: usize,
+    cache: Mutex<Cache>,
+}
+
+impl Authenticator {
+    fn with_verifier(jwt: JwtVerifier, introspection: Introspection) -> Self {
+        Self {
+            jwt,
+            introspection,
+            options: ValidationOptions::default(),
+            cache_ttl: Duration::from_secs(60),
+            cache_capacity: 10_000,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn new(jwks: JwkSetVerifier, introspection: Introspection) -> Self {
+        Self::with_verifier(JwtVerifier::Local(Box::new(jwks)), introspection)
+    }
+
+    pub fn with_remote_jwks(jwks: RemoteJwksVerifier, introspection: Introspection) -> Self {
+        Self::with_verifier(JwtVerifier::Remote(Box::new(jwks)), introspection)
+    }
+
+    /// How long to cache introspection results. Defaults to 60 seconds.
+    pub fn set_cache_ttl(&mut self, ttl: Duration) {
+        self.cache_ttl = ttl;
+    }
+
+    /// Maximum number of cached introspection results. Defaults to 10000.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache_capacity = capacity;
+    }
+
+    pub async fn authenticate(&self, token: &str) -> Result<Authenticated> {
+        if Self::is_jwt(token) {
+            let verified = match &self.jwt {
+                JwtVerifier::Local(v) => v.verify(token)?,
+                JwtVerifier::Remote(v) => v.verify(token).await?,
+            };
+            return Ok(Authenticated {
+                source: TokenSource::Jwt,
+                claims: verified.claims,
+            });
+        }
+
+        let key = hash(MessageDigest::sha256(), token.as_bytes())?;
+        let key: [u8; 32] = key[..].try_into().unwrap();
+        let cached = match self.cache.lock().unwrap().get(&key) {
+            Some((until, claims)) if *until > Instant::now() => Some(claims.clone()),
+            _ => None,
+        };
+        if let Some(claims) = cached {
+            // The options may have changed since the token was cached.
+            self.check(&claims)?;
+            return Ok(Authenticated {
+                source: TokenSource::Introspection,
+                claims,
+            });
+        }
+
+        let claims = self.introspection.introspect(token).await?;
+        let now = self.check(&claims)?;
+
+        let ttl = match claims.exp {
+            Some(exp) => self.cache_ttl.min(exp.saturating_sub(now)),
+            None => self.cache_ttl,
+        };
+        let mut cache = self.cache.lock().unwrap();
+        let now = Instant::now();
+        if cache.len() >= self.cache_capacity {
+            cache.retain(|_, (until, _)| *until > now);
+        }
+        if cache.len() < self.cache_capacity {
+            cache.insert(key, (now + ttl, claims.clone()));
+        }
+        Ok(Authenticated {
+            source: TokenSource::Introspection,
+            claims,
+        })
+    }
+
+    /// Check the times and claims of an introspection result, also when it
+    /// comes from the cache. Returns the current time since the epoch.
+    fn check(&self, claims: &Claims<Map<String, Value>>) -> Result<Duration> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let leeway = self.options.leeway;
+        if claims
+            .exp
+            .is_some_and(|exp| now > exp.saturating_add(leeway))
+        {
+            return Err(Error::Expired);
+        }
+        if claims
+            .nbf
+            .is_some_and(|nbf| now.saturating_add(leeway) < nbf)
+        {
+            return Err(Error::Before);
+        }
+        self.options.check_auth_time(claims.auth_time, now)?;
+        self.options.check_claims(claims)?;
+        Ok(now)
+    }
+
+    /// Options applied to introspection results, also cached ones. Use the
+    /// JWK Set verifier's options for JWTs.
+    pub fn set_validation_options(&mut self, options: ValidationOptions) {
+        self.options = options;
+    }
+
+    fn is_jwt(token: &str) -> bool {
+        let options = ValidationOptions::default();
+        options.check_token_len(token).is_ok()
+            && split_token(token)
+                .is_ok_and(|(header, _, _)| decode_header(header, &options).is_ok())
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        jwk::{JwkSet, WithKid},
+        sign, HeaderAndClaims, PublicKeyToJwk,
+    };
+
+    use super::*;
+
+    /// Serve introspection responses for tokens `active` and `inactive`.
+    fn serve_introspection() -> Result<(String, Arc<AtomicUsize>)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/introspect", listener.local_addr()?);
+        let count = Arc::new(AtomicUsize::new(0));
+        let served = count.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                served.fetch_add(1, Ordering::SeqCst);
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !String::from_utf8_lossy(&request).contains("token=") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let body = if String::from_utf8_lossy(&request).contains("token=inactive") {
+                    r#"{"active":false}"#
+                } else {
+                    r#"{"active":true,"sub":"alice","scope":"read","aud":"api"}"#
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        Ok((url, count))
+    }
+
+    #[tokio::test]
+    async fn jwt_and_opaque() -> Result<()> {
+        let k = WithKid::new_with_thumbprint_id(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?)?;
+        let jwks = JwkSet {
+            keys: vec![k.public_key_to_jwk()?],
+        };
+        let (url, count) = serve_introspection()?;
+        let mut authenticator = Authenticator::new(jwks.verifier(), Introspection::new(url, None));
+
+        let jwt = sign(HeaderAndClaims::new_dynamic().set_sub("bob"), &k)?;
+        let authenticated = authenticator.authenticate(&jwt).await?;
+        assert_eq!(authenticated.source, TokenSource::Jwt);
+        assert_eq!(authenticated.claims.sub.as_deref(), Some("bob"));
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        let mut tampered = jwt.clone();
+        tampered.pop();
+        assert!(authenticator.authenticate(&tampered).await.is_err());
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        for _ in 0..2 {
+            let authenticated = authenticator.authenticate("opaque").await?;
+            assert_eq!(authenticated.source, TokenSource::Introspection);
+            assert_eq!(authenticated.claims.sub.as_deref(), Some("alice"));
+            assert_eq!(authenticated.claims.extra["scope"], "read");
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        assert!(matches!(
+            authenticator.authenticate("inactive").await,
+            Err(Error::Inactive)
+        ));
+
+        // The cached result is checked against the new options.
+        authenticator.set_validation_options(ValidationOptions::new().require_aud("other"));
+        assert!(matches!(
+            authenticator.authenticate("opaque").await,
+            Err(Error::AudMismatch)
+        ));
+        assert!(matches!(
+            authenticator.authenticate("another").await,
+            Err(Error::AudMismatch)
+        ));
+        authenticator.set_validation_options(ValidationOptions::new().require_aud("api"));
+        authenticator.authenticate("opaque").await?;
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! Base64, behind one module so that the engine can be swapped.
+//!
+//! Tokens and JWKs use URL safe base64 without padding. Decoding accepts
+//! padding and non-zero trailing bits, as jwtk always has. With the `base64ct`
+//! feature, encoding and decoding are constant-time, for key material, and
+//! decoding is strict: no padding in URL safe base64, and no non-zero
+//! trailing bits. With the `base64-simd` feature, and without `base64ct`,
+//! encoding and decoding use SIMD instructions where the CPU has them, for
+//! tokens with large claims; input that the SIMD decoder rejects, e.g.
+//! padded, is decoded again with [`URL_SAFE`], so the same input is accepted
+//! as without the feature. The streaming encoders and decoders use
+//! [`URL_SAFE`] either way.
+
+#[cfg(not(feature = "base64ct"))]
+use base64::Engine;
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+};
+
+use crate::DecodeError;
+
+/// URL safe without padding.
+pub(crate) const URL_SAFE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+        .with_decode_allow_trailing_bits(true),
+);
+
+#[cfg(not(feature = "base64ct"))]
+const STANDARD: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+#[cfg(not(any(feature = "base64ct", feature = "base64-simd")))]
+pub(crate) fn encode_url(input: impl AsRef<[u8]>) -> String {
+    URL_SAFE.encode(input)
+}
+
+#[cfg(not(any(feature = "base64ct", feature = "base64-simd")))]
+#[cfg_attr(feature = "verify-only", allow(dead_code))]
+pub(crate) fn encode_url_buf(input: impl AsRef<[u8]>, buf: &mut String) {
+    URL_SAFE.encode_string(input, buf)
+}
+
+#[cfg(not(any(feature = "base64ct", feature = "base64-simd")))]
+pub(crate) fn decode_url(input: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+    URL_SAFE.decode(input).map_err(DecodeError)
+}
+
+/// Standard base64 with padding.
+#[cfg(not(any(feature = "base64ct", feature = "base64-simd")))]
+#[cfg_attr(feature = "verify-only", allow(dead_code))]
+pub(crate) fn encode(input: impl AsRef<[u8]>) -> String {
+    STANDARD.encode(input)
+}
+
+#[cfg(not(any(feature = "base64ct", feature = "base64-simd")))]
+pub(crate) fn decode(input: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+    STANDARD.decode(input).map_err(DecodeError)
+}
+
+#[cfg(all(feature = "base64-simd", not(feature = "base64ct")))]
+pub(crate) fn encode_url(input: impl AsRef<[u8]>) -> String {
+    base64_simd::URL_SAFE_NO_PAD.encode_to_string(input)
+}
+
+#[cfg(all(feature = "base64-simd", not(feature = "base64ct")))]
+#[cfg_attr(feature = "verify-only", allow(dead_code))]
+pub(crate) fn encode_url_buf(input: impl AsRef<[u8]>, buf: &mut String) {
+    base64_simd::URL_SAFE_NO_PAD.encode_append(input, buf)
+}
+
+#[cfg(all(feature = "base64-simd", not(feature = "base64ct")))]
+pub(crate) fn decode_url(input: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+    let input = input.as_ref();
+    base64_simd::URL_SAFE_NO_PAD
+        .decode_to_vec(input)
+        .or_else(|_| URL_SAFE.decode(input))
+        .map_err(DecodeError)
+}
+
+/// Standard base64 with padding.
+#[cfg(all(feature = "base64-simd", not(feature = "base64ct")))]
+#[cfg_attr(feature = "verify-only", allow(dead_code))]
+pub(crate) fn encode(input: impl AsRef<[u8]>) -> String {
+    base64_simd::STANDARD.encode_to_string(input)
+}
+
+#[cfg(all(feature = "base64-simd", not(feature = "base64ct")))]
+pub(crate) fn decode(input: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+    let input = input.as_ref();
+    base64_simd::STANDARD
+        .decode_to_vec(input)
+        .or_else(|_| STANDARD.decode(input))
+        .map_err(DecodeError)
+}
+
+#[cfg(feature = "base64ct")]
+pub(crate) fn encode_url(input: impl AsRef<[u8]>) -> String {
+    use base64ct::Encoding;
+    base64ct::Base64UrlUnpadded::encode_string(input.as_ref())
+}
+
+#[cfg(feature = "base64ct")]
+#[cfg_attr(feature = "verify-only", allow(dead_code))]
+pub(crate) fn encode_url_buf(input: impl AsRef<[u8]>, buf: &mut String) {
+    buf.push_str(&encode_url(input))
+}
+
+#[cfg(feature = "base64ct")]
+pub(crate) fn decode_url(input: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+    decode_ct::<base64ct::Base64UrlUnpadded>(
+        input.as_ref(),
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+    )
+}
+
+/// Standard base64 with padding.
+#[cfg(feature = "base64ct")]
+#[cfg_attr(feature = "verify-only", allow(dead_code))]
+pub(crate) fn encode(input: impl AsRef<[u8]>) -> String {
+    use base64ct::Encoding;
+    base64ct::Base64::encode_string(input.as_ref())
+}
+
+#[cfg(feature = "base64ct")]
+pub(crate) fn decode(input: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+    decode_ct::<base64ct::Base64>(input.as_ref(), &base64::engine::general_purpose::STANDARD)
+}
+
+/// Decode with `E`, and on failure report the error of the equally strict
+/// `engine`.
+#[cfg(feature = "base64ct")]
+fn decode_ct<E: base64ct::Encoding>(
+    input: &[u8],
+    engine: &GeneralPurpose,
+) -> Result<Vec<u8>, DecodeError> {
+    use base64::Engine;
+    std::str::from_utf8(input)
+        .ok()
+        .and_then(|s| E::decode_vec(s).ok())
+        .ok_or_else(|| {
+            DecodeError(
+                engine
+                    .decode(input)
+                    .err()
+                    .unwrap_or(base64::DecodeError::InvalidPadding),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        // Long enough for the SIMD paths too.
+        for len in (0..8).chain([31, 32, 33, 100, 1000]) {
+            let v: Vec<u8> = (0..len).map(|i: usize| (i * 37) as u8).collect();
+            assert_eq!(decode_url(encode_url(&v)).unwrap(), v);
+            assert_eq!(decode(encode(&v)).unwrap(), v);
+            let mut buf = "x.".to_string();
+            encode_url_buf(&v, &mut buf);
+            assert_eq!(buf, format!("x.{}", encode_url(&v)));
+        }
+        assert_eq!(encode_url([0xfb, 0xff]), "-_8");
+        assert_eq!(encode([0xfb, 0xff]), "+/8=");
+        assert!(decode_url("a b").is_err());
+        assert!(decode("-_8=").is_err());
+    }
+
+    #[cfg(not(feature = "base64ct"))]
+    #[test]
+    fn lenient() {
+        assert_eq!(decode_url("-_8=").unwrap(), [0xfb, 0xff]);
+        assert_eq!(decode_url("-_9").unwrap(), [0xfb, 0xff]);
+        assert_eq!(decode("+/8").unwrap(), [0xfb, 0xff]);
+    }
+
+    #[cfg(feature = "base64ct")]
+    #[test]
+    fn strict() {
+        assert!(decode_url("-_8=").is_err());
+        assert!(decode_url("-_9").is_err());
+        assert!(decode("+/8").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! Command line tool for inspecting, signing and verifying tokens.
+
+use std::{process::exit, time::Duration};
+
+use jwtk::{
+    decode_without_verify,
+    explain::explain,
+    jwk::{JwkSet, RemoteJwksVerifier},
+    keygen::KeyGen,
+    rsa::RsaAlgorithm,
+    sign, verify, verify_only, HeaderAndClaims, SomePrivateKey, SomePublicKey,
+};
+use serde_json::{Map, Value};
+
+const USAGE: &str = "\
+Usage:
+    jwtk decode [--explain] <token>
+    jwtk verify (--jwks-url <url> | --jwks <file> | --key <pem>) [--no-time-check] <token>
+    jwtk sign --key <pem> [--rsa-alg <alg>] [--kid <kid>] [--claim <name>=<value>]...
+              [--exp <duration>] [--nbf <duration>]
+    jwtk keygen --alg <alg> [--out-jwk | --out-pem]
+    jwtk jwks-snapshot <url>
+
+Claim values are parsed as JSON if possible, otherwise used as strings.
+Durations are in seconds, or have a s/m/h/d suffix, e.g. 1h.";
+
+type Claims = HeaderAndClaims<Map<String, Value>>;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let res = match args.first().map(|a| &a[..]) {
+        Some("decode") => decode(&args[1..]),
+        Some("verify") => verify_cmd(&args[1..]),
+        Some("sign") => sign_cmd(&args[1..]),
+        Some("keygen") => keygen(&args[1..]),
+        Some("jwks-snapshot") => jwks_snapshot(&args[1..]),
+        Some("-h" | "--help" | "help") => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => Err(USAGE.into()),
+    };
+    if let Err(e) = res {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
+
+type CliResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+/// Parsed `--option value` pairs, flags and positional arguments.
+struct Args {
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: &[String], flags: &[&str]) -> std::result::Result<Self, String> {
+        let mut parsed = Args {
+            options: Vec::new(),
+            flags: Vec::new(),
+            positional: Vec::new(),
+        };
+        let mut it = args.iter();
+        while let Some(arg) = it.next() {
+            if flags.contains(&&arg[..]) {
+                parsed.flags.push(arg.clone());
+            } else if let Some(name) = arg.strip_prefix("--") {
+                let value = it
+                    .next()
+                    .ok_or_else(|| format!("missing value for --{}", name))?;
+                parsed.options.push((name.into(), value.clone()));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| &v[..])
+    }
+
+    fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.options
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .map(|(_, v)| &v[..])
+    }
+
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    fn token(&self) -> std::result::Result<&str, String> {
+        match &self.positional[..] {
+            [token] => Ok(token.trim()),
+            _ => Err(USAGE.into()),
+        }
+    }
+}
+
+fn print_token(token: &Claims) -> CliResult {
+    println!("{}", serde_json::to_string_pretty(token.header())?);
+    println!("{}", serde_json::to_string_pretty(token.claims())?);
+    Ok(())
+}
+
+fn decode(args: &[String]) -> CliResult {
+    let args = Args::parse(args, &["--explain"])?;
+    if args.has_flag("--explain") {
+        println!("{}", explain(args.token()?)?);
+        Ok(())
+    } else {
+        print_token(&decode_without_verify(args.token()?)?)
+    }
+}
+
+fn verify_cmd(args: &[String]) -> CliResult {
+    let args = Args::parse(args, &["--no-time-check"])?;
+    let token = args.token()?;
+    let verified: Claims = if let Some(url) = args.get("jwks-url") {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let v = RemoteJwksVerifier::new(url.into(), None, Duration::from_secs(60));
+        if args.has_flag("--no-time-check") {
+            rt.block_on(v.verify_only(token))?
+        } else {
+            rt.block_on(v.verify(token))?
+        }
+    } else if let Some(path) = args.get("jwks") {
+        let jwks: JwkSet = serde_json::from_slice(&std::fs::read(path)?)?;
+        let v = jwks.verifier();
+        if args.has_flag("--no-time-check") {
+            v.verify_only(token)?
+        } else {
+            v.verify(token)?
+        }
+    } else if let Some(path) = args.get("key") {
+        let k = SomePublicKey::from_pem(&std::fs::read(path)?)?;
+        if args.has_flag("--no-time-check") {
+            verify_only(token, &k)?
+        } else {
+            verify(token, &k)?
+        }
+    } else {
+        return Err(USAGE.into());
+    };
+    print_token(&verified)
+}
+
+fn parse_duration(d: &str) -> std::result::Result<Duration, String> {
+    let (n, unit) = match d.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => d.split_at(i),
+        None => (d, "s"),
+    };
+    let n: u64 = n.parse().map_err(|_| format!("invalid duration: {}", d))?;
+    let secs = match unit {
+        "s" => n,
+        "m" => n * 60,
+        "h" => n * 60 * 60,
+        "d" => n * 60 * 60 * 24,
+        _ => return Err(format!("invalid duration: {}", d)),
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+fn sign_cmd(args: &[String]) -> CliResult {
+    let args = Args::parse(args, &[])?;
+    if !args.positional.is_empty() {
+        return Err(USAGE.into());
+    }
+    let rsa_alg = RsaAlgorithm::from_name(args.get("rsa-alg").unwrap_or("RS256"))?;
+    let k = SomePrivateKey::from_pem(&std::fs::read(args.get("key").ok_or(USAGE)?)?, rsa_alg)?;
+
+    let mut token = HeaderAndClaims::new_dynamic();
+    token.set_iat_now();
+    if let Some(kid) = args.get("kid") {
+        token.set_kid(kid);
+    }
+    if let Some(exp) = args.get("exp") {
+        token.set_exp_from_now(parse_duration(exp)?);
+    }
+    if let Some(nbf) = args.get("nbf") {
+        token.set_nbf_from_now(parse_duration(nbf)?);
+    }
+    for claim in args.get_all("claim") {
+        let (name, value) = claim
+            .split_once('=')
+            .ok_or_else(|| format!("invalid claim: {}", claim))?;
+        match name {
+            "iss" => token.set_iss(value),
+            "sub" => token.set_sub(value),
+            "jti" => token.set_jti(value),
+            "aud" => token.add_aud(value),
+            _ => token.insert(
+                name,
+                serde_json::from_str(value).unwrap_or_else(|_| Value::from(value)),
+            ),
+        };
+    }
+
+    println!("{}", sign(&mut token, &k)?);
+    Ok(())
+}
+
+fn keygen(args: &[String]) -> CliResult {
+    let args = Args::parse(args, &["--out-jwk", "--out-pem"])?;
+    let alg = match args.get("alg").ok_or(USAGE)? {
+        "Ed25519" => "EdDSA",
+        alg => alg,
+    };
+    let generated = KeyGen::new(alg).kid_from_thumbprint().generate()?;
+    if args.has_flag("--out-pem") {
+        print!("{}", generated.private_pem);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&generated.private_jwk)?);
+    }
+    Ok(())
+}
+
+/// Fetch a JWK Set and print it, for use with `JwkSet::pinned`.
+fn jwks_snapshot(args: &[String]) -> CliResult {
+    let args = Args::parse(args, &[])?;
+    let url = match &args.positional[..] {
+        [url] => url,
+        _ => return Err(USAGE.into()),
+    };
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let json = rt.block_on(async { reqwest::get(url).await?.error_for_status()?.text().await })?;
+    // Refuse to write a snapshot that the verifier would reject.
+    let jwks = JwkSet::pinned(&json)?;
+    println!("{}", serde_json::to_string_pretty(&jwks)?);
+    Ok(())
+}
@@ -0,0 +1,287 @@
+//! Chains of tokens, where a child token is minted from a parent token.
+//!
+//! A workflow system that mints sub-tokens from a user's token records the
+//! parent in the [`PARENT_CLAIM`] of the child: its `jti` and a hash of the
+//! token. A child must not outlive its parent, and must have the issuer of
+//! its parent unless a [`ChainPolicy`] allows another one.
+//!
+//! ```
+//! # use jwtk::{chain::ChainPolicy, ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey}, sign, verify_token, HeaderAndClaims, ValidationOptions};
+//! # use std::time::Duration;
+//! # let idp = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+//! # let workflow = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+//! # let user_token = sign(HeaderAndClaims::new_dynamic().set_iss("idp").set_sub("alice").set_jti("1").set_exp_from_now(Duration::from_secs(600)), &idp)?;
+//! let options = ValidationOptions::default();
+//! let parent = verify_token::<serde_json::Map<_, _>>(&user_token, &idp, &options)?;
+//! let child = sign(
+//!     HeaderAndClaims::new_dynamic()
+//!         .set_iss("workflow")
+//!         .set_sub("alice")
+//!         .set_exp_from_now(Duration::from_secs(3600))
+//!         .set_parent(&parent)?,
+//!     &workflow,
+//! )?;
+//!
+//! let child = verify_token::<serde_json::Map<_, _>>(&child, &workflow, &options)?;
+//! ChainPolicy::new()
+//!     .allow_issuer("idp", "workflow")
+//!     .check(child.claims(), &parent)?;
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use openssl::hash::{hash, MessageDigest};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+use crate::{actor::Actor, b64, Claims, Error, HeaderAndClaims, Result, VerifiedToken};
+
+/// The claim with the [`ParentRef`] of a child token.
+pub const PARENT_CLAIM: &str = "parent";
+
+/// Reference to the parent of a token.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParentRef {
+    /// `jti` of the parent, if it has one.
+    pub jti: Option<String>,
+    /// Base64url SHA-256 of the compact serialization of the parent.
+    pub hash: String,
+}
+
+impl ParentRef {
+    pub fn of<ExtraClaims>(parent: &VerifiedToken<ExtraClaims>) -> Result<Self> {
+        Ok(Self {
+            jti: parent.claims().jti.clone(),
+            hash: token_hash(parent.as_str())?,
+        })
+    }
+}
+
+fn token_hash(token: &str) -> Result<String> {
+    Ok(b64::encode_url(hash(
+        MessageDigest::sha256(),
+        token.as_bytes(),
+    )?))
+}
+
+impl HeaderAndClaims<Map<String, Value>> {
+    /// Make this token a child of `parent`: reference it in the
+    /// [`PARENT_CLAIM`], and cap `exp` at the `exp` of the parent. Call this
+    /// after setting `exp`.
+    pub fn set_parent<ExtraClaims>(
+        &mut self,
+        parent: &VerifiedToken<ExtraClaims>,
+    ) -> Result<&mut Self> {
+        let parent_ref = serde_json::to_value(ParentRef::of(parent)?)?;
+        self.claims.extra.insert(PARENT_CLAIM.into(), parent_ref);
+        if let Some(parent_exp) = parent.claims().exp {
+            self.claims.exp = Some(
+                self.claims
+                    .exp
+                    .map_or(parent_exp, |exp| exp.min(parent_exp)),
+            );
+        }
+        Ok(self)
+    }
+}
+
+/// Issuer rules of token chains. By default a child must have the issuer of
+/// its parent.
+#[derive(Debug, Clone, Default)]
+pub struct ChainPolicy {
+    issuers: Vec<(String, String)>,
+}
+
+impl ChainPolicy {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also accept children issued by `child_iss` of parents issued by
+    /// `parent_iss`, e.g. a workflow service that mints sub-tokens from the
+    /// tokens of an identity provider.
+    pub fn allow_issuer(
+        mut self,
+        parent_iss: impl Into<String>,
+        child_iss: impl Into<String>,
+    ) -> Self {
+        self.issuers.push((parent_iss.into(), child_iss.into()));
+        self
+    }
+
+    /// Check that `child` was minted from `parent`, does not expire after it,
+    /// and has an allowed issuer. If `parent` has a `may_act` claim, the
+    /// actor of `child`, its `act` claim or else its `sub` and `iss`, must be
+    /// that party. Verify the child before calling this.
+    ///
+    /// Returns `Error::ChainMismatch` if `child` doesn't reference `parent`,
+    /// outlives it or has another actor, `Error::MissingClaim` if either token
+    /// has no `iss`, and `Error::IssMismatch` if the issuer is not allowed.
+    pub fn check<ExtraClaims>(
+        &self,
+        child: &Claims<Map<String, Value>>,
+        parent: &VerifiedToken<ExtraClaims>,
+    ) -> Result<()> {
+        let parent_ref = match child.extra.get(PARENT_CLAIM) {
+            Some(v) => ParentRef::deserialize(v).map_err(|_| Error::InvalidToken)?,
+            None => return Err(Error::ChainMismatch),
+        };
+        let p = parent.claims();
+        if parent_ref.hash != token_hash(parent.as_str())? || parent_ref.jti != p.jti {
+            return Err(Error::ChainMismatch);
+        }
+        match (child.exp, p.exp) {
+            (_, None) => {}
+            (Some(exp), Some(parent_exp)) if exp <= parent_exp => {}
+            _ => return Err(Error::ChainMismatch),
+        }
+        let (parent_iss, child_iss) = match (&p.iss, &child.iss) {
+            (Some(parent_iss), Some(child_iss)) => (parent_iss, child_iss),
+            _ => return Err(Error::MissingClaim("iss".into())),
+        };
+        let allowed = child_iss == parent_iss
+            || self
+                .issuers
+                .iter()
+                .any(|(p, c)| p == parent_iss && c == child_iss);
+        if !allowed {
+            return Err(Error::IssMismatch);
+        }
+        if p.may_act.is_some() {
+            let actor = match &child.act {
+                Some(act) => act.clone(),
+                None => Actor {
+                    sub: child.sub.clone(),
+                    iss: child.iss.clone(),
+                    ..Actor::default()
+                },
+            };
+            if !p.may_act_as(&actor) {
+                return Err(Error::ChainMismatch);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        sign, verify_token, ValidationOptions,
+    };
+
+    use super::*;
+
+    #[test]
+    fn chain() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let options = ValidationOptions::default();
+        let verify = |token: &str| verify_token::<Map<String, Value>>(token, &k, &options);
+        let parent = verify(&sign(
+            HeaderAndClaims::new_dynamic()
+                .set_iss("idp")
+                .set_jti("p1")
+                .set_exp_from_now(Duration::from_secs(600)),
+            &k,
+        )?)?;
+        let parent_exp = parent.claims().exp.unwrap();
+
+        let mut child = HeaderAndClaims::new_dynamic();
+        child
+            .set_iss("workflow")
+            .set_exp_from_now(Duration::from_secs(3600))
+            .set_parent(&parent)?;
+        assert_eq!(child.claims().exp, Some(parent_exp));
+        assert_eq!(child.claims().extra[PARENT_CLAIM]["jti"], "p1");
+        let verified = verify(&sign(&mut child, &k)?)?;
+
+        let policy = ChainPolicy::new().allow_issuer("idp", "workflow");
+        policy.check(verified.claims(), &parent)?;
+        assert!(matches!(
+            ChainPolicy::new().check(verified.claims(), &parent),
+            Err(Error::IssMismatch)
+        ));
+
+        // Another parent.
+        let other = verify(&sign(
+            HeaderAndClaims::new_dynamic()
+                .set_iss("idp")
+                .set_jti("p1")
+                .set_exp_from_now(Duration::from_secs(601)),
+            &k,
+        )?)?;
+        assert!(matches!(
+            policy.check(verified.claims(), &other),
+            Err(Error::ChainMismatch)
+        ));
+
+        // Outlives the parent.
+        child.claims_mut().exp = Some(parent_exp + Duration::from_secs(1));
+        let verified = verify(&sign(&mut child, &k)?)?;
+        assert!(matches!(
+            policy.check(verified.claims(), &parent),
+            Err(Error::ChainMismatch)
+        ));
+
+        // No parent.
+        assert!(matches!(
+            policy.check(parent.claims(), &parent),
+            Err(Error::ChainMismatch)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn chain_iss_required() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let options = ValidationOptions::default();
+        let verify = |token: &str| verify_token::<Map<String, Value>>(token, &k, &options);
+        let parent = verify(&sign(HeaderAndClaims::new_dynamic().set_jti("p1"), &k)?)?;
+        let child = verify(&sign(
+            HeaderAndClaims::new_dynamic().set_parent(&parent)?,
+            &k,
+        )?)?;
+        assert!(matches!(
+            ChainPolicy::new().check(child.claims(), &parent),
+            Err(Error::MissingClaim(c)) if c == "iss"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn chain_may_act() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let options = ValidationOptions::default();
+        let verify = |token: &str| verify_token::<Map<String, Value>>(token, &k, &options);
+        let parent = verify(&sign(
+            HeaderAndClaims::new_dynamic()
+                .set_iss("idp")
+                .set_sub("alice")
+                .set_may_act(Actor::new("workflow").with_iss("idp")),
+            &k,
+        )?)?;
+        let policy = ChainPolicy::new();
+        let check = |child: &mut HeaderAndClaims<Map<String, Value>>| {
+            let child = verify(&sign(child.set_iss("idp").set_parent(&parent)?, &k)?)?;
+            policy.check(child.claims(), &parent)
+        };
+
+        check(HeaderAndClaims::new_dynamic().set_act(Actor::new("workflow").with_iss("idp")))?;
+        check(HeaderAndClaims::new_dynamic().set_sub("workflow"))?;
+        assert!(matches!(
+            check(HeaderAndClaims::new_dynamic().set_act(Actor::new("other").with_iss("idp"))),
+            Err(Error::ChainMismatch)
+        ));
+        assert!(matches!(
+            check(HeaderAndClaims::new_dynamic().set_sub("alice")),
+            Err(Error::ChainMismatch)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! Compact serialization of tokens.
+
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+    str::FromStr,
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{b64, redact::RedactedToken, Error, Result};
+
+/// A token in compact serialization, `header.payload.signature`.
+///
+/// Parsing only checks the structure: three base64url segments, of which the
+/// payload may be empty, as in detached signatures. Nothing is decoded or
+/// verified. Pass [`as_str`](Self::as_str) to e.g. [`verify`](crate::verify).
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct CompactToken {
+    token: String,
+    // Positions of the two dots.
+    dots: (usize, usize),
+}
+
+impl CompactToken {
+    pub fn as_str(&self) -> &str {
+        &self.token
+    }
+
+    pub fn into_string(self) -> String {
+        self.token
+    }
+
+    pub fn header_segment(&self) -> &str {
+        &self.token[..self.dots.0]
+    }
+
+    pub fn payload_segment(&self) -> &str {
+        &self.token[self.dots.0 + 1..self.dots.1]
+    }
+
+    pub fn signature_segment(&self) -> &str {
+        &self.token[self.dots.1 + 1..]
+    }
+
+    /// The bytes the signature is over, i.e. `header.payload`.
+    pub fn signing_input(&self) -> &[u8] {
+        &self.token.as_bytes()[..self.dots.1]
+    }
+
+    /// The decoded signature.
+    pub fn signature(&self) -> Result<Vec<u8>> {
+        Ok(b64::decode_url(self.signature_segment())?)
+    }
+}
+
+fn is_base64url(segment: &str) -> bool {
+    segment
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+impl TryFrom<String> for CompactToken {
+    type Error = Error;
+
+    fn try_from(token: String) -> Result<Self> {
+        let first = token.find('.').ok_or(Error::InvalidToken)?;
+        let second = first + 1 + token[first + 1..].find('.').ok_or(Error::InvalidToken)?;
+        let (header, payload, sig) = (
+            &token[..first],
+            &token[first + 1..second],
+            &token[second + 1..],
+        );
+        if header.is_empty()
+            || sig.is_empty()
+            || !is_base64url(header)
+            || !is_base64url(payload)
+            || !is_base64url(sig)
+        {
+            return Err(Error::InvalidToken);
+        }
+        Ok(Self {
+            token,
+            dots: (first, second),
+        })
+    }
+}
+
+impl FromStr for CompactToken {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.to_string().try_into()
+    }
+}
+
+impl fmt::Display for CompactToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.token)
+    }
+}
+
+impl fmt::Debug for CompactToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CompactToken")
+            .field(&RedactedToken(&self.token))
+            .finish()
+    }
+}
+
+impl AsRef<str> for CompactToken {
+    fn as_ref(&self) -> &str {
+        &self.token
+    }
+}
+
+impl From<CompactToken> for String {
+    fn from(token: CompactToken) -> Self {
+        token.token
+    }
+}
+
+impl Serialize for CompactToken {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.token)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactToken {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .try_into()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(all(test, feature = "eddsa", not(feature = "verify-only")))]
+mod tests {
+    use crate::{eddsa::Ed25519PrivateKey, sign, verify, HeaderAndClaims};
+
+    use super::*;
+
+    #[test]
+    fn compact_token() -> Result<()> {
+        let k = Ed25519PrivateKey::generate()?;
+        let token = sign(HeaderAndClaims::new_dynamic().set_sub("alice"), &k)?;
+        let compact: CompactToken = token.parse()?;
+        assert_eq!(compact.to_string(), token);
+        verify::<()>(compact.as_str(), &k)?;
+        let segments: Vec<_> = token.split('.').collect();
+        assert_eq!(compact.header_segment(), segments[0]);
+        assert_eq!(compact.payload_segment(), segments[1]);
+        assert_eq!(compact.signature_segment(), segments[2]);
+        assert_eq!(compact.signature()?.len(), 64);
+        assert!(token.as_bytes().starts_with(compact.signing_input()));
+
+        let json = serde_json::to_string(&compact)?;
+        assert_eq!(serde_json::from_str::<CompactToken>(&json)?, compact);
+
+        "a..c".parse::<CompactToken>()?;
+        for invalid in ["", "a.b", "a.b.c.d", ".b.c", "a.b.", "a.b+.c", "a.b.c="] {
+            assert!(invalid.parse::<CompactToken>().is_err(), "{}", invalid);
+        }
+        assert!(serde_json::from_str::<CompactToken>("\"a.b\"").is_err());
+        Ok(())
+    }
+}
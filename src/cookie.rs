@@ -0,0 +1,250 @@
+//! Tokens in cookies.
+//!
+//! Browsers limit a cookie to about 4 KiB, name and attributes included.
+//! Tokens that don't fit are split: the cookie `name` is set to
+//! `chunks-<n>` and the token is stored in `name.1` to `name.<n>`.
+//! [`read_cookie`] puts them back together.
+//!
+//! Tokens are only signed here. Encrypted tokens (JWE) aren't supported by
+//! this crate, so don't put anything in the claims that the browser must not
+//! see.
+//!
+//! ```
+//! # use jwtk::cookie::{read_cookie, CookieOptions};
+//! let token = "x".repeat(6000);
+//! let headers = CookieOptions::new().set_cookies("session", &token);
+//! assert_eq!(headers.len(), 3);
+//!
+//! // What the browser sends back.
+//! let cookie_header = headers
+//!     .iter()
+//!     .map(|h| h.split(';').next().unwrap())
+//!     .collect::<Vec<_>>()
+//!     .join("; ");
+//! assert_eq!(read_cookie(&cookie_header, "session").unwrap(), token);
+//! ```
+
+use std::{fmt::Write, time::Duration};
+
+/// Maximum size of a `Set-Cookie` value that browsers are required to accept.
+pub const MAX_COOKIE_SIZE: usize = 4096;
+
+const CHUNKS_PREFIX: &str = "chunks-";
+
+/// The `SameSite` cookie attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    /// Requires `Secure`.
+    None,
+}
+
+/// Attributes of token cookies.
+///
+/// Defaults to `HttpOnly; Secure; SameSite=Lax; Path=/` and a session
+/// cookie.
+#[derive(Debug, Clone)]
+pub struct CookieOptions {
+    path: String,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    same_site: SameSite,
+    secure: bool,
+    http_only: bool,
+}
+
+impl Default for CookieOptions {
+    fn default() -> Self {
+        Self {
+            path: "/".into(),
+            domain: None,
+            max_age: None,
+            same_site: SameSite::Lax,
+            secure: true,
+            http_only: true,
+        }
+    }
+}
+
+impl CookieOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// E.g. the lifetime of the token. Without it the cookie is removed when
+    /// the browser is closed.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Only disable for local development over plain http.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    fn attributes(&self, max_age: Option<Duration>) -> String {
+        let mut attributes = format!("; Path={}", self.path);
+        if let Some(domain) = &self.domain {
+            write!(attributes, "; Domain={}", domain).unwrap();
+        }
+        if let Some(max_age) = max_age {
+            write!(attributes, "; Max-Age={}", max_age.as_secs()).unwrap();
+        }
+        attributes.push_str(match self.same_site {
+            SameSite::Strict => "; SameSite=Strict",
+            SameSite::Lax => "; SameSite=Lax",
+            SameSite::None => "; SameSite=None",
+        });
+        if self.secure {
+            attributes.push_str("; Secure");
+        }
+        if self.http_only {
+            attributes.push_str("; HttpOnly");
+        }
+        attributes
+    }
+
+    /// A `Set-Cookie` header value storing `token` in cookie `name`.
+    ///
+    /// Doesn't check the size, see [`set_cookies`](Self::set_cookies).
+    pub fn set_cookie(&self, name: &str, token: &str) -> String {
+        format!("{}={}{}", name, token, self.attributes(self.max_age))
+    }
+
+    /// `Set-Cookie` header values storing `token` in cookie `name`, split
+    /// into chunks if it doesn't fit in one cookie.
+    pub fn set_cookies(&self, name: &str, token: &str) -> Vec<String> {
+        let attributes = self.attributes(self.max_age);
+        // name.NNN=
+        let chunk_len = MAX_COOKIE_SIZE.saturating_sub(name.len() + 5 + attributes.len());
+        if name.len() + 1 + token.len() + attributes.len() <= MAX_COOKIE_SIZE || chunk_len == 0 {
+            return vec![self.set_cookie(name, token)];
+        }
+        // Tokens are normally ASCII, but don't split a char if not.
+        let mut chunks = Vec::new();
+        let mut rest = token;
+        while !rest.is_empty() {
+            let mut end = chunk_len.min(rest.len());
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            if end == 0 {
+                end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+            }
+            let (chunk, tail) = rest.split_at(end);
+            chunks.push(chunk);
+            rest = tail;
+        }
+        let mut headers = vec![format!(
+            "{}={}{}{}",
+            name,
+            CHUNKS_PREFIX,
+            chunks.len(),
+            attributes
+        )];
+        for (i, chunk) in chunks.iter().enumerate() {
+            headers.push(format!("{}.{}={}{}", name, i + 1, chunk, attributes));
+        }
+        headers
+    }
+
+    /// A `Set-Cookie` header value that removes cookie `name`.
+    ///
+    /// When replacing a split token with a shorter one, remove the chunks
+    /// that are no longer used, e.g. `name.3`.
+    pub fn remove_cookie(&self, name: &str) -> String {
+        format!("{}={}", name, self.attributes(Some(Duration::ZERO)))
+    }
+}
+
+/// Read the token in cookie `name` from a `Cookie` header value, joining the
+/// chunks of a split token.
+///
+/// Returns `None` if the cookie or any of its chunks is missing.
+pub fn read_cookie(cookie_header: &str, name: &str) -> Option<String> {
+    let get = |name: &str| {
+        cookie_header.split(';').find_map(|pair| {
+            let (n, v) = pair.trim().split_once('=')?;
+            (n == name).then_some(v)
+        })
+    };
+    let value = get(name)?;
+    let n: usize = match value.strip_prefix(CHUNKS_PREFIX) {
+        Some(n) => n.parse().ok()?,
+        None => return Some(value.into()),
+    };
+    let mut token = String::new();
+    for i in 1..=n {
+        token.push_str(get(&format!("{}.{}", name, i))?);
+    }
+    Some(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cookies() {
+        let options = CookieOptions::new()
+            .domain("example.com")
+            .max_age(Duration::from_secs(3600))
+            .same_site(SameSite::Strict);
+        assert_eq!(
+            options.set_cookie("at", "a.b.c"),
+            "at=a.b.c; Path=/; Domain=example.com; Max-Age=3600; SameSite=Strict; Secure; HttpOnly"
+        );
+        assert_eq!(options.set_cookies("at", "a.b.c").len(), 1);
+        assert_eq!(read_cookie("x=1; at=a.b.c", "at").as_deref(), Some("a.b.c"));
+        assert_eq!(read_cookie("x=1", "at"), None);
+        assert!(options.remove_cookie("at").contains("Max-Age=0"));
+
+        let token = "t".repeat(10_000);
+        let headers = options.set_cookies("at", &token);
+        assert_eq!(headers.len(), 4);
+        assert!(headers[0].starts_with("at=chunks-3;"));
+        assert!(headers.iter().all(|h| h.len() <= MAX_COOKIE_SIZE));
+        let pairs: Vec<&str> = headers
+            .iter()
+            .map(|h| h.split(';').next().unwrap())
+            .collect();
+        assert_eq!(read_cookie(&pairs.join("; "), "at").unwrap(), token);
+
+        // Missing chunk.
+        assert_eq!(read_cookie(&pairs[..3].join("; "), "at"), None);
+
+        // Multibyte chars aren't split.
+        let token = "é".repeat(5_000);
+        let headers = options.set_cookies("at", &token);
+        assert!(headers.iter().all(|h| h.len() <= MAX_COOKIE_SIZE));
+        let pairs: Vec<&str> = headers
+            .iter()
+            .map(|h| h.split(';').next().unwrap())
+            .collect();
+        assert_eq!(read_cookie(&pairs.join("; "), "at").unwrap(), token);
+    }
+}
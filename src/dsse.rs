@@ -0,0 +1,233 @@
+//! DSSE (Dead Simple Signing Envelope), as used by in-toto attestations and
+//! SLSA provenance.
+//!
+//! The signatures are over the pre-authentication encoding ([`pae`]) of the
+//! payload type and the payload. ECDSA signatures are ASN.1 DER encoded, as
+//! other DSSE implementations expect.
+//!
+//! ```
+//! # use jwtk::{dsse::{Envelope, IN_TOTO_PAYLOAD_TYPE}, ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey}};
+//! let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+//! let statement = br#"{"_type":"https://in-toto.io/Statement/v1"}"#;
+//! let envelope = Envelope::sign(IN_TOTO_PAYLOAD_TYPE, statement, &k)?;
+//! let json = serde_json::to_string(&envelope)?;
+//!
+//! let envelope: Envelope = serde_json::from_str(&json)?;
+//! let payload = envelope.verify(IN_TOTO_PAYLOAD_TYPE, &k, "ES256")?;
+//! assert_eq!(payload, statement);
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+use crate::ecdsa::signature_to_der;
+#[cfg(feature = "ecdsa")]
+use crate::ecdsa::{signature_from_der, EcdsaAlgorithm};
+#[cfg(not(feature = "verify-only"))]
+use crate::SigningKey;
+use crate::{b64, Error, Result, VerificationKey};
+
+/// Payload type of in-toto statements.
+pub const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// A signature of an [`Envelope`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvelopeSignature {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyid: Option<String>,
+    /// Standard base64 encoded signature.
+    pub sig: String,
+}
+
+/// A DSSE envelope, in its JSON serialization.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Standard base64 encoded payload.
+    pub payload: String,
+    #[serde(rename = "payloadType")]
+    pub payload_type: String,
+    pub signatures: Vec<EnvelopeSignature>,
+}
+
+/// The pre-authentication encoding of `payload_type` and `payload`, i.e.
+/// what is signed.
+pub fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut out = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    out.extend_from_slice(payload);
+    out
+}
+
+#[cfg(feature = "ecdsa")]
+fn ecdsa_algorithm(alg: &str) -> Option<EcdsaAlgorithm> {
+    use EcdsaAlgorithm::*;
+    [ES256, ES256K, ES384, ES512]
+        .iter()
+        .copied()
+        .find(|a| a.name() == alg)
+}
+
+/// ECDSA signatures to DER, others as they are.
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+fn encode_signature(sig: &[u8], alg: &str) -> Result<Vec<u8>> {
+    match ecdsa_algorithm(alg) {
+        Some(alg) => signature_to_der(sig, alg),
+        None => Ok(sig.to_vec()),
+    }
+}
+
+#[cfg(not(any(feature = "ecdsa", feature = "verify-only")))]
+fn encode_signature(sig: &[u8], _alg: &str) -> Result<Vec<u8>> {
+    Ok(sig.to_vec())
+}
+
+/// ECDSA signatures from DER, others as they are.
+#[cfg(feature = "ecdsa")]
+fn decode_signature(sig: Vec<u8>, alg: &str) -> Result<Vec<u8>> {
+    match ecdsa_algorithm(alg) {
+        Some(alg) => Ok(signature_from_der(&sig, alg)?.to_vec()),
+        None => Ok(sig),
+    }
+}
+
+#[cfg(not(feature = "ecdsa"))]
+fn decode_signature(sig: Vec<u8>, _alg: &str) -> Result<Vec<u8>> {
+    Ok(sig)
+}
+
+/// Decode standard or URL safe base64, which the spec allows both of.
+fn decode(v: &str) -> Result<Vec<u8>> {
+    Ok(b64::decode(v).or_else(|_| b64::decode_url(v.trim_end_matches('=')))?)
+}
+
+impl Envelope {
+    /// Sign `payload` of `payload_type` with `k`, whose `kid`, if any, is
+    /// the signature's `keyid`.
+    #[cfg(not(feature = "verify-only"))]
+    pub fn sign(payload_type: &str, payload: &[u8], k: &dyn SigningKey) -> Result<Self> {
+        let mut envelope = Self {
+            payload: b64::encode(payload),
+            payload_type: payload_type.into(),
+            signatures: Vec::new(),
+        };
+        envelope.add_signature(k)?;
+        Ok(envelope)
+    }
+
+    /// Sign the envelope with another key.
+    #[cfg(not(feature = "verify-only"))]
+    pub fn add_signature(&mut self, k: &dyn SigningKey) -> Result<()> {
+        let pae = pae(&self.payload_type, &decode(&self.payload)?);
+        let sig = encode_signature(&k.sign(&pae)?, k.alg())?;
+        self.signatures.push(EnvelopeSignature {
+            keyid: k.kid().map(Into::into),
+            sig: b64::encode(sig),
+        });
+        Ok(())
+    }
+
+    /// Verify that any signature is by `k` with `alg`, and that the payload
+    /// is of `payload_type`, and return the payload.
+    pub fn verify(
+        &self,
+        payload_type: &str,
+        k: &dyn VerificationKey,
+        alg: &str,
+    ) -> Result<Vec<u8>> {
+        if self.payload_type != payload_type {
+            return Err(Error::InvalidToken);
+        }
+        let payload = decode(&self.payload)?;
+        let pae = pae(&self.payload_type, &payload);
+        for s in &self.signatures {
+            let sig = match decode(&s.sig).and_then(|sig| decode_signature(sig, alg)) {
+                Ok(sig) => sig,
+                Err(_) => continue,
+            };
+            if k.verify(&pae, &sig, alg).is_ok() {
+                return Ok(payload);
+            }
+        }
+        Err(Error::VerificationError)
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "rsa",
+    feature = "ecdsa",
+    feature = "eddsa",
+    not(feature = "verify-only")
+))]
+mod tests {
+    use crate::{
+        ecdsa::EcdsaPrivateKey,
+        eddsa::Ed25519PrivateKey,
+        jwk::WithKid,
+        rsa::{RsaAlgorithm, RsaPrivateKey},
+    };
+
+    use super::*;
+
+    #[test]
+    fn pae_encoding() {
+        // From the DSSE specification.
+        assert_eq!(
+            pae("http://example.com/HelloWorld", b"hello world"),
+            b"DSSEv1 29 http://example.com/HelloWorld 11 hello world"
+        );
+        assert_eq!(pae("", b""), b"DSSEv1 0  0 ");
+    }
+
+    #[test]
+    fn envelope() -> Result<()> {
+        let es = WithKid::new(
+            "es".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        let ed = Ed25519PrivateKey::generate()?;
+        let rs = RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?;
+        let payload = br#"{"_type":"https://in-toto.io/Statement/v1"}"#;
+
+        let mut envelope = Envelope::sign(IN_TOTO_PAYLOAD_TYPE, payload, &es)?;
+        envelope.add_signature(&ed)?;
+        assert_eq!(envelope.signatures[0].keyid.as_deref(), Some("es"));
+        let json = serde_json::to_value(&envelope)?;
+        assert_eq!(json["payloadType"], IN_TOTO_PAYLOAD_TYPE);
+        // DER, not r || s.
+        let sig = b64::decode(envelope.signatures[0].sig.as_bytes()).unwrap();
+        assert_eq!(sig[0], 0x30);
+
+        let envelope: Envelope = serde_json::from_value(json)?;
+        assert_eq!(
+            envelope.verify(IN_TOTO_PAYLOAD_TYPE, &es, "ES256")?,
+            payload
+        );
+        assert_eq!(
+            envelope.verify(IN_TOTO_PAYLOAD_TYPE, &ed, "EdDSA")?,
+            payload
+        );
+        assert!(envelope.verify(IN_TOTO_PAYLOAD_TYPE, &rs, "PS256").is_err());
+        assert!(matches!(
+            envelope.verify("application/json", &es, "ES256"),
+            Err(Error::InvalidToken)
+        ));
+
+        let mut tampered = envelope.clone();
+        tampered.payload_type = "application/json".into();
+        assert!(tampered.verify("application/json", &es, "ES256").is_err());
+        let mut tampered = envelope;
+        tampered.payload = b64::encode(b"{}");
+        assert!(tampered.verify(IN_TOTO_PAYLOAD_TYPE, &ed, "EdDSA").is_err());
+
+        let envelope = Envelope::sign("text/plain", b"hi", &rs)?;
+        assert_eq!(envelope.verify("text/plain", &rs, "PS256")?, b"hi");
+        Ok(())
+    }
+}
@@ -1,19 +1,23 @@
 use foreign_types::ForeignTypeRef;
 use openssl::{
-    bn::{BigNum, BigNumContext},
-    ec::{EcGroup, EcKey},
-    ecdsa::EcdsaSig,
-    hash::{hash, MessageDigest},
+    bn::{BigNum, BigNumContext, BigNumRef},
+    ec::{EcGroup, EcGroupRef, EcKey, EcPoint},
+    ecdsa::{EcdsaSig, EcdsaSigRef},
+    hash::{hash, Hasher, MessageDigest},
     nid::Nid,
-    pkey::{HasPublic, PKey, PKeyRef, Private, Public},
+    pkey::{HasPublic, PKey, PKeyRef, Public},
 };
+#[cfg(not(feature = "verify-only"))]
+use openssl::{pkey::Private, pkey_ctx::PkeyCtx};
 use openssl_sys::BN_bn2bin;
 use smallvec::{smallvec, SmallVec};
 
 use crate::{
-    jwk::Jwk, url_safe_trailing_bits, Error, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey,
+    b64, jwk::Jwk, stream::VerifyStream, Error, Fingerprint, PublicKeyToJwk, Result,
     VerificationKey,
 };
+#[cfg(not(feature = "verify-only"))]
+use crate::{stream::SignStream, PrivateKeyToJwk, SigningKey};
 
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -101,18 +105,22 @@ impl EcdsaAlgorithm {
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 #[derive(Debug, Clone)]
 pub struct EcdsaPrivateKey {
     private_key: PKey<Private>,
     algorithm: EcdsaAlgorithm,
+    low_s: bool,
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl EcdsaPrivateKey {
     pub fn generate(algorithm: EcdsaAlgorithm) -> Result<Self> {
         let ec_key = EcKey::generate(EcGroup::from_curve_name(algorithm.curve())?.as_ref())?;
         Ok(Self {
             private_key: PKey::from_ec_key(ec_key)?,
             algorithm,
+            low_s: false,
         })
     }
 
@@ -128,6 +136,19 @@ impl EcdsaPrivateKey {
         Ok(Self {
             private_key: pk,
             algorithm,
+            low_s: false,
+        })
+    }
+
+    /// A key that may be held by an OpenSSL provider, so it can't be
+    /// exported to check it.
+    #[cfg(feature = "openssl-store")]
+    pub(crate) fn from_provider_pkey(pk: PKey<Private>) -> Result<Self> {
+        let algorithm = EcdsaAlgorithm::from_curve(crate::store::curve(&pk)?)?;
+        Ok(Self {
+            private_key: pk,
+            algorithm,
+            low_s: false,
         })
     }
 
@@ -141,17 +162,13 @@ impl EcdsaPrivateKey {
         let k = EcKey::from_private_components(
             group.as_ref(),
             BigNum::from_slice(d)?.as_ref(),
-            EcKey::from_public_key_affine_coordinates(
-                group.as_ref(),
-                BigNum::from_slice(x)?.as_ref(),
-                BigNum::from_slice(y)?.as_ref(),
-            )?
-            .public_key(),
+            &*public_point(&group, algorithm, x, y)?,
         )?;
         k.check_key()?;
         Ok(Self {
             private_key: PKey::from_ec_key(k)?,
             algorithm,
+            low_s: false,
         })
     }
 
@@ -170,6 +187,16 @@ impl EcdsaPrivateKey {
         Ok(String::from_utf8(self.private_key.public_key_to_pem()?)?)
     }
 
+    /// PKCS#8 DER.
+    pub fn private_key_to_der_pkcs8(&self) -> Result<Vec<u8>> {
+        Ok(self.private_key.private_key_to_pkcs8()?)
+    }
+
+    /// SubjectPublicKeyInfo DER.
+    pub fn public_key_to_der(&self) -> Result<Vec<u8>> {
+        Ok(self.private_key.public_key_to_der()?)
+    }
+
     /// Public key X Y coordinates. Always padded to the full size.
     pub fn coordinates(&self) -> Result<(Vec<u8>, Vec<u8>)> {
         let mut ctx = BigNumContext::new()?;
@@ -187,8 +214,16 @@ impl EcdsaPrivateKey {
     pub fn d(&self) -> Result<Vec<u8>> {
         Ok(self.private_key.ec_key()?.private_key().to_vec())
     }
+
+    /// Produce only low-S signatures, i.e. `s <= n / 2`, and reject high-S
+    /// signatures on verification. Some ecosystems, notably secp256k1
+    /// consumers, require this to rule out signature malleability.
+    pub fn set_low_s(&mut self, low_s: bool) {
+        self.low_s = low_s;
+    }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl PublicKeyToJwk for EcdsaPrivateKey {
     fn public_key_to_jwk(&self) -> Result<Jwk> {
         let (x, y) = self.coordinates()?;
@@ -196,13 +231,14 @@ impl PublicKeyToJwk for EcdsaPrivateKey {
             kty: "EC".into(),
             use_: Some("sig".into()),
             crv: Some(self.algorithm.curve_name().into()),
-            x: Some(base64::encode_config(x, url_safe_trailing_bits())),
-            y: Some(base64::encode_config(y, url_safe_trailing_bits())),
+            x: Some(b64::encode_url(x)),
+            y: Some(b64::encode_url(y)),
             ..Default::default()
         })
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl PrivateKeyToJwk for EcdsaPrivateKey {
     fn private_key_to_jwk(&self) -> Result<Jwk> {
         let (x, y) = self.coordinates()?;
@@ -211,9 +247,9 @@ impl PrivateKeyToJwk for EcdsaPrivateKey {
             kty: "EC".into(),
             use_: Some("sig".into()),
             crv: Some(self.algorithm.curve_name().into()),
-            d: Some(base64::encode_config(d, url_safe_trailing_bits())),
-            x: Some(base64::encode_config(x, url_safe_trailing_bits())),
-            y: Some(base64::encode_config(y, url_safe_trailing_bits())),
+            d: Some(b64::encode_url(d)),
+            x: Some(b64::encode_url(x)),
+            y: Some(b64::encode_url(y)),
             ..Default::default()
         })
     }
@@ -230,6 +266,40 @@ fn pad_left(v: &mut Vec<u8>, len: usize) {
     v[..(len - old_len)].fill(0);
 }
 
+/// Validate public key coordinates, e.g. from a JWK, and convert them to a
+/// point.
+///
+/// OpenSSL would reject most invalid points by itself, but we don't want to
+/// depend on the details of that for input from untrusted JWK Sets.
+fn public_point(
+    group: &EcGroupRef,
+    algorithm: EcdsaAlgorithm,
+    x: &[u8],
+    y: &[u8],
+) -> Result<EcPoint> {
+    // Coordinates are field elements, so no longer than half the signature.
+    if x.len() > algorithm.len() / 2 || y.len() > algorithm.len() / 2 {
+        return Err(Error::InvalidPoint);
+    }
+    let mut ctx = BigNumContext::new()?;
+    let (mut p, mut a, mut b) = (BigNum::new()?, BigNum::new()?, BigNum::new()?);
+    group.components_gfp(&mut p, &mut a, &mut b, &mut ctx)?;
+    let x = BigNum::from_slice(x)?;
+    let y = BigNum::from_slice(y)?;
+    if x >= p || y >= p {
+        return Err(Error::InvalidPoint);
+    }
+
+    let mut point = EcPoint::new(group)?;
+    point
+        .set_affine_coordinates_gfp(group, &x, &y, &mut ctx)
+        .map_err(|_| Error::InvalidPoint)?;
+    if point.is_infinity(group) || !point.is_on_curve(group, &mut ctx)? {
+        return Err(Error::InvalidPoint);
+    }
+    Ok(point)
+}
+
 #[cfg(test)]
 #[test]
 fn test_pad_left() {
@@ -240,10 +310,11 @@ fn test_pad_left() {
     assert_eq!(v, [0, 0, 0, 0, 0, 5, 6, 7]);
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EcdsaPublicKey {
     public_key: PKey<Public>,
     algorithm: EcdsaAlgorithm,
+    low_s: bool,
 }
 
 impl EcdsaPublicKey {
@@ -260,6 +331,7 @@ impl EcdsaPublicKey {
         Ok(Self {
             public_key: pkey,
             algorithm,
+            low_s: false,
         })
     }
 
@@ -272,6 +344,16 @@ impl EcdsaPublicKey {
         Ok(String::from_utf8(self.public_key.public_key_to_pem()?)?)
     }
 
+    /// See [`Fingerprint`].
+    pub fn fingerprint_sha256(&self) -> Result<Fingerprint> {
+        Fingerprint::of_spki_der(&self.public_key.public_key_to_der()?)
+    }
+
+    /// Whether `other` is the same point on the same curve.
+    pub fn key_eq(&self, other: &Self) -> bool {
+        self.public_key.public_eq(&other.public_key)
+    }
+
     /// X Y coordinates. Always padded to the full size.
     pub fn coordinates(&self) -> Result<(Vec<u8>, Vec<u8>)> {
         let mut ctx = BigNumContext::new()?;
@@ -286,18 +368,24 @@ impl EcdsaPublicKey {
         Ok((x, y))
     }
 
+    /// Returns `Error::InvalidPoint` unless `x` and `y` are the coordinates of
+    /// a point on the curve other than the identity.
     pub fn from_coordinates(x: &[u8], y: &[u8], algorithm: EcdsaAlgorithm) -> Result<Self> {
-        let k = EcKey::from_public_key_affine_coordinates(
-            EcGroup::from_curve_name(algorithm.curve())?.as_ref(),
-            BigNum::from_slice(x)?.as_ref(),
-            BigNum::from_slice(y)?.as_ref(),
-        )?;
+        let group = EcGroup::from_curve_name(algorithm.curve())?;
+        let point = public_point(&group, algorithm, x, y)?;
+        let k = EcKey::from_public_key(&group, &point)?;
         k.check_key()?;
         Ok(Self {
             public_key: PKey::from_ec_key(k)?,
             algorithm,
+            low_s: false,
         })
     }
+
+    /// Reject high-S signatures, i.e. `s > n / 2`.
+    pub fn set_low_s(&mut self, low_s: bool) {
+        self.low_s = low_s;
+    }
 }
 
 impl PublicKeyToJwk for EcdsaPublicKey {
@@ -307,48 +395,109 @@ impl PublicKeyToJwk for EcdsaPublicKey {
             kty: "EC".into(),
             use_: Some("sig".into()),
             crv: Some(self.algorithm.curve_name().into()),
-            x: Some(base64::encode_config(x, url_safe_trailing_bits())),
-            y: Some(base64::encode_config(y, url_safe_trailing_bits())),
+            x: Some(b64::encode_url(x)),
+            y: Some(b64::encode_url(y)),
             ..Default::default()
         })
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl SigningKey for EcdsaPrivateKey {
     fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>> {
-        let hash = hash(self.algorithm.digest(), v)?;
+        self.sign_digest(&hash(self.algorithm.digest(), v)?)
+    }
 
-        // Use the low-level signing API we get the `r`, `s` bytes more easily:
-        // No need to parse the ASN.1 DER encoded signature.
-        let sig = EcdsaSig::sign(&hash, self.private_key.ec_key()?.as_ref())?;
+    fn alg(&self) -> &'static str {
+        self.algorithm.name()
+    }
 
-        let sig_len = self.algorithm.len();
-        let mut out = smallvec![0u8; sig_len];
+    fn sign_stream(&self) -> Result<SignStream<'_>> {
+        let hasher = Hasher::new(self.algorithm.digest())?;
+        Ok(SignStream::digest(hasher, move |digest| {
+            self.sign_digest(digest)
+        }))
+    }
+}
 
-        let r = sig.r();
-        let r_len = r.num_bytes() as usize;
-        debug_assert!(r_len <= sig_len / 2);
+#[cfg(not(feature = "verify-only"))]
+impl EcdsaPrivateKey {
+    /// Sign a digest of the JWS signing input computed elsewhere, e.g. the
+    /// SHA-256 digest for ES256. The result is the same as [`sign`] of the
+    /// signing input.
+    ///
+    /// [`sign`]: SigningKey::sign
+    pub fn sign_digest(&self, digest: &[u8]) -> Result<SmallVec<[u8; 64]>> {
+        check_digest_len(self.algorithm, digest)?;
+        // Sign through EVP rather than with the `EC_KEY`, so that keys held
+        // by OpenSSL providers, e.g. in a smartcard, work too.
+        let mut ctx = PkeyCtx::new(&self.private_key)?;
+        ctx.sign_init()?;
+        let mut der = Vec::new();
+        ctx.sign_to_vec(digest, &mut der)?;
+        let mut sig = EcdsaSig::from_der(&der)?;
+        if self.low_s {
+            let mut ctx = BigNumContext::new()?;
+            let mut n = BigNum::new()?;
+            EcGroup::from_curve_name(self.algorithm.curve())?.order(&mut n, &mut ctx)?;
+            if !is_low_s(sig.s(), &n)? {
+                let mut s = BigNum::new()?;
+                s.checked_sub(&n, sig.s())?;
+                sig = EcdsaSig::from_private_components(sig.r().to_owned()?, s)?;
+            }
+        }
 
-        let s = sig.s();
-        let s_len = s.num_bytes() as usize;
-        debug_assert!(s_len <= sig_len / 2);
+        to_raw(&sig, self.algorithm)
+    }
+}
 
-        unsafe { BN_bn2bin(r.as_ptr(), out[sig_len / 2 - r_len..].as_mut_ptr()) };
-        unsafe { BN_bn2bin(s.as_ptr(), out[sig_len - s_len..].as_mut_ptr()) };
+/// `r || s`, each padded to the full size.
+fn to_raw(sig: &EcdsaSigRef, algorithm: EcdsaAlgorithm) -> Result<SmallVec<[u8; 64]>> {
+    let sig_len = algorithm.len();
+    let mut out = smallvec![0u8; sig_len];
 
-        Ok(out)
+    let r = sig.r();
+    let r_len = r.num_bytes() as usize;
+    let s = sig.s();
+    let s_len = s.num_bytes() as usize;
+    if r_len > sig_len / 2 || s_len > sig_len / 2 {
+        return Err(Error::VerificationError);
     }
 
-    fn alg(&self) -> &'static str {
-        self.algorithm.name()
+    unsafe { BN_bn2bin(r.as_ptr(), out[sig_len / 2 - r_len..].as_mut_ptr()) };
+    unsafe { BN_bn2bin(s.as_ptr(), out[sig_len - s_len..].as_mut_ptr()) };
+
+    Ok(out)
+}
+
+/// Convert an ASN.1 DER encoded ECDSA signature, as returned by most HSMs,
+/// KMSes and `openssl dgst`, to the JWS `r || s` format.
+pub fn signature_from_der(der: &[u8], algorithm: EcdsaAlgorithm) -> Result<SmallVec<[u8; 64]>> {
+    to_raw(&*EcdsaSig::from_der(der)?, algorithm)
+}
+
+/// Convert a JWS `r || s` ECDSA signature to ASN.1 DER.
+pub fn signature_to_der(sig: &[u8], algorithm: EcdsaAlgorithm) -> Result<Vec<u8>> {
+    if sig.len() != algorithm.len() {
+        return Err(Error::VerificationError);
     }
+    let (r, s) = sig.split_at(algorithm.len() / 2);
+    let sig = EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?;
+    Ok(sig.to_der()?)
+}
+
+fn is_low_s(s: &BigNumRef, n: &BigNumRef) -> Result<bool> {
+    let mut half = BigNum::new()?;
+    half.rshift1(n)?;
+    Ok(s <= &*half)
 }
 
-fn ecdsa_verify<T: HasPublic>(
+fn ecdsa_verify_digest<T: HasPublic>(
     alg: EcdsaAlgorithm,
     k: &PKeyRef<T>,
-    v: &[u8],
+    digest: &[u8],
     sig: &[u8],
+    low_s: bool,
 ) -> Result<()> {
     if sig.len() != alg.len() {
         return Err(Error::VerificationError);
@@ -356,14 +505,73 @@ fn ecdsa_verify<T: HasPublic>(
     // There may be some leading zero bytes in r and s, but it does not matter.
     let (r, s) = sig.split_at(alg.len() / 2);
     let sig = EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?;
-    let hash = hash(alg.digest(), v)?;
-    if sig.verify(&hash, k.ec_key()?.as_ref())? {
+    let ec_key = k.ec_key()?;
+    if low_s {
+        let mut ctx = BigNumContext::new()?;
+        let mut n = BigNum::new()?;
+        ec_key.group().order(&mut n, &mut ctx)?;
+        if !is_low_s(sig.s(), &n)? {
+            return Err(Error::VerificationError);
+        }
+    }
+    if sig.verify(digest, ec_key.as_ref())? {
         Ok(())
     } else {
         Err(Error::VerificationError)
     }
 }
 
+fn check_digest_len(alg: EcdsaAlgorithm, digest: &[u8]) -> Result<()> {
+    if digest.len() == alg.digest().size() {
+        Ok(())
+    } else {
+        Err(Error::InvalidDigest)
+    }
+}
+
+fn ecdsa_verify_stream<T: HasPublic>(
+    alg: EcdsaAlgorithm,
+    k: &PKeyRef<T>,
+    low_s: bool,
+) -> Result<VerifyStream<'_>> {
+    let hasher = Hasher::new(alg.digest())?;
+    Ok(VerifyStream::digest(hasher, move |digest, sig| {
+        ecdsa_verify_digest(alg, k, digest, sig, low_s)
+    }))
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl EcdsaPrivateKey {
+    /// Verify a signature over a digest of the JWS signing input, see
+    /// [`sign_digest`](Self::sign_digest).
+    pub fn verify_digest(&self, digest: &[u8], sig: &[u8]) -> Result<()> {
+        check_digest_len(self.algorithm, digest)?;
+        ecdsa_verify_digest(
+            self.algorithm,
+            self.private_key.as_ref(),
+            digest,
+            sig,
+            self.low_s,
+        )
+    }
+}
+
+impl EcdsaPublicKey {
+    /// Verify a signature over a digest of the JWS signing input, see
+    /// [`EcdsaPrivateKey::sign_digest`].
+    pub fn verify_digest(&self, digest: &[u8], sig: &[u8]) -> Result<()> {
+        check_digest_len(self.algorithm, digest)?;
+        ecdsa_verify_digest(
+            self.algorithm,
+            self.public_key.as_ref(),
+            digest,
+            sig,
+            self.low_s,
+        )
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
 impl VerificationKey for EcdsaPrivateKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
         // ECDSA keys can handle only one algorithm.
@@ -371,7 +579,22 @@ impl VerificationKey for EcdsaPrivateKey {
             return Err(Error::VerificationError);
         }
 
-        ecdsa_verify(self.algorithm, self.private_key.as_ref(), v, sig)
+        let digest = hash(self.algorithm.digest(), v)?;
+        ecdsa_verify_digest(
+            self.algorithm,
+            self.private_key.as_ref(),
+            &digest,
+            sig,
+            self.low_s,
+        )
+    }
+
+    fn verify_stream(&self, alg: &str) -> Result<VerifyStream<'_>> {
+        if alg != self.algorithm.name() {
+            return Err(Error::VerificationError);
+        }
+
+        ecdsa_verify_stream(self.algorithm, self.private_key.as_ref(), self.low_s)
     }
 }
 
@@ -381,17 +604,133 @@ impl VerificationKey for EcdsaPublicKey {
             return Err(Error::VerificationError);
         }
 
-        ecdsa_verify(self.algorithm, self.public_key.as_ref(), v, sig)
+        let digest = hash(self.algorithm.digest(), v)?;
+        ecdsa_verify_digest(
+            self.algorithm,
+            self.public_key.as_ref(),
+            &digest,
+            sig,
+            self.low_s,
+        )
+    }
+
+    fn verify_stream(&self, alg: &str) -> Result<VerifyStream<'_>> {
+        if alg != self.algorithm.name() {
+            return Err(Error::VerificationError);
+        }
+
+        ecdsa_verify_stream(self.algorithm, self.public_key.as_ref(), self.low_s)
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "verify-only")))]
 mod tests {
     use crate::{rsa::RsaAlgorithm, SomePrivateKey};
 
     use super::*;
 
     #[test]
+    fn low_s() -> Result<()> {
+        let mut k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256K)?;
+        let mut pk = EcdsaPublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+        let mut ctx = BigNumContext::new()?;
+        let mut n = BigNum::new()?;
+        k.private_key.ec_key()?.group().order(&mut n, &mut ctx)?;
+
+        // Flip s of a signature to get a valid one with the other form.
+        let flip = |sig: &[u8]| -> Result<Vec<u8>> {
+            let s = BigNum::from_slice(&sig[32..])?;
+            let mut s1 = BigNum::new()?;
+            s1.checked_sub(&n, &s)?;
+            let mut out = sig[..32].to_vec();
+            let mut s1 = s1.to_vec();
+            pad_left(&mut s1, 32);
+            out.extend_from_slice(&s1);
+            Ok(out)
+        };
+
+        let sig = k.sign(b"...")?;
+        let sig1 = flip(&sig)?;
+        pk.verify(b"...", &sig, "ES256K")?;
+        pk.verify(b"...", &sig1, "ES256K")?;
+
+        k.set_low_s(true);
+        pk.set_low_s(true);
+        for _ in 0..8 {
+            let sig = k.sign(b"...")?;
+            assert!(is_low_s(&*BigNum::from_slice(&sig[32..])?, &n)?);
+            pk.verify(b"...", &sig, "ES256K")?;
+            assert!(pk.verify(b"...", &flip(&sig)?, "ES256K").is_err());
+            assert!(k.verify(b"...", &flip(&sig)?, "ES256K").is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn der_conversion() -> Result<()> {
+        for alg in [EcdsaAlgorithm::ES256, EcdsaAlgorithm::ES512] {
+            let k = EcdsaPrivateKey::generate(alg)?;
+            // OpenSSL's high level API produces DER signatures.
+            let mut signer = openssl::sign::Signer::new(alg.digest(), &k.private_key)?;
+            let der = signer.sign_oneshot_to_vec(b"...")?;
+
+            let raw = signature_from_der(&der, alg)?;
+            assert_eq!(raw.len(), alg.len());
+            k.verify(b"...", &raw, alg.name())?;
+            assert_eq!(signature_to_der(&raw, alg)?, der);
+        }
+
+        // Short r and s are padded.
+        let sig = EcdsaSig::from_private_components(BigNum::from_u32(1)?, BigNum::from_u32(2)?)?;
+        let raw = signature_from_der(&sig.to_der()?, EcdsaAlgorithm::ES256)?;
+        assert_eq!(raw[31], 1);
+        assert_eq!(raw[63], 2);
+        assert!(raw[..31].iter().all(|b| *b == 0));
+
+        let der = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?;
+        let mut signer = openssl::sign::Signer::new(MessageDigest::sha384(), &der.private_key)?;
+        let der = signer.sign_oneshot_to_vec(b"...")?;
+        assert!(signature_from_der(&der, EcdsaAlgorithm::ES256).is_err());
+        assert!(signature_to_der(&[0; 63], EcdsaAlgorithm::ES256).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_points() -> Result<()> {
+        for alg in [
+            EcdsaAlgorithm::ES256,
+            EcdsaAlgorithm::ES256K,
+            EcdsaAlgorithm::ES384,
+            EcdsaAlgorithm::ES512,
+        ] {
+            let (x, y) = EcdsaPrivateKey::generate(alg)?.coordinates()?;
+            EcdsaPublicKey::from_coordinates(&x, &y, alg)?;
+
+            let invalid = |x: &[u8], y: &[u8]| {
+                matches!(
+                    EcdsaPublicKey::from_coordinates(x, y, alg),
+                    Err(Error::InvalidPoint)
+                )
+            };
+            // Not on the curve.
+            let mut y1 = y.clone();
+            *y1.last_mut().unwrap() ^= 1;
+            assert!(invalid(&x, &y1));
+            // Identity / zero coordinates.
+            assert!(invalid(&[0], &[0]));
+            // Too long.
+            let mut x1 = x.clone();
+            x1.insert(0, 1);
+            assert!(invalid(&x1, &y));
+            // Coordinate not reduced modulo p.
+            let ff = vec![0xff; alg.len() / 2];
+            assert!(invalid(&ff, &y));
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[allow(irrefutable_let_patterns)]
     fn conversion() -> Result<()> {
         let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
         let pem = k.private_key_to_pem_pkcs8()?;
@@ -462,4 +801,21 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn digest() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?;
+        let pk = EcdsaPublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+        let digest = hash(MessageDigest::sha384(), b"...")?;
+        let sig = k.sign_digest(&digest)?;
+        pk.verify(b"...", &sig, "ES384")?;
+        pk.verify_digest(&digest, &k.sign(b"...")?)?;
+        k.verify_digest(&digest, &sig)?;
+        assert!(pk.verify_digest(&digest, &sig[1..]).is_err());
+        assert!(matches!(
+            k.sign_digest(&digest[..32]),
+            Err(Error::InvalidDigest)
+        ));
+        Ok(())
+    }
 }
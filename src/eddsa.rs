@@ -1,23 +1,41 @@
+//! Ed25519 signatures.
+//!
+//! Verification is RFC 8032 strict and cofactorless: a signature `(R, S)` is
+//! accepted iff `[S]B = R + [k]A`, and additionally
+//!
+//! * `S` must be canonical, i.e. less than the group order `L`,
+//! * `A` and `R` must be canonically encoded, i.e. `y < p`,
+//! * `A` and `R` must not be of small order.
+//!
+//! So every message has at most one valid signature per key, and no signature
+//! validates for more than one message by way of a small order key. Public
+//! keys are checked on import, `R` and `S` on every verification.
+
 use std::ptr;
 
 use foreign_types::ForeignType;
 use openssl::{
+    bn::{BigNum, BigNumContext},
     error::ErrorStack,
-    pkey::{PKey, Private, Public},
-    sign::{Signer, Verifier},
+    pkey::{PKey, Public},
+    sign::Verifier,
 };
+#[cfg(not(feature = "verify-only"))]
+use openssl::{pkey::Private, sign::Signer};
+#[cfg(not(feature = "verify-only"))]
 use smallvec::SmallVec;
 
-use crate::{
-    jwk::Jwk, url_safe_trailing_bits, Error, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey,
-    VerificationKey,
-};
+use crate::{b64, jwk::Jwk, Error, Fingerprint, PublicKeyToJwk, Result, VerificationKey};
+#[cfg(not(feature = "verify-only"))]
+use crate::{PrivateKeyToJwk, SigningKey};
 
+#[cfg(not(feature = "verify-only"))]
 #[derive(Debug, Clone)]
 pub struct Ed25519PrivateKey {
     private_key: PKey<Private>,
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl Ed25519PrivateKey {
     pub fn generate() -> Result<Self> {
         let pkey = PKey::generate_ed25519()?;
@@ -48,6 +66,13 @@ impl Ed25519PrivateKey {
         Ok(Self { private_key: pk })
     }
 
+    /// A key held by an OpenSSL provider, whose type has already been
+    /// checked.
+    #[cfg(feature = "openssl-store")]
+    pub(crate) fn from_provider_pkey(pk: PKey<Private>) -> Self {
+        Self { private_key: pk }
+    }
+
     pub fn from_pem(pem: &[u8]) -> Result<Self> {
         let pk = PKey::private_key_from_pem(pem)?;
         Self::from_pkey(pk)
@@ -92,20 +117,32 @@ impl Ed25519PrivateKey {
     pub fn public_key_to_pem(&self) -> Result<String> {
         Ok(String::from_utf8(self.private_key.public_key_to_pem()?)?)
     }
+
+    /// PKCS#8 DER.
+    pub fn private_key_to_der_pkcs8(&self) -> Result<Vec<u8>> {
+        Ok(self.private_key.private_key_to_pkcs8()?)
+    }
+
+    /// SubjectPublicKeyInfo DER.
+    pub fn public_key_to_der(&self) -> Result<Vec<u8>> {
+        Ok(self.private_key.public_key_to_der()?)
+    }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl PublicKeyToJwk for Ed25519PrivateKey {
     fn public_key_to_jwk(&self) -> Result<Jwk> {
         let bytes: [u8; 32] = self.public_key_bytes()?;
         Ok(Jwk {
             kty: "OKP".into(),
             crv: Some("Ed25519".into()),
-            x: Some(base64::encode_config(bytes, url_safe_trailing_bits())),
+            x: Some(b64::encode_url(bytes)),
             ..Jwk::default()
         })
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl PrivateKeyToJwk for Ed25519PrivateKey {
     fn private_key_to_jwk(&self) -> Result<Jwk> {
         let d = self.private_key_bytes()?;
@@ -113,24 +150,38 @@ impl PrivateKeyToJwk for Ed25519PrivateKey {
         Ok(Jwk {
             kty: "OKP".into(),
             crv: Some("Ed25519".into()),
-            d: Some(base64::encode_config(d, url_safe_trailing_bits())),
-            x: Some(base64::encode_config(x, url_safe_trailing_bits())),
+            d: Some(b64::encode_url(d)),
+            x: Some(b64::encode_url(x)),
             ..Jwk::default()
         })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Ed25519PublicKey {
     public_key: PKey<Public>,
 }
 
 impl Ed25519PublicKey {
+    /// See [`Fingerprint`].
+    pub fn fingerprint_sha256(&self) -> Result<Fingerprint> {
+        Fingerprint::of_spki_der(&self.public_key.public_key_to_der()?)
+    }
+
+    /// Whether `other` is the same key.
+    pub fn key_eq(&self, other: &Self) -> bool {
+        self.public_key.public_eq(&other.public_key)
+    }
+
     pub(crate) fn from_pkey(pkey: PKey<Public>) -> Result<Self> {
         if pkey.id() != openssl::pkey::Id::ED25519 {
             return Err(Error::UnsupportedOrInvalidKey);
         }
-        Ok(Self { public_key: pkey })
+        let k = Self { public_key: pkey };
+        if !is_valid_point(&k.to_bytes()?)? {
+            return Err(Error::InvalidPoint);
+        }
+        Ok(k)
     }
 
     pub fn from_pem(pem: &[u8]) -> Result<Self> {
@@ -138,7 +189,12 @@ impl Ed25519PublicKey {
         Self::from_pkey(pk)
     }
 
+    /// Returns `Error::InvalidPoint` for non-canonical encodings and small
+    /// order points.
     pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        if b.len() == 32 && !is_valid_point(b)? {
+            return Err(Error::InvalidPoint);
+        }
         let pkey = unsafe {
             openssl_sys::EVP_PKEY_new_raw_public_key(
                 openssl_sys::EVP_PKEY_ED25519,
@@ -181,12 +237,13 @@ impl PublicKeyToJwk for Ed25519PublicKey {
         Ok(Jwk {
             kty: "OKP".into(),
             crv: Some("Ed25519".into()),
-            x: Some(base64::encode_config(bytes, url_safe_trailing_bits())),
+            x: Some(b64::encode_url(bytes)),
             ..Jwk::default()
         })
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl SigningKey for Ed25519PrivateKey {
     fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>> {
         let mut signer = Signer::new_without_digest(self.private_key.as_ref())?;
@@ -203,12 +260,14 @@ impl SigningKey for Ed25519PrivateKey {
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl VerificationKey for Ed25519PrivateKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
         if alg != "EdDSA" {
             return Err(Error::VerificationError);
         }
 
+        check_signature(sig)?;
         let mut verifier = Verifier::new_without_digest(self.private_key.as_ref())?;
         if verifier.verify_oneshot(sig, v)? {
             Ok(())
@@ -224,6 +283,7 @@ impl VerificationKey for Ed25519PublicKey {
             return Err(Error::VerificationError);
         }
 
+        check_signature(sig)?;
         let mut verifier = Verifier::new_without_digest(self.public_key.as_ref())?;
         if verifier.verify_oneshot(sig, v)? {
             Ok(())
@@ -233,7 +293,84 @@ impl VerificationKey for Ed25519PublicKey {
     }
 }
 
-#[cfg(test)]
+// Field prime p = 2^255 - 19.
+const P: &str = "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed";
+// Curve constant d = -121665 / 121666 mod p.
+const D: &str = "52036cee2b6ffe738cc740797779e89800700a4d4141d8ab75eb4dca135978a3";
+// Group order L = 2^252 + 27742317777372353535851937790883648493.
+const L: &str = "1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed";
+
+/// Little endian bytes to `BigNum`.
+fn from_le(b: &[u8]) -> Result<BigNum> {
+    let mut be = b.to_vec();
+    be.reverse();
+    Ok(BigNum::from_slice(&be)?)
+}
+
+fn check_signature(sig: &[u8]) -> Result<()> {
+    if sig.len() != 64
+        || !is_valid_point(&sig[..32])?
+        || from_le(&sig[32..])? >= BigNum::from_hex_str(L)?
+    {
+        return Err(Error::VerificationError);
+    }
+    Ok(())
+}
+
+/// Whether `b` is a canonical encoding of a point that is not of small order.
+///
+/// A point `P` has small order iff `[8]P` is the identity. The y coordinate
+/// of `[2]P` only depends on the y coordinate of `P`, so this never needs to
+/// recover x. Encodings that are not on the curve are left to OpenSSL.
+fn is_valid_point(b: &[u8]) -> Result<bool> {
+    let mut y = b.to_vec();
+    y[31] &= 0x7f;
+    let mut y = from_le(&y)?;
+
+    let p = BigNum::from_hex_str(P)?;
+    if y >= p {
+        return Ok(false);
+    }
+
+    let d = BigNum::from_hex_str(D)?;
+    let one = BigNum::from_u32(1)?;
+    let two = BigNum::from_u32(2)?;
+    let mut ctx = BigNumContext::new()?;
+    for _ in 0..3 {
+        // From -x^2 + y^2 = 1 + d x^2 y^2:
+        //   x^2 = (y^2 - 1) / (d y^2 + 1)
+        // and doubling:
+        //   y' = (y^2 + x^2) / (2 + x^2 - y^2)
+        let mut y2 = BigNum::new()?;
+        y2.mod_sqr(&y, &p, &mut ctx)?;
+        let mut t = BigNum::new()?;
+        t.mod_sub(&y2, &one, &p, &mut ctx)?;
+        let mut u = BigNum::new()?;
+        u.mod_mul(&d, &y2, &p, &mut ctx)?;
+        let mut u1 = BigNum::new()?;
+        u1.mod_add(&u, &one, &p, &mut ctx)?;
+        let mut inv = BigNum::new()?;
+        if inv.mod_inverse(&u1, &p, &mut ctx).is_err() {
+            return Ok(false);
+        }
+        let mut x2 = BigNum::new()?;
+        x2.mod_mul(&t, &inv, &p, &mut ctx)?;
+
+        let mut num = BigNum::new()?;
+        num.mod_add(&y2, &x2, &p, &mut ctx)?;
+        let mut den = BigNum::new()?;
+        den.mod_add(&two, &x2, &p, &mut ctx)?;
+        let mut den1 = BigNum::new()?;
+        den1.mod_sub(&den, &y2, &p, &mut ctx)?;
+        if inv.mod_inverse(&den1, &p, &mut ctx).is_err() {
+            return Ok(false);
+        }
+        y.mod_mul(&num, &inv, &p, &mut ctx)?;
+    }
+    Ok(y != one)
+}
+
+#[cfg(all(test, not(feature = "verify-only")))]
 mod tests {
     use openssl::{
         ec::{EcGroup, EcKey},
@@ -245,6 +382,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(irrefutable_let_patterns)]
     fn conversion() -> Result<()> {
         let k = Ed25519PrivateKey::generate()?;
 
@@ -289,6 +427,71 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn constants() -> Result<()> {
+        let mut ctx = BigNumContext::new()?;
+        let p = BigNum::from_hex_str(P)?;
+        let d = BigNum::from_hex_str(D)?;
+        let zero = BigNum::new()?;
+        let a = BigNum::from_u32(121665)?;
+        let b = BigNum::from_u32(121666)?;
+        let mut lhs = BigNum::new()?;
+        lhs.mod_mul(&d, &b, &p, &mut ctx)?;
+        let mut rhs = BigNum::new()?;
+        rhs.mod_sub(&zero, &a, &p, &mut ctx)?;
+        assert_eq!(lhs, rhs);
+        Ok(())
+    }
+
+    #[test]
+    fn small_order_and_non_canonical() -> Result<()> {
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        // y = -1, order 2.
+        let mut order2 = [0xffu8; 32];
+        order2[0] = 0xec;
+        order2[31] = 0x7f;
+        // y = 0, order 4.
+        let order4 = [0u8; 32];
+        // y = p + 1, a non-canonical identity.
+        let mut p1 = order2;
+        p1[0] = 0xee;
+        // Order 8, from the libsodium blocklist.
+        let order8 = [
+            0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0, 0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef,
+            0x98, 0xf0, 0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39, 0xb1, 0x38, 0x02, 0x88,
+            0x6d, 0x53, 0xfc, 0x05,
+        ];
+        for b in [identity, order2, order4, p1, order8] {
+            assert!(!is_valid_point(&b)?);
+            assert!(matches!(
+                Ed25519PublicKey::from_bytes(&b),
+                Err(Error::InvalidPoint)
+            ));
+        }
+
+        let k = Ed25519PrivateKey::generate()?;
+        let pk = Ed25519PublicKey::from_bytes(&k.public_key_bytes()?)?;
+        let mut sig = k.sign(b"...")?;
+        pk.verify(b"...", &sig, "EdDSA")?;
+
+        // S + L.
+        let l = BigNum::from_hex_str(L)?;
+        let s0 = from_le(&sig[32..])?;
+        let mut s = BigNum::new()?;
+        s.checked_add(&s0, &l)?;
+        let mut s = s.to_vec();
+        s.reverse();
+        s.resize(32, 0);
+        let mut malleated = sig.clone();
+        malleated[32..].copy_from_slice(&s);
+        assert!(pk.verify(b"...", &malleated, "EdDSA").is_err());
+
+        sig[..32].copy_from_slice(&identity);
+        assert!(pk.verify(b"...", &sig, "EdDSA").is_err());
+        Ok(())
+    }
+
     #[test]
     fn sign_verify() -> Result<()> {
         let k = Ed25519PrivateKey::generate()?;
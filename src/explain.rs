@@ -0,0 +1,197 @@
+//! Human readable breakdown of tokens, for debugging and support tickets.
+//!
+//! ```
+//! # use jwtk::{explain::explain, sign, HeaderAndClaims, hmac::{HmacKey, HmacAlgorithm}};
+//! # let k = HmacKey::generate(HmacAlgorithm::HS256)?;
+//! # let token = sign(HeaderAndClaims::new_dynamic().set_sub("alice"), &k)?;
+//! let explanation = explain(&token)?;
+//! assert_eq!(explanation.signature_len, 32);
+//! println!("{}", explanation);
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{b64, decode_header, decode_segment, split_token, Header, Result, ValidationOptions};
+
+/// The parts of a token, see [`explain`].
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct Explanation {
+    pub header: Header,
+    pub claims: Map<String, Value>,
+    /// `exp`, `nbf` and `iat`, if present and valid.
+    pub times: Vec<Time>,
+    /// Length of the decoded signature.
+    pub signature_len: usize,
+    /// Length of the compact serialization.
+    pub token_len: usize,
+    /// Length of the decoded header JSON.
+    pub header_len: usize,
+    /// Length of the decoded claims JSON.
+    pub claims_len: usize,
+}
+
+/// A time claim.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct Time {
+    pub claim: &'static str,
+    /// Seconds since the epoch.
+    pub seconds: u64,
+    /// RFC 3339 in UTC, e.g. `2023-11-14T22:13:20Z`.
+    pub rfc3339: String,
+}
+
+/// Decode `token` without verifying it, and break it down.
+///
+/// Unlike [`decode_without_verify`](crate::decode_without_verify), claims
+/// are not checked, e.g. an `exp` that isn't a number is shown as is.
+pub fn explain(token: &str) -> Result<Explanation> {
+    let options = ValidationOptions::default();
+    options.check_token_len(token)?;
+    let (header_segment, payload, sig) = split_token(token)?;
+    let header = decode_header(header_segment, &options)?;
+    let claims: Map<String, Value> = decode_segment(
+        payload,
+        options.max_claims_len,
+        options.max_depth,
+        options.reject_duplicate_claim_keys,
+    )?;
+
+    let times = ["exp", "nbf", "iat"]
+        .iter()
+        .filter_map(|&claim| {
+            let seconds = claims.get(claim)?.as_f64()?;
+            // Up to 9999-12-31T23:59:59Z.
+            if !(0.0..253_402_300_800.0).contains(&seconds) {
+                return None;
+            }
+            let seconds = seconds as u64;
+            Some(Time {
+                claim,
+                seconds,
+                rfc3339: rfc3339(seconds),
+            })
+        })
+        .collect();
+
+    Ok(Explanation {
+        header,
+        claims,
+        times,
+        signature_len: b64::decode_url(sig)?.len(),
+        token_len: token.len(),
+        header_len: decoded_len(header_segment),
+        claims_len: decoded_len(payload),
+    })
+}
+
+fn decoded_len(segment: &str) -> usize {
+    segment.len() * 3 / 4
+}
+
+/// `seconds` since the epoch as `YYYY-MM-DDThh:mm:ssZ`.
+fn rfc3339(seconds: u64) -> String {
+    // Days to civil date, from http://howardhinnant.github.io/date_algorithms.html
+    let z = seconds / 86400 + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    let s = seconds % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        s / 3600,
+        s / 60 % 60,
+        s % 60
+    )
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = serde_json::to_string_pretty(&self.header).map_err(|_| fmt::Error)?;
+        let claims = serde_json::to_string_pretty(&self.claims).map_err(|_| fmt::Error)?;
+        writeln!(f, "Header: {}", header)?;
+        writeln!(f, "Claims: {}", claims)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        for t in &self.times {
+            let relative = if t.seconds >= now {
+                format!("in {}", human(t.seconds - now))
+            } else {
+                format!("{} ago", human(now - t.seconds))
+            };
+            writeln!(f, "{}: {} ({})", t.claim, t.rfc3339, relative)?;
+        }
+        writeln!(f, "Signature: {} bytes", self.signature_len)?;
+        write!(
+            f,
+            "Size: {} bytes, decoded header {} bytes, claims {} bytes",
+            self.token_len, self.header_len, self.claims_len
+        )
+    }
+}
+
+/// A duration in seconds, roughly.
+fn human(seconds: u64) -> String {
+    match seconds {
+        0..=119 => format!("{} s", seconds),
+        120..=7199 => format!("{} min", seconds / 60),
+        7200..=172_799 => format!("{} h", seconds / 3600),
+        _ => format!("{} days", seconds / 86400),
+    }
+}
+
+#[cfg(all(test, feature = "eddsa", not(feature = "verify-only")))]
+mod tests {
+    use crate::{eddsa::Ed25519PrivateKey, sign, HeaderAndClaims};
+
+    use super::*;
+
+    #[test]
+    fn rfc3339_dates() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(rfc3339(253_402_300_799), "9999-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn explain_token() -> Result<()> {
+        let k = Ed25519PrivateKey::generate()?;
+        let mut token = HeaderAndClaims::new_dynamic();
+        token
+            .set_sub("alice")
+            .insert("exp", 1_700_000_000)
+            .insert("nbf", "not a number");
+        let token = sign(&mut token, &k)?;
+
+        let e = explain(&token)?;
+        assert_eq!(e.header.alg, "EdDSA");
+        assert_eq!(e.claims["sub"], "alice");
+        assert_eq!(e.times.len(), 1);
+        assert_eq!(e.times[0].rfc3339, "2023-11-14T22:13:20Z");
+        assert_eq!(e.signature_len, 64);
+        assert_eq!(e.token_len, token.len());
+        let text = e.to_string();
+        assert!(text.contains("exp: 2023-11-14T22:13:20Z (") && text.contains(" days ago)"));
+        assert!(explain("a.b").is_err());
+        Ok(())
+    }
+}
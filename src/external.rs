@@ -0,0 +1,102 @@
+//! Signing with keys that live outside of OpenSSL.
+//!
+//! Platform key stores such as the Apple Secure Enclave or the Windows CNG
+//! key storage provider keep the private key in hardware and sign on
+//! request. [`ExternalKey`] turns such a signing function into a
+//! [`SigningKey`], e.g. for the Secure Enclave, which returns DER encoded
+//! ECDSA signatures:
+//!
+//! ```ignore
+//! use jwtk::{ecdsa::{signature_from_der, EcdsaAlgorithm}, external::ExternalKey};
+//!
+//! let k = ExternalKey::new("ES256", |signing_input| {
+//!     // SecKeyCreateSignature(key, kSecKeyAlgorithmECDSASignatureMessageX962SHA256, ...)
+//!     let der = secure_enclave_sign(signing_input)?;
+//!     Ok(signature_from_der(&der, EcdsaAlgorithm::ES256)?.to_vec())
+//! });
+//! ```
+//!
+//! Wrap it in a [`WithKid`](crate::jwk::WithKid) to set a key id. Verify
+//! the tokens with the public key, exported from the key store.
+
+use std::fmt;
+
+use smallvec::SmallVec;
+
+use crate::{Result, SigningKey};
+
+/// A [`SigningKey`] that calls a function to sign.
+///
+/// The function gets the JWS signing input, and must return the signature in
+/// JWS format for `alg`, e.g. `r || s` for ECDSA.
+pub struct ExternalKey<F> {
+    alg: &'static str,
+    sign: F,
+}
+
+impl<F> ExternalKey<F>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    pub fn new(alg: &'static str, sign: F) -> Self {
+        Self { alg, sign }
+    }
+}
+
+impl<F> SigningKey for ExternalKey<F>
+where
+    F: Fn(&[u8]) -> Result<Vec<u8>>,
+{
+    fn alg(&self) -> &'static str {
+        self.alg
+    }
+
+    fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>> {
+        Ok((self.sign)(v)?.into())
+    }
+}
+
+impl<F> fmt::Debug for ExternalKey<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalKey")
+            .field("alg", &self.alg)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa"))]
+mod tests {
+    use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+
+    use crate::{
+        ecdsa::{signature_from_der, EcdsaAlgorithm, EcdsaPrivateKey, EcdsaPublicKey},
+        jwk::WithKid,
+        sign, verify, Error, HeaderAndClaims,
+    };
+
+    use super::*;
+
+    #[test]
+    fn external_key() -> Result<()> {
+        // Stands in for a key store that signs with the DER format.
+        let pem = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?.private_key_to_pem_pkcs8()?;
+        let store = PKey::private_key_from_pem(pem.as_bytes())?;
+        let pk = EcdsaPublicKey::from_pem(&store.public_key_to_pem()?)?;
+
+        let k = WithKid::new(
+            "enclave".into(),
+            ExternalKey::new("ES256", |v: &[u8]| {
+                let mut signer = Signer::new(MessageDigest::sha256(), &store)?;
+                let der = signer.sign_oneshot_to_vec(v)?;
+                Ok(signature_from_der(&der, EcdsaAlgorithm::ES256)?.to_vec())
+            }),
+        );
+        let token = sign(HeaderAndClaims::new_dynamic().set_sub("device"), &k)?;
+        let verified = verify::<serde_json::Value>(&token, &pk)?;
+        assert_eq!(verified.header().kid.as_deref(), Some("enclave"));
+
+        let failing = ExternalKey::new("ES256", |_: &[u8]| Err(Error::UnsupportedOrInvalidKey));
+        assert!(sign(&mut HeaderAndClaims::new_dynamic(), &failing).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,475 @@
+//! C ABI, for use from e.g. Kotlin (JNA), Swift and C based proxies, see
+//! `include/jwtk.h`.
+//!
+//! Build a shared or static library with the `ffi` feature:
+//!
+//! ```sh
+//! cargo rustc --release --features ffi --lib --crate-type cdylib
+//! ```
+//!
+//! The header is generated with [cbindgen](https://github.com/mozilla/cbindgen):
+//!
+//! ```sh
+//! cbindgen --config cbindgen.toml --output include/jwtk.h src/ffi.rs
+//! ```
+//!
+//! All strings are NUL terminated UTF-8. Functions return `JWTK_OK` or an
+//! error code, and pass results out through pointers. Strings returned this
+//! way must be freed with [`jwtk_string_free`].
+//!
+//! Keys and verifiers that are used for many tokens can be parsed once into
+//! opaque handles, [`JwtkKey`] and [`JwtkVerifier`]. Handles are immutable,
+//! and may be used from several threads at once.
+//!
+//! Panics don't unwind into the caller, functions return `JWTK_PANIC`
+//! instead.
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    jwk::{Jwk, JwkSet, JwkSetVerifier},
+    rsa::RsaAlgorithm,
+    sign, verify, Error, HeaderAndClaims, PublicKeyToJwk, SomePrivateKey, SomePublicKey,
+};
+
+pub const JWTK_OK: c_int = 0;
+pub const JWTK_INVALID_ARGUMENT: c_int = 1;
+pub const JWTK_INVALID_TOKEN: c_int = 2;
+pub const JWTK_VERIFICATION_FAILED: c_int = 3;
+pub const JWTK_EXPIRED: c_int = 4;
+pub const JWTK_NOT_YET_VALID: c_int = 5;
+pub const JWTK_NO_KEY: c_int = 6;
+pub const JWTK_INVALID_KEY: c_int = 7;
+pub const JWTK_PANIC: c_int = 98;
+pub const JWTK_OTHER: c_int = 99;
+
+/// A private key, see [`jwtk_key_new`].
+pub struct JwtkKey(SomePrivateKey);
+
+/// A public key or JWK Set, see [`jwtk_verifier_from_key`] and
+/// [`jwtk_verifier_from_jwks`].
+pub struct JwtkVerifier(Keys);
+
+enum Keys {
+    Key(SomePublicKey),
+    Jwks(Box<JwkSetVerifier>),
+}
+
+fn error_code(e: &Error) -> c_int {
+    match e {
+        Error::InvalidToken | Error::Decode(_) | Error::SerdeJson(_) | Error::TooLarge => {
+            JWTK_INVALID_TOKEN
+        }
+        Error::VerificationError | Error::AlgMismatch => JWTK_VERIFICATION_FAILED,
+        Error::Expired => JWTK_EXPIRED,
+        Error::Before => JWTK_NOT_YET_VALID,
+        Error::NoKid | Error::NoKey => JWTK_NO_KEY,
+        Error::UnsupportedOrInvalidKey | Error::InvalidPoint => JWTK_INVALID_KEY,
+        _ => JWTK_OTHER,
+    }
+}
+
+unsafe fn arg<'a>(s: *const c_char) -> Result<&'a str, c_int> {
+    if s.is_null() {
+        return Err(JWTK_INVALID_ARGUMENT);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| JWTK_INVALID_ARGUMENT)
+}
+
+unsafe fn handle<'a, T>(h: *const T) -> Result<&'a T, c_int> {
+    h.as_ref().ok_or(JWTK_INVALID_ARGUMENT)
+}
+
+/// Run `f`, turning panics into `JWTK_PANIC`.
+fn guard<T>(f: impl FnOnce() -> Result<T, c_int>) -> Result<T, c_int> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(Err(JWTK_PANIC))
+}
+
+unsafe fn output<T>(out: *mut *mut T, result: Result<*mut T, c_int>) -> c_int {
+    match result {
+        Ok(p) => {
+            *out = p;
+            JWTK_OK
+        }
+        Err(code) => {
+            *out = ptr::null_mut();
+            code
+        }
+    }
+}
+
+unsafe fn output_string(out: *mut *mut c_char, f: impl FnOnce() -> Result<String, c_int>) -> c_int {
+    if out.is_null() {
+        return JWTK_INVALID_ARGUMENT;
+    }
+    let result = guard(|| CString::new(f()?).map_err(|_| JWTK_OTHER));
+    output(out, result.map(CString::into_raw))
+}
+
+unsafe fn output_handle<T>(out: *mut *mut T, f: impl FnOnce() -> Result<T, c_int>) -> c_int {
+    if out.is_null() {
+        return JWTK_INVALID_ARGUMENT;
+    }
+    output(out, guard(f).map(|h| Box::into_raw(Box::new(h))))
+}
+
+fn private_key(key: &str) -> Result<SomePrivateKey, c_int> {
+    SomePrivateKey::from_text(key, RsaAlgorithm::RS256).map_err(|_| JWTK_INVALID_KEY)
+}
+
+fn public_key(key: &str) -> Result<SomePublicKey, c_int> {
+    match serde_json::from_str::<Jwk>(key) {
+        Ok(jwk) => jwk.to_verification_key(),
+        Err(_) => SomePublicKey::from_pem(key.as_bytes()),
+    }
+    .map_err(|_| JWTK_INVALID_KEY)
+}
+
+fn jwks(jwks: &str) -> Result<JwkSetVerifier, c_int> {
+    let jwks: JwkSet = serde_json::from_str(jwks).map_err(|_| JWTK_INVALID_KEY)?;
+    Ok(jwks.verifier())
+}
+
+fn sign_json(k: &SomePrivateKey, claims: &str) -> Result<String, c_int> {
+    let claims = serde_json::from_str(claims).map_err(|_| JWTK_INVALID_ARGUMENT)?;
+    let mut token = HeaderAndClaims::<Map<String, Value>>::new_dynamic();
+    *token.claims_mut() = claims;
+    sign(&mut token, k).map_err(|e| error_code(&e))
+}
+
+fn verify_json(v: &JwtkVerifier, token: &str) -> Result<String, c_int> {
+    let verified = match &v.0 {
+        Keys::Key(k) => verify::<Map<String, Value>>(token, k),
+        Keys::Jwks(jwks) => jwks.verify::<Map<String, Value>>(token),
+    }
+    .map_err(|e| error_code(&e))?;
+    Ok(json!({
+        "header": verified.header(),
+        "claims": verified.claims(),
+    })
+    .to_string())
+}
+
+fn public_jwk_json(k: &SomePrivateKey) -> Result<String, c_int> {
+    let jwk = k.public_key_to_jwk().map_err(|e| error_code(&e))?;
+    serde_json::to_string(&jwk).map_err(|_| JWTK_OTHER)
+}
+
+/// Sign `claims_json`, a JSON object, with `private_key`, PEM or a private
+/// JWK. RSA keys without an algorithm use RS256.
+///
+/// # Safety
+///
+/// Arguments must be valid NUL terminated strings, and `out_token` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_sign(
+    private_key: *const c_char,
+    claims_json: *const c_char,
+    out_token: *mut *mut c_char,
+) -> c_int {
+    output_string(out_token, || {
+        let k = self::private_key(arg(private_key)?)?;
+        sign_json(&k, arg(claims_json)?)
+    })
+}
+
+/// Verify `token` with the keys in `jwks_json`, a JWK Set, and check `exp`
+/// and `nbf`. On success the output is `{"header": {...}, "claims": {...}}`.
+///
+/// # Safety
+///
+/// Arguments must be valid NUL terminated strings, and `out_json` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_verify(
+    token: *const c_char,
+    jwks_json: *const c_char,
+    out_json: *mut *mut c_char,
+) -> c_int {
+    output_string(out_json, || {
+        let v = JwtkVerifier(Keys::Jwks(Box::new(jwks(arg(jwks_json)?)?)));
+        verify_json(&v, arg(token)?)
+    })
+}
+
+/// Verify `token` with a single public key, PEM or JWK, see [`jwtk_verify`].
+///
+/// # Safety
+///
+/// Arguments must be valid NUL terminated strings, and `out_json` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_verify_with_key(
+    token: *const c_char,
+    public_key: *const c_char,
+    out_json: *mut *mut c_char,
+) -> c_int {
+    output_string(out_json, || {
+        let v = JwtkVerifier(Keys::Key(self::public_key(arg(public_key)?)?));
+        verify_json(&v, arg(token)?)
+    })
+}
+
+/// The public JWK of `private_key`, PEM or a private JWK.
+///
+/// # Safety
+///
+/// `private_key` must be a valid NUL terminated string, and `out_jwk` must
+/// be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_public_jwk(
+    private_key: *const c_char,
+    out_jwk: *mut *mut c_char,
+) -> c_int {
+    output_string(out_jwk, || {
+        public_jwk_json(&self::private_key(arg(private_key)?)?)
+    })
+}
+
+/// Free a string returned by one of the functions above. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `s` must have been returned by jwtk and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Parse `private_key`, PEM or a private JWK, into a handle to free with
+/// [`jwtk_key_free`].
+///
+/// # Safety
+///
+/// `private_key` must be a valid NUL terminated string, and `out_key` must
+/// be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_key_new(
+    private_key: *const c_char,
+    out_key: *mut *mut JwtkKey,
+) -> c_int {
+    output_handle(out_key, || {
+        Ok(JwtkKey(self::private_key(arg(private_key)?)?))
+    })
+}
+
+/// Sign `claims_json` with `key`, see [`jwtk_sign`].
+///
+/// # Safety
+///
+/// `key` must be a live handle, `claims_json` a valid NUL terminated string,
+/// and `out_token` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_key_sign(
+    key: *const JwtkKey,
+    claims_json: *const c_char,
+    out_token: *mut *mut c_char,
+) -> c_int {
+    output_string(out_token, || sign_json(&handle(key)?.0, arg(claims_json)?))
+}
+
+/// The public JWK of `key`.
+///
+/// # Safety
+///
+/// `key` must be a live handle, and `out_jwk` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_key_public_jwk(
+    key: *const JwtkKey,
+    out_jwk: *mut *mut c_char,
+) -> c_int {
+    output_string(out_jwk, || public_jwk_json(&handle(key)?.0))
+}
+
+/// Free `key`. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `key` must have been returned by [`jwtk_key_new`] and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_key_free(key: *mut JwtkKey) {
+    if !key.is_null() {
+        drop(Box::from_raw(key));
+    }
+}
+
+/// A verifier of the keys in `jwks_json`, a JWK Set, to free with
+/// [`jwtk_verifier_free`].
+///
+/// # Safety
+///
+/// `jwks_json` must be a valid NUL terminated string, and `out_verifier`
+/// must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_verifier_from_jwks(
+    jwks_json: *const c_char,
+    out_verifier: *mut *mut JwtkVerifier,
+) -> c_int {
+    output_handle(out_verifier, || {
+        Ok(JwtkVerifier(Keys::Jwks(Box::new(jwks(arg(jwks_json)?)?))))
+    })
+}
+
+/// A verifier of a single public key, PEM or JWK, to free with
+/// [`jwtk_verifier_free`].
+///
+/// # Safety
+///
+/// `public_key` must be a valid NUL terminated string, and `out_verifier`
+/// must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_verifier_from_key(
+    public_key: *const c_char,
+    out_verifier: *mut *mut JwtkVerifier,
+) -> c_int {
+    output_handle(out_verifier, || {
+        Ok(JwtkVerifier(Keys::Key(self::public_key(arg(public_key)?)?)))
+    })
+}
+
+/// Verify `token` with `verifier`, see [`jwtk_verify`].
+///
+/// # Safety
+///
+/// `verifier` must be a live handle, `token` a valid NUL terminated string,
+/// and `out_json` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_verifier_verify(
+    verifier: *const JwtkVerifier,
+    token: *const c_char,
+    out_json: *mut *mut c_char,
+) -> c_int {
+    output_string(out_json, || verify_json(handle(verifier)?, arg(token)?))
+}
+
+/// Free `verifier`. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `verifier` must have been returned by jwtk and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn jwtk_verifier_free(verifier: *mut JwtkVerifier) {
+    if !verifier.is_null() {
+        drop(Box::from_raw(verifier));
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use super::*;
+
+    unsafe fn take(s: *mut c_char) -> String {
+        let owned = CStr::from_ptr(s).to_str().unwrap().to_string();
+        jwtk_string_free(s);
+        owned
+    }
+
+    #[test]
+    fn ffi() -> crate::Result<()> {
+        let k = SomePrivateKey::generate("ES256")?;
+        let pem = CString::new(k.private_key_to_pem_pkcs8()?).unwrap();
+        let claims = CString::new(r#"{"sub":"alice"}"#).unwrap();
+        unsafe {
+            let mut out = ptr::null_mut();
+            assert_eq!(jwtk_sign(pem.as_ptr(), claims.as_ptr(), &mut out), JWTK_OK);
+            let token = CString::new(take(out)).unwrap();
+
+            assert_eq!(jwtk_public_jwk(pem.as_ptr(), &mut out), JWTK_OK);
+            let mut jwk: Value = serde_json::from_str(&take(out))?;
+            let public = CString::new(jwk.to_string()).unwrap();
+            assert_eq!(
+                jwtk_verify_with_key(token.as_ptr(), public.as_ptr(), &mut out),
+                JWTK_OK
+            );
+            let verified: Value = serde_json::from_str(&take(out))?;
+            assert_eq!(verified["claims"]["sub"], "alice");
+
+            // JWK Sets need key ids.
+            jwk["kid"] = "k".into();
+            let jwks = CString::new(json!({ "keys": [jwk] }).to_string()).unwrap();
+            assert_eq!(
+                jwtk_verify(token.as_ptr(), jwks.as_ptr(), &mut out),
+                JWTK_NO_KEY
+            );
+            assert!(out.is_null());
+
+            let garbage = CString::new("a.b.c").unwrap();
+            assert_eq!(
+                jwtk_verify_with_key(garbage.as_ptr(), public.as_ptr(), &mut out),
+                JWTK_INVALID_TOKEN
+            );
+            assert_eq!(
+                jwtk_sign(ptr::null(), claims.as_ptr(), &mut out),
+                JWTK_INVALID_ARGUMENT
+            );
+            jwtk_string_free(ptr::null_mut());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn handles() -> crate::Result<()> {
+        let pem =
+            CString::new(SomePrivateKey::generate("ES256")?.private_key_to_pem_pkcs8()?).unwrap();
+        let claims = CString::new(r#"{"sub":"bob"}"#).unwrap();
+        unsafe {
+            let mut key = ptr::null_mut();
+            assert_eq!(jwtk_key_new(pem.as_ptr(), &mut key), JWTK_OK);
+            let mut out = ptr::null_mut();
+            assert_eq!(jwtk_key_sign(key, claims.as_ptr(), &mut out), JWTK_OK);
+            let token = CString::new(take(out)).unwrap();
+            assert_eq!(jwtk_key_public_jwk(key, &mut out), JWTK_OK);
+            let mut jwk: Value = serde_json::from_str(&take(out))?;
+            jwtk_key_free(key);
+
+            let mut verifier = ptr::null_mut();
+            let public = CString::new(jwk.to_string()).unwrap();
+            assert_eq!(
+                jwtk_verifier_from_key(public.as_ptr(), &mut verifier),
+                JWTK_OK
+            );
+            assert_eq!(
+                jwtk_verifier_verify(verifier, token.as_ptr(), &mut out),
+                JWTK_OK
+            );
+            let verified: Value = serde_json::from_str(&take(out))?;
+            assert_eq!(verified["claims"]["sub"], "bob");
+            jwtk_verifier_free(verifier);
+
+            jwk["kid"] = "k".into();
+            let jwks = CString::new(json!({ "keys": [jwk] }).to_string()).unwrap();
+            assert_eq!(
+                jwtk_verifier_from_jwks(jwks.as_ptr(), &mut verifier),
+                JWTK_OK
+            );
+            assert_eq!(
+                jwtk_verifier_verify(verifier, token.as_ptr(), &mut out),
+                JWTK_NO_KEY
+            );
+            jwtk_verifier_free(verifier);
+
+            assert_eq!(
+                jwtk_verifier_verify(ptr::null(), token.as_ptr(), &mut out),
+                JWTK_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                jwtk_key_new(pem.as_ptr(), ptr::null_mut()),
+                JWTK_INVALID_ARGUMENT
+            );
+            assert!(matches!(guard::<()>(|| panic!()), Err(JWTK_PANIC)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+//! Public key fingerprints.
+
+use std::{convert::TryInto, fmt};
+
+use openssl::hash::{hash, MessageDigest};
+
+use crate::{b64, Result};
+
+/// SHA-256 digest of the DER encoded SubjectPublicKeyInfo of a public key.
+///
+/// The same key always has the same fingerprint, whether it was loaded from
+/// PEM or a JWK, and regardless of its `kid` or `alg`. `Display` is lowercase
+/// hex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint(pub [u8; 32]);
+
+impl Fingerprint {
+    pub(crate) fn of_spki_der(der: &[u8]) -> Result<Self> {
+        let digest = hash(MessageDigest::sha256(), der)?;
+        Ok(Self(digest[..].try_into().unwrap()))
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.to_string()
+    }
+
+    /// Base64url without padding, like JWK thumbprints and `x5t#S256`.
+    pub fn to_base64url(&self) -> String {
+        b64::encode_url(self.0)
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "rsa",
+    feature = "ecdsa",
+    feature = "eddsa",
+    not(feature = "verify-only")
+))]
+mod tests {
+    use crate::{PublicKeyToJwk, SomePrivateKey, SomePublicKey};
+
+    use super::*;
+
+    #[test]
+    fn fingerprint() -> Result<()> {
+        for alg in ["EdDSA", "ES384", "PS256"] {
+            let k = SomePrivateKey::generate(alg)?;
+            let from_pem = SomePublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+            let mut jwk = k.public_key_to_jwk()?;
+            jwk.kid = Some("k".into());
+            let from_jwk = SomePublicKey::from_jwk(&jwk)?;
+            let fp = from_pem.fingerprint_sha256()?;
+            assert_eq!(fp, from_jwk.fingerprint_sha256()?);
+            assert_eq!(fp, Fingerprint::of_spki_der(&k.public_key_to_der()?)?);
+            let other = SomePrivateKey::generate(alg)?;
+            assert_ne!(fp, Fingerprint::of_spki_der(&other.public_key_to_der()?)?);
+            assert_eq!(fp.to_hex().len(), 64);
+            assert_eq!(fp.to_base64url().len(), 43);
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,13 @@
-use openssl::{hash::MessageDigest, memcmp, pkey::PKey, rand::rand_bytes, sign::Signer};
+use std::fmt;
+
+#[cfg(not(feature = "verify-only"))]
+use openssl::rand::rand_bytes;
+use openssl::{hash::MessageDigest, memcmp, pkey::PKey, sign::Signer};
 use smallvec::{smallvec, SmallVec};
 
-use crate::{Error, Result, SigningKey, VerificationKey};
+#[cfg(not(feature = "verify-only"))]
+use crate::SigningKey;
+use crate::{Error, Result, VerificationKey};
 
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -31,13 +37,22 @@ impl HmacAlgorithm {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct HmacKey {
     k: SmallVec<[u8; 32]>,
     algorithm: HmacAlgorithm,
 }
 
+impl fmt::Debug for HmacKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacKey")
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
 impl HmacKey {
+    #[cfg(not(feature = "verify-only"))]
     #[inline]
     pub fn generate(algorithm: HmacAlgorithm) -> Result<Self> {
         let len = match algorithm {
@@ -66,10 +81,8 @@ impl HmacKey {
     pub fn serialize(&self) -> &[u8] {
         &self.k
     }
-}
 
-impl SigningKey for HmacKey {
-    fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>> {
+    fn mac(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>> {
         let pk = PKey::hmac(&self.k)?;
         let mut signer = Signer::new(self.algorithm.digest(), pk.as_ref())?;
 
@@ -77,6 +90,13 @@ impl SigningKey for HmacKey {
         signer.sign_oneshot(&mut sig, v)?;
         Ok(sig)
     }
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl SigningKey for HmacKey {
+    fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>> {
+        self.mac(v)
+    }
 
     #[inline]
     fn alg(&self) -> &'static str {
@@ -90,7 +110,7 @@ impl VerificationKey for HmacKey {
             return Err(Error::VerificationError);
         }
 
-        let expected = self.sign(v)?;
+        let expected = self.mac(v)?;
 
         if memcmp::eq(sig, &expected) {
             Ok(())
@@ -100,7 +120,7 @@ impl VerificationKey for HmacKey {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "verify-only")))]
 mod tests {
     use super::*;
 
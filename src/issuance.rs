@@ -0,0 +1,169 @@
+//! Reusing recently signed tokens.
+//!
+//! Services that mint the same service-to-service token over and over can
+//! sign it once and hand out the cached token until it gets close to
+//! expiring.
+//!
+//! ```
+//! # use jwtk::{ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey}, issuance::IssuanceCache, HeaderAndClaims};
+//! # use std::time::Duration;
+//! let cache = IssuanceCache::new(
+//!     EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+//!     Duration::from_secs(300),
+//! );
+//! let mut claims = HeaderAndClaims::new_dynamic();
+//! claims.set_sub("billing").add_aud("ledger");
+//! let first = cache.sign(&mut claims)?;
+//! assert_eq!(first, cache.sign(&mut claims)?);
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use openssl::hash::{hash, MessageDigest};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{canonical_json, sign, HeaderAndClaims, Result, SigningKey};
+
+struct Cached {
+    token: String,
+    exp: Duration,
+}
+
+/// Signs tokens with a key, reusing the token signed for identical header and
+/// claims while it has at least half of its lifetime left, by default.
+///
+/// `iat` and `exp` are set on every token, and are not part of the
+/// comparison. Other claims are, so don't set e.g. `nbf` or `jti` to a new
+/// value for every token.
+pub struct IssuanceCache<K> {
+    key: K,
+    lifetime: Duration,
+    min_remaining: Duration,
+    tokens: Mutex<HashMap<[u8; 32], Cached>>,
+}
+
+impl<K: SigningKey> IssuanceCache<K> {
+    /// Sign tokens with `key`, valid for `lifetime`.
+    pub fn new(key: K, lifetime: Duration) -> Self {
+        Self {
+            key,
+            lifetime,
+            min_remaining: lifetime / 2,
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Only reuse tokens that are valid for at least `min_remaining`.
+    /// Defaults to half the lifetime.
+    pub fn set_min_remaining(&mut self, min_remaining: Duration) {
+        self.min_remaining = min_remaining;
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Sign `claims` like [`sign`], or return a cached token for them.
+    pub fn sign<ExtraClaims: Serialize>(
+        &self,
+        claims: &mut HeaderAndClaims<ExtraClaims>,
+    ) -> Result<String> {
+        claims.header.alg = self.key.alg().into();
+        if let Some(kid) = self.key.kid() {
+            claims.set_kid(kid);
+        }
+        let digest = self.digest(claims)?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        if let Some(cached) = self.tokens.lock().unwrap().get(&digest) {
+            if cached.exp >= now + self.min_remaining {
+                return Ok(cached.token.clone());
+            }
+        }
+
+        let exp = now + self.lifetime;
+        claims.claims.iat = Some(now);
+        claims.claims.exp = Some(exp);
+        let token = sign(claims, &self.key)?;
+
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.retain(|_, cached| cached.exp >= now + self.min_remaining);
+        tokens.insert(
+            digest,
+            Cached {
+                token: token.clone(),
+                exp,
+            },
+        );
+        Ok(token)
+    }
+
+    /// Forget all cached tokens, e.g. after changes that aren't in the
+    /// claims.
+    pub fn clear(&self) {
+        self.tokens.lock().unwrap().clear();
+    }
+
+    fn digest<ExtraClaims: Serialize>(
+        &self,
+        claims: &HeaderAndClaims<ExtraClaims>,
+    ) -> Result<[u8; 32]> {
+        let mut c = serde_json::to_value(&claims.claims)?;
+        if let Value::Object(map) = &mut c {
+            map.remove("iat");
+            map.remove("exp");
+        }
+        let mut input = canonical_json(&claims.header)?;
+        input.push(b'.');
+        input.extend(canonical_json(&c)?);
+        let digest = hash(MessageDigest::sha256(), &input)?;
+        Ok(digest[..].try_into().unwrap())
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa"))]
+mod tests {
+    use serde_json::{Map, Value};
+
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        verify,
+    };
+
+    use super::*;
+
+    #[test]
+    fn issuance_cache() -> Result<()> {
+        // ECDSA signatures are randomized, so a new token is never equal.
+        let cache = IssuanceCache::new(
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+            Duration::from_secs(60),
+        );
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.set_sub("svc").insert("scope", "read");
+        let first = cache.sign(&mut claims)?;
+        let verified = verify::<Map<String, Value>>(&first, cache.key())?;
+        assert!(verified.claims().exp.is_some());
+
+        // Caller set times don't matter.
+        claims.set_exp_from_now(Duration::from_secs(5));
+        assert_eq!(first, cache.sign(&mut claims)?);
+
+        claims.insert("scope", "write");
+        let second = cache.sign(&mut claims)?;
+        assert_ne!(first, second);
+
+        let mut cache = cache;
+        cache.set_min_remaining(Duration::from_secs(61));
+        assert_ne!(second, cache.sign(&mut claims)?);
+        cache.clear();
+        Ok(())
+    }
+}
@@ -2,30 +2,55 @@
 //!
 //! Only public keys are really supported for now.
 
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+#[cfg(feature = "ecdsa")]
+use crate::ecdsa::{EcdsaAlgorithm, EcdsaPublicKey};
+#[cfg(feature = "eddsa")]
+use crate::eddsa::Ed25519PublicKey;
+#[cfg(feature = "rsa")]
+use crate::rsa::RsaPublicKey;
+
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+use crate::ecdsa::EcdsaPrivateKey;
+#[cfg(all(feature = "eddsa", not(feature = "verify-only")))]
+use crate::eddsa::Ed25519PrivateKey;
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+use crate::rsa::RsaPrivateKey;
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+use openssl::{
+    bn::BigNum,
+    pkey::PKey,
+    rsa::{Rsa, RsaPrivateKeyBuilder},
+};
 
+#[cfg(any(feature = "rsa", not(feature = "verify-only")))]
+use crate::rsa::RsaAlgorithm;
 use crate::{
-    ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey, EcdsaPublicKey},
-    eddsa::{Ed25519PrivateKey, Ed25519PublicKey},
-    rsa::{RsaAlgorithm, RsaPrivateKey, RsaPublicKey},
+    b64, decode_header,
+    revocation::{check_revocation, RevocationCheck},
+    rsa::RsaKeyPolicy,
     some::SomePublicKey,
-    url_safe_trailing_bits, verify, verify_only, Error, Header, HeaderAndClaims, PublicKeyToJwk,
-    Result, SigningKey, SomePrivateKey, VerificationKey,
+    split_token, verify_only_with_options, verify_with_options, Error, HeaderAndClaims,
+    PublicKeyToJwk, Result, ValidationOptions, VerificationKey,
 };
+#[cfg(not(feature = "verify-only"))]
+use crate::{SigningKey, SomePrivateKey};
 use openssl::{
-    bn::BigNum,
     hash::{hash, MessageDigest},
-    pkey::PKey,
-    rsa::{Rsa, RsaPrivateKeyBuilder},
+    x509::X509,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 // TODO: private key jwk.
 
 /// JWK Representation.
 #[non_exhaustive]
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Clone, Deserialize, Serialize, Default)]
 pub struct Jwk {
     pub kty: String,
     #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
@@ -63,25 +88,76 @@ pub struct Jwk {
     pub qi: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub oth: Vec<Value>,
+
+    /// Whether the key is extractable, from WebCrypto.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ext: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5u: Option<String>,
+    /// Certificate chain, standard base64 encoded DER.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub x5c: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5t: Option<String>,
+    #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
+
+    /// All other parameters, e.g. provider specific expiry times. They are
+    /// kept when the JWK is serialized again.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl Jwk {
     pub fn to_verification_key(&self) -> Result<SomePublicKey> {
-        // Check `use` and `key_ops`.
-        if !matches!(self.use_.as_deref(), None | Some("sig")) {
+        self.to_verification_key_with_rsa_policy(&RsaKeyPolicy::default())
+    }
+
+    /// Like `to_verification_key`, but RSA keys must satisfy `rsa_policy`.
+    pub fn to_verification_key_with_rsa_policy(
+        &self,
+        rsa_policy: &RsaKeyPolicy,
+    ) -> Result<SomePublicKey> {
+        if !self.allows_verify() {
             return Err(Error::UnsupportedOrInvalidKey);
         }
-        if !(self.key_ops.is_empty() || self.key_ops.iter().any(|ops| ops == "verify")) {
-            return Err(Error::UnsupportedOrInvalidKey);
+        self.verification_key(rsa_policy)
+    }
+
+    /// Whether `use` and `key_ops`, if present, allow verifying signatures.
+    pub fn allows_verify(&self) -> bool {
+        self.allows_verify_with_uses(&["sig"])
+    }
+
+    /// Like `allows_verify`, but `use`, if present, must be one of `uses`,
+    /// for directories with their own `use` values.
+    pub fn allows_verify_with_uses(&self, uses: &[impl AsRef<str>]) -> bool {
+        let use_ok = match self.use_.as_deref() {
+            None => true,
+            Some(u) => uses.iter().any(|allowed| allowed.as_ref() == u),
+        };
+        use_ok && (self.key_ops.is_empty() || self.key_ops.iter().any(|ops| ops == "verify"))
+    }
+
+    /// The verification key, regardless of `use` and `key_ops`.
+    #[cfg_attr(not(feature = "rsa"), allow(unused_variables))]
+    fn verification_key(&self, rsa_policy: &RsaKeyPolicy) -> Result<SomePublicKey> {
+        // Some directories, e.g. of Open Banking ecosystems, publish keys
+        // with only their certificate.
+        if self.n.is_none() && self.x.is_none() {
+            if let Some(cert) = self.x5c.first() {
+                return self.certificate_key(cert, rsa_policy);
+            }
         }
 
         // If let would be too long.
         #[allow(clippy::single_match)]
         match &*self.kty {
+            #[cfg(feature = "rsa")]
             "RSA" => match (self.alg.as_deref(), &self.n, &self.e) {
                 (alg, Some(ref n), Some(ref e)) => {
-                    let n = base64::decode_config(n, url_safe_trailing_bits())?;
-                    let e = base64::decode_config(e, url_safe_trailing_bits())?;
+                    let n = b64::decode_url(n)?;
+                    let e = b64::decode_url(e)?;
                     // If `alg` is specified, the key will only verify
                     // signatures generated by ONLY this specific `alg`,
                     // otherwise it will verify signatures generated by ANY RSA
@@ -91,17 +167,18 @@ impl Jwk {
                     } else {
                         None
                     };
-                    return Ok(SomePublicKey::Rsa(RsaPublicKey::from_components(
-                        &n, &e, alg,
-                    )?));
+                    return Ok(SomePublicKey::Rsa(
+                        RsaPublicKey::from_components_with_policy(&n, &e, alg, rsa_policy)?,
+                    ));
                 }
                 _ => {}
             },
+            #[cfg(feature = "ecdsa")]
             "EC" => match (self.crv.as_deref(), &self.x, &self.y) {
                 // For EC keys `crv` is required.
                 (Some(crv), Some(ref x), Some(ref y)) => {
-                    let x = base64::decode_config(x, url_safe_trailing_bits())?;
-                    let y = base64::decode_config(y, url_safe_trailing_bits())?;
+                    let x = b64::decode_url(x)?;
+                    let y = b64::decode_url(y)?;
                     let alg = EcdsaAlgorithm::from_curve_name(crv)?;
                     return Ok(SomePublicKey::Ecdsa(EcdsaPublicKey::from_coordinates(
                         &x, &y, alg,
@@ -109,9 +186,10 @@ impl Jwk {
                 }
                 _ => {}
             },
+            #[cfg(feature = "eddsa")]
             "OKP" => match (self.crv.as_deref(), &self.x) {
                 (Some(crv), Some(ref x)) => {
-                    let x = base64::decode_config(x, url_safe_trailing_bits())?;
+                    let x = b64::decode_url(x)?;
                     match crv {
                         "Ed25519" => {
                             return Ok(SomePublicKey::Ed25519(Ed25519PublicKey::from_bytes(&x)?));
@@ -127,9 +205,30 @@ impl Jwk {
         Err(Error::UnsupportedOrInvalidKey)
     }
 
+    /// The key of the first certificate of `x5c`, which must match
+    /// `x5t#S256` if present. The certificate itself is not validated.
+    fn certificate_key(&self, cert: &str, rsa_policy: &RsaKeyPolicy) -> Result<SomePublicKey> {
+        let der = b64::decode(cert)?;
+        if let Some(ref x5t) = self.x5t_s256 {
+            if *x5t != b64::encode_url(hash(MessageDigest::sha256(), &der)?) {
+                return Err(Error::UnsupportedOrInvalidKey);
+            }
+        }
+        let pk = X509::from_der(&der)?.public_key()?;
+        let mut jwk = SomePublicKey::from_pkey(pk)?.public_key_to_jwk()?;
+        if jwk.kty != self.kty {
+            return Err(Error::UnsupportedOrInvalidKey);
+        }
+        jwk.alg.clone_from(&self.alg);
+        jwk.verification_key(rsa_policy)
+    }
+
+    #[cfg(not(feature = "verify-only"))]
     #[allow(clippy::many_single_char_names)]
+    #[cfg_attr(not(feature = "rsa"), allow(unused_variables))]
     pub fn to_signing_key(&self, rsa_fallback_algorithm: RsaAlgorithm) -> Result<SomePrivateKey> {
         match &*self.kty {
+            #[cfg(feature = "rsa")]
             "RSA" => {
                 let alg = if let Some(ref alg) = self.alg {
                     RsaAlgorithm::from_name(alg)?
@@ -139,10 +238,7 @@ impl Jwk {
                 match (self.d.as_deref(), self.n.as_deref(), self.e.as_deref()) {
                     (Some(d), Some(n), Some(e)) => {
                         fn decode(x: &str) -> Result<BigNum> {
-                            Ok(BigNum::from_slice(&base64::decode_config(
-                                x,
-                                url_safe_trailing_bits(),
-                            )?)?)
+                            Ok(BigNum::from_slice(&b64::decode_url(x)?)?)
                         }
                         let d = decode(d)?;
                         let n = decode(n)?;
@@ -176,6 +272,7 @@ impl Jwk {
                     _ => Err(Error::UnsupportedOrInvalidKey),
                 }
             }
+            #[cfg(feature = "ecdsa")]
             "EC" => {
                 match (
                     self.crv.as_deref(),
@@ -185,17 +282,18 @@ impl Jwk {
                 ) {
                     (Some(crv), Some(d), Some(x), Some(y)) => {
                         let alg = EcdsaAlgorithm::from_curve_name(crv)?;
-                        let d = base64::decode_config(d, url_safe_trailing_bits())?;
-                        let x = base64::decode_config(x, url_safe_trailing_bits())?;
-                        let y = base64::decode_config(y, url_safe_trailing_bits())?;
+                        let d = b64::decode_url(d)?;
+                        let x = b64::decode_url(x)?;
+                        let y = b64::decode_url(y)?;
                         EcdsaPrivateKey::from_private_components(alg, &d, &x, &y).map(Into::into)
                     }
                     _ => Err(Error::UnsupportedOrInvalidKey),
                 }
             }
+            #[cfg(feature = "eddsa")]
             "OKP" => match (self.crv.as_deref(), self.d.as_deref()) {
                 (Some("Ed25519"), Some(d)) => {
-                    let d = base64::decode_config(d, url_safe_trailing_bits())?;
+                    let d = b64::decode_url(d)?;
                     Ed25519PrivateKey::from_bytes(&d).map(Into::into)
                 }
                 _ => Err(Error::UnsupportedOrInvalidKey),
@@ -260,10 +358,7 @@ impl Jwk {
 
     /// Get key thumbprint with SHA-256, base64url-encoded.
     pub fn get_thumbprint_sha256_base64(&self) -> Result<String> {
-        Ok(base64::encode_config(
-            self.get_thumbprint_sha256()?,
-            url_safe_trailing_bits(),
-        ))
+        Ok(b64::encode_url(self.get_thumbprint_sha256()?))
     }
 }
 
@@ -274,14 +369,82 @@ pub struct JwkSet {
 }
 
 impl JwkSet {
+    /// Convert a JSON object of key ids to PEM encoded X.509 certificates, as
+    /// served by e.g. Google for Firebase ID tokens, to a JWK Set.
+    ///
+    /// Certificates that can't be parsed or have unsupported keys are
+    /// skipped. The certificates themselves are not validated.
+    pub fn from_x509_pem_map(certs: &HashMap<String, String>) -> JwkSet {
+        fn to_jwk(kid: &str, pem: &str) -> Result<Jwk> {
+            let pk = X509::from_pem(pem.as_bytes())?.public_key()?;
+            let mut jwk = SomePublicKey::from_pkey(pk)?.public_key_to_jwk()?;
+            jwk.kid = Some(kid.into());
+            Ok(jwk)
+        }
+        JwkSet {
+            keys: certs
+                .iter()
+                .filter_map(|(kid, pem)| to_jwk(kid, pem).ok())
+                .collect(),
+        }
+    }
+
+    /// Parse a pinned JWK Set snapshot, for deployments that must never fetch
+    /// keys at runtime, e.g. embedded with
+    /// `JwkSet::pinned(include_str!("jwks.json"))` or read from a file at
+    /// startup. Regenerate the snapshot with `jwtk jwks-snapshot <url>`.
+    ///
+    /// Unlike `verifier`, which skips unusable keys, this fails closed: the
+    /// set must not be empty, and every key must be valid and have a `kid`.
+    pub fn pinned(json: &str) -> Result<JwkSet> {
+        let jwks: JwkSet = serde_json::from_str(json)?;
+        if jwks.keys.is_empty() {
+            return Err(Error::NoKey);
+        }
+        for k in &jwks.keys {
+            k.kid.as_ref().ok_or(Error::NoKid)?;
+            k.to_verification_key()?;
+        }
+        Ok(jwks)
+    }
+
     pub fn verifier(&self) -> JwkSetVerifier {
+        self.verifier_with_rsa_policy(&RsaKeyPolicy::default())
+    }
+
+    /// Like `verifier`, but RSA keys that don't satisfy `rsa_policy` are
+    /// skipped.
+    pub fn verifier_with_rsa_policy(&self, rsa_policy: &RsaKeyPolicy) -> JwkSetVerifier {
+        self.verifier_with_policies(rsa_policy, KeyUsePolicy::Enforce)
+    }
+
+    /// Like `verifier_with_rsa_policy`, with `key_use_policy` for keys whose
+    /// `use` or `key_ops` don't allow verification.
+    pub fn verifier_with_policies(
+        &self,
+        rsa_policy: &RsaKeyPolicy,
+        key_use_policy: KeyUsePolicy,
+    ) -> JwkSetVerifier {
         let mut prepared = JwkSetVerifier {
             keys: HashMap::new(),
             require_kid: true,
+            revocation_check: None,
+            options: ValidationOptions::default(),
+            key_use_violations: Vec::new(),
         };
         for k in self.keys.iter() {
             if let Some(ref kid) = k.kid {
-                if let Ok(vk) = k.to_verification_key() {
+                let allowed = match &key_use_policy {
+                    KeyUsePolicy::AllowUses(uses) => k.allows_verify_with_uses(uses),
+                    _ => k.allows_verify(),
+                };
+                if !allowed {
+                    if key_use_policy != KeyUsePolicy::Warn {
+                        continue;
+                    }
+                    prepared.key_use_violations.push(kid.clone());
+                }
+                if let Ok(vk) = k.verification_key(rsa_policy) {
                     prepared.keys.insert(kid.clone(), vk);
                 }
             }
@@ -290,19 +453,95 @@ impl JwkSet {
     }
 }
 
+/// What to do with JWKs whose `use` or `key_ops` don't allow verifying
+/// signatures, e.g. `"use": "enc"`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum KeyUsePolicy {
+    /// Skip them.
+    #[default]
+    Enforce,
+    /// Use them anyway, for providers that get `use` or `key_ops` wrong, and
+    /// report them in [`JwkSetVerifier::key_use_violations`].
+    Warn,
+    /// Skip them, but accept these `use` values instead of `sig`, e.g.
+    /// `vec!["sig".into(), "signing".into()]` for directories that separate
+    /// signing and transport (`tls`) keys with their own values.
+    AllowUses(Vec<String>),
+}
+
+/// Changes between two versions of a JWK Set, by key id, see
+/// [`JwkSetVerifier::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JwksDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Key ids that now have another key.
+    pub changed: Vec<String>,
+}
+
+impl JwksDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
 /// Jwk set parsed and converted, ready to verify tokens.
 pub struct JwkSetVerifier {
     keys: HashMap<String, SomePublicKey>,
     require_kid: bool,
+    revocation_check: Option<Arc<dyn RevocationCheck>>,
+    options: ValidationOptions,
+    key_use_violations: Vec<String>,
 }
 
 impl JwkSetVerifier {
+    /// Changes from `old` to this key set. Keys are compared by their
+    /// [`Fingerprint`](crate::Fingerprint), so e.g. a changed `alg` alone is
+    /// not a change.
+    pub fn diff(&self, old: &JwkSetVerifier) -> JwksDiff {
+        let mut diff = JwksDiff::default();
+        for (kid, k) in &self.keys {
+            match old.keys.get(kid) {
+                None => diff.added.push(kid.clone()),
+                Some(o) if o.fingerprint_sha256().ok() != k.fingerprint_sha256().ok() => {
+                    diff.changed.push(kid.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        for kid in old.keys.keys() {
+            if !self.keys.contains_key(kid) {
+                diff.removed.push(kid.clone());
+            }
+        }
+        diff.added.sort();
+        diff.removed.sort();
+        diff.changed.sort();
+        diff
+    }
+
+    /// Key ids of keys that are used although their `use` or `key_ops`
+    /// don't allow it, with [`KeyUsePolicy::Warn`].
+    pub fn key_use_violations(&self) -> &[String] {
+        &self.key_use_violations
+    }
+
     /// If called with `false`, subsequent `verify` and `verify_only` calls will
     /// try all keys from the key set if a `kid` is not specified in the token.
     pub fn set_require_kid(&mut self, required: bool) {
         self.require_kid = required;
     }
 
+    /// Reject tokens that `check` considers revoked after their signatures
+    /// are verified.
+    pub fn set_revocation_check(&mut self, check: Arc<dyn RevocationCheck>) {
+        self.revocation_check = Some(check);
+    }
+
+    pub fn set_validation_options(&mut self, options: ValidationOptions) {
+        self.options = options;
+    }
+
     pub fn find(&self, kid: &str) -> Option<&SomePublicKey> {
         if let Some(vk) = self.keys.get(kid) {
             Some(vk)
@@ -318,7 +557,28 @@ impl JwkSetVerifier {
         &self,
         token: &str,
     ) -> Result<HeaderAndClaims<ExtraClaims>> {
-        self.find_and_verify(token, verify)
+        self.find_and_verify(token, |token, k| {
+            verify_with_options(token, k, &self.options)
+        })
+    }
+
+    /// Like `verify`, but validate with `options` instead of the options set
+    /// with `set_validation_options`.
+    pub fn verify_with_options<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+        options: &ValidationOptions,
+    ) -> Result<HeaderAndClaims<ExtraClaims>> {
+        self.find_and_verify(token, |token, k| verify_with_options(token, k, options))
+    }
+
+    /// Like `verify`, but also keep the verified token, see
+    /// [`VerifiedToken`](crate::VerifiedToken).
+    pub fn verify_token<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<crate::VerifiedToken<ExtraClaims>> {
+        Ok(crate::VerifiedToken::new(self.verify(token)?, token))
     }
 
     /// Decode and verify token with keys from this JWK set. Won't check `exp` and `nbf`.
@@ -326,7 +586,9 @@ impl JwkSetVerifier {
         &self,
         token: &str,
     ) -> Result<HeaderAndClaims<ExtraClaims>> {
-        self.find_and_verify(token, verify_only)
+        self.find_and_verify(token, |token, k| {
+            verify_only_with_options(token, k, &self.options)
+        })
     }
 
     /// Find and verify token with keys from this JWK set.
@@ -335,14 +597,23 @@ impl JwkSetVerifier {
     fn find_and_verify<ExtraClaims: DeserializeOwned>(
         &self,
         token: &str,
-        verifier: fn(&str, &dyn VerificationKey) -> Result<HeaderAndClaims<ExtraClaims>>,
+        verifier: impl Fn(&str, &dyn VerificationKey) -> Result<HeaderAndClaims<ExtraClaims>>,
     ) -> Result<HeaderAndClaims<ExtraClaims>> {
-        let mut parts = token.split('.');
-
-        let mut header = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
+        let verified = self.find_key_and_verify(token, verifier)?;
+        if let Some(ref check) = self.revocation_check {
+            check_revocation(&**check, verified.claims())?;
+        }
+        Ok(verified)
+    }
 
-        let header_r = base64::read::DecoderReader::new(&mut header, url_safe_trailing_bits());
-        let header: Header = serde_json::from_reader(header_r)?;
+    fn find_key_and_verify<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+        verifier: impl Fn(&str, &dyn VerificationKey) -> Result<HeaderAndClaims<ExtraClaims>>,
+    ) -> Result<HeaderAndClaims<ExtraClaims>> {
+        self.options.check_token_len(token)?;
+        let (header, _, _) = split_token(token)?;
+        let header = decode_header(header, &self.options)?;
 
         if let Some(kid) = header.kid {
             let k = self.find(&kid).ok_or(Error::NoKey)?;
@@ -367,7 +638,7 @@ impl JwkSetVerifier {
 /// A key associated with a key id (`kid`).
 ///
 /// When the key is used for signing, `kid` is automatically set.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WithKid<S> {
     kid: String,
     inner: S,
@@ -410,6 +681,7 @@ impl<S> WithKid<S> {
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl<S: SigningKey> SigningKey for WithKid<S> {
     fn kid(&self) -> Option<&str> {
         Some(&self.kid)
@@ -422,12 +694,20 @@ impl<S: SigningKey> SigningKey for WithKid<S> {
     fn alg(&self) -> &'static str {
         self.inner.alg()
     }
+
+    fn sign_stream(&self) -> Result<crate::stream::SignStream<'_>> {
+        self.inner.sign_stream()
+    }
 }
 
 impl<S: VerificationKey> VerificationKey for WithKid<S> {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
         self.inner.verify(v, sig, alg)
     }
+
+    fn verify_stream(&self, alg: &str) -> Result<crate::stream::VerifyStream<'_>> {
+        self.inner.verify_stream(alg)
+    }
 }
 
 impl<K: PublicKeyToJwk> PublicKeyToJwk for WithKid<K> {
@@ -444,6 +724,19 @@ struct JWKSCache {
     valid_until: std::time::Instant,
 }
 
+/// Stops the background refresh of
+/// [`RemoteJwksVerifier::spawn_refresher`] when dropped.
+#[cfg(feature = "remote-jwks")]
+#[derive(Debug)]
+pub struct RefresherGuard(tokio::task::JoinHandle<()>);
+
+#[cfg(feature = "remote-jwks")]
+impl Drop for RefresherGuard {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 /// A JWK Set served from a remote url. Automatically fetched and cached.
 #[cfg(feature = "remote-jwks")]
 pub struct RemoteJwksVerifier {
@@ -452,8 +745,17 @@ pub struct RemoteJwksVerifier {
     cache_duration: std::time::Duration,
     cache: tokio::sync::RwLock<Option<JWKSCache>>,
     require_kid: bool,
+    revocation_check: Option<Arc<dyn RevocationCheck>>,
+    options: ValidationOptions,
+    rsa_policy: RsaKeyPolicy,
+    key_use_policy: KeyUsePolicy,
+    x509_pem_map: bool,
+    on_change: Option<OnChange>,
 }
 
+#[cfg(feature = "remote-jwks")]
+type OnChange = Arc<dyn Fn(&JwksDiff) + Send + Sync>;
+
 #[cfg(feature = "remote-jwks")]
 impl RemoteJwksVerifier {
     pub fn new(
@@ -467,6 +769,26 @@ impl RemoteJwksVerifier {
             cache_duration,
             cache: tokio::sync::RwLock::new(None),
             require_kid: true,
+            revocation_check: None,
+            options: ValidationOptions::default(),
+            rsa_policy: RsaKeyPolicy::default(),
+            key_use_policy: KeyUsePolicy::Enforce,
+            x509_pem_map: false,
+            on_change: None,
+        }
+    }
+
+    /// Like `new`, but `url` serves a JSON object of key ids to PEM encoded
+    /// X.509 certificates instead of a JWK Set, see
+    /// [`JwkSet::from_x509_pem_map`].
+    pub fn new_x509_pem_map(
+        url: String,
+        client: Option<reqwest::Client>,
+        cache_duration: std::time::Duration,
+    ) -> Self {
+        Self {
+            x509_pem_map: true,
+            ..Self::new(url, client, cache_duration)
         }
     }
 
@@ -479,6 +801,119 @@ impl RemoteJwksVerifier {
         }
     }
 
+    /// Reject tokens that `check` considers revoked after their signatures
+    /// are verified.
+    pub fn set_revocation_check(&mut self, check: Arc<dyn RevocationCheck>) {
+        if let Some(ref mut v) = self.cache.get_mut() {
+            v.jwks.revocation_check = Some(check.clone());
+        }
+        self.revocation_check = Some(check);
+    }
+
+    pub fn set_validation_options(&mut self, options: ValidationOptions) {
+        if let Some(ref mut v) = self.cache.get_mut() {
+            v.jwks.options = options.clone();
+        }
+        self.options = options;
+    }
+
+    /// RSA keys that don't satisfy `policy` are skipped. Takes effect the
+    /// next time the JWK Set is fetched.
+    pub fn set_rsa_key_policy(&mut self, policy: RsaKeyPolicy) {
+        self.rsa_policy = policy;
+    }
+
+    /// See [`KeyUsePolicy`]. Takes effect the next time the JWK Set is
+    /// fetched.
+    pub fn set_key_use_policy(&mut self, policy: KeyUsePolicy) {
+        self.key_use_policy = policy;
+    }
+
+    /// Call `on_change` when a refresh changes the JWK Set, e.g. to alert
+    /// when the issuer rotates its keys unexpectedly. It is called during
+    /// the refresh, so it should not block.
+    ///
+    /// ```no_run
+    /// # use jwtk::jwk::RemoteJwksVerifier;
+    /// # use std::{sync::Arc, time::Duration};
+    /// let mut v = RemoteJwksVerifier::new("https://example.com/jwks".into(), None, Duration::from_secs(300));
+    /// v.set_on_change(Arc::new(|diff| eprintln!("JWK Set changed: {:?}", diff)));
+    /// ```
+    pub fn set_on_change(&mut self, on_change: Arc<dyn Fn(&JwksDiff) + Send + Sync>) {
+        self.on_change = Some(on_change);
+    }
+
+    /// Key ids of keys in the current JWK Set that are used although their
+    /// `use` or `key_ops` don't allow it, with [`KeyUsePolicy::Warn`].
+    pub async fn key_use_violations(&self) -> Vec<String> {
+        match &*self.cache.read().await {
+            Some(c) => c.jwks.key_use_violations.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Fetch the JWK Set now, regardless of the cache.
+    pub async fn refresh(&self) -> Result<()> {
+        let v = self.fetch().await?;
+        self.store(&mut *self.cache.write().await, v);
+        Ok(())
+    }
+
+    /// Refresh the JWK Set every `interval` in a background task, so that
+    /// `verify` doesn't have to wait for it when the cache expires. Use an
+    /// `interval` shorter than the cache duration.
+    ///
+    /// The first refresh is immediate. Failed refreshes are ignored, the
+    /// cached keys are used until they expire. The task stops when the
+    /// returned guard is dropped. Must be called within a tokio runtime.
+    pub fn spawn_refresher(self: &Arc<Self>, interval: std::time::Duration) -> RefresherGuard {
+        let this = Arc::downgrade(self);
+        RefresherGuard(tokio::spawn(async move {
+            loop {
+                match this.upgrade() {
+                    Some(v) => {
+                        let _ = v.refresh().await;
+                    }
+                    None => return,
+                }
+                tokio::time::sleep(interval).await;
+            }
+        }))
+    }
+
+    async fn fetch(&self) -> Result<JwkSetVerifier> {
+        let response = self
+            .client
+            .get(&self.url)
+            .header("accept", "application/json")
+            .send()
+            .await?;
+        let jwks: JwkSet = if self.x509_pem_map {
+            JwkSet::from_x509_pem_map(&response.json().await?)
+        } else {
+            response.json().await?
+        };
+
+        let mut v = jwks.verifier_with_policies(&self.rsa_policy, self.key_use_policy.clone());
+        v.require_kid = self.require_kid;
+        v.revocation_check = self.revocation_check.clone();
+        v.options = self.options.clone();
+        Ok(v)
+    }
+
+    fn store(&self, cache: &mut Option<JWKSCache>, v: JwkSetVerifier) {
+        if let (Some(on_change), Some(old)) = (&self.on_change, &*cache) {
+            let diff = v.diff(&old.jwks);
+            if !diff.is_empty() {
+                on_change(&diff);
+            }
+        }
+        *cache = Some(JWKSCache {
+            jwks: v,
+            valid_until: std::time::Instant::now() + self.cache_duration,
+        });
+    }
+
     async fn get_verifier(&self) -> Result<tokio::sync::RwLockReadGuard<'_, JwkSetVerifier>> {
         let cache = self.cache.read().await;
         // Cache still valid.
@@ -505,22 +940,8 @@ impl RemoteJwksVerifier {
                 }));
             }
         }
-        let response = self
-            .client
-            .get(&self.url)
-            .header("accept", "application/json")
-            .send()
-            .await?;
-        let jwks: JwkSet = response.json().await?;
-
-        *cache = Some(JWKSCache {
-            jwks: {
-                let mut v = jwks.verifier();
-                v.require_kid = self.require_kid;
-                v
-            },
-            valid_until: std::time::Instant::now() + self.cache_duration,
-        });
+        let v = self.fetch().await?;
+        self.store(&mut cache, v);
 
         Ok(tokio::sync::RwLockReadGuard::map(cache.downgrade(), |c| {
             &c.as_ref().unwrap().jwks
@@ -541,7 +962,13 @@ impl RemoteJwksVerifier {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(
+    test,
+    feature = "rsa",
+    feature = "ecdsa",
+    feature = "eddsa",
+    not(feature = "verify-only")
+))]
 mod tests {
     use crate::{
         ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
@@ -572,6 +999,97 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jwk_passthrough() -> Result<()> {
+        let json = r#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo","ext":true,"x5t":"abc","x5t#S256":"def","issuer":"https://login.example","exp":1700000000}"#;
+        let jwk: Jwk = serde_json::from_str(json)?;
+        assert_eq!(jwk.ext, Some(true));
+        assert_eq!(jwk.x5t.as_deref(), Some("abc"));
+        assert_eq!(jwk.x5t_s256.as_deref(), Some("def"));
+        assert_eq!(jwk.extra["exp"], 1700000000);
+        jwk.to_verification_key()?;
+
+        let reserialized: Value = serde_json::to_value(&jwk)?;
+        assert_eq!(reserialized, serde_json::from_str::<Value>(json)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned() -> Result<()> {
+        let k = WithKid::new("pinned".into(), Ed25519PrivateKey::generate()?);
+        let jwk = k.public_key_to_jwk()?;
+        let snapshot = serde_json::to_string(&JwkSet {
+            keys: vec![jwk.clone()],
+        })?;
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        JwkSet::pinned(&snapshot)?
+            .verifier()
+            .verify::<Map<String, Value>>(&token)?;
+
+        assert!(matches!(
+            JwkSet::pinned(r#"{"keys":[]}"#),
+            Err(Error::NoKey)
+        ));
+        let mut no_kid = jwk.clone();
+        no_kid.kid = None;
+        let snapshot = serde_json::to_string(&JwkSet { keys: vec![no_kid] })?;
+        assert!(matches!(JwkSet::pinned(&snapshot), Err(Error::NoKid)));
+        let mut invalid = jwk;
+        invalid.x = Some("AAAA".into());
+        let snapshot = serde_json::to_string(&JwkSet {
+            keys: vec![invalid],
+        })?;
+        assert!(JwkSet::pinned(&snapshot).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwks_diff() -> Result<()> {
+        let jwk = |kid: &str, k: &Ed25519PrivateKey| -> Result<Jwk> {
+            WithKid::new(kid.into(), k.clone()).public_key_to_jwk()
+        };
+        let (a, b, c) = (
+            Ed25519PrivateKey::generate()?,
+            Ed25519PrivateKey::generate()?,
+            Ed25519PrivateKey::generate()?,
+        );
+        let old = JwkSet {
+            keys: vec![jwk("a", &a)?, jwk("b", &b)?, jwk("c", &c)?],
+        }
+        .verifier();
+        let mut same_b = jwk("b", &b)?;
+        same_b.alg = Some("EdDSA".into());
+        let new = JwkSet {
+            keys: vec![jwk("a", &b)?, same_b, jwk("d", &c)?],
+        }
+        .verifier();
+
+        let diff = new.diff(&old);
+        assert_eq!(diff.added, ["d"]);
+        assert_eq!(diff.removed, ["c"]);
+        assert_eq!(diff.changed, ["a"]);
+        assert!(new.diff(&new).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_use_policy() -> Result<()> {
+        let k = WithKid::new("enc".into(), Ed25519PrivateKey::generate()?);
+        let mut jwk = k.public_key_to_jwk()?;
+        jwk.use_ = Some("enc".into());
+        let jwks = JwkSet { keys: vec![jwk] };
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+
+        let v = jwks.verifier();
+        assert!(matches!(v.verify::<Value>(&token), Err(Error::NoKey)));
+        assert!(v.key_use_violations().is_empty());
+
+        let v = jwks.verifier_with_policies(&RsaKeyPolicy::default(), KeyUsePolicy::Warn);
+        v.verify::<Value>(&token)?;
+        assert_eq!(v.key_use_violations(), ["enc"]);
+        Ok(())
+    }
+
     #[test]
     fn test_thumbprint() -> Result<()> {
         RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?
@@ -657,4 +1175,138 @@ mod tests {
 
         Ok(())
     }
+    #[test]
+    fn test_x509_pem_map() -> Result<()> {
+        use openssl::{
+            asn1::Asn1Time,
+            ec::{EcGroup, EcKey},
+            nid::Nid,
+            x509::X509NameBuilder,
+        };
+
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let pkey = PKey::from_ec_key(EcKey::generate(&group)?)?;
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_text("CN", "test")?;
+        let name = name.build();
+        let mut cert = X509::builder()?;
+        cert.set_version(2)?;
+        cert.set_subject_name(&name)?;
+        cert.set_issuer_name(&name)?;
+        cert.set_pubkey(&pkey)?;
+        cert.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+        cert.set_not_after(&*Asn1Time::days_from_now(1)?)?;
+        cert.sign(&pkey, MessageDigest::sha256())?;
+        let pem = String::from_utf8(cert.build().to_pem()?)?;
+
+        let certs: HashMap<String, String> = serde_json::from_value(serde_json::json!({
+            "k1": pem,
+            "k2": "-----BEGIN CERTIFICATE-----\ninvalid\n-----END CERTIFICATE-----\n",
+        }))?;
+        let jwks = JwkSet::from_x509_pem_map(&certs);
+        assert_eq!(jwks.keys.len(), 1);
+
+        let k = SomePrivateKey::from_pem(&pkey.private_key_to_pem_pkcs8()?, RsaAlgorithm::RS256)?;
+        let token = sign(HeaderAndClaims::new_dynamic().set_kid("k1"), &k)?;
+        jwks.verifier().verify::<Value>(&token)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_x5c_only() -> Result<()> {
+        use openssl::{asn1::Asn1Time, x509::X509NameBuilder};
+
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let pkey = PKey::private_key_from_pem(k.private_key_to_pem_pkcs8()?.as_bytes())?;
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_text("CN", "org-1 signing")?;
+        let name = name.build();
+        let mut cert = X509::builder()?;
+        cert.set_version(2)?;
+        cert.set_subject_name(&name)?;
+        cert.set_issuer_name(&name)?;
+        cert.set_pubkey(&pkey)?;
+        cert.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+        cert.set_not_after(&*Asn1Time::days_from_now(1)?)?;
+        cert.sign(&pkey, MessageDigest::sha256())?;
+        let der = cert.build().to_der()?;
+        let x5c = b64::encode(&der);
+        let x5t = b64::encode_url(hash(MessageDigest::sha256(), &der)?);
+
+        let jwks: JwkSet = serde_json::from_value(serde_json::json!({ "keys": [
+            { "kty": "EC", "kid": "sig", "use": "sig", "x5c": [x5c], "x5t#S256": x5t },
+            { "kty": "EC", "kid": "tls", "use": "tls", "x5c": [x5c] },
+            { "kty": "EC", "kid": "signing", "use": "signing", "x5c": [x5c] },
+            { "kty": "EC", "kid": "bad-x5t", "x5c": [x5c], "x5t#S256": "AAAA" },
+            { "kty": "RSA", "kid": "bad-kty", "x5c": [x5c] },
+        ]}))?;
+        let verify = |v: &JwkSetVerifier, kid: &str| {
+            let token = sign(HeaderAndClaims::new_dynamic().set_kid(kid), &k)?;
+            v.verify::<Value>(&token)
+        };
+        let v = jwks.verifier();
+        verify(&v, "sig")?;
+        for kid in ["tls", "signing", "bad-x5t", "bad-kty"] {
+            assert!(matches!(verify(&v, kid), Err(Error::NoKey)), "{}", kid);
+        }
+
+        let v = jwks.verifier_with_policies(
+            &RsaKeyPolicy::default(),
+            KeyUsePolicy::AllowUses(vec!["sig".into(), "signing".into()]),
+        );
+        verify(&v, "sig")?;
+        verify(&v, "signing")?;
+        assert!(matches!(verify(&v, "tls"), Err(Error::NoKey)));
+        assert!(v.key_use_violations().is_empty());
+        Ok(())
+    }
+
+    #[cfg(feature = "remote-jwks")]
+    #[tokio::test(start_paused = true)]
+    async fn test_spawn_refresher() -> Result<()> {
+        use std::time::{Duration, Instant};
+
+        use crate::test_util::MockIssuer;
+
+        let mut issuer = MockIssuer::with_alg("EdDSA")?;
+        let url = issuer.serve()?;
+        let v = Arc::new(RemoteJwksVerifier::new(url, None, Duration::from_secs(60)));
+        // When a refresh is stored, the refresher is asleep. Yielding doesn't
+        // advance the paused clock.
+        let refreshed = |since: Option<Instant>| {
+            let v = v.clone();
+            async move {
+                loop {
+                    let valid_until = v.cache.read().await.as_ref().map(|c| c.valid_until);
+                    if valid_until.is_some() && valid_until != since {
+                        return valid_until;
+                    }
+                    tokio::task::yield_now().await;
+                }
+            }
+        };
+
+        let interval = Duration::from_secs(10);
+        let guard = v.spawn_refresher(interval);
+        let mut last = refreshed(None).await;
+        assert_eq!(issuer.jwks_fetches(), 1);
+        for fetches in 2..=3 {
+            tokio::time::advance(interval).await;
+            last = refreshed(last).await;
+            assert_eq!(issuer.jwks_fetches(), fetches);
+        }
+
+        // Already cached.
+        let token = issuer.mint(|_| {})?;
+        v.verify::<Value>(&token).await?;
+        assert_eq!(issuer.jwks_fetches(), 3);
+
+        drop(guard);
+        tokio::time::advance(interval * 3).await;
+        for _ in 0..100 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(issuer.jwks_fetches(), 3);
+        Ok(())
+    }
 }
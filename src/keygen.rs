@@ -0,0 +1,145 @@
+//! Generate a key and all its usual encodings in one call.
+
+#[cfg(feature = "rsa")]
+use crate::rsa::RsaPrivateKey;
+use crate::{jwk::Jwk, rsa::RsaAlgorithm, PrivateKeyToJwk, PublicKeyToJwk, Result, SomePrivateKey};
+
+/// Key generator.
+///
+/// ```
+/// # use jwtk::keygen::KeyGen;
+/// let generated = KeyGen::new("ES256").kid_from_thumbprint().generate()?;
+/// assert_eq!(generated.public_jwk.kid, generated.kid);
+/// # Ok::<_, jwtk::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyGen {
+    alg: String,
+    #[cfg_attr(not(feature = "rsa"), allow(dead_code))]
+    rsa_bits: u32,
+    kid: Kid,
+}
+
+#[derive(Debug, Clone)]
+enum Kid {
+    None,
+    Thumbprint,
+    Fixed(String),
+}
+
+/// A generated key in all its encodings.
+#[non_exhaustive]
+#[derive(Debug)]
+pub struct GeneratedKey {
+    pub key: SomePrivateKey,
+    /// Recommended key id, if requested.
+    pub kid: Option<String>,
+    /// PKCS#8 PEM.
+    pub private_pem: String,
+    /// PKCS#8 DER.
+    pub private_der: Vec<u8>,
+    pub private_jwk: Jwk,
+    /// SubjectPublicKeyInfo PEM.
+    pub public_pem: String,
+    /// SubjectPublicKeyInfo DER.
+    pub public_der: Vec<u8>,
+    pub public_jwk: Jwk,
+}
+
+impl KeyGen {
+    /// `alg` is a JWS algorithm name, e.g. `ES256`, `PS384` or `EdDSA`. HMAC
+    /// algorithms are not supported.
+    pub fn new(alg: impl Into<String>) -> Self {
+        Self {
+            alg: alg.into(),
+            rsa_bits: 2048,
+            kid: Kid::None,
+        }
+    }
+
+    /// RSA modulus size. Defaults to 2048.
+    pub fn rsa_bits(mut self, bits: u32) -> Self {
+        self.rsa_bits = bits;
+        self
+    }
+
+    /// Use the RFC 7638 SHA-256 thumbprint of the public key as key id.
+    pub fn kid_from_thumbprint(mut self) -> Self {
+        self.kid = Kid::Thumbprint;
+        self
+    }
+
+    pub fn kid(mut self, kid: impl Into<String>) -> Self {
+        self.kid = Kid::Fixed(kid.into());
+        self
+    }
+
+    pub fn generate(&self) -> Result<GeneratedKey> {
+        let key = match RsaAlgorithm::from_name(&self.alg) {
+            #[cfg(feature = "rsa")]
+            Ok(alg) => RsaPrivateKey::generate(self.rsa_bits, alg)?.into(),
+            _ => SomePrivateKey::generate(&self.alg)?,
+        };
+
+        let mut public_jwk = key.public_key_to_jwk()?;
+        let mut private_jwk = key.private_key_to_jwk()?;
+        let kid = match &self.kid {
+            Kid::None => None,
+            Kid::Thumbprint => Some(public_jwk.get_thumbprint_sha256_base64()?),
+            Kid::Fixed(kid) => Some(kid.clone()),
+        };
+        for jwk in [&mut public_jwk, &mut private_jwk] {
+            jwk.kid = kid.clone();
+            jwk.alg = Some(self.alg.clone());
+        }
+
+        Ok(GeneratedKey {
+            kid,
+            private_pem: key.private_key_to_pem_pkcs8()?,
+            private_der: key.private_key_to_der_pkcs8()?,
+            private_jwk,
+            public_pem: key.public_key_to_pem()?,
+            public_der: key.public_key_to_der()?,
+            public_jwk,
+            key,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "rsa", feature = "ecdsa", feature = "eddsa"))]
+mod tests {
+    use serde_json::{Map, Value};
+
+    use crate::{sign, verify, HeaderAndClaims, SomePublicKey};
+
+    use super::*;
+
+    #[test]
+    fn generate() -> Result<()> {
+        for alg in ["EdDSA", "ES256", "ES384", "PS256", "RS256"] {
+            let g = KeyGen::new(alg).kid_from_thumbprint().generate()?;
+            assert_eq!(g.kid, Some(g.public_jwk.get_thumbprint_sha256_base64()?));
+            assert_eq!(g.private_jwk.kid, g.kid);
+            assert_eq!(g.public_jwk.alg.as_deref(), Some(alg));
+
+            let token = sign(&mut HeaderAndClaims::new_dynamic(), &g.key)?;
+            verify::<Map<String, Value>>(&token, &g.public_jwk.to_verification_key()?)?;
+            verify::<Map<String, Value>>(
+                &token,
+                &SomePublicKey::from_pem(g.public_pem.as_bytes())?,
+            )?;
+            let k = g.private_jwk.to_signing_key(RsaAlgorithm::RS256)?;
+            verify::<Map<String, Value>>(&sign(&mut HeaderAndClaims::new_dynamic(), &k)?, &g.key)?;
+            assert_eq!(
+                openssl::pkey::PKey::private_key_from_der(&g.private_der)?
+                    .private_key_to_pem_pkcs8()?,
+                g.private_pem.as_bytes()
+            );
+        }
+
+        let g = KeyGen::new("ES256").kid("my key").generate()?;
+        assert_eq!(g.public_jwk.kid.as_deref(), Some("my key"));
+        assert!(KeyGen::new("HS256").generate().is_err());
+        Ok(())
+    }
+}
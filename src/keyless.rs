@@ -0,0 +1,323 @@
+//! Keyless verification, with short-lived certificates from a Sigstore
+//! Fulcio CA.
+//!
+//! Fulcio certifies an ephemeral key for the identity of an OIDC token, e.g.
+//! a CI workflow or an email address, for about ten minutes. A signature by
+//! that key is trusted if the certificate chains up to the Fulcio root, was
+//! valid when the signature was made, and is for an allowed identity.
+//!
+//! When the signature was made must come from a trusted source, e.g. the
+//! integrated time of its Rekor transparency log entry, or an RFC 3161
+//! timestamp. This module doesn't verify log inclusion or timestamps.
+//!
+//! ```no_run
+//! # fn f(token: &str, fulcio_root: openssl::x509::X509, integrated_time: std::time::SystemTime) -> jwtk::Result<()> {
+//! use jwtk::{keyless::KeylessVerifier, ValidationOptions};
+//!
+//! let mut verifier = KeylessVerifier::new(vec![fulcio_root]);
+//! verifier.allow_identity(
+//!     "https://token.actions.githubusercontent.com",
+//!     "https://github.com/octo-org/*",
+//! );
+//! let verified = verifier.verify_token::<serde_json::Value>(
+//!     token,
+//!     &ValidationOptions::default(),
+//!     integrated_time,
+//! )?;
+//! println!("signed by {}", verified.identity.san);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::SystemTime;
+
+use openssl::x509::{X509Ref, X509};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    b64,
+    dsse::Envelope,
+    presets::github::subject_matches,
+    verify_with_options,
+    x509::{extension_data, verify_chain},
+    Error, HeaderAndClaims, Result, SomePublicKey, UntrustedToken, ValidationOptions,
+};
+
+/// The OIDC issuer, as a DER encoded UTF8String.
+const ISSUER_OID: &str = "1.3.6.1.4.1.57264.1.8";
+
+/// The OIDC issuer, as raw bytes, in older certificates.
+const ISSUER_V1_OID: &str = "1.3.6.1.4.1.57264.1.1";
+
+/// The identity a Fulcio certificate is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FulcioIdentity {
+    /// Issuer of the OIDC token the certificate was issued for.
+    pub issuer: String,
+    /// The email address or URI subject alternative name, e.g. the
+    /// workflow of a GitHub Actions token.
+    pub san: String,
+}
+
+/// A verified payload, and the identity that signed it.
+#[derive(Debug, Clone)]
+pub struct Keyless<T> {
+    pub identity: FulcioIdentity,
+    pub verified: T,
+}
+
+/// Verifies signatures by keys certified by a Fulcio CA.
+pub struct KeylessVerifier {
+    roots: Vec<X509>,
+    intermediates: Vec<X509>,
+    identities: Vec<(String, String)>,
+    any_identity: bool,
+}
+
+impl KeylessVerifier {
+    /// Trust certificates that chain up to `roots`.
+    pub fn new(roots: Vec<X509>) -> Self {
+        Self {
+            roots,
+            intermediates: Vec::new(),
+            identities: Vec::new(),
+            any_identity: false,
+        }
+    }
+
+    /// An intermediate CA certificate, for chains that don't include it.
+    pub fn add_intermediate(&mut self, cert: X509) {
+        self.intermediates.push(cert);
+    }
+
+    /// Accept certificates for `issuer` and a SAN matching `san_pattern`, in
+    /// which `*` matches any sequence of characters and `?` any single
+    /// character, see [`subject_matches`]. A literal `?`, e.g. in a URL, is
+    /// matched by the wildcard too.
+    ///
+    /// Without any allowed identities all certificates are rejected, unless
+    /// [`allow_any_identity`](Self::allow_any_identity) is called.
+    pub fn allow_identity(&mut self, issuer: impl Into<String>, san_pattern: impl Into<String>) {
+        self.identities.push((issuer.into(), san_pattern.into()));
+    }
+
+    /// Accept certificates for any identity. The caller must then check
+    /// [`Keyless::identity`].
+    pub fn allow_any_identity(&mut self) {
+        self.any_identity = true;
+    }
+
+    /// Verify the certificate `chain`, leaf first, at `signed_at`, and return
+    /// its key and identity.
+    ///
+    /// This is the building block for payload formats other than tokens and
+    /// DSSE envelopes: verify their signature with the returned key.
+    pub fn verify_certificate(
+        &self,
+        chain: &[X509],
+        signed_at: SystemTime,
+    ) -> Result<(SomePublicKey, FulcioIdentity)> {
+        let (leaf, rest) = chain.split_first().ok_or(Error::InvalidToken)?;
+        let mut untrusted = rest.to_vec();
+        untrusted.extend(self.intermediates.iter().cloned());
+        verify_chain(&self.roots, leaf, &untrusted, signed_at)?;
+
+        let identity = identity(leaf)?;
+        if !self.any_identity
+            && !self
+                .identities
+                .iter()
+                .any(|(iss, san)| *iss == identity.issuer && subject_matches(san, &identity.san))
+        {
+            return Err(Error::VerificationError);
+        }
+        Ok((SomePublicKey::from_pkey(leaf.public_key()?)?, identity))
+    }
+
+    /// Verify a token whose `x5c` header has the certificate chain, see
+    /// [`verify_with_options`].
+    pub fn verify_token<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+        options: &ValidationOptions,
+        signed_at: SystemTime,
+    ) -> Result<Keyless<HeaderAndClaims<ExtraClaims>>> {
+        let untrusted = UntrustedToken::parse(token)?;
+        let x5c: Vec<String> = match untrusted.header().peek().extra.get("x5c") {
+            Some(x5c) => serde_json::from_value(x5c.clone())?,
+            None => return Err(Error::InvalidToken),
+        };
+        let chain = x5c
+            .iter()
+            .map(|c| Ok(X509::from_der(&b64::decode(c)?)?))
+            .collect::<Result<Vec<_>>>()?;
+        let (k, identity) = self.verify_certificate(&chain, signed_at)?;
+        Ok(Keyless {
+            identity,
+            verified: verify_with_options(token, &k, options)?,
+        })
+    }
+
+    /// Verify a DSSE envelope signed with `alg` by the key of `chain`, see
+    /// [`Envelope::verify`].
+    pub fn verify_envelope(
+        &self,
+        envelope: &Envelope,
+        payload_type: &str,
+        alg: &str,
+        chain: &[X509],
+        signed_at: SystemTime,
+    ) -> Result<Keyless<Vec<u8>>> {
+        let (k, identity) = self.verify_certificate(chain, signed_at)?;
+        Ok(Keyless {
+            identity,
+            verified: envelope.verify(payload_type, &k, alg)?,
+        })
+    }
+}
+
+fn identity(leaf: &X509Ref) -> Result<FulcioIdentity> {
+    let issuer = match extension_data(leaf, ISSUER_OID)? {
+        Some(der) => der_utf8_string(&der)?,
+        None => extension_data(leaf, ISSUER_V1_OID)?
+            .and_then(|v| String::from_utf8(v).ok())
+            .ok_or(Error::InvalidToken)?,
+    };
+    let san = leaf
+        .subject_alt_names()
+        .and_then(|names| {
+            names
+                .iter()
+                .find_map(|n| n.email().or_else(|| n.uri()).map(String::from))
+        })
+        .ok_or(Error::InvalidToken)?;
+    Ok(FulcioIdentity { issuer, san })
+}
+
+fn der_utf8_string(der: &[u8]) -> Result<String> {
+    let (len, rest) = match der {
+        [0x0c, len, rest @ ..] if *len < 0x80 => (*len as usize, rest),
+        [0x0c, 0x81, len, rest @ ..] => (*len as usize, rest),
+        [0x0c, 0x82, hi, lo, rest @ ..] => (usize::from(*hi) << 8 | usize::from(*lo), rest),
+        _ => return Err(Error::InvalidToken),
+    };
+    if rest.len() != len {
+        return Err(Error::InvalidToken);
+    }
+    String::from_utf8(rest.to_vec()).map_err(|_| Error::InvalidToken)
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use openssl::pkey::{PKey, Private};
+
+    use crate::{
+        dsse::IN_TOTO_PAYLOAD_TYPE,
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        sign,
+        x509::testing::{cert, extension, pkey},
+    };
+
+    use super::*;
+
+    fn leaf_cert(
+        key: &PKey<Private>,
+        ca: (&X509, &PKey<Private>),
+        issuer: &str,
+        email: &str,
+    ) -> Result<X509> {
+        let mut issuer_der = vec![0x0c, issuer.len() as u8];
+        issuer_der.extend_from_slice(issuer.as_bytes());
+        // GeneralNames with an rfc822Name.
+        let mut san_der = vec![0x30, email.len() as u8 + 2, 0x81, email.len() as u8];
+        san_der.extend_from_slice(email.as_bytes());
+        cert(
+            "ephemeral",
+            key,
+            Some(ca),
+            false,
+            vec![
+                extension("2.5.29.17", &san_der)?,
+                extension(ISSUER_OID, &issuer_der)?,
+            ],
+        )
+    }
+
+    #[test]
+    fn keyless() -> Result<()> {
+        let root_key = pkey(&EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?)?;
+        let intermediate_key = pkey(&EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?)?;
+        let root = cert("sigstore", &root_key, None, true, vec![])?;
+        let intermediate = cert(
+            "sigstore-intermediate",
+            &intermediate_key,
+            Some((&root, &root_key)),
+            true,
+            vec![],
+        )?;
+        let ephemeral = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let leaf = leaf_cert(
+            &pkey(&ephemeral)?,
+            (&intermediate, &intermediate_key),
+            "https://accounts.example.com",
+            "alice@example.com",
+        )?;
+        let now = SystemTime::now();
+
+        let mut verifier = KeylessVerifier::new(vec![root.clone()]);
+        verifier.add_intermediate(intermediate.clone());
+        verifier.allow_identity("https://accounts.example.com", "*@example.com");
+
+        let mut token = HeaderAndClaims::new_dynamic();
+        token.header_mut().extra.insert(
+            "x5c".into(),
+            serde_json::json!([b64::encode(leaf.to_der()?)]),
+        );
+        let jwt = sign(token.set_sub("build"), &ephemeral)?;
+        let verified =
+            verifier.verify_token::<serde_json::Value>(&jwt, &ValidationOptions::default(), now)?;
+        assert_eq!(
+            verified.identity,
+            FulcioIdentity {
+                issuer: "https://accounts.example.com".into(),
+                san: "alice@example.com".into(),
+            }
+        );
+        assert_eq!(verified.verified.claims().sub.as_deref(), Some("build"));
+
+        let envelope = Envelope::sign(IN_TOTO_PAYLOAD_TYPE, b"{}", &ephemeral)?;
+        let verified = verifier.verify_envelope(
+            &envelope,
+            IN_TOTO_PAYLOAD_TYPE,
+            "ES256",
+            &[leaf.clone(), intermediate],
+            now,
+        )?;
+        assert_eq!(verified.verified, b"{}");
+
+        // Not valid at the time of signing.
+        let before = now - std::time::Duration::from_secs(3600);
+        assert!(verifier
+            .verify_certificate(std::slice::from_ref(&leaf), before)
+            .is_err());
+        // Another identity.
+        let mut other = KeylessVerifier::new(vec![root]);
+        other.add_intermediate(verifier.intermediates[0].clone());
+        other.allow_identity("https://accounts.example.com", "bob@example.com");
+        assert!(other
+            .verify_certificate(std::slice::from_ref(&leaf), now)
+            .is_err());
+        // No allowed identities.
+        other.identities.clear();
+        assert!(other
+            .verify_certificate(std::slice::from_ref(&leaf), now)
+            .is_err());
+        other.allow_any_identity();
+        other.verify_certificate(std::slice::from_ref(&leaf), now)?;
+        // Another CA.
+        let mut other = KeylessVerifier::new(vec![verifier.intermediates[0].clone()]);
+        other.allow_any_identity();
+        assert!(other.verify_certificate(&[leaf], now).is_err());
+        Ok(())
+    }
+}
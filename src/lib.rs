@@ -4,11 +4,13 @@ use openssl::error::ErrorStack;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{Map, Value};
 use serde_with::{serde_as, skip_serializing_none};
+#[cfg(not(feature = "verify-only"))]
 use smallvec::SmallVec;
+#[cfg(not(feature = "verify-only"))]
+use std::io::Write;
 use std::{
     borrow::Cow,
     fmt,
-    io::Write,
     string::FromUtf8Error,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
@@ -16,21 +18,137 @@ use std::{
 use jwk::Jwk;
 pub use some::*;
 
+#[cfg(not(any(feature = "rsa", feature = "ecdsa", feature = "eddsa")))]
+compile_error!("at least one of the features rsa, ecdsa and eddsa must be enabled");
+
+#[cfg(all(
+    feature = "verify-only",
+    any(
+        feature = "cli",
+        feature = "ffi",
+        feature = "test-util",
+        feature = "test-vectors",
+        feature = "openssl-store"
+    )
+))]
+compile_error!("the verify-only feature can't be combined with features that sign");
+
 mod some;
 
+#[cfg(feature = "hmac")]
 pub mod hmac;
 
+#[cfg(feature = "eddsa")]
 pub mod eddsa;
 
+#[cfg(feature = "ecdsa")]
 pub mod ecdsa;
 
+/// RSA keys require the `rsa` feature. The algorithms and policies are
+/// always available, as they are part of other APIs.
 pub mod rsa;
 
 pub mod jwk;
 
+#[cfg(not(feature = "verify-only"))]
+pub mod keygen;
+
+pub mod revocation;
+
+pub mod status_list;
+
+pub mod vendor;
+
+pub mod presets;
+
+pub mod spiffe;
+
+#[cfg(not(feature = "verify-only"))]
+pub mod session;
+
+pub mod cookie;
+
+pub mod reload;
+
+pub mod stream;
+
+#[cfg(not(feature = "verify-only"))]
+pub mod issuance;
+
+pub mod throttle;
+
+#[cfg(not(feature = "verify-only"))]
+pub mod external;
+
+pub mod lint;
+
+pub mod explain;
+
+pub mod dsse;
+
+pub mod keyless;
+
+pub mod audience;
+
+pub mod chain;
+
+pub mod webhook;
+
+pub mod http_sig;
+
+pub mod roles;
+
+pub mod token_cache;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "json-schema")]
+pub mod schema;
+
+#[cfg(feature = "remote-jwks")]
+pub mod authenticator;
+
+#[cfg(feature = "offload")]
+pub mod offload;
+
+#[cfg(any(feature = "test-util", all(test, not(feature = "verify-only"))))]
+pub mod test_util;
+
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod test_vectors;
+
+mod validation;
+pub use validation::{Coercion, Profile, Rule, ValidationOptions};
+
+mod actor;
+pub use actor::Actor;
+
+mod fingerprint;
+pub use fingerprint::Fingerprint;
+
+mod redact;
+pub use redact::Unredacted;
+
+mod verifier;
+pub use verifier::{AnyOfVerifier, SharedVerifier, Verifier, VerifierConfig};
+
+mod untrusted;
+pub use untrusted::{Untrusted, UntrustedToken};
+
+mod compact;
+pub use compact::CompactToken;
+
+mod x509;
+
+mod b64;
+
+#[cfg(feature = "openssl-store")]
+mod store;
+
 /// JWT header.
 #[non_exhaustive]
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct Header {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub typ: Option<String>,
@@ -40,6 +158,8 @@ pub struct Header {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub kid: Option<String>,
 
+    /// All other parameters, e.g. `x5t` or `crit`. They are kept when a
+    /// decoded header is signed again.
     #[serde(flatten)]
     pub extra: Map<String, Value>,
 }
@@ -71,7 +191,7 @@ impl<T> Default for OneOrMany<T> {
 #[serde_as]
 #[skip_serializing_none]
 #[non_exhaustive]
-#[derive(Debug, Serialize, Default, Deserialize)]
+#[derive(Clone, Serialize, Default, Deserialize)]
 pub struct Claims<ExtraClaims> {
     #[serde_as(as = "Option<serde_with::DurationSeconds<f64>>")]
     pub exp: Option<Duration>,
@@ -85,11 +205,92 @@ pub struct Claims<ExtraClaims> {
     #[serde(default, skip_serializing_if = "OneOrMany::is_empty")]
     pub aud: OneOrMany<String>,
     pub jti: Option<String>,
+    /// Actor, RFC 8693 section 4.1.
+    pub act: Option<Actor>,
+    /// Authorized actor, RFC 8693 section 4.4.
+    pub may_act: Option<Actor>,
+
+    // The OpenID Connect and security event claims below are ignored when
+    // malformed, rather than rejecting the token, as issuers differ in how
+    // they fill them in.
+    /// When the end user authenticated, OpenID Connect Core section 2.
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DefaultOnError<Option<serde_with::DurationSeconds<f64>>>")]
+    pub auth_time: Option<Duration>,
+    /// Authentication context class reference.
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DefaultOnError")]
+    pub acr: Option<String>,
+    /// Authentication methods references, RFC 8176 section 2. A single
+    /// string is accepted too, other values are skipped.
+    #[serde(
+        default,
+        deserialize_with = "lenient_strings",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub amr: Vec<String>,
+    /// Authorized party, the client the token was issued to.
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DefaultOnError")]
+    pub azp: Option<String>,
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DefaultOnError")]
+    pub nonce: Option<String>,
+    /// Session id, OpenID Connect Front-Channel Logout section 3.
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DefaultOnError")]
+    pub sid: Option<String>,
+    /// Security events, by event type, RFC 8417 section 2.2.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    #[serde_as(as = "serde_with::DefaultOnError")]
+    pub events: Map<String, Value>,
+    /// Time of the event, RFC 8417 section 2.2.
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DefaultOnError<Option<serde_with::DurationSeconds<f64>>>")]
+    pub toe: Option<Duration>,
+    /// Transaction identifier, RFC 8417 section 2.2.
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DefaultOnError")]
+    pub txn: Option<String>,
 
     #[serde(flatten)]
     pub extra: ExtraClaims,
 }
 
+impl<ExtraClaims> Claims<ExtraClaims> {
+    /// Whether `acr` is present and one of `acrs`.
+    pub fn acr_is_one_of(&self, acrs: &[&str]) -> bool {
+        self.acr.as_deref().is_some_and(|acr| acrs.contains(&acr))
+    }
+
+    /// Whether `amr` contains `method`.
+    pub fn has_amr(&self, method: &str) -> bool {
+        self.amr.iter().any(|m| m == method)
+    }
+
+    /// Whether `amr` contains any of `methods`, e.g. `&["mfa", "hwk"]`.
+    pub fn has_any_amr(&self, methods: &[&str]) -> bool {
+        methods.iter().any(|m| self.has_amr(m))
+    }
+}
+
+/// A string or an array of strings, skipping anything else.
+fn lenient_strings<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<String>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => vec![s],
+        Value::Array(values) => values
+            .into_iter()
+            .filter_map(|v| match v {
+                Value::String(s) => Some(s),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
 /// JWT header and claims.
 ///
 /// # `ExtraClaims`
@@ -97,7 +298,7 @@ pub struct Claims<ExtraClaims> {
 /// Use `serde_json::Map<String, Value>` for dynamic claims.
 ///
 /// Or define your own claims type which implements `Serialize`/`Deserialize`.
-#[derive(Default, Debug)]
+#[derive(Default)]
 pub struct HeaderAndClaims<ExtraClaims> {
     header: Header,
     claims: Claims<ExtraClaims>,
@@ -133,6 +334,17 @@ impl<ExtraClaims> HeaderAndClaims<ExtraClaims> {
                 jti: None,
                 nbf: None,
                 sub: None,
+                act: None,
+                may_act: None,
+                auth_time: None,
+                acr: None,
+                amr: Vec::new(),
+                azp: None,
+                nonce: None,
+                sid: None,
+                events: Map::new(),
+                toe: None,
+                txn: None,
                 extra,
             },
         }
@@ -167,6 +379,11 @@ impl<ExtraClaims> HeaderAndClaims<ExtraClaims> {
     define_setter!(set_iss, iss);
     define_setter!(set_sub, sub);
     define_setter!(set_jti, jti);
+    define_setter!(set_acr, acr);
+    define_setter!(set_azp, azp);
+    define_setter!(set_nonce, nonce);
+    define_setter!(set_sid, sid);
+    define_setter!(set_txn, txn);
 
     #[inline]
     pub fn set_auds(&mut self, auds: Vec<String>) -> &mut Self {
@@ -185,6 +402,18 @@ impl<ExtraClaims> HeaderAndClaims<ExtraClaims> {
         self
     }
 
+    #[inline]
+    pub fn set_act(&mut self, act: Actor) -> &mut Self {
+        self.claims.act = Some(act);
+        self
+    }
+
+    #[inline]
+    pub fn set_may_act(&mut self, may_act: Actor) -> &mut Self {
+        self.claims.may_act = Some(may_act);
+        self
+    }
+
     /// Set token issued-at time (`iat`) to the current system time, i.e.
     /// `SystemTime::now()`.
     pub fn set_iat_now(&mut self) -> &mut Self {
@@ -200,7 +429,7 @@ impl<ExtraClaims> HeaderAndClaims<ExtraClaims> {
     pub fn iat_is_later_than(&self, t: SystemTime) -> bool {
         self.claims
             .iat
-            .map_or(false, |iat| iat > t.duration_since(UNIX_EPOCH).unwrap())
+            .is_some_and(|iat| iat > t.duration_since(UNIX_EPOCH).unwrap())
     }
 
     /// Set token expiration time (`exp`) to some time after the current time,
@@ -230,11 +459,63 @@ impl HeaderAndClaims<Map<String, Value>> {
         self.claims.extra.insert(k.into(), v.into());
         self
     }
+
+    /// Set a nested extra claim, see [`Claims::set_path`].
+    #[inline]
+    pub fn set_path(&mut self, path: &str, v: impl Into<Value>) -> &mut Self {
+        self.claims.set_path(path, v);
+        self
+    }
 }
 
-#[inline(always)]
-fn url_safe_trailing_bits() -> base64::Config {
-    base64::URL_SAFE_NO_PAD.decode_allow_trailing_bits(true)
+/// Dotted path accessors for nested extra claims, e.g. Keycloak's
+/// `realm_access.roles`.
+///
+/// Path segments are separated by `.`, and index into objects by member name
+/// or into arrays by position. Members whose names contain a `.`, e.g.
+/// namespaced Auth0 claims, must be read from `extra` directly.
+impl Claims<Map<String, Value>> {
+    /// The value at `path`, if any.
+    pub fn get_path_value(&self, path: &str) -> Option<&Value> {
+        let mut segments = path.split('.');
+        let mut v = self.extra.get(segments.next()?)?;
+        for segment in segments {
+            v = match v {
+                Value::Object(o) => o.get(segment)?,
+                Value::Array(a) => a.get(segment.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(v)
+    }
+
+    /// The value at `path` deserialized as `T`, e.g.
+    /// `claims.get_path::<Vec<String>>("realm_access.roles")`.
+    ///
+    /// Returns `None` if there is no such value or if it is not a `T`.
+    pub fn get_path<T: DeserializeOwned>(&self, path: &str) -> Option<T> {
+        T::deserialize(self.get_path_value(path)?).ok()
+    }
+
+    /// Set the value at `path`, creating objects along the way. Any
+    /// non-object value along the way is replaced by an object.
+    pub fn set_path(&mut self, path: &str, v: impl Into<Value>) {
+        let mut segments = path.split('.');
+        let mut last = segments.next().unwrap_or_default();
+        let mut map = &mut self.extra;
+        for segment in segments {
+            let entry = map.entry(last).or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            map = match entry {
+                Value::Object(o) => o,
+                _ => unreachable!(),
+            };
+            last = segment;
+        }
+        map.insert(last.into(), v.into());
+    }
 }
 
 /// Encode and sign this header and claims with the signing key.
@@ -243,6 +524,7 @@ fn url_safe_trailing_bits() -> base64::Config {
 /// automatically set if the key has an id.
 ///
 /// Returns a signed JWT.
+#[cfg(not(feature = "verify-only"))]
 pub fn sign<ExtraClaims: Serialize>(
     claims: &mut HeaderAndClaims<ExtraClaims>,
     k: &dyn SigningKey,
@@ -252,12 +534,12 @@ pub fn sign<ExtraClaims: Serialize>(
         claims.set_kid(kid);
     }
 
-    let mut w = base64::write::EncoderStringWriter::new(url_safe_trailing_bits());
+    let mut w = base64::write::EncoderStringWriter::new(&b64::URL_SAFE);
     serde_json::to_writer(&mut w, &claims.header)?;
 
     let mut buf = w.into_inner();
     buf.push('.');
-    let mut w = base64::write::EncoderStringWriter::from(buf, url_safe_trailing_bits());
+    let mut w = base64::write::EncoderStringWriter::from_consumer(buf, &b64::URL_SAFE);
 
     serde_json::to_writer(&mut w, &claims.claims)?;
     let mut buf = w.into_inner();
@@ -266,11 +548,86 @@ pub fn sign<ExtraClaims: Serialize>(
 
     buf.push('.');
 
-    let mut w = base64::write::EncoderStringWriter::from(buf, url_safe_trailing_bits());
+    let mut w = base64::write::EncoderStringWriter::from_consumer(buf, &b64::URL_SAFE);
     w.write_all(&sig)?;
     Ok(w.into_inner())
 }
 
+/// Sign the same header and claims with each of the keys, returning one token
+/// per key, in order.
+///
+/// Useful during an algorithm or key migration, when tokens should be
+/// accepted both by verifiers that only know the old key and by those that
+/// only know the new one. `alg` and `kid` are set per key as with [`sign`],
+/// keys without an id keep the original `kid`. Afterwards the header is left
+/// as it was for the last key.
+#[cfg(not(feature = "verify-only"))]
+pub fn sign_compact_multi<ExtraClaims: Serialize>(
+    claims: &mut HeaderAndClaims<ExtraClaims>,
+    keys: &[&dyn SigningKey],
+) -> Result<Vec<String>> {
+    let kid = claims.header.kid.clone();
+    keys.iter()
+        .map(|k| {
+            claims.header.kid.clone_from(&kid);
+            sign(claims, *k)
+        })
+        .collect()
+}
+
+/// Like [`sign`], but the same header and claims always give byte-identical
+/// header and payload segments.
+///
+/// Object keys are sorted, and numbers without a fractional part are written
+/// as integers, e.g. `exp`. Tokens can then be compared or cached by their
+/// signing input. The signature itself is only deterministic with HMAC,
+/// Ed25519 and RS* keys: ECDSA and PS* signatures are randomized.
+#[cfg(not(feature = "verify-only"))]
+pub fn sign_deterministic<ExtraClaims: Serialize>(
+    claims: &mut HeaderAndClaims<ExtraClaims>,
+    k: &dyn SigningKey,
+) -> Result<String> {
+    claims.header.alg = k.alg().into();
+    if let Some(kid) = k.kid() {
+        claims.set_kid(kid);
+    }
+
+    let header = canonical_json(&claims.header)?;
+    let payload = canonical_json(&claims.claims)?;
+    encode_and_sign(&header, &payload, k)
+}
+
+/// Serialize with sorted object keys and integral floats as integers.
+#[cfg(not(feature = "verify-only"))]
+pub(crate) fn canonical_json<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    fn canonicalize(value: Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.into_iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| (k, canonicalize(v)))
+                        .collect(),
+                )
+            }
+            Value::Array(values) => Value::Array(values.into_iter().map(canonicalize).collect()),
+            Value::Number(n) => match n.as_f64() {
+                // Below 2^53 every integer is exactly representable.
+                Some(f) if !n.is_i64() && !n.is_u64() && f.fract() == 0.0 && f.abs() < 9.0e15 => {
+                    Value::from(f as i64)
+                }
+                _ => Value::Number(n),
+            },
+            value => value,
+        }
+    }
+    Ok(serde_json::to_vec(&canonicalize(serde_json::to_value(
+        value,
+    )?))?)
+}
+
 /// Decode and verify token.
 ///
 /// The `alg`, `exp` and `nbf` fields are automatically checked.
@@ -278,24 +635,104 @@ pub fn verify<ExtraClaims: DeserializeOwned>(
     token: &str,
     k: &dyn VerificationKey,
 ) -> Result<HeaderAndClaims<ExtraClaims>> {
-    let claims = verify_only(token, k)?;
+    verify_with_options(token, k, &ValidationOptions::default())
+}
 
-    // Check exp and nbf.
-    let now = SystemTime::now();
-    if let Some(exp) = claims.claims.exp {
-        let exp = SystemTime::UNIX_EPOCH + exp;
-        if now > exp {
-            return Err(Error::Expired);
+/// Decode and verify token, and validate it according to `options`.
+///
+/// The `alg`, `exp` and `nbf` fields are automatically checked.
+pub fn verify_with_options<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &ValidationOptions,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let (header, payload) = verify_signature(token, k, options)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let claims = decode_and_check_claims(payload, options, Some(now))?;
+
+    Ok(HeaderAndClaims { header, claims })
+}
+
+/// A verified token, with the exact compact serialization that was verified.
+///
+/// Derefs to [`HeaderAndClaims`]. Middleware can e.g. log the header's `kid`,
+/// or keep the signing input and signature as evidence. A proxy should
+/// forward [`as_str`](Self::as_str): encoding the decoded header and claims
+/// again need not give the same bytes, e.g. the order of header parameters
+/// may change, so the signature would no longer match.
+pub struct VerifiedToken<ExtraClaims> {
+    token: HeaderAndClaims<ExtraClaims>,
+    compact: String,
+}
+
+impl<ExtraClaims> VerifiedToken<ExtraClaims> {
+    pub(crate) fn new(token: HeaderAndClaims<ExtraClaims>, compact: &str) -> Self {
+        Self {
+            token,
+            compact: compact.into(),
         }
     }
-    if let Some(nbf) = claims.claims.nbf {
-        let nbf = SystemTime::UNIX_EPOCH + nbf;
-        if now < nbf {
-            return Err(Error::Before);
-        }
+
+    /// The compact serialization.
+    pub fn as_str(&self) -> &str {
+        &self.compact
+    }
+
+    /// The header exactly as it was encoded in the token.
+    pub fn header_segment(&self) -> &str {
+        self.compact.split('.').next().unwrap()
     }
 
-    Ok(claims)
+    /// The claims exactly as they were encoded in the token.
+    pub fn payload_segment(&self) -> &str {
+        self.compact.split('.').nth(1).unwrap()
+    }
+
+    /// The bytes the signature is over, i.e. `header.payload`.
+    pub fn signing_input(&self) -> &[u8] {
+        let end = self.compact.rfind('.').unwrap();
+        &self.compact.as_bytes()[..end]
+    }
+
+    /// The decoded signature.
+    pub fn signature(&self) -> Vec<u8> {
+        let start = self.compact.rfind('.').unwrap() + 1;
+        b64::decode_url(&self.compact[start..]).unwrap()
+    }
+
+    pub fn into_header_and_claims(self) -> HeaderAndClaims<ExtraClaims> {
+        self.token
+    }
+}
+
+impl<ExtraClaims> std::ops::Deref for VerifiedToken<ExtraClaims> {
+    type Target = HeaderAndClaims<ExtraClaims>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.token
+    }
+}
+
+impl<ExtraClaims: fmt::Debug> fmt::Debug for VerifiedToken<ExtraClaims> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerifiedToken")
+            .field("token", &self.token)
+            .field("compact", &redact::RedactedToken(&self.compact))
+            .finish()
+    }
+}
+
+/// Like [`verify_with_options`], but also keep the verified token, see
+/// [`VerifiedToken`].
+pub fn verify_token<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &ValidationOptions,
+) -> Result<VerifiedToken<ExtraClaims>> {
+    Ok(VerifiedToken::new(
+        verify_with_options(token, k, options)?,
+        token,
+    ))
 }
 
 /// Decode and verify token, but do not check `exp` and `nbf`.
@@ -305,58 +742,262 @@ pub fn verify_only<ExtraClaims: DeserializeOwned>(
     token: &str,
     k: &dyn VerificationKey,
 ) -> Result<HeaderAndClaims<ExtraClaims>> {
-    let mut parts = token.split('.');
+    verify_only_with_options(token, k, &ValidationOptions::default())
+}
+
+pub(crate) fn verify_only_with_options<ExtraClaims: DeserializeOwned>(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &ValidationOptions,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let (header, payload) = verify_signature(token, k, options)?;
+    let claims = decode_and_check_claims(payload, options, None)?;
 
-    let mut header = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
-    let mut payload = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
+    Ok(HeaderAndClaims { header, claims })
+}
+
+/// Verify the signature of `token`, and check its header. Returns the header
+/// and the still encoded payload.
+fn verify_signature<'a>(
+    token: &'a str,
+    k: &dyn VerificationKey,
+    options: &ValidationOptions,
+) -> Result<(Header, &'a str)> {
+    options.check_token_len(token)?;
+    let (header, payload, sig) = split_token(token)?;
     let header_and_payload_len = header.len() + payload.len() + 1;
-    let sig = parts.next().ok_or(Error::InvalidToken)?;
-    if parts.next().is_some() {
-        return Err(Error::InvalidToken);
-    }
 
-    let header_r = base64::read::DecoderReader::new(&mut header, url_safe_trailing_bits());
-    let header: Header = serde_json::from_reader(header_r)?;
+    let header = decode_header(header, options)?;
+    options.check_alg(&header.alg)?;
 
-    let sig = base64::decode_config(sig, url_safe_trailing_bits())?;
+    let sig = b64::decode_url(sig)?;
 
     // Verify the signature.
     k.verify(
-        token[..header_and_payload_len].as_bytes(),
+        &token.as_bytes()[..header_and_payload_len],
         &sig,
         &header.alg,
     )?;
 
-    let payload_r = base64::read::DecoderReader::new(&mut payload, url_safe_trailing_bits());
-    let claims: Claims<ExtraClaims> = serde_json::from_reader(payload_r)?;
+    options.check_typ(header.typ.as_deref())?;
 
-    Ok(HeaderAndClaims { header, claims })
+    Ok((header, payload))
+}
+
+/// Sign `payload` as is, e.g. CBOR or protobuf, instead of JSON claims.
+///
+/// `cty` is the content type of the payload, e.g. `application/cbor`, if
+/// any. There are no claims, so nothing like `exp` is checked on
+/// verification, see [`verify_raw`].
+#[cfg(not(feature = "verify-only"))]
+pub fn sign_raw(payload: &[u8], cty: Option<&str>, k: &dyn SigningKey) -> Result<String> {
+    let mut header = Header {
+        alg: k.alg().into(),
+        kid: k.kid().map(Into::into),
+        ..Header::default()
+    };
+    if let Some(cty) = cty {
+        header.extra.insert("cty".into(), cty.into());
+    }
+
+    encode_and_sign(&serde_json::to_vec(&header)?, payload, k)
+}
+
+/// `header.payload.signature` from the encoded header and payload.
+#[cfg(not(feature = "verify-only"))]
+fn encode_and_sign(header: &[u8], payload: &[u8], k: &dyn SigningKey) -> Result<String> {
+    let mut buf = b64::encode_url(header);
+    buf.push('.');
+    b64::encode_url_buf(payload, &mut buf);
+    let sig = k.sign(buf.as_bytes())?;
+    buf.push('.');
+    b64::encode_url_buf(sig, &mut buf);
+    Ok(buf)
+}
+
+/// Verify a token signed with [`sign_raw`], and return its header and
+/// payload.
+///
+/// Only the header is validated according to `options`, e.g. its size, `alg`
+/// and `typ`. Check `cty` in the header's `extra` before parsing the payload.
+pub fn verify_raw(
+    token: &str,
+    k: &dyn VerificationKey,
+    options: &ValidationOptions,
+) -> Result<(Header, Vec<u8>)> {
+    let (header, payload) = verify_signature(token, k, options)?;
+    let payload = b64::decode_url(payload)?;
+    Ok((header, payload))
 }
 
 /// Decode token.
 ///
-/// No verification or validation is performed.
+/// No verification or validation is performed, except for the size limits
+/// of the default [`ValidationOptions`].
 pub fn decode_without_verify<ExtraClaims: DeserializeOwned>(
     token: &str,
 ) -> Result<HeaderAndClaims<ExtraClaims>> {
+    let options = ValidationOptions::default();
+    options.check_token_len(token)?;
+    let (header, payload, _sig) = split_token(token)?;
+
+    let header = decode_header(header, &options)?;
+    let claims = decode_claims(payload, &options)?;
+
+    Ok(HeaderAndClaims { header, claims })
+}
+
+/// Verify `token` with `k`, let `transform` modify its claims, and sign the
+/// result with `signer`.
+///
+/// This is the core of a token exchange (RFC 8693) service, e.g. to downscope
+/// `aud` or to copy `sub` into a token for another audience. The inbound
+/// header is not carried over: `transform` starts with the verified claims and
+/// an empty header, except for `typ`. Returning an error from `transform`
+/// aborts the exchange.
+///
+/// ```
+/// # use jwtk::{ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey}, resign, sign, verify, HeaderAndClaims};
+/// # let inbound = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+/// # let outbound = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?;
+/// # let token = sign(HeaderAndClaims::new_dynamic().set_sub("me").add_aud("gateway"), &inbound)?;
+/// let exchanged = resign::<serde_json::Map<_, _>>(&token, &inbound, &outbound, |t| {
+///     t.claims_mut().aud = Default::default();
+///     t.add_aud("backend");
+///     Ok(())
+/// })?;
+/// # verify::<serde_json::Map<_, _>>(&exchanged, &outbound)?;
+/// # Ok::<_, jwtk::Error>(())
+/// ```
+#[cfg(not(feature = "verify-only"))]
+pub fn resign<ExtraClaims: DeserializeOwned + Serialize>(
+    token: &str,
+    k: &dyn VerificationKey,
+    signer: &dyn SigningKey,
+    transform: impl FnOnce(&mut HeaderAndClaims<ExtraClaims>) -> Result<()>,
+) -> Result<String> {
+    let verified = verify::<ExtraClaims>(token, k)?;
+    let mut exchanged = HeaderAndClaims {
+        header: Header {
+            typ: verified.header.typ,
+            ..Header::default()
+        },
+        claims: verified.claims,
+    };
+    transform(&mut exchanged)?;
+    sign(&mut exchanged, signer)
+}
+
+/// Split a compact serialization into its header, payload and signature
+/// segments.
+fn split_token(token: &str) -> Result<(&str, &str, &str)> {
     let mut parts = token.split('.');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(payload), Some(sig), None) => Ok((header, payload, sig)),
+        _ => Err(Error::InvalidToken),
+    }
+}
 
-    let mut header = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
-    let mut payload = parts.next().ok_or(Error::InvalidToken)?.as_bytes();
-    let _sig = parts.next().ok_or(Error::InvalidToken)?;
-    if parts.next().is_some() {
-        return Err(Error::InvalidToken);
+fn decode_header(segment: &str, options: &ValidationOptions) -> Result<Header> {
+    decode_segment(
+        segment,
+        options.max_header_len,
+        options.max_depth,
+        options.reject_duplicate_header_keys,
+    )
+}
+
+fn decode_claims<ExtraClaims: DeserializeOwned>(
+    segment: &str,
+    options: &ValidationOptions,
+) -> Result<Claims<ExtraClaims>> {
+    if options.inspects_claims() {
+        let mut claims: Value = decode_segment(
+            segment,
+            options.max_claims_len,
+            options.max_depth,
+            options.reject_duplicate_claim_keys,
+        )?;
+        options.prepare_claims(&mut claims)?;
+        return Ok(serde_json::from_value(claims)?);
     }
+    decode_segment(
+        segment,
+        options.max_claims_len,
+        options.max_depth,
+        options.reject_duplicate_claim_keys,
+    )
+}
 
-    let header_r = base64::read::DecoderReader::new(&mut header, url_safe_trailing_bits());
-    let header: Header = serde_json::from_reader(header_r)?;
+/// Decode the claims and check them, and at `now` also their `exp`, `nbf`
+/// and `auth_time`. The claims mappers of `options` run after these checks,
+/// so they only see claims that passed them.
+fn decode_and_check_claims<ExtraClaims: DeserializeOwned>(
+    segment: &str,
+    options: &ValidationOptions,
+    now: Option<Duration>,
+) -> Result<Claims<ExtraClaims>> {
+    if !options.maps_claims() {
+        let claims = decode_claims(segment, options)?;
+        check_claims(&claims, options, now)?;
+        return Ok(claims);
+    }
+    let mut claims: Value = decode_segment(
+        segment,
+        options.max_claims_len,
+        options.max_depth,
+        options.reject_duplicate_claim_keys,
+    )?;
+    options.prepare_claims(&mut claims)?;
+    check_claims(&Claims::<()>::deserialize(&claims)?, options, now)?;
+    options.apply_claims_mappers(&mut claims);
+    Ok(serde_json::from_value(claims)?)
+}
 
-    let payload_r = base64::read::DecoderReader::new(&mut payload, url_safe_trailing_bits());
-    let claims: Claims<ExtraClaims> = serde_json::from_reader(payload_r)?;
+fn check_claims<ExtraClaims>(
+    claims: &Claims<ExtraClaims>,
+    options: &ValidationOptions,
+    now: Option<Duration>,
+) -> Result<()> {
+    options.check_claims(claims)?;
+    let now = match now {
+        Some(now) => now,
+        None => return Ok(()),
+    };
 
-    Ok(HeaderAndClaims { header, claims })
+    // Check exp and nbf. Compare durations since the epoch so that huge
+    // values can't overflow `SystemTime`.
+    if let Some(exp) = claims.exp {
+        if now > exp.saturating_add(options.leeway) {
+            return Err(Error::Expired);
+        }
+    }
+    if let Some(nbf) = claims.nbf {
+        if now.saturating_add(options.leeway) < nbf {
+            return Err(Error::Before);
+        }
+    }
+    options.check_auth_time(claims.auth_time, now)
 }
 
+/// Decode a base64url encoded JSON segment, checking its size before
+/// decoding and its structure before parsing.
+fn decode_segment<T: DeserializeOwned>(
+    segment: &str,
+    max_len: usize,
+    max_depth: usize,
+    reject_duplicate_keys: bool,
+) -> Result<T> {
+    validation::check_encoded_len(segment, max_len)?;
+    let json = b64::decode_url(segment)?;
+    validation::check_depth(&json, max_depth)?;
+    if reject_duplicate_keys {
+        validation::check_duplicate_keys(&json)?;
+    }
+    Ok(serde_json::from_slice(&json)?)
+}
+
+#[cfg(not(feature = "verify-only"))]
 pub trait SigningKey {
     // A signing key has a rigid algorithm.
     fn alg(&self) -> &'static str;
@@ -369,18 +1010,35 @@ pub trait SigningKey {
 
     // Es256 and eddsa signatures are 64-byte long.
     fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>>;
+
+    /// Start a signature over input that is written to the returned stream,
+    /// instead of passed at once. See the [`stream`] module.
+    ///
+    /// Returns `Error::UnsupportedOrInvalidKey` for keys that can't sign
+    /// incrementally, e.g. Ed25519 keys.
+    fn sign_stream(&self) -> Result<stream::SignStream<'_>> {
+        Err(Error::UnsupportedOrInvalidKey)
+    }
 }
 
 pub trait VerificationKey {
     // `alg` is passed in because HMAC and RSA verification keys can verify
     // signatures generated with multiple algorithms.
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()>;
+
+    /// Start verifying a signature over input that is written to the
+    /// returned stream. See [`SigningKey::sign_stream`].
+    fn verify_stream(&self, alg: &str) -> Result<stream::VerifyStream<'_>> {
+        let _ = alg;
+        Err(Error::UnsupportedOrInvalidKey)
+    }
 }
 
 pub trait PublicKeyToJwk {
     fn public_key_to_jwk(&self) -> Result<Jwk>;
 }
 
+#[cfg(not(feature = "verify-only"))]
 pub trait PrivateKeyToJwk {
     fn private_key_to_jwk(&self) -> Result<Jwk>;
 }
@@ -396,12 +1054,71 @@ pub enum Error {
     Expired,
     /// The token is not valid yet , i.e. `nbf` check failed.
     Before,
+    /// The end user authenticated longer ago than the
+    /// [`ValidationOptions`] allow, i.e. `auth_time` check failed.
+    AuthTooOld,
+    /// The token has been revoked, see [`revocation`].
+    Revoked,
+    /// A single-use token has already been used, see
+    /// [`revocation::ReplayCache`].
+    Replayed,
+    /// Token introspection reported the token as not active.
+    Inactive,
+    /// The `typ` header is not the one required by the [`ValidationOptions`].
+    TypMismatch,
+    /// The `iss` claim is not one of those required by the
+    /// [`ValidationOptions`].
+    IssMismatch,
+    /// The `aud` claim doesn't contain any of the audiences required by the
+    /// [`ValidationOptions`].
+    AudMismatch,
+    /// The `acr` claim is not one of those required by the
+    /// [`ValidationOptions`].
+    AcrMismatch,
+    /// The `amr` claim doesn't contain any of the methods required by the
+    /// [`ValidationOptions`].
+    AmrMismatch,
+    /// A claim required by the [`ValidationOptions`] is missing.
+    MissingClaim(String),
+    /// The token is valid for longer from `iat` to `exp` than the
+    /// [`ValidationOptions`] allow.
+    LifetimeTooLong,
+    /// The restrictions of the token for an audience don't allow a required
+    /// scope, see [`audience`].
+    InsufficientScope,
+    /// A child token doesn't reference its parent token, or expires after
+    /// it, see [`chain`].
+    ChainMismatch,
+    /// Too many verifications are waiting for a thread, see [`offload`].
+    #[cfg(feature = "offload")]
+    Overloaded,
+    /// The token, or its header or claims, exceed the limits of the
+    /// [`ValidationOptions`].
+    TooLarge,
     UnsupportedOrInvalidKey,
+    /// A key in an environment variable is missing or couldn't be loaded,
+    /// see [`SomePrivateKey::from_env`].
+    EnvKey {
+        var: String,
+        message: String,
+    },
+    /// A public key is not a valid point of its curve, or is a point that
+    /// must not be used, e.g. the identity.
+    InvalidPoint,
+    /// A digest passed to `sign_digest` or `verify_digest` doesn't have the
+    /// length of the algorithm's hash.
+    InvalidDigest,
+    /// A JSON Schema passed to [`schema::ClaimsSchema::new`] is invalid.
+    #[cfg(feature = "json-schema")]
+    InvalidSchema(String),
+    /// The claims don't match the JSON Schema of the [`ValidationOptions`].
+    #[cfg(feature = "json-schema")]
+    SchemaMismatch(String),
     Utf8(FromUtf8Error),
     IoError(std::io::Error),
     OpenSsl(ErrorStack),
     SerdeJson(serde_json::Error),
-    Decode(base64::DecodeError),
+    Decode(DecodeError),
     #[cfg(feature = "remote-jwks")]
     Reqwest(reqwest::Error),
 }
@@ -425,8 +1142,33 @@ impl fmt::Display for Error {
             Error::NoKid => "the kid field is missing from the JWT header".fmt(f),
             Error::NoKey => "no key in the JWK Set matches the kid".fmt(f),
             Error::UnsupportedOrInvalidKey => "unsupported or invalid key".fmt(f),
+            Error::EnvKey { var, message } => write!(f, "key in ${}: {}", var, message),
+            Error::InvalidPoint => "invalid public key point".fmt(f),
+            Error::InvalidDigest => "digest length does not match the algorithm".fmt(f),
+            #[cfg(feature = "json-schema")]
+            Error::InvalidSchema(e) => write!(f, "invalid JSON Schema: {}", e),
+            #[cfg(feature = "json-schema")]
+            Error::SchemaMismatch(e) => e.fmt(f),
             Error::Expired => "token expired (exp check failed)".fmt(f),
             Error::Before => "token is not valid yet (nbf check failed)".fmt(f),
+            Error::AuthTooOld => "authentication is too old (auth_time check failed)".fmt(f),
+            Error::Revoked => "token has been revoked".fmt(f),
+            Error::Inactive => "token is not active".fmt(f),
+            Error::Replayed => "token has already been used".fmt(f),
+            Error::TypMismatch => "the typ field in JWT header is not the expected one".fmt(f),
+            Error::IssMismatch => "the iss claim is not the expected one".fmt(f),
+            Error::AudMismatch => "the aud claim does not contain the expected audience".fmt(f),
+            Error::AcrMismatch => "the acr claim is not one of the required levels".fmt(f),
+            Error::AmrMismatch => {
+                "the amr claim does not contain a required authentication method".fmt(f)
+            }
+            Error::MissingClaim(name) => write!(f, "the {} claim is missing", name),
+            Error::LifetimeTooLong => "the token is valid for too long".fmt(f),
+            Error::InsufficientScope => "the token does not allow the required scope".fmt(f),
+            Error::ChainMismatch => "the token is not a valid child of the parent token".fmt(f),
+            #[cfg(feature = "offload")]
+            Error::Overloaded => "too many verifications are waiting".fmt(f),
+            Error::TooLarge => "token too large or too deeply nested".fmt(f),
         }
     }
 }
@@ -467,9 +1209,9 @@ impl From<serde_json::Error> for Error {
     }
 }
 
-impl From<base64::DecodeError> for Error {
+impl From<DecodeError> for Error {
     #[inline]
-    fn from(e: base64::DecodeError) -> Self {
+    fn from(e: DecodeError) -> Self {
         Error::Decode(e)
     }
 }
@@ -489,14 +1231,371 @@ impl From<reqwest::Error> for Error {
     }
 }
 
+/// Invalid base64 in a token, key or header, see [`Error::Decode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError(base64::DecodeError);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "verify-only")))]
 mod tests {
+    #[cfg(feature = "ecdsa")]
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[cfg(feature = "ecdsa")]
     use crate::ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey};
 
     use super::*;
 
+    #[cfg(feature = "ecdsa")]
+    #[test]
+    fn verified_token() -> Result<()> {
+        let k = jwk::WithKid::new(
+            "k1".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        let token = sign(HeaderAndClaims::new_dynamic().set_sub("alice"), &k)?;
+        let verified = verify_token::<Map<String, Value>>(&token, &k, &ValidationOptions::new())?;
+        assert_eq!(verified.header().kid.as_deref(), Some("k1"));
+        assert_eq!(verified.claims().sub.as_deref(), Some("alice"));
+        assert_eq!(verified.as_str(), token);
+        k.verify(verified.signing_input(), &verified.signature(), "ES256")?;
+        assert!(!format!("{:?}", verified).contains(&token));
+
+        let jwks = jwk::JwkSet {
+            keys: vec![k.public_key_to_jwk()?],
+        };
+        let verified = jwks.verifier().verify_token::<Map<String, Value>>(&token)?;
+        assert_eq!(
+            verified.into_header_and_claims().claims().sub.as_deref(),
+            Some("alice")
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "ecdsa")]
+    #[test]
+    fn header_pass_through() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mut token = HeaderAndClaims::new_dynamic();
+        token.set_sub("alice");
+        token.header_mut().extra.insert("x5t".into(), "abc".into());
+        token
+            .header_mut()
+            .extra
+            .insert("crit".into(), serde_json::json!(["exp"]));
+        let jwt = sign(&mut token, &k)?;
+
+        let verified = verify_token::<Map<String, Value>>(&jwt, &k, &ValidationOptions::new())?;
+        assert_eq!(verified.header(), token.header());
+        assert_eq!(
+            format!(
+                "{}.{}",
+                verified.header_segment(),
+                verified.payload_segment()
+            )
+            .as_bytes(),
+            verified.signing_input()
+        );
+
+        let mut decoded = verified.into_header_and_claims();
+        let resigned = sign(&mut decoded, &k)?;
+        let decoded = decode_without_verify::<Map<String, Value>>(&resigned)?;
+        assert_eq!(decoded.header().extra["x5t"], "abc");
+        assert_eq!(decoded.header().extra["crit"], serde_json::json!(["exp"]));
+        Ok(())
+    }
+
+    #[cfg(feature = "eddsa")]
+    #[test]
+    fn deterministic() -> Result<()> {
+        #[derive(Serialize)]
+        struct Extra {
+            zone: String,
+            roles: Value,
+            admin: bool,
+        }
+
+        let k = eddsa::Ed25519PrivateKey::generate()?;
+        let mut token = HeaderAndClaims::with_claims(Extra {
+            zone: "eu".into(),
+            roles: serde_json::json!({"b": 1, "a": 2.0}),
+            admin: false,
+        });
+        token
+            .set_sub("alice")
+            .set_exp_from_now(Duration::from_secs(60));
+        let first = sign_deterministic(&mut token, &k)?;
+        assert_eq!(first, sign_deterministic(&mut token, &k)?);
+
+        let payload = first.split('.').nth(1).unwrap();
+        let payload = b64::decode_url(payload)?;
+        let payload = String::from_utf8(payload)?;
+        assert!(payload.starts_with(r#"{"admin":false,"exp":"#));
+        assert!(payload.ends_with(r#""roles":{"a":2,"b":1},"sub":"alice","zone":"eu"}"#));
+        verify::<Map<String, Value>>(&first, &k)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "ecdsa")]
+    #[test]
+    fn claims_mappers_after_checks() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let mapped = Arc::new(AtomicUsize::new(0));
+        let options = ValidationOptions::new()
+            .require_aud("api")
+            .map_claims(Arc::new({
+                let mapped = mapped.clone();
+                move |claims| {
+                    mapped.fetch_add(1, Ordering::SeqCst);
+                    claims.insert("aud".into(), "api".into());
+                    claims.insert("exp".into(), u32::MAX.into());
+                    claims.insert("mapped".into(), true.into());
+                }
+            }));
+
+        let mut token = HeaderAndClaims::new_dynamic();
+        token.set_auds(vec!["other".into()]);
+        let other = sign(&mut token, &k)?;
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&other, &k, &options),
+            Err(Error::AudMismatch)
+        ));
+        token.set_auds(vec!["api".into()]);
+        token.claims_mut().exp = Some(Duration::from_secs(1700000000));
+        let expired = sign(&mut token, &k)?;
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&expired, &k, &options),
+            Err(Error::Expired)
+        ));
+        assert_eq!(mapped.load(Ordering::SeqCst), 0);
+
+        let verified = verify_only_with_options::<Map<String, Value>>(&expired, &k, &options)?;
+        assert_eq!(verified.claims().extra["mapped"], true);
+        assert_eq!(mapped.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn registered_claims() -> Result<()> {
+        let claims: Claims<Map<String, Value>> = serde_json::from_value(serde_json::json!({
+            "auth_time": 1700000000,
+            "acr": "urn:example:loa:2",
+            "amr": ["pwd", "otp", "mfa"],
+            "azp": "app",
+            "nonce": "n-0S6_WzA2Mj",
+            "sid": "08a5019c",
+            "events": {"https://schemas.openid.net/secevent/risc/event-type/account-disabled": {}},
+            "toe": 1700000001,
+            "txn": "8675309",
+            "other": 1,
+        }))?;
+        assert_eq!(claims.auth_time, Some(Duration::from_secs(1700000000)));
+        assert_eq!(claims.azp.as_deref(), Some("app"));
+        assert_eq!(claims.sid.as_deref(), Some("08a5019c"));
+        assert_eq!(claims.events.len(), 1);
+        assert_eq!(claims.toe, Some(Duration::from_secs(1700000001)));
+        assert_eq!(claims.txn.as_deref(), Some("8675309"));
+        assert_eq!(claims.extra.len(), 1);
+        assert!(claims.acr_is_one_of(&["urn:example:loa:2", "urn:example:loa:3"]));
+        assert!(!claims.acr_is_one_of(&["urn:example:loa:3"]));
+        assert!(claims.has_amr("otp"));
+        assert!(claims.has_any_amr(&["hwk", "mfa"]));
+        assert!(!claims.has_any_amr(&["hwk"]));
+
+        let json = serde_json::to_value(&claims)?;
+        assert_eq!(json["amr"], serde_json::json!(["pwd", "otp", "mfa"]));
+        let empty = serde_json::to_value(HeaderAndClaims::new_dynamic().set_nonce("n").claims())?;
+        assert_eq!(empty, serde_json::json!({"nonce": "n"}));
+
+        // Malformed values are ignored.
+        let claims: Claims<Map<String, Value>> = serde_json::from_value(serde_json::json!({
+            "sub": "alice",
+            "auth_time": "yesterday",
+            "acr": 2,
+            "amr": "pwd",
+            "nonce": null,
+            "events": [],
+        }))?;
+        assert_eq!(claims.sub.as_deref(), Some("alice"));
+        assert_eq!(claims.auth_time, None);
+        assert_eq!(claims.acr, None);
+        assert_eq!(claims.amr, ["pwd"]);
+        assert!(claims.events.is_empty());
+        let claims: Claims<Map<String, Value>> =
+            serde_json::from_value(serde_json::json!({"amr": ["pwd", 1, "otp"]}))?;
+        assert_eq!(claims.amr, ["pwd", "otp"]);
+        Ok(())
+    }
+
+    #[cfg(feature = "ecdsa")]
+    #[test]
+    fn raw_payload() -> Result<()> {
+        let k = jwk::WithKid::new(
+            "k1".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        let payload = [0xa1, 0x63, b's', b'u', b'b', 0x00, 0xff];
+        let token = sign_raw(&payload, Some("application/cbor"), &k)?;
+        let (header, decoded) = verify_raw(&token, &k, &ValidationOptions::new())?;
+        assert_eq!(decoded, payload);
+        assert_eq!(header.kid.as_deref(), Some("k1"));
+        assert_eq!(header.extra["cty"], "application/cbor");
+
+        let mut tampered = token.clone();
+        tampered.replace_range(token.find('.').unwrap() + 1.., "AAAA.");
+        tampered.push_str(token.rsplit('.').next().unwrap());
+        assert!(verify_raw(&tampered, &k, &ValidationOptions::new()).is_err());
+        assert!(verify::<Map<String, Value>>(&token, &k).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        #[cfg(feature = "hmac")]
+        assert_send_sync::<hmac::HmacKey>();
+        #[cfg(feature = "rsa")]
+        {
+            assert_send_sync::<rsa::RsaPrivateKey>();
+            assert_send_sync::<rsa::RsaPublicKey>();
+        }
+        #[cfg(feature = "ecdsa")]
+        {
+            assert_send_sync::<ecdsa::EcdsaPrivateKey>();
+            assert_send_sync::<ecdsa::EcdsaPublicKey>();
+        }
+        #[cfg(feature = "eddsa")]
+        {
+            assert_send_sync::<eddsa::Ed25519PrivateKey>();
+            assert_send_sync::<eddsa::Ed25519PublicKey>();
+        }
+        assert_send_sync::<SomePrivateKey>();
+        assert_send_sync::<SomePublicKey>();
+        assert_send_sync::<jwk::WithKid<SomePrivateKey>>();
+        assert_send_sync::<jwk::JwkSet>();
+        assert_send_sync::<jwk::JwkSetVerifier>();
+        assert_send_sync::<Verifier>();
+        assert_send_sync::<SharedVerifier>();
+        assert_send_sync::<revocation::RevocationList>();
+        assert_send_sync::<revocation::ReplayCache>();
+        assert_send_sync::<reload::KeyFile>();
+        assert_send_sync::<reload::JwksFile>();
+        assert_send_sync::<session::SessionIssuer<SomePrivateKey>>();
+        assert_send_sync::<spiffe::JwtSvidVerifier>();
+        assert_send_sync::<HeaderAndClaims<Map<String, Value>>>();
+        assert_send_sync::<Error>();
+        #[cfg(feature = "remote-jwks")]
+        {
+            assert_send_sync::<jwk::RemoteJwksVerifier>();
+            assert_send_sync::<status_list::RemoteStatusListResolver>();
+            assert_send_sync::<authenticator::Authenticator>();
+        }
+    }
+
+    #[cfg(feature = "ecdsa")]
+    #[test]
+    fn resign_token() -> Result<()> {
+        let inbound = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let outbound = jwk::WithKid::new(
+            "outbound".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?,
+        );
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims
+            .set_kid("inbound")
+            .set_sub("me")
+            .add_aud("gateway")
+            .insert("scope", "read write");
+        claims.header_mut().typ = Some("at+jwt".into());
+        let token = sign(&mut claims, &inbound)?;
+
+        let exchanged = resign::<Map<String, Value>>(&token, &inbound, &outbound, |t| {
+            t.claims_mut().aud = OneOrMany::One("backend".into());
+            t.insert("scope", "read");
+            Ok(())
+        })?;
+        let exchanged = verify::<Map<String, Value>>(&exchanged, &outbound)?;
+        assert_eq!(exchanged.header().kid.as_deref(), Some("outbound"));
+        assert_eq!(exchanged.header().typ.as_deref(), Some("at+jwt"));
+        assert_eq!(exchanged.claims().sub.as_deref(), Some("me"));
+        assert_eq!(exchanged.claims().aud, OneOrMany::One("backend".into()));
+        assert_eq!(exchanged.claims().extra["scope"], "read");
+
+        assert!(resign::<Map<String, Value>>(&token, &outbound, &outbound, |_| Ok(())).is_err());
+        assert!(matches!(
+            resign::<Map<String, Value>>(&token, &inbound, &outbound, |_| Err(Error::InvalidToken)),
+            Err(Error::InvalidToken)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn claim_paths() -> Result<()> {
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims
+            .set_path("realm_access.roles", vec!["admin", "user"])
+            .set_path("resource_access.account.roles", vec!["view"])
+            .set_path("resource_access.other", 1)
+            .insert("scalar", 1)
+            .set_path("scalar.nested", true);
+        let claims = claims.claims();
+        assert_eq!(
+            claims.get_path::<Vec<String>>("realm_access.roles"),
+            Some(vec!["admin".to_string(), "user".to_string()])
+        );
+        assert_eq!(
+            claims.get_path::<String>("resource_access.account.roles.0"),
+            Some("view".into())
+        );
+        assert_eq!(claims.get_path::<u32>("resource_access.other"), Some(1));
+        assert_eq!(claims.get_path::<bool>("scalar.nested"), Some(true));
+        assert_eq!(claims.get_path::<String>("resource_access.other"), None);
+        assert_eq!(claims.get_path_value("realm_access.roles.2"), None);
+        assert_eq!(claims.get_path_value("realm_access.roles.x"), None);
+        assert_eq!(claims.get_path_value("missing.path"), None);
+        assert_eq!(claims.get_path_value("realm_access.roles.0.x"), None);
+        Ok(())
+    }
+
+    #[cfg(all(feature = "rsa", feature = "ecdsa"))]
+    #[test]
+    fn sign_multi() -> Result<()> {
+        let old = crate::rsa::RsaPrivateKey::generate(2048, crate::rsa::RsaAlgorithm::RS256)?;
+        let new = jwk::WithKid::new(
+            "new".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.set_kid("old").set_sub("you");
+
+        let tokens = sign_compact_multi(&mut claims, &[&new, &old])?;
+        assert_eq!(tokens.len(), 2);
+
+        let new_token = verify::<Map<String, Value>>(&tokens[0], &new)?;
+        assert_eq!(new_token.header().alg, "ES256");
+        assert_eq!(new_token.header().kid.as_deref(), Some("new"));
+        let old_token = verify::<Map<String, Value>>(&tokens[1], &old)?;
+        assert_eq!(old_token.header().alg, "RS256");
+        assert_eq!(old_token.header().kid.as_deref(), Some("old"));
+        assert_eq!(old_token.claims().sub.as_deref(), Some("you"));
+        assert!(verify::<Map<String, Value>>(&tokens[0], &old).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "ecdsa")]
     #[test]
     fn signing_and_verification() -> Result<()> {
         let mut claims = HeaderAndClaims::new_dynamic();
@@ -534,10 +1633,155 @@ mod tests {
     fn claim_deserialization() {
         let mut json = r#"eyJpYXQiOjEuNjkyMTkwMTI1RTksImV4cCI6MS42OTIxOTM3MjVFOSwiYW50aUNzcmZUb2tlbiI6bnVsbCwic3ViIjoiYTM5ZmZjNWUtNjc5ZC00YjAzLWI5YmYtYTliZjEzNDk4NGYzIiwiaXNzIjoiaHR0cDovL2xvY2FsaG9zdDozOTk5L2F1dGgiLCJzZXNzaW9uSGFuZGxlIjoiNTAyMWQ2MTQtYzFmNi00ZTZkLWI1NjktZGQxN2Q0N2EyOWI0IiwicGFyZW50UmVmcmVzaFRva2VuSGFzaDEiOm51bGwsInJlZnJlc2hUb2tlbkhhc2gxIjoiNTZiMjcxZDcxNGRlMzg3M2UwMmIyZjAyYTJiZDcyYWJjZDIyZDM0NGZlZjE2YTJkMWJjYmM1NGU2YWUxN2M3OCJ9"#.as_bytes();
 
-        let r = base64::read::DecoderReader::new(&mut json, url_safe_trailing_bits());
+        let r = base64::read::DecoderReader::new(&mut json, &b64::URL_SAFE);
 
         let claims: Claims<Value> = serde_json::from_reader(r).unwrap();
         assert_eq!(claims.iat, Some(Duration::from_secs(1692190125)));
         assert_eq!(claims.exp, Some(Duration::from_secs(1692193725)));
     }
+
+    #[cfg(feature = "hmac")]
+    fn encode(segment: &[u8]) -> String {
+        b64::encode_url(segment)
+    }
+
+    #[cfg(feature = "hmac")]
+    fn check_parse(token: &str) {
+        let k = hmac::HmacKey::from_bytes(b"key", hmac::HmacAlgorithm::HS256);
+        let _ = decode_without_verify::<Map<String, Value>>(token);
+        let _ = verify::<Map<String, Value>>(token, &k);
+        let _ = verify_only::<Map<String, Value>>(token, &k);
+        let _ = jwk::JwkSet { keys: vec![] }
+            .verifier()
+            .verify::<Map<String, Value>>(token);
+    }
+
+    #[cfg(feature = "hmac")]
+    proptest::proptest! {
+        #[test]
+        fn parse_arbitrary_input(token in "[A-Za-z0-9_=+/.\\-\\x00]{0,256}|\\PC{0,64}") {
+            check_parse(&token);
+        }
+
+        #[test]
+        fn parse_arbitrary_segments(
+            header in "\\{(\"(alg|kid|typ|[a-z]{1,3})\":(\"[a-zA-Z0-9\\x00]{0,8}\"|-?[0-9]{1,24}|null|true|\\[|\\{|\\}),?){0,8}\\}?",
+            payload in proptest::collection::vec(proptest::num::u8::ANY, 0..128),
+            sig in "[A-Za-z0-9_\\-]{0,90}",
+        ) {
+            let token = format!("{}.{}.{}", encode(header.as_bytes()), encode(&payload), sig);
+            check_parse(&token);
+            let token = format!("{}.{}.{}", header, encode(&payload), sig);
+            check_parse(&token);
+        }
+
+        #[test]
+        fn verify_arbitrary_time_claims(exp in proptest::num::f64::ANY, nbf in proptest::num::f64::ANY) {
+            let k = hmac::HmacKey::from_bytes(b"key", hmac::HmacAlgorithm::HS256);
+            let signing_input = format!(
+                "{}.{}",
+                encode(br#"{"alg":"HS256"}"#),
+                encode(format!(r#"{{"exp":{:e},"nbf":{:e}}}"#, exp, nbf).as_bytes()),
+            );
+            let sig = k.sign(signing_input.as_bytes()).unwrap();
+            let token = format!("{}.{}", signing_input, encode(&sig));
+            check_parse(&token);
+        }
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn size_limits() -> Result<()> {
+        let k = hmac::HmacKey::from_bytes(b"key", hmac::HmacAlgorithm::HS256);
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.insert("nested", serde_json::json!([[[[["deep"]]]]]));
+        let token = sign(&mut claims, &k)?;
+        verify::<Map<String, Value>>(&token, &k)?;
+
+        let too_large = |options: ValidationOptions| {
+            matches!(
+                verify_with_options::<Map<String, Value>>(&token, &k, &options),
+                Err(Error::TooLarge)
+            )
+        };
+        assert!(too_large(
+            ValidationOptions::new().max_token_len(token.len() - 1)
+        ));
+        assert!(too_large(ValidationOptions::new().max_header_len(10)));
+        assert!(too_large(ValidationOptions::new().max_claims_len(10)));
+        assert!(too_large(ValidationOptions::new().max_depth(5)));
+        assert!(!too_large(ValidationOptions::new().max_depth(6)));
+
+        claims.insert("big", "a".repeat(100_000));
+        let token = sign(&mut claims, &k)?;
+        assert!(matches!(
+            decode_without_verify::<Map<String, Value>>(&token),
+            Err(Error::TooLarge)
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn duplicate_keys() -> Result<()> {
+        let k = hmac::HmacKey::from_bytes(b"key", hmac::HmacAlgorithm::HS256);
+        let token = |header: &[u8], payload: &[u8]| -> Result<String> {
+            let signing_input = format!("{}.{}", encode(header), encode(payload));
+            let sig = k.sign(signing_input.as_bytes())?;
+            Ok(format!("{}.{}", signing_input, encode(&sig)))
+        };
+
+        let dup_header = token(br#"{"alg":"HS256","cty":"a","cty":"b"}"#, b"{}")?;
+        assert!(verify::<Map<String, Value>>(&dup_header, &k).is_err());
+        let options = ValidationOptions::new().reject_duplicate_header_keys(false);
+        verify_with_options::<Map<String, Value>>(&dup_header, &k, &options)?;
+
+        let dup_claims = token(
+            br#"{"alg":"HS256"}"#,
+            br#"{"x":{"role":"user","role":"admin"}}"#,
+        )?;
+        verify::<Map<String, Value>>(&dup_claims, &k)?;
+        let options = ValidationOptions::new().reject_duplicate_claim_keys(true);
+        assert!(verify_with_options::<Map<String, Value>>(&dup_claims, &k, &options).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn require_typ() -> Result<()> {
+        let k = hmac::HmacKey::from_bytes(b"key", hmac::HmacAlgorithm::HS256);
+        let mut claims = HeaderAndClaims::new_dynamic();
+        claims.header_mut().typ = Some("at+jwt".into());
+        let token = sign(&mut claims, &k)?;
+
+        let options = ValidationOptions::new().require_typ("application/at+jwt");
+        verify_with_options::<Map<String, Value>>(&token, &k, &options)?;
+        let options = ValidationOptions::new().require_typ("JWT");
+        assert!(matches!(
+            verify_with_options::<Map<String, Value>>(&token, &k, &options),
+            Err(Error::TypMismatch)
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn parse_edge_cases() {
+        let header = encode(br#"{"alg":"HS256","alg":"none"}"#);
+        let payload = encode(br#"{"sub":"a","sub":"b"}"#);
+        for token in [
+            "".to_string(),
+            "..".into(),
+            "...".into(),
+            "a.b".into(),
+            format!("{}.{}.", header, payload),
+            format!("{}.{}..", header, payload),
+            format!("{}\0.{}.", header, payload),
+            format!("{}.{}.", encode(&[b'['; 100_000]), payload),
+            format!("{}.{}.", encode(&vec![b' '; 1 << 20]), payload),
+        ] {
+            check_parse(&token);
+            assert!(decode_without_verify::<Map<String, Value>>(&token).is_err());
+        }
+    }
 }
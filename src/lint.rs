@@ -0,0 +1,200 @@
+//! Checking tokens against the best practices of RFC 8725, without a key.
+//!
+//! Meant for CI checks of issuer configurations and for security tooling:
+//!
+//! ```
+//! # use jwtk::{lint::{lint, LintWarning}, sign, HeaderAndClaims, hmac::{HmacKey, HmacAlgorithm}};
+//! # let k = HmacKey::generate(HmacAlgorithm::HS256)?;
+//! let token = sign(HeaderAndClaims::new_dynamic().set_sub("alice"), &k)?;
+//! let warnings = lint(&token);
+//! assert!(warnings.contains(&LintWarning::MissingExp));
+//! for w in &warnings {
+//!     println!("{}", w);
+//! }
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use std::{fmt, time::Duration};
+
+use serde_json::{Map, Value};
+
+use crate::decode_without_verify;
+
+/// Lifetimes, i.e. `exp - iat`, above this are reported.
+pub const MAX_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+const ALGORITHMS: &[&str] = &[
+    "HS256", "HS384", "HS512", "RS256", "RS384", "RS512", "PS256", "PS384", "PS512", "ES256",
+    "ES384", "ES512", "ES256K", "EdDSA",
+];
+
+/// `typ` values registered for JWTs, without the `application/` prefix.
+const TYPES: &[&str] = &[
+    "jwt",
+    "at+jwt",
+    "dpop+jwt",
+    "secevent+jwt",
+    "logout+jwt",
+    "token-introspection+jwt",
+    "oauth-authz-req+jwt",
+    "kb+jwt",
+    "vc+sd-jwt",
+];
+
+/// Parameters of private or symmetric JWKs.
+const PRIVATE_JWK_PARAMS: &[&str] = &["d", "p", "q", "dp", "dq", "qi", "oth", "k"];
+
+/// A best practice the token doesn't follow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LintWarning {
+    /// The token couldn't be decoded.
+    Malformed,
+    /// `alg` is `none`.
+    NoneAlgorithm,
+    /// `alg` is not a JWS algorithm that jwtk knows of.
+    UnknownAlgorithm(String),
+    /// There is no `kid`, so verifiers can't pick a key during key rotation.
+    MissingKid,
+    /// There is no `typ`, so tokens of one kind may be confused with
+    /// another, RFC 8725 section 3.11.
+    MissingTyp,
+    /// `typ` is not registered for JWTs.
+    UnregisteredTyp(String),
+    /// The `jwk` header parameter contains private key parameters.
+    PrivateJwkInHeader,
+    /// There is no `exp`, so the token is valid forever.
+    MissingExp,
+    /// The token is valid for longer than [`MAX_LIFETIME`].
+    ExcessiveLifetime(Duration),
+    /// There is no `iss`, RFC 8725 section 3.8.
+    MissingIss,
+    /// There is no `aud`, RFC 8725 section 3.9.
+    MissingAud,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintWarning::Malformed => "the token is malformed".fmt(f),
+            LintWarning::NoneAlgorithm => "alg is none".fmt(f),
+            LintWarning::UnknownAlgorithm(alg) => write!(f, "unknown alg {:?}", alg),
+            LintWarning::MissingKid => "no kid header".fmt(f),
+            LintWarning::MissingTyp => "no typ header".fmt(f),
+            LintWarning::UnregisteredTyp(typ) => write!(f, "unregistered typ {:?}", typ),
+            LintWarning::PrivateJwkInHeader => "the jwk header contains a private key".fmt(f),
+            LintWarning::MissingExp => "no exp claim".fmt(f),
+            LintWarning::ExcessiveLifetime(d) => {
+                write!(
+                    f,
+                    "valid for {} s, more than {} s",
+                    d.as_secs(),
+                    MAX_LIFETIME.as_secs()
+                )
+            }
+            LintWarning::MissingIss => "no iss claim".fmt(f),
+            LintWarning::MissingAud => "no aud claim".fmt(f),
+        }
+    }
+}
+
+/// Check `token` against best practices. The signature is not verified.
+pub fn lint(token: &str) -> Vec<LintWarning> {
+    let token = match decode_without_verify::<Map<String, Value>>(token) {
+        Ok(token) => token,
+        Err(_) => return vec![LintWarning::Malformed],
+    };
+    let (header, claims) = (token.header(), token.claims());
+    let mut warnings = Vec::new();
+
+    if header.alg.eq_ignore_ascii_case("none") {
+        warnings.push(LintWarning::NoneAlgorithm);
+    } else if !ALGORITHMS.contains(&&*header.alg) {
+        warnings.push(LintWarning::UnknownAlgorithm(header.alg.to_string()));
+    }
+    if header.kid.is_none() {
+        warnings.push(LintWarning::MissingKid);
+    }
+    match &header.typ {
+        None => warnings.push(LintWarning::MissingTyp),
+        Some(typ) => {
+            let lower = typ.to_ascii_lowercase();
+            let bare = lower.strip_prefix("application/").unwrap_or(&lower);
+            if !TYPES.contains(&bare) {
+                warnings.push(LintWarning::UnregisteredTyp(typ.clone()));
+            }
+        }
+    }
+    if let Some(Value::Object(jwk)) = header.extra.get("jwk") {
+        if PRIVATE_JWK_PARAMS.iter().any(|p| jwk.contains_key(*p)) {
+            warnings.push(LintWarning::PrivateJwkInHeader);
+        }
+    }
+
+    match (claims.exp, claims.iat.or(claims.nbf)) {
+        (None, _) => warnings.push(LintWarning::MissingExp),
+        (Some(exp), Some(start)) if exp.saturating_sub(start) > MAX_LIFETIME => {
+            warnings.push(LintWarning::ExcessiveLifetime(exp - start));
+        }
+        _ => {}
+    }
+    if claims.iss.is_none() {
+        warnings.push(LintWarning::MissingIss);
+    }
+    if claims.aud.is_empty() {
+        warnings.push(LintWarning::MissingAud);
+    }
+    warnings
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use serde_json::json;
+
+    use crate::{b64, ecdsa::EcdsaPrivateKey, jwk::WithKid, sign, HeaderAndClaims};
+
+    use super::*;
+
+    #[test]
+    fn lint_token() -> crate::Result<()> {
+        let k = WithKid::new(
+            "k".into(),
+            EcdsaPrivateKey::generate(crate::ecdsa::EcdsaAlgorithm::ES256)?,
+        );
+        let mut token = HeaderAndClaims::new_dynamic();
+        token.header_mut().typ = Some("at+jwt".into());
+        token
+            .set_iss("https://issuer.example")
+            .add_aud("api")
+            .set_iat_now()
+            .set_exp_from_now(Duration::from_secs(300));
+        assert_eq!(lint(&sign(&mut token, &k)?), vec![]);
+
+        token.set_exp_from_now(Duration::from_secs(7 * 24 * 60 * 60));
+        let header = token.header_mut();
+        header.typ = Some("application/x-custom".into());
+        header
+            .extra
+            .insert("jwk".into(), json!({ "kty": "EC", "d": "secret" }));
+        let warnings = lint(&sign(&mut token, &k)?);
+        assert!(matches!(
+            &warnings[..],
+            [
+                LintWarning::UnregisteredTyp(_),
+                LintWarning::PrivateJwkInHeader,
+                LintWarning::ExcessiveLifetime(_),
+            ]
+        ));
+
+        let unsigned = format!(
+            "{}.{}.",
+            b64::encode_url(r#"{"alg":"none"}"#),
+            b64::encode_url("{}"),
+        );
+        let warnings = lint(&unsigned);
+        assert_eq!(warnings[0], LintWarning::NoneAlgorithm);
+        assert!(warnings.contains(&LintWarning::MissingExp));
+        assert_eq!(lint("a.b"), vec![LintWarning::Malformed]);
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! Verification on blocking threads.
+//!
+//! Verifying an RS256 signature with a 4096 bit key takes a fraction of a
+//! millisecond, which is long enough to stall other tasks when an async
+//! server verifies many tokens on its runtime threads. ECDSA, EdDSA and HMAC
+//! are cheap enough to verify inline; offload RSA verification with large
+//! keys, or any verification when profiling shows the runtime threads busy
+//! with it.
+//!
+//! [`Offloader`] runs verifications on tokio's blocking threads, with a
+//! bounded number in flight and waiting, so that a flood of tokens is
+//! rejected with `Error::Overloaded` instead of queuing without limit.
+//!
+//! ```
+//! # use jwtk::{offload::Offloader, rsa::{RsaAlgorithm, RsaPrivateKey}, sign, HeaderAndClaims, ValidationOptions};
+//! # use std::sync::Arc;
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> jwtk::Result<()> {
+//! # let k = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+//! # let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+//! let offloader = Offloader::new(4, 64);
+//! let key = Arc::new(k);
+//! let verified = offloader
+//!     .verify::<serde_json::Value>(&token, key, ValidationOptions::new())
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use tokio::sync::Semaphore;
+
+use crate::{
+    verify_with_options, Error, HeaderAndClaims, Result, ValidationOptions, VerificationKey,
+};
+
+/// Runs verifications on blocking threads, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Offloader {
+    /// Verifications running or waiting.
+    admitted: Arc<Semaphore>,
+    /// Verifications running.
+    running: Arc<Semaphore>,
+}
+
+impl Offloader {
+    /// Run at most `max_running` verifications at once, with at most
+    /// `max_waiting` more waiting for one of them to finish. Both are capped
+    /// at `Semaphore::MAX_PERMITS` in total.
+    pub fn new(max_running: usize, max_waiting: usize) -> Self {
+        let admitted = max_running
+            .saturating_add(max_waiting)
+            .min(Semaphore::MAX_PERMITS);
+        Self {
+            admitted: Arc::new(Semaphore::new(admitted)),
+            running: Arc::new(Semaphore::new(max_running.min(Semaphore::MAX_PERMITS))),
+        }
+    }
+
+    /// Run `f`, e.g. `move || jwks.verify::<Claims>(&token)`, on a blocking
+    /// thread. Must be called within a tokio runtime.
+    ///
+    /// Returns `Error::Overloaded` without running `f` if too many
+    /// verifications are already waiting.
+    pub async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce() -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let admitted = self
+            .admitted
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| Error::Overloaded)?;
+        let running = self
+            .running
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::Overloaded)?;
+        let result = tokio::task::spawn_blocking(move || {
+            let _permits = (admitted, running);
+            f()
+        })
+        .await;
+        match result {
+            Ok(result) => result,
+            // The runtime is shutting down. `io::Error::other` needs Rust
+            // 1.74.
+            #[allow(clippy::io_other_error)]
+            Err(e) => match e.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(e) => Err(Error::IoError(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    e,
+                ))),
+            },
+        }
+    }
+
+    /// Like [`verify_with_options`](crate::verify_with_options), on a
+    /// blocking thread.
+    pub async fn verify<ExtraClaims: DeserializeOwned + Send + 'static>(
+        &self,
+        token: &str,
+        k: Arc<impl VerificationKey + Send + Sync + 'static>,
+        options: ValidationOptions,
+    ) -> Result<HeaderAndClaims<ExtraClaims>> {
+        let token = token.to_string();
+        self.run(move || verify_with_options(&token, &*k, &options))
+            .await
+    }
+}
+
+/// Verify `token` on a blocking thread, without a bound on the number of
+/// verifications waiting. Prefer an [`Offloader`] in servers.
+pub async fn verify_blocking_offloaded<ExtraClaims: DeserializeOwned + Send + 'static>(
+    token: &str,
+    k: Arc<impl VerificationKey + Send + Sync + 'static>,
+    options: ValidationOptions,
+) -> Result<HeaderAndClaims<ExtraClaims>> {
+    Offloader::new(Semaphore::MAX_PERMITS, 0)
+        .verify(token, k, options)
+        .await
+}
+
+#[cfg(all(test, feature = "rsa", not(feature = "verify-only")))]
+mod tests {
+    use std::sync::mpsc;
+
+    use serde_json::Value;
+
+    use crate::{
+        rsa::{RsaAlgorithm, RsaPrivateKey},
+        sign,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn offloader() -> Result<()> {
+        let k = Arc::new(RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?);
+        let token = sign(HeaderAndClaims::new_dynamic().set_sub("alice"), &*k)?;
+        let options = ValidationOptions::new();
+        let verified =
+            verify_blocking_offloaded::<Value>(&token, k.clone(), options.clone()).await?;
+        assert_eq!(verified.claims().sub.as_deref(), Some("alice"));
+
+        // One running, one waiting, the third is rejected.
+        let offloader = Offloader::new(1, 1);
+        let (tx, rx) = mpsc::channel::<u8>();
+        let running = tokio::spawn({
+            let offloader = offloader.clone();
+            async move { offloader.run(move || Ok(rx.recv().unwrap())).await }
+        });
+        while offloader.running.available_permits() > 0 {
+            tokio::task::yield_now().await;
+        }
+        let waiting = tokio::spawn({
+            let (offloader, token, k) = (offloader.clone(), token.clone(), k.clone());
+            async move { offloader.verify::<Value>(&token, k, options).await }
+        });
+        while offloader.admitted.available_permits() > 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(matches!(
+            offloader.run(|| Ok(())).await,
+            Err(Error::Overloaded)
+        ));
+
+        tx.send(1).unwrap();
+        running.await.unwrap()?;
+        waiting.await.unwrap()?;
+        offloader.run(|| Ok(())).await?;
+
+        let offloader = Offloader::new(usize::MAX, usize::MAX);
+        assert_eq!(
+            offloader.admitted.available_permits(),
+            Semaphore::MAX_PERMITS
+        );
+        offloader.run(|| Ok(())).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,422 @@
+//! Sign in with Apple ID tokens, App Store Server API signed payloads and
+//! tokens for Apple's APIs.
+//!
+//! ID tokens are ordinary tokens, verified with Apple's JWK Set. The JWS
+//! payloads of the App Store Server API and App Store Server Notifications,
+//! e.g. `signedTransactionInfo`, are instead verified with the certificate
+//! chain in their `x5c` header, which must lead to an Apple root CA.
+//!
+//! ```no_run
+//! # fn f(signed_transaction_info: &str) -> jwtk::Result<()> {
+//! use jwtk::presets::apple::AppStoreVerifier;
+//! use openssl::x509::X509;
+//!
+//! // From https://www.apple.com/certificateauthority/
+//! let root = X509::from_der(&std::fs::read("AppleRootCA-G3.cer")?)?;
+//! let verifier = AppStoreVerifier::new(vec![root], "com.example.app");
+//! let transaction: serde_json::Value = verifier.verify(signed_transaction_info)?;
+//! println!("{}", transaction["productId"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    slice,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use openssl::x509::X509;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    b64, verify_raw,
+    x509::{extension_data, verify_chain},
+    Error, Result, SomePublicKey, UntrustedToken, ValidationOptions,
+};
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+use crate::{ecdsa::EcdsaPrivateKey, sign, HeaderAndClaims};
+
+pub const APPLE_ISSUER: &str = "https://appleid.apple.com";
+
+pub const APPLE_JWKS_URL: &str = "https://appleid.apple.com/auth/keys";
+
+/// Marks the intermediate CA of App Store receipt signing certificates.
+const INTERMEDIATE_OID: &str = "1.2.840.113635.100.6.2.1";
+
+/// Marks App Store receipt signing certificates.
+const LEAF_OID: &str = "1.2.840.113635.100.6.11.1";
+
+/// Claims of Sign in with Apple ID tokens.
+///
+/// `email` is only present if the app requested it, and may be a private
+/// relay address.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppleIdClaims {
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub email_verified: Option<bool>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub is_private_email: Option<bool>,
+    /// 0 unsupported, 1 unknown, 2 likely real.
+    pub real_user_status: Option<u8>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub nonce_supported: Option<bool>,
+    /// The `sub` of the user in the team that transferred the app.
+    pub transfer_sub: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Apple sends some booleans as the strings `"true"` and `"false"`.
+fn bool_or_string<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<bool>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+    Ok(match Option::<BoolOrString>::deserialize(d)? {
+        Some(BoolOrString::Bool(b)) => Some(b),
+        Some(BoolOrString::String(s)) => Some(s == "true"),
+        None => None,
+    })
+}
+
+/// Issuer and audience rules of ID tokens for `client_id`, the bundle id of
+/// the app or the services id of the website.
+pub fn apple_id_options(client_id: &str) -> ValidationOptions {
+    ValidationOptions::new()
+        .require_iss(APPLE_ISSUER)
+        .require_aud(client_id)
+        .allow_alg("RS256")
+}
+
+/// A verifier of ID tokens for `client_id`, with keys fetched from
+/// [`APPLE_JWKS_URL`].
+#[cfg(feature = "remote-jwks")]
+pub fn apple_id_verifier(
+    client_id: &str,
+    client: Option<reqwest::Client>,
+) -> crate::jwk::RemoteJwksVerifier {
+    let mut verifier = crate::jwk::RemoteJwksVerifier::new(
+        APPLE_JWKS_URL.into(),
+        client,
+        Duration::from_secs(60 * 60),
+    );
+    verifier.set_validation_options(apple_id_options(client_id));
+    verifier
+}
+
+/// Verifies App Store Server API and App Store Server Notifications JWS
+/// payloads of an app.
+///
+/// A payload is accepted if its `x5c` header has a leaf, an intermediate and
+/// a root certificate, the leaf and the intermediate are marked as App Store
+/// certificates, the chain leads to one of the root certificates and is
+/// valid at the payload's `signedDate`, the payload is signed by the leaf
+/// with ES256, and its `bundleId`, or that of its `data`, is the app's.
+///
+/// Certificates are not checked for revocation.
+pub struct AppStoreVerifier {
+    roots: Vec<X509>,
+    bundle_id: String,
+}
+
+impl AppStoreVerifier {
+    /// `roots` are Apple's root certificates, e.g. Apple Root CA - G3.
+    pub fn new(roots: Vec<X509>, bundle_id: impl Into<String>) -> Self {
+        Self {
+            roots,
+            bundle_id: bundle_id.into(),
+        }
+    }
+
+    /// Verify `jws` and deserialize its payload.
+    pub fn verify<T: DeserializeOwned>(&self, jws: &str) -> Result<T> {
+        let untrusted = UntrustedToken::parse(jws)?;
+        let x5c: Vec<String> = match untrusted.header().peek().extra.get("x5c") {
+            Some(x5c) => serde_json::from_value(x5c.clone())?,
+            None => return Err(Error::InvalidToken),
+        };
+        let chain = x5c
+            .iter()
+            .map(|c| Ok(X509::from_der(&b64::decode(c)?)?))
+            .collect::<Result<Vec<_>>>()?;
+        let (leaf, intermediate) = match &chain[..] {
+            [leaf, intermediate, _root] => (leaf, intermediate),
+            _ => return Err(Error::InvalidToken),
+        };
+        if extension_data(leaf, LEAF_OID)?.is_none()
+            || extension_data(intermediate, INTERMEDIATE_OID)?.is_none()
+        {
+            return Err(Error::VerificationError);
+        }
+        let signed_date = untrusted
+            .claims::<Map<String, Value>>()?
+            .peek()
+            .extra
+            .get("signedDate")
+            .and_then(Value::as_u64)
+            .map(|ms| UNIX_EPOCH + Duration::from_millis(ms));
+        verify_chain(
+            &self.roots,
+            leaf,
+            slice::from_ref(intermediate),
+            signed_date.unwrap_or_else(SystemTime::now),
+        )?;
+
+        let k = SomePublicKey::from_pkey(leaf.public_key()?)?;
+        let options = ValidationOptions::new().allow_alg("ES256");
+        let (_, payload) = verify_raw(jws, &k, &options)?;
+        let payload: Value = serde_json::from_slice(&payload)?;
+        let bundle_id = payload
+            .get("bundleId")
+            .or_else(|| payload.get("data")?.get("bundleId"));
+        if bundle_id.and_then(Value::as_str) != Some(&self.bundle_id) {
+            return Err(Error::InvalidToken);
+        }
+        Ok(serde_json::from_value(payload)?)
+    }
+}
+
+/// The longest lifetime Apple accepts for client secrets and MusicKit
+/// developer tokens, six months.
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+pub const MAX_DEVELOPER_TOKEN_LIFETIME: Duration = Duration::from_secs(15_777_000);
+
+/// App Store Connect API tokens must expire within 20 minutes.
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+const APP_STORE_CONNECT_LIFETIME: Duration = Duration::from_secs(20 * 60);
+
+/// A private key from the Apple Developer account, a `.p8` file, for minting
+/// the tokens of Apple's APIs.
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+#[derive(Debug, Clone)]
+pub struct AppleDeveloperKey {
+    /// The team id, or for App Store Connect keys the issuer id.
+    pub issuer: String,
+    pub key_id: String,
+    key: EcdsaPrivateKey,
+}
+
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+impl AppleDeveloperKey {
+    /// `p8` is the PEM encoded key, `key_id` the id of the key, and `issuer`
+    /// the team id, or for App Store Connect keys the issuer id.
+    pub fn new(issuer: impl Into<String>, key_id: impl Into<String>, p8: &[u8]) -> Result<Self> {
+        Ok(Self {
+            issuer: issuer.into(),
+            key_id: key_id.into(),
+            key: EcdsaPrivateKey::from_pem(p8)?,
+        })
+    }
+
+    fn token(&self) -> HeaderAndClaims<Map<String, Value>> {
+        let mut token = HeaderAndClaims::new_dynamic();
+        token
+            .set_kid(&self.key_id)
+            .set_iss(&self.issuer)
+            .set_iat_now();
+        token
+    }
+
+    /// The `client_secret` of the Sign in with Apple client `client_id` at the
+    /// token endpoint. `lifetime` is capped at
+    /// [`MAX_DEVELOPER_TOKEN_LIFETIME`].
+    pub fn client_secret(&self, client_id: &str, lifetime: Duration) -> Result<String> {
+        sign(
+            self.token()
+                .set_sub(client_id)
+                .add_aud(APPLE_ISSUER)
+                .set_exp_from_now(lifetime.min(MAX_DEVELOPER_TOKEN_LIFETIME)),
+            &self.key,
+        )
+    }
+
+    /// An Apple Music API developer token. `lifetime` is capped at
+    /// [`MAX_DEVELOPER_TOKEN_LIFETIME`].
+    pub fn musickit_token(&self, lifetime: Duration) -> Result<String> {
+        sign(
+            self.token()
+                .set_exp_from_now(lifetime.min(MAX_DEVELOPER_TOKEN_LIFETIME)),
+            &self.key,
+        )
+    }
+
+    /// An APNs provider token. It has no `exp`: APNs accepts it for an
+    /// hour, and rejects tokens that are renewed more often than every 20
+    /// minutes.
+    pub fn apns_token(&self) -> Result<String> {
+        sign(&mut self.token(), &self.key)
+    }
+
+    /// An App Store Connect API token, valid for 20 minutes. With
+    /// `bundle_id`, it is for the App Store Server API of that app.
+    pub fn app_store_connect_token(&self, bundle_id: Option<&str>) -> Result<String> {
+        let mut token = self.token();
+        token.header_mut().typ = Some("JWT".into());
+        token
+            .add_aud("appstoreconnect-v1")
+            .set_exp_from_now(APP_STORE_CONNECT_LIFETIME);
+        if let Some(bid) = bundle_id {
+            token.insert("bid", bid);
+        }
+        sign(&mut token, &self.key)
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use openssl::pkey::{PKey, Private};
+    use serde_json::json;
+
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        sign,
+        x509::testing::{self, pkey},
+        Claims, HeaderAndClaims,
+    };
+
+    use super::*;
+
+    fn cert(
+        name: &str,
+        key: &PKey<Private>,
+        issuer: Option<(&X509, &PKey<Private>)>,
+        oid: Option<&str>,
+    ) -> Result<X509> {
+        let ca = issuer.is_none() || oid == Some(INTERMEDIATE_OID);
+        let mut extensions = Vec::new();
+        if let Some(oid) = oid {
+            // DER NULL, as in Apple's certificates.
+            extensions.push(testing::extension(oid, &[5, 0])?);
+        }
+        testing::cert(name, key, issuer, ca, extensions)
+    }
+
+    #[test]
+    fn app_store_payload() -> Result<()> {
+        let root_key = pkey(&EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?)?;
+        let intermediate_key = pkey(&EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?)?;
+        let leaf = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let root = cert("Root", &root_key, None, None)?;
+        let intermediate = cert(
+            "Intermediate",
+            &intermediate_key,
+            Some((&root, &root_key)),
+            Some(INTERMEDIATE_OID),
+        )?;
+        let sign_payload = |leaf_oid: Option<&str>, payload: Value| -> Result<String> {
+            let leaf_cert = cert(
+                "Leaf",
+                &pkey(&leaf)?,
+                Some((&intermediate, &intermediate_key)),
+                leaf_oid,
+            )?;
+            let x5c: Vec<String> = [&leaf_cert, &intermediate, &root]
+                .iter()
+                .map(|c| Ok(b64::encode(c.to_der()?)))
+                .collect::<Result<_>>()?;
+            let mut token = HeaderAndClaims::with_claims(payload);
+            token.header_mut().extra.insert("x5c".into(), json!(x5c));
+            sign(&mut token, &leaf)
+        };
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let transaction = json!({
+            "bundleId": "com.example.app",
+            "productId": "premium",
+            "signedDate": now_ms,
+        });
+        let jws = sign_payload(Some(LEAF_OID), transaction.clone())?;
+
+        let verifier = AppStoreVerifier::new(vec![root.clone()], "com.example.app");
+        let verified: Value = verifier.verify(&jws)?;
+        assert_eq!(verified["productId"], "premium");
+
+        let notification = json!({
+            "notificationType": "DID_RENEW",
+            "data": { "bundleId": "com.example.app" },
+            "signedDate": now_ms,
+        });
+        verifier.verify::<Value>(&sign_payload(Some(LEAF_OID), notification)?)?;
+
+        // Not an App Store certificate.
+        assert!(verifier
+            .verify::<Value>(&sign_payload(None, transaction.clone())?)
+            .is_err());
+        // Another app.
+        let other = AppStoreVerifier::new(vec![root.clone()], "com.example.other");
+        assert!(other.verify::<Value>(&jws).is_err());
+        // Another root.
+        let other_root = cert("Other", &root_key, None, None)?;
+        let other = AppStoreVerifier::new(vec![other_root], "com.example.app");
+        assert!(other.verify::<Value>(&jws).is_err());
+        // Signed before the certificates were valid.
+        let mut old = transaction;
+        old["signedDate"] = json!(now_ms - 7 * 24 * 60 * 60 * 1000);
+        assert!(verifier
+            .verify::<Value>(&sign_payload(Some(LEAF_OID), old)?)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn apple_id_claims() -> Result<()> {
+        let claims: Claims<AppleIdClaims> = serde_json::from_value(json!({
+            "email": "abc@privaterelay.appleid.com",
+            "email_verified": "true",
+            "is_private_email": true,
+            "real_user_status": 2,
+            "auth_time": 1700000000,
+        }))?;
+        assert_eq!(claims.extra.email_verified, Some(true));
+        assert_eq!(claims.extra.is_private_email, Some(true));
+        assert_eq!(claims.auth_time, Some(Duration::from_secs(1700000000)));
+        Ok(())
+    }
+
+    #[test]
+    fn developer_tokens() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let dev = AppleDeveloperKey::new(
+            "TEAM123456",
+            "KEY1234567",
+            k.private_key_to_pem_pkcs8()?.as_bytes(),
+        )?;
+        let verify = |token: &str, options: ValidationOptions| {
+            let verified = crate::verify_with_options::<Map<String, Value>>(token, &k, &options)?;
+            assert_eq!(verified.header().alg, "ES256");
+            assert_eq!(verified.header().kid.as_deref(), Some("KEY1234567"));
+            assert!(verified.claims().iat.is_some());
+            Ok::<_, Error>(verified)
+        };
+        let options = || ValidationOptions::new().require_iss("TEAM123456");
+
+        let secret = dev.client_secret("com.example.web", Duration::from_secs(365 * 86400))?;
+        let verified = verify(&secret, options().require_aud(APPLE_ISSUER))?;
+        assert_eq!(verified.claims().sub.as_deref(), Some("com.example.web"));
+        let (iat, exp) = (
+            verified.claims().iat.unwrap(),
+            verified.claims().exp.unwrap(),
+        );
+        assert!(exp - iat <= MAX_DEVELOPER_TOKEN_LIFETIME + Duration::from_secs(1));
+
+        verify(&dev.musickit_token(Duration::from_secs(3600))?, options())?;
+        let apns = verify(&dev.apns_token()?, options())?;
+        assert!(apns.claims().exp.is_none());
+
+        let connect = dev.app_store_connect_token(Some("com.example.app"))?;
+        let verified = verify(&connect, options().require_aud("appstoreconnect-v1"))?;
+        assert_eq!(verified.header().typ.as_deref(), Some("JWT"));
+        assert_eq!(verified.claims().extra["bid"], "com.example.app");
+        Ok(())
+    }
+}
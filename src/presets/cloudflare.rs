@@ -0,0 +1,165 @@
+//! Cloudflare Access application tokens.
+//!
+//! Access puts a token in the `Cf-Access-Jwt-Assertion` header of every
+//! request it lets through to the application. Its audience is the
+//! application's AUD tag, found in the application's settings in the Zero
+//! Trust dashboard.
+//!
+//! ```no_run
+//! # async fn f(headers: &std::collections::HashMap<String, String>) -> jwtk::Result<()> {
+//! use jwtk::presets::cloudflare::{self, CloudflareAccessClaims, CF_ACCESS_JWT_HEADER};
+//!
+//! let verifier = cloudflare::cloudflare_access_verifier("my-team", "<aud tag>", None);
+//! let token = headers.get(CF_ACCESS_JWT_HEADER).ok_or(jwtk::Error::InvalidToken)?;
+//! let verified = verifier.verify::<CloudflareAccessClaims>(token).await?;
+//! println!("{:?}", verified.claims().extra.email);
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+use crate::ValidationOptions;
+
+/// The request header with the token. Prefer it over the
+/// [`CF_AUTHORIZATION_COOKIE`], which is only sent by browsers.
+pub const CF_ACCESS_JWT_HEADER: &str = "Cf-Access-Jwt-Assertion";
+
+/// The cookie with the token, see [`read_cookie`](crate::cookie::read_cookie).
+pub const CF_AUTHORIZATION_COOKIE: &str = "CF_Authorization";
+
+/// Claims of Cloudflare Access application tokens.
+///
+/// Tokens of users have an `email`, tokens of service tokens a
+/// `common_name` (the client id) and an empty `sub`.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CloudflareAccessClaims {
+    pub email: Option<String>,
+    /// `app` for application tokens.
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub identity_nonce: Option<String>,
+    /// Country of the request, as an ISO 3166-1 alpha-2 code.
+    pub country: Option<String>,
+    pub common_name: Option<String>,
+    /// Custom SAML attributes or OIDC claims of the identity provider.
+    pub custom: Option<Map<String, Value>>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The issuer of tokens of the team `team_domain`, which is either the team
+/// name, e.g. `my-team`, or its domain, e.g. `my-team.cloudflareaccess.com`,
+/// with or without `https://`.
+pub fn cloudflare_access_issuer(team_domain: &str) -> String {
+    let domain = team_domain
+        .trim_start_matches("https://")
+        .trim_end_matches('/');
+    if domain.contains('.') {
+        format!("https://{}", domain)
+    } else {
+        format!("https://{}.cloudflareaccess.com", domain)
+    }
+}
+
+/// The JWK Set of the keys signing the tokens of `team_domain`, see
+/// [`cloudflare_access_issuer`].
+pub fn cloudflare_access_certs_url(team_domain: &str) -> String {
+    format!(
+        "{}/cdn-cgi/access/certs",
+        cloudflare_access_issuer(team_domain)
+    )
+}
+
+/// Issuer and audience rules of tokens of the application with the AUD tag
+/// `aud` of `team_domain`, see [`cloudflare_access_issuer`].
+pub fn cloudflare_access_options(team_domain: &str, aud: &str) -> ValidationOptions {
+    ValidationOptions::new()
+        .require_iss(cloudflare_access_issuer(team_domain))
+        .require_aud(aud)
+        .allow_alg("RS256")
+}
+
+/// A verifier of tokens of the application with the AUD tag `aud`, with keys
+/// fetched from [`cloudflare_access_certs_url`].
+#[cfg(feature = "remote-jwks")]
+pub fn cloudflare_access_verifier(
+    team_domain: &str,
+    aud: &str,
+    client: Option<reqwest::Client>,
+) -> crate::jwk::RemoteJwksVerifier {
+    // Access rotates keys every six weeks, and keeps the previous key
+    // published for a week.
+    let mut verifier = crate::jwk::RemoteJwksVerifier::new(
+        cloudflare_access_certs_url(team_domain),
+        client,
+        std::time::Duration::from_secs(60 * 60),
+    );
+    verifier.set_validation_options(cloudflare_access_options(team_domain, aud));
+    verifier
+}
+
+#[cfg(all(test, feature = "rsa", not(feature = "verify-only")))]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        jwk::{JwkSet, WithKid},
+        rsa::{RsaAlgorithm, RsaPrivateKey},
+        sign, HeaderAndClaims, PublicKeyToJwk, Result,
+    };
+
+    use super::*;
+
+    #[test]
+    fn cloudflare_access_token() -> Result<()> {
+        for team in [
+            "my-team",
+            "my-team.cloudflareaccess.com",
+            "https://my-team.cloudflareaccess.com/",
+        ] {
+            assert_eq!(
+                cloudflare_access_certs_url(team),
+                "https://my-team.cloudflareaccess.com/cdn-cgi/access/certs"
+            );
+        }
+
+        let k = WithKid::new(
+            "cf".into(),
+            RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?,
+        );
+        // The certs endpoint serves certificates next to the keys.
+        let certs: JwkSet = serde_json::from_value(json!({
+            "keys": [k.public_key_to_jwk()?],
+            "public_cert": { "kid": "cf", "cert": "-----BEGIN CERTIFICATE-----" },
+            "public_certs": [],
+        }))?;
+        let mut v = certs.verifier();
+        v.set_validation_options(cloudflare_access_options("my-team", "app-tag"));
+
+        let extra: CloudflareAccessClaims = serde_json::from_value(json!({
+            "email": "alice@example.com",
+            "type": "app",
+            "identity_nonce": "6ei69kawdKzMIAPF",
+            "country": "SE",
+        }))?;
+        let mut claims = HeaderAndClaims::with_claims(extra);
+        claims
+            .set_iss("https://my-team.cloudflareaccess.com")
+            .add_aud("app-tag")
+            .set_sub("7335d417-61da-459d-899c-0a01c76a2f94");
+        let token = sign(&mut claims, &k)?;
+        let verified = v.verify::<CloudflareAccessClaims>(&token)?;
+        assert_eq!(verified.claims().extra.type_.as_deref(), Some("app"));
+        assert_eq!(verified.claims().extra.country.as_deref(), Some("SE"));
+
+        v.set_validation_options(cloudflare_access_options("my-team", "other-tag"));
+        assert!(v.verify::<CloudflareAccessClaims>(&token).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+//! GitHub Actions OIDC tokens.
+//!
+//! ```no_run
+//! # async fn f(token: &str) -> jwtk::Result<()> {
+//! use jwtk::presets::github::{self, GithubActionsClaims};
+//!
+//! let verifier = github::github_actions_verifier("https://github.com/octo-org", None);
+//! let verified = verifier.verify::<GithubActionsClaims>(token).await?;
+//! github::check_subject(&verified, &["repo:octo-org/*:environment:prod"])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+use crate::{Error, HeaderAndClaims, Result, ValidationOptions};
+
+pub const GITHUB_ACTIONS_ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+pub const GITHUB_ACTIONS_JWKS_URL: &str =
+    "https://token.actions.githubusercontent.com/.well-known/jwks";
+
+/// Claims of GitHub Actions OIDC tokens.
+///
+/// Ids and numbers, e.g. `repository_id` and `run_number`, are strings in
+/// these tokens.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GithubActionsClaims {
+    /// `owner/name` of the repository the workflow runs in.
+    pub repository: Option<String>,
+    pub repository_id: Option<String>,
+    pub repository_owner: Option<String>,
+    pub repository_owner_id: Option<String>,
+    /// `public`, `private` or `internal`.
+    pub repository_visibility: Option<String>,
+    /// The git ref, e.g. `refs/heads/main`.
+    #[serde(rename = "ref")]
+    pub ref_: Option<String>,
+    /// `branch` or `tag`.
+    pub ref_type: Option<String>,
+    pub sha: Option<String>,
+    pub head_ref: Option<String>,
+    pub base_ref: Option<String>,
+    /// Name of the workflow.
+    pub workflow: Option<String>,
+    /// `owner/repo/path/to/workflow.yml@ref` of the workflow.
+    pub workflow_ref: Option<String>,
+    /// Like `workflow_ref`, but of the reusable workflow the job runs, if
+    /// any.
+    pub job_workflow_ref: Option<String>,
+    pub job_workflow_sha: Option<String>,
+    pub environment: Option<String>,
+    pub event_name: Option<String>,
+    pub actor: Option<String>,
+    pub actor_id: Option<String>,
+    pub run_id: Option<String>,
+    pub run_number: Option<String>,
+    pub run_attempt: Option<String>,
+    /// `github-hosted` or `self-hosted`.
+    pub runner_environment: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Issuer and audience rules of GitHub Actions OIDC tokens.
+///
+/// `audience` defaults to the url of the repository owner, e.g.
+/// `https://github.com/octo-org`, unless the workflow requests another.
+pub fn github_actions_options(audience: &str) -> ValidationOptions {
+    ValidationOptions::new()
+        .require_iss(GITHUB_ACTIONS_ISSUER)
+        .require_aud(audience)
+}
+
+/// A verifier of GitHub Actions OIDC tokens for `audience`, with keys fetched
+/// from [`GITHUB_ACTIONS_JWKS_URL`].
+#[cfg(feature = "remote-jwks")]
+pub fn github_actions_verifier(
+    audience: &str,
+    client: Option<reqwest::Client>,
+) -> crate::jwk::RemoteJwksVerifier {
+    let mut verifier = crate::jwk::RemoteJwksVerifier::new(
+        GITHUB_ACTIONS_JWKS_URL.into(),
+        client,
+        std::time::Duration::from_secs(60 * 60),
+    );
+    verifier.set_validation_options(github_actions_options(audience));
+    verifier
+}
+
+/// Whether `sub` matches `pattern`, in which `*` matches any sequence of
+/// characters, including none, and `?` any single character, as in the
+/// `StringLike` conditions of cloud trust policies.
+///
+/// E.g. `repo:octo-org/*:ref:refs/heads/main` matches
+/// `repo:octo-org/app:ref:refs/heads/main`.
+pub fn subject_matches(pattern: &str, sub: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let sub: Vec<char> = sub.chars().collect();
+    // Greedy matching with backtracking to the last `*`.
+    let (mut p, mut s) = (0, 0);
+    let mut star = None;
+    while s < sub.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, s));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == sub[s] => {
+                p += 1;
+                s += 1;
+            }
+            _ => match star {
+                Some((sp, ss)) => {
+                    p = sp + 1;
+                    s = ss + 1;
+                    star = Some((sp, ss + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Check that `sub` matches any of `patterns`, see [`subject_matches`].
+pub fn check_subject<ExtraClaims>(
+    token: &HeaderAndClaims<ExtraClaims>,
+    patterns: &[&str],
+) -> Result<()> {
+    match token.claims().sub.as_deref() {
+        Some(sub) if patterns.iter().any(|p| subject_matches(p, sub)) => Ok(()),
+        _ => Err(Error::InvalidToken),
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use serde_json::json;
+
+    use crate::{ecdsa::EcdsaPrivateKey, sign, verify_with_options};
+
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        let sub = "repo:octo-org/app:environment:prod";
+        assert!(subject_matches(sub, sub));
+        assert!(subject_matches("repo:octo-org/*:environment:prod", sub));
+        assert!(subject_matches("repo:octo-org/*", sub));
+        assert!(subject_matches("*", sub));
+        assert!(subject_matches("repo:octo-org/ap?:*:prod", sub));
+        assert!(subject_matches("repo:*:*:prod", sub));
+        assert!(!subject_matches("repo:octo-org/*:environment:dev", sub));
+        assert!(!subject_matches("repo:other-org/*", sub));
+        assert!(!subject_matches("repo:octo-org/app", sub));
+        assert!(!subject_matches("", sub));
+        assert!(subject_matches("", ""));
+        assert!(subject_matches("a*b*c", "aXbYbZc"));
+        assert!(!subject_matches("a*b*c", "aXbYbZ"));
+    }
+
+    #[test]
+    fn github_actions_token() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(crate::ecdsa::EcdsaAlgorithm::ES256)?;
+        let extra: GithubActionsClaims = serde_json::from_value(json!({
+            "repository": "octo-org/app",
+            "repository_id": "42",
+            "ref": "refs/heads/main",
+            "workflow": "deploy",
+            "job_workflow_ref": "octo-org/workflows/.github/workflows/deploy.yml@refs/heads/main",
+            "environment": "prod",
+        }))?;
+        let mut claims = HeaderAndClaims::with_claims(extra);
+        claims
+            .set_iss(GITHUB_ACTIONS_ISSUER)
+            .add_aud("https://github.com/octo-org")
+            .set_sub("repo:octo-org/app:environment:prod");
+        let token = sign(&mut claims, &k)?;
+
+        let options = github_actions_options("https://github.com/octo-org");
+        let verified = verify_with_options::<GithubActionsClaims>(&token, &k, &options)?;
+        assert_eq!(
+            verified.claims().extra.ref_.as_deref(),
+            Some("refs/heads/main")
+        );
+        assert_eq!(verified.claims().extra.environment.as_deref(), Some("prod"));
+        check_subject(&verified, &["repo:octo-org/*:environment:prod"])?;
+        assert!(check_subject(&verified, &["repo:octo-org/*:ref:*"]).is_err());
+
+        let options = github_actions_options("sts.amazonaws.com");
+        assert!(verify_with_options::<GithubActionsClaims>(&token, &k, &options).is_err());
+        Ok(())
+    }
+}
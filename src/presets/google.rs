@@ -0,0 +1,362 @@
+//! Firebase Authentication and Google Sign-In ID tokens, and service account
+//! assertions.
+//!
+//! ```no_run
+//! # async fn f(token: &str) -> jwtk::Result<()> {
+//! use jwtk::presets::google::FirebaseVerifier;
+//!
+//! let verifier = FirebaseVerifier::new("my-project", None);
+//! let verified = verifier.verify(token).await?;
+//! println!("{:?}", verified.claims().extra.firebase);
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(any(
+    feature = "remote-jwks",
+    all(feature = "rsa", not(feature = "verify-only"))
+))]
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+use crate::{
+    rsa::{RsaAlgorithm, RsaPrivateKey},
+    sign,
+};
+use crate::{Error, HeaderAndClaims, Result, ValidationOptions};
+
+/// X.509 certificates (not a JWK Set) of the keys signing Firebase ID tokens.
+pub const FIREBASE_CERTS_URL: &str =
+    "https://www.googleapis.com/robot/v1/metadata/x509/securetoken@system.gserviceaccount.com";
+
+/// JWK Set of the keys signing Google ID tokens.
+pub const GOOGLE_JWKS_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
+
+/// Issuers of Google ID tokens. Both forms are in use.
+pub const GOOGLE_ISSUERS: &[&str] = &["https://accounts.google.com", "accounts.google.com"];
+
+/// Google rotates its keys every few days and serves them with a cache
+/// lifetime of several hours.
+#[cfg(feature = "remote-jwks")]
+const CACHE_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// The `firebase` claim.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FirebaseInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sign_in_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Identities of the user, by provider.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub identities: Map<String, Value>,
+}
+
+/// Claims of Firebase ID tokens. Custom claims end up in `extra`.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FirebaseClaims {
+    pub user_id: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub phone_number: Option<String>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    pub firebase: Option<FirebaseInfo>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Claims of Google ID tokens.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GoogleClaims {
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    /// Hosted Google Workspace domain of the user.
+    pub hd: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub picture: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Issuer and audience rules of Firebase ID tokens for `project_id`.
+pub fn firebase_options(project_id: &str) -> ValidationOptions {
+    ValidationOptions::new()
+        .require_iss(format!("https://securetoken.google.com/{}", project_id))
+        .require_aud(project_id)
+}
+
+/// Issuer and audience rules of Google ID tokens for the OAuth client
+/// `client_id`.
+pub fn google_id_token_options(client_id: &str) -> ValidationOptions {
+    GOOGLE_ISSUERS
+        .iter()
+        .fold(ValidationOptions::new(), |o, iss| o.require_iss(*iss))
+        .require_aud(client_id)
+}
+
+/// Checks of Firebase ID tokens beyond [`firebase_options`]: `alg` must be
+/// `RS256`, `sub` must be a non-empty uid of at most 128 characters and
+/// `auth_time` must be in the past.
+pub fn check_firebase_token(token: &HeaderAndClaims<FirebaseClaims>) -> Result<()> {
+    if token.header().alg != "RS256" {
+        return Err(Error::AlgMismatch);
+    }
+    match token.claims().sub.as_deref() {
+        Some(sub) if !sub.is_empty() && sub.len() <= 128 => {}
+        _ => return Err(Error::InvalidToken),
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    match token.claims().auth_time {
+        Some(auth_time) if auth_time <= now => Ok(()),
+        Some(_) => Err(Error::Before),
+        None => Err(Error::InvalidToken),
+    }
+}
+
+/// Verifies Firebase ID tokens of a project, with keys fetched from
+/// [`FIREBASE_CERTS_URL`].
+#[cfg(feature = "remote-jwks")]
+pub struct FirebaseVerifier {
+    inner: crate::jwk::RemoteJwksVerifier,
+}
+
+#[cfg(feature = "remote-jwks")]
+impl FirebaseVerifier {
+    pub fn new(project_id: &str, client: Option<reqwest::Client>) -> Self {
+        let mut inner = crate::jwk::RemoteJwksVerifier::new_x509_pem_map(
+            FIREBASE_CERTS_URL.into(),
+            client,
+            CACHE_DURATION,
+        );
+        inner.set_validation_options(firebase_options(project_id));
+        Self { inner }
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<HeaderAndClaims<FirebaseClaims>> {
+        let verified = self.inner.verify(token).await?;
+        check_firebase_token(&verified)?;
+        Ok(verified)
+    }
+}
+
+/// A verifier of Google ID tokens for the OAuth client `client_id`, with keys
+/// fetched from [`GOOGLE_JWKS_URL`].
+#[cfg(feature = "remote-jwks")]
+pub fn google_id_token_verifier(
+    client_id: &str,
+    client: Option<reqwest::Client>,
+) -> crate::jwk::RemoteJwksVerifier {
+    let mut verifier =
+        crate::jwk::RemoteJwksVerifier::new(GOOGLE_JWKS_URL.into(), client, CACHE_DURATION);
+    verifier.set_validation_options(google_id_token_options(client_id));
+    verifier
+}
+
+/// Token endpoint of Google OAuth2.
+pub const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+/// The `grant_type` for exchanging a [`ServiceAccountKey::oauth2_assertion`]
+/// for an access token.
+pub const JWT_BEARER_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+
+/// Google accepts assertions that expire within an hour.
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+const ASSERTION_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// A service account key, from a JSON key file of the Cloud console.
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+#[derive(Debug, Clone)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    pub private_key_id: String,
+    pub token_uri: String,
+    key: RsaPrivateKey,
+}
+
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+impl ServiceAccountKey {
+    pub fn from_json(json: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct KeyFile {
+            client_email: String,
+            private_key_id: String,
+            private_key: String,
+            token_uri: Option<String>,
+        }
+        let f: KeyFile = serde_json::from_str(json)?;
+        Ok(Self {
+            key: RsaPrivateKey::from_pem(f.private_key.as_bytes(), RsaAlgorithm::RS256)?,
+            client_email: f.client_email,
+            private_key_id: f.private_key_id,
+            token_uri: f.token_uri.unwrap_or_else(|| GOOGLE_TOKEN_URL.into()),
+        })
+    }
+
+    /// An assertion to post to `token_uri` with [`JWT_BEARER_GRANT_TYPE`],
+    /// for an access token with `scopes`. `subject` is the user to
+    /// impersonate with domain-wide delegation.
+    pub fn oauth2_assertion(&self, scopes: &[&str], subject: Option<&str>) -> Result<String> {
+        let mut token = HeaderAndClaims::new_dynamic();
+        token
+            .set_kid(&self.private_key_id)
+            .set_iss(&self.client_email)
+            .add_aud(&self.token_uri)
+            .insert("scope", scopes.join(" "))
+            .set_iat_now()
+            .set_exp_from_now(ASSERTION_LIFETIME);
+        if let Some(sub) = subject {
+            token.set_sub(sub);
+        }
+        sign(&mut token, &self.key)
+    }
+
+    /// A token to call a Google API with directly, without exchanging it for
+    /// an access token. `audience` is the API's endpoint, e.g.
+    /// `https://pubsub.googleapis.com/`.
+    pub fn self_signed_jwt(&self, audience: &str) -> Result<String> {
+        sign(
+            HeaderAndClaims::new_dynamic()
+                .set_kid(&self.private_key_id)
+                .set_iss(&self.client_email)
+                .set_sub(&self.client_email)
+                .add_aud(audience)
+                .set_iat_now()
+                .set_exp_from_now(ASSERTION_LIFETIME),
+            &self.key,
+        )
+    }
+}
+
+#[cfg(all(test, feature = "rsa", not(feature = "verify-only")))]
+mod tests {
+    use serde_json::json;
+
+    use crate::verify_with_options;
+
+    use super::*;
+
+    #[test]
+    fn firebase() -> Result<()> {
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let options = firebase_options("my-project");
+        // Claim times are serialized as whole seconds.
+        let now = Duration::from_secs(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        );
+
+        let mut claims = HeaderAndClaims::with_claims(FirebaseClaims::default());
+        claims.claims_mut().auth_time = Some(now);
+        claims
+            .set_iss("https://securetoken.google.com/my-project")
+            .add_aud("my-project")
+            .set_sub("uid")
+            .set_exp_from_now(Duration::from_secs(60));
+        let token = sign(&mut claims, &k)?;
+        let verified = verify_with_options::<FirebaseClaims>(&token, &k, &options)?;
+        check_firebase_token(&verified)?;
+
+        let other = firebase_options("other-project");
+        assert!(matches!(
+            verify_with_options::<FirebaseClaims>(&token, &k, &other),
+            Err(Error::IssMismatch)
+        ));
+
+        claims.claims_mut().auth_time = Some(now + Duration::from_secs(600));
+        let token = sign(&mut claims, &k)?;
+        let verified = verify_with_options::<FirebaseClaims>(&token, &k, &options)?;
+        assert!(matches!(
+            check_firebase_token(&verified),
+            Err(Error::Before)
+        ));
+
+        claims.claims_mut().auth_time = Some(now);
+        claims.set_sub("");
+        let token = sign(&mut claims, &k)?;
+        let verified = verify_with_options::<FirebaseClaims>(&token, &k, &options)?;
+        assert!(check_firebase_token(&verified).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn google() -> Result<()> {
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let options = google_id_token_options("client");
+        for iss in GOOGLE_ISSUERS {
+            let token = sign(
+                HeaderAndClaims::new_dynamic()
+                    .set_iss(*iss)
+                    .add_aud("client"),
+                &k,
+            )?;
+            verify_with_options::<GoogleClaims>(&token, &k, &options)?;
+        }
+        let token = sign(
+            HeaderAndClaims::new_dynamic()
+                .set_iss(GOOGLE_ISSUERS[0])
+                .add_aud("other"),
+            &k,
+        )?;
+        assert!(matches!(
+            verify_with_options::<GoogleClaims>(&token, &k, &options),
+            Err(Error::AudMismatch)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn service_account_assertion() -> Result<()> {
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let key_file = json!({
+            "type": "service_account",
+            "project_id": "my-project",
+            "private_key_id": "abc123",
+            "private_key": k.private_key_to_pem_pkcs8()?,
+            "client_email": "robot@my-project.iam.gserviceaccount.com",
+            "client_id": "1234567890",
+        });
+        let sa = ServiceAccountKey::from_json(&key_file.to_string())?;
+
+        let assertion = sa.oauth2_assertion(
+            &[
+                "https://www.googleapis.com/auth/devstorage.read_only",
+                "https://www.googleapis.com/auth/pubsub",
+            ],
+            Some("alice@example.com"),
+        )?;
+        let options = ValidationOptions::new()
+            .require_iss("robot@my-project.iam.gserviceaccount.com")
+            .require_aud(GOOGLE_TOKEN_URL);
+        let verified = verify_with_options::<Map<String, Value>>(&assertion, &k, &options)?;
+        assert_eq!(verified.header().kid.as_deref(), Some("abc123"));
+        assert_eq!(
+            verified.claims().extra["scope"],
+            "https://www.googleapis.com/auth/devstorage.read_only https://www.googleapis.com/auth/pubsub"
+        );
+        assert_eq!(verified.claims().sub.as_deref(), Some("alice@example.com"));
+        assert!(verified.claims().exp.is_some() && verified.claims().iat.is_some());
+
+        let jwt = sa.self_signed_jwt("https://pubsub.googleapis.com/")?;
+        let options = ValidationOptions::new().require_aud("https://pubsub.googleapis.com/");
+        let verified = verify_with_options::<Map<String, Value>>(&jwt, &k, &options)?;
+        assert_eq!(verified.claims().sub, verified.claims().iss);
+        Ok(())
+    }
+}
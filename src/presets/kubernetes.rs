@@ -0,0 +1,221 @@
+//! Kubernetes service account tokens, e.g. projected volume tokens bound to
+//! an audience and a pod.
+//!
+//! Keys are discovered through the cluster's service account issuer
+//! discovery endpoints. In cluster the discovery document normally requires
+//! authentication, see [`in_cluster_client`] and [`in_cluster_token`]. The
+//! JWK Set is fetched without the token, so it must be readable
+//! anonymously, e.g. by binding the `system:service-account-issuer-discovery`
+//! cluster role to `system:unauthenticated`.
+//!
+//! ```no_run
+//! # async fn f(token: &str) -> jwtk::Result<()> {
+//! use jwtk::presets::kubernetes::{self, KubernetesClaims, IN_CLUSTER_ISSUER};
+//!
+//! let client = kubernetes::in_cluster_client()?;
+//! let sa_token = kubernetes::in_cluster_token()?;
+//! let verifier = kubernetes::kubernetes_verifier(
+//!     IN_CLUSTER_ISSUER,
+//!     "my-service",
+//!     Some(client),
+//!     Some(&sa_token),
+//! )
+//! .await?;
+//! let verified = verifier.verify::<KubernetesClaims>(token).await?;
+//! kubernetes::check_kubernetes_token(&verified)?;
+//! println!("{}", verified.claims().extra.kubernetes.namespace);
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+use crate::{Error, HeaderAndClaims, Result, ValidationOptions};
+
+/// The default issuer of in cluster service account tokens.
+pub const IN_CLUSTER_ISSUER: &str = "https://kubernetes.default.svc.cluster.local";
+
+/// Where the pod's own service account token is mounted.
+pub const SERVICE_ACCOUNT_TOKEN_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+/// Where the cluster CA certificate is mounted.
+pub const SERVICE_ACCOUNT_CA_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/ca.crt";
+
+/// A reference to a Kubernetes object.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectRef {
+    pub name: String,
+    pub uid: String,
+}
+
+/// The `kubernetes.io` claim.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KubernetesInfo {
+    pub namespace: String,
+    pub serviceaccount: ObjectRef,
+    /// The pod the token is bound to.
+    pub pod: Option<ObjectRef>,
+    /// The secret the token is bound to.
+    pub secret: Option<ObjectRef>,
+    /// The node the pod runs on, in recent versions.
+    pub node: Option<ObjectRef>,
+    /// When kubelet refreshes the token, for tokens with extended lifetime.
+    pub warnafter: Option<u64>,
+}
+
+/// Claims of service account tokens.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KubernetesClaims {
+    #[serde(rename = "kubernetes.io")]
+    pub kubernetes: KubernetesInfo,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// The `sub` of tokens of the service account `name` in `namespace`.
+pub fn service_account_sub(namespace: &str, name: &str) -> String {
+    format!("system:serviceaccount:{}:{}", namespace, name)
+}
+
+/// Issuer and audience rules of service account tokens.
+///
+/// Always require an audience, so that tokens for other services, e.g. the
+/// API server itself, aren't accepted.
+pub fn kubernetes_options(issuer: &str, audience: &str) -> ValidationOptions {
+    ValidationOptions::new()
+        .require_iss(issuer)
+        .require_aud(audience)
+}
+
+/// Check that `sub` matches the service account in the `kubernetes.io`
+/// claim.
+pub fn check_kubernetes_token(token: &HeaderAndClaims<KubernetesClaims>) -> Result<()> {
+    let k8s = &token.claims().extra.kubernetes;
+    if token.claims().sub.as_deref()
+        != Some(&*service_account_sub(
+            &k8s.namespace,
+            &k8s.serviceaccount.name,
+        ))
+    {
+        return Err(Error::InvalidToken);
+    }
+    Ok(())
+}
+
+/// A client for the API server, trusting the CA certificate mounted into
+/// pods.
+#[cfg(feature = "remote-jwks")]
+pub fn in_cluster_client() -> Result<reqwest::Client> {
+    let ca = reqwest::Certificate::from_pem(&std::fs::read(SERVICE_ACCOUNT_CA_PATH)?)?;
+    Ok(reqwest::Client::builder()
+        .add_root_certificate(ca)
+        .build()?)
+}
+
+/// The token mounted into pods, to authenticate to the API server.
+pub fn in_cluster_token() -> Result<String> {
+    Ok(std::fs::read_to_string(SERVICE_ACCOUNT_TOKEN_PATH)?
+        .trim()
+        .to_string())
+}
+
+/// A verifier of service account tokens issued by `issuer` for `audience`.
+///
+/// The JWK Set url is discovered from the issuer's OpenID provider
+/// configuration, which must be for `issuer`. `discovery_token` is sent
+/// with the discovery request only, not to the JWK Set url.
+#[cfg(feature = "remote-jwks")]
+pub async fn kubernetes_verifier(
+    issuer: &str,
+    audience: &str,
+    client: Option<reqwest::Client>,
+    discovery_token: Option<&str>,
+) -> Result<crate::jwk::RemoteJwksVerifier> {
+    let client = client.unwrap_or_default();
+    let jwks_uri = super::discover_jwks_uri(&client, issuer, discovery_token).await?;
+    let mut verifier = crate::jwk::RemoteJwksVerifier::new(
+        jwks_uri,
+        Some(client),
+        std::time::Duration::from_secs(5 * 60),
+    );
+    verifier.set_validation_options(kubernetes_options(issuer, audience));
+    Ok(verifier)
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use serde_json::json;
+
+    use crate::{ecdsa::EcdsaPrivateKey, sign, verify_with_options};
+
+    use super::*;
+
+    #[test]
+    fn service_account_token() -> Result<()> {
+        let k = EcdsaPrivateKey::generate(crate::ecdsa::EcdsaAlgorithm::ES256)?;
+        let extra: KubernetesClaims = serde_json::from_value(json!({
+            "kubernetes.io": {
+                "namespace": "default",
+                "pod": { "name": "app-7d9f", "uid": "a1" },
+                "serviceaccount": { "name": "app", "uid": "b2" },
+                "warnafter": 1700000000,
+            },
+        }))?;
+        let mut claims = HeaderAndClaims::with_claims(extra);
+        claims
+            .set_iss(IN_CLUSTER_ISSUER)
+            .add_aud("vault")
+            .set_sub(service_account_sub("default", "app"));
+        let token = sign(&mut claims, &k)?;
+
+        let options = kubernetes_options(IN_CLUSTER_ISSUER, "vault");
+        let verified = verify_with_options::<KubernetesClaims>(&token, &k, &options)?;
+        check_kubernetes_token(&verified)?;
+        let k8s = &verified.claims().extra.kubernetes;
+        assert_eq!(k8s.pod.as_ref().unwrap().name, "app-7d9f");
+        assert_eq!(k8s.serviceaccount.uid, "b2");
+
+        let options = kubernetes_options(IN_CLUSTER_ISSUER, "other");
+        assert!(verify_with_options::<KubernetesClaims>(&token, &k, &options).is_err());
+
+        claims.set_sub(service_account_sub("kube-system", "app"));
+        let token = sign(&mut claims, &k)?;
+        let options = kubernetes_options(IN_CLUSTER_ISSUER, "vault");
+        let verified = verify_with_options::<KubernetesClaims>(&token, &k, &options)?;
+        assert!(check_kubernetes_token(&verified).is_err());
+        Ok(())
+    }
+
+    #[cfg(all(feature = "remote-jwks", feature = "test-util"))]
+    #[tokio::test]
+    async fn discovery() -> Result<()> {
+        let mut issuer = crate::test_util::MockIssuer::new()?;
+        issuer.serve()?;
+        let verifier = kubernetes_verifier(issuer.issuer(), "vault", None, Some("sa")).await?;
+        let token = issuer.mint(|t| {
+            t.add_aud("vault").insert(
+                "kubernetes.io",
+                json!({
+                    "namespace": "default",
+                    "serviceaccount": { "name": "app", "uid": "b2" },
+                }),
+            );
+        })?;
+        let verified = verifier.verify::<KubernetesClaims>(&token).await?;
+        assert_eq!(verified.claims().extra.kubernetes.namespace, "default");
+
+        // The configuration is for another issuer.
+        let other = format!("{}/", issuer.issuer());
+        assert!(matches!(
+            kubernetes_verifier(&other, "vault", None, None).await,
+            Err(Error::IssMismatch)
+        ));
+        Ok(())
+    }
+}
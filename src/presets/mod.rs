@@ -0,0 +1,43 @@
+//! Ready made verification for tokens issued by well known platforms.
+//!
+//! Each preset knows where the platform publishes its keys and which issuer
+//! and audience rules apply, and comes with typed claims. The verifiers
+//! require the `remote-jwks` feature, the claims and validation options don't.
+
+pub mod apple;
+
+pub mod cloudflare;
+
+pub mod github;
+
+pub mod google;
+
+pub mod kubernetes;
+
+/// Fetch the OpenID provider configuration of `issuer`, with `bearer` token
+/// if any, check that it is for `issuer`, and return its `jwks_uri`.
+#[cfg(feature = "remote-jwks")]
+async fn discover_jwks_uri(
+    client: &reqwest::Client,
+    issuer: &str,
+    bearer: Option<&str>,
+) -> crate::Result<String> {
+    #[derive(serde::Deserialize)]
+    struct Configuration {
+        issuer: String,
+        jwks_uri: String,
+    }
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    let mut request = client.get(url).header("accept", "application/json");
+    if let Some(bearer) = bearer {
+        request = request.bearer_auth(bearer);
+    }
+    let configuration: Configuration = request.send().await?.error_for_status()?.json().await?;
+    if configuration.issuer != issuer {
+        return Err(crate::Error::IssMismatch);
+    }
+    Ok(configuration.jwks_uri)
+}
@@ -0,0 +1,226 @@
+//! `Debug` output that is safe to log.
+//!
+//! Registered claims are shown by name only, other claims not at all, tokens
+//! are truncated and secret key material is left out. Use `unredacted()`
+//! where the values are needed, e.g. in tests.
+
+use std::fmt;
+
+use crate::{jwk::Jwk, Claims, HeaderAndClaims};
+
+/// Full `Debug` output of claims and tokens, see e.g.
+/// [`Claims::unredacted`].
+pub struct Unredacted<'a, T>(pub(crate) &'a T);
+
+/// Shows the first few characters and the length of a token.
+pub(crate) struct RedactedToken<'a>(pub(crate) &'a str);
+
+impl fmt::Debug for RedactedToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.get(..8) {
+            Some(prefix) if self.0.len() > 8 => {
+                write!(f, "\"{}…\" ({} bytes)", prefix, self.0.len())
+            }
+            _ => write!(f, "\"…\" ({} bytes)", self.0.len()),
+        }
+    }
+}
+
+impl<ExtraClaims: fmt::Debug> fmt::Debug for Claims<ExtraClaims> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = self;
+        let present = [
+            ("exp", c.exp.is_some()),
+            ("nbf", c.nbf.is_some()),
+            ("iat", c.iat.is_some()),
+            ("iss", c.iss.is_some()),
+            ("sub", c.sub.is_some()),
+            ("aud", !c.aud.is_empty()),
+            ("jti", c.jti.is_some()),
+            ("act", c.act.is_some()),
+            ("may_act", c.may_act.is_some()),
+            ("auth_time", c.auth_time.is_some()),
+            ("acr", c.acr.is_some()),
+            ("amr", !c.amr.is_empty()),
+            ("azp", c.azp.is_some()),
+            ("nonce", c.nonce.is_some()),
+            ("sid", c.sid.is_some()),
+            ("events", !c.events.is_empty()),
+            ("toe", c.toe.is_some()),
+            ("txn", c.txn.is_some()),
+        ];
+        let names: Vec<&str> = present
+            .iter()
+            .filter(|(_, present)| *present)
+            .map(|(name, _)| *name)
+            .collect();
+        f.debug_struct("Claims")
+            .field("names", &names)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<ExtraClaims> Claims<ExtraClaims> {
+    /// `Debug` output including claim values.
+    pub fn unredacted(&self) -> Unredacted<'_, Self> {
+        Unredacted(self)
+    }
+}
+
+impl<ExtraClaims: fmt::Debug> fmt::Debug for Unredacted<'_, Claims<ExtraClaims>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = self.0;
+        f.debug_struct("Claims")
+            .field("exp", &c.exp)
+            .field("nbf", &c.nbf)
+            .field("iat", &c.iat)
+            .field("iss", &c.iss)
+            .field("sub", &c.sub)
+            .field("aud", &c.aud)
+            .field("jti", &c.jti)
+            .field("act", &c.act)
+            .field("may_act", &c.may_act)
+            .field("auth_time", &c.auth_time)
+            .field("acr", &c.acr)
+            .field("amr", &c.amr)
+            .field("azp", &c.azp)
+            .field("nonce", &c.nonce)
+            .field("sid", &c.sid)
+            .field("events", &c.events)
+            .field("toe", &c.toe)
+            .field("txn", &c.txn)
+            .field("extra", &c.extra)
+            .finish()
+    }
+}
+
+impl<ExtraClaims: fmt::Debug> fmt::Debug for HeaderAndClaims<ExtraClaims> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeaderAndClaims")
+            .field("header", self.header())
+            .field("claims", self.claims())
+            .finish()
+    }
+}
+
+impl<ExtraClaims> HeaderAndClaims<ExtraClaims> {
+    /// `Debug` output including claim values.
+    pub fn unredacted(&self) -> Unredacted<'_, Self> {
+        Unredacted(self)
+    }
+}
+
+impl<ExtraClaims: fmt::Debug> fmt::Debug for Unredacted<'_, HeaderAndClaims<ExtraClaims>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeaderAndClaims")
+            .field("header", self.0.header())
+            .field("claims", &self.0.claims().unredacted())
+            .finish()
+    }
+}
+
+impl fmt::Debug for Jwk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let private = self.d.is_some();
+        f.debug_struct("Jwk")
+            .field("kty", &self.kty)
+            .field("use_", &self.use_)
+            .field("alg", &self.alg)
+            .field("crv", &self.crv)
+            .field("kid", &self.kid)
+            .field("private", &private)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Jwk {
+    /// `Debug` output including all parameters, private ones too.
+    pub fn unredacted(&self) -> Unredacted<'_, Self> {
+        Unredacted(self)
+    }
+}
+
+impl fmt::Debug for Unredacted<'_, Jwk> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let k = self.0;
+        f.debug_struct("Jwk")
+            .field("kty", &k.kty)
+            .field("use_", &k.use_)
+            .field("key_ops", &k.key_ops)
+            .field("alg", &k.alg)
+            .field("crv", &k.crv)
+            .field("kid", &k.kid)
+            .field("n", &k.n)
+            .field("e", &k.e)
+            .field("x", &k.x)
+            .field("y", &k.y)
+            .field("d", &k.d)
+            .field("p", &k.p)
+            .field("q", &k.q)
+            .field("dp", &k.dp)
+            .field("dq", &k.dq)
+            .field("qi", &k.qi)
+            .field("oth", &k.oth)
+            .field("ext", &k.ext)
+            .field("x5u", &k.x5u)
+            .field("x5c", &k.x5c)
+            .field("x5t", &k.x5t)
+            .field("x5t_s256", &k.x5t_s256)
+            .field("extra", &k.extra)
+            .finish()
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "rsa",
+    feature = "ecdsa",
+    feature = "hmac",
+    not(feature = "verify-only")
+))]
+mod tests {
+    use serde_json::{Map, Value};
+
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        hmac::{HmacAlgorithm, HmacKey},
+        rsa::{RsaAlgorithm, RsaPrivateKey},
+        sign, PrivateKeyToJwk, Result,
+    };
+
+    use super::*;
+
+    #[test]
+    fn redacted() -> Result<()> {
+        let mut token = HeaderAndClaims::new_dynamic();
+        token
+            .set_sub("alice@example.com")
+            .insert("email", "alice@example.com");
+        let debug = format!("{:?}", token);
+        assert!(debug.contains("\"sub\""), "{}", debug);
+        assert!(!debug.contains("email"), "{}", debug);
+        assert!(!debug.contains("alice"), "{}", debug);
+        assert!(format!("{:?}", token.unredacted()).contains("alice"));
+
+        let k = HmacKey::from_bytes(b"0123456789abcdef", HmacAlgorithm::HS256);
+        assert!(!format!("{:?}", k).contains("48"));
+        let jwt = sign(&mut token, &k)?;
+        let debug = format!("{:?}", RedactedToken(&jwt));
+        assert!(!debug.contains(jwt.split('.').nth(1).unwrap()), "{}", debug);
+
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let jwk = k.private_key_to_jwk()?;
+        let d = jwk.d.clone().unwrap();
+        assert!(!format!("{:?}", jwk).contains(&d));
+        assert!(format!("{:?}", jwk.unredacted()).contains(&d));
+        assert!(!format!("{:?}", k).contains(&d));
+
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let jwk = k.private_key_to_jwk()?;
+        assert!(!format!("{:?}", k).contains(&jwk.d.unwrap()));
+
+        let claims: Claims<Map<String, Value>> = Claims::default();
+        assert_eq!(format!("{:?}", claims), "Claims { names: [], .. }");
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+//! Token revocation.
+//!
+//! A [`RevocationCheck`] is consulted after the signature of a token has been
+//! verified. Install one on a [`JwkSetVerifier`](crate::jwk::JwkSetVerifier)
+//! or `RemoteJwksVerifier` to reject revoked tokens without changing the code
+//! that calls `verify`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Mutex, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Claims, Error, Result};
+
+/// Decides whether a token has been revoked, based on its `jti` and `sub`
+/// claims.
+///
+/// Implemented for closures, so a check against e.g. a database can be
+/// plugged in directly:
+///
+/// ```
+/// # use jwtk::revocation::RevocationCheck;
+/// let check = |jti: Option<&str>, _sub: Option<&str>| jti == Some("revoked");
+/// assert!(check.is_revoked(Some("revoked"), None));
+/// ```
+pub trait RevocationCheck: Send + Sync {
+    fn is_revoked(&self, jti: Option<&str>, sub: Option<&str>) -> bool;
+}
+
+impl<F> RevocationCheck for F
+where
+    F: Fn(Option<&str>, Option<&str>) -> bool + Send + Sync,
+{
+    #[inline]
+    fn is_revoked(&self, jti: Option<&str>, sub: Option<&str>) -> bool {
+        self(jti, sub)
+    }
+}
+
+/// Return `Error::Revoked` if `check` considers the token with these claims
+/// revoked.
+pub fn check_revocation<ExtraClaims>(
+    check: &dyn RevocationCheck,
+    claims: &Claims<ExtraClaims>,
+) -> Result<()> {
+    if check.is_revoked(claims.jti.as_deref(), claims.sub.as_deref()) {
+        Err(Error::Revoked)
+    } else {
+        Ok(())
+    }
+}
+
+/// In-memory set of revoked `jti`s and `sub`s.
+///
+/// Revoking a `sub` rejects all tokens issued to that subject.
+#[derive(Debug, Default)]
+pub struct RevocationList {
+    jtis: RwLock<HashSet<String>>,
+    subs: RwLock<HashSet<String>>,
+}
+
+impl RevocationList {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn revoke_jti(&self, jti: impl Into<String>) {
+        self.jtis.write().unwrap().insert(jti.into());
+    }
+
+    pub fn revoke_sub(&self, sub: impl Into<String>) {
+        self.subs.write().unwrap().insert(sub.into());
+    }
+
+    /// Returns `true` if the `jti` was revoked.
+    pub fn unrevoke_jti(&self, jti: &str) -> bool {
+        self.jtis.write().unwrap().remove(jti)
+    }
+
+    /// Returns `true` if the `sub` was revoked.
+    pub fn unrevoke_sub(&self, sub: &str) -> bool {
+        self.subs.write().unwrap().remove(sub)
+    }
+}
+
+impl RevocationCheck for RevocationList {
+    fn is_revoked(&self, jti: Option<&str>, sub: Option<&str>) -> bool {
+        jti.is_some_and(|jti| self.jtis.read().unwrap().contains(jti))
+            || sub.is_some_and(|sub| self.subs.read().unwrap().contains(sub))
+    }
+}
+
+/// Remembers `jti`s of single-use tokens until they expire.
+///
+/// ```
+/// # use jwtk::revocation::ReplayCache;
+/// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+/// let cache = ReplayCache::new();
+/// let exp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap() + Duration::from_secs(60);
+/// assert!(cache.check_and_record("jti", exp).is_ok());
+/// assert!(cache.check_and_record("jti", exp).is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct ReplayCache {
+    seen: Mutex<HashMap<String, Duration>>,
+}
+
+impl ReplayCache {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `jti` as used until `exp`, seconds since the epoch. Returns
+    /// `Error::Replayed` if it was already used.
+    pub fn check_and_record(&self, jti: &str, exp: Duration) -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, exp| *exp >= now);
+        if seen.contains_key(jti) {
+            return Err(Error::Replayed);
+        }
+        seen.insert(jti.into(), exp);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::{Map, Value};
+
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        jwk::{JwkSet, WithKid},
+        sign, HeaderAndClaims, PublicKeyToJwk,
+    };
+
+    use super::*;
+
+    #[test]
+    fn revocation_list() -> Result<()> {
+        let k = WithKid::new(
+            "k".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        let jwks = JwkSet {
+            keys: vec![k.public_key_to_jwk()?],
+        };
+        let list = Arc::new(RevocationList::new());
+        let mut verifier = jwks.verifier();
+        verifier.set_revocation_check(list.clone());
+
+        let token = sign(
+            HeaderAndClaims::new_dynamic().set_jti("1").set_sub("alice"),
+            &k,
+        )?;
+        verifier.verify::<Map<String, Value>>(&token)?;
+
+        list.revoke_jti("1");
+        assert!(matches!(
+            verifier.verify::<Map<String, Value>>(&token),
+            Err(Error::Revoked)
+        ));
+        assert!(list.unrevoke_jti("1"));
+        verifier.verify::<Map<String, Value>>(&token)?;
+
+        list.revoke_sub("alice");
+        assert!(matches!(
+            verifier.verify_only::<Map<String, Value>>(&token),
+            Err(Error::Revoked)
+        ));
+
+        Ok(())
+    }
+}
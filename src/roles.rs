@@ -0,0 +1,247 @@
+//! Permissions of groups, resolved while the claims are mapped.
+//!
+//! A [`RoleMapper`] reads the groups of a token, resolves them to
+//! permissions with a function of the application, e.g. a database lookup,
+//! and puts those in the claims before they are deserialized. It only runs
+//! for tokens that passed validation. Results are cached per `sub` and set
+//! of groups, so that the lookup doesn't run for every request.
+//!
+//! ```
+//! # use jwtk::{roles::RoleMapper, ValidationOptions};
+//! # use std::{sync::Arc, time::Duration};
+//! let mapper = RoleMapper::new(|_sub, groups| {
+//!     groups
+//!         .iter()
+//!         .flat_map(|g| match g.as_str() {
+//!             "admins" => vec!["users:read".to_string(), "users:write".to_string()],
+//!             _ => vec!["users:read".to_string()],
+//!         })
+//!         .collect()
+//! })
+//! .groups_claim("cognito:groups")
+//! .ttl(Duration::from_secs(60));
+//! let options = ValidationOptions::new().map_roles(Arc::new(mapper));
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use openssl::hash::{Hasher, MessageDigest};
+use serde_json::{Map, Value};
+
+type Resolve = Arc<dyn Fn(&str, &[String]) -> Vec<String> + Send + Sync>;
+
+/// `sub` and hash of the groups.
+type CacheKey = (String, [u8; 32]);
+
+#[derive(Default)]
+struct Cache {
+    permissions: HashMap<CacheKey, (Instant, Vec<String>)>,
+    /// The same keys, ordered by expiry, for eviction.
+    by_expiry: BTreeSet<(Instant, CacheKey)>,
+}
+
+/// Resolves the groups of tokens to permissions, see the [module
+/// docs](self).
+pub struct RoleMapper {
+    resolve: Resolve,
+    groups_claim: String,
+    permissions_claim: String,
+    ttl: Duration,
+    max_entries: usize,
+    cache: Mutex<Cache>,
+}
+
+impl RoleMapper {
+    /// `resolve` gets the `sub` of a token, empty if it has none, and its
+    /// groups, and returns their permissions.
+    pub fn new(resolve: impl Fn(&str, &[String]) -> Vec<String> + Send + Sync + 'static) -> Self {
+        Self {
+            resolve: Arc::new(resolve),
+            groups_claim: "groups".into(),
+            permissions_claim: "permissions".into(),
+            ttl: Duration::from_secs(5 * 60),
+            max_entries: 10_000,
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    /// The claim with the groups, a string or an array of strings. Defaults
+    /// to `groups`.
+    pub fn groups_claim(mut self, name: impl Into<String>) -> Self {
+        self.groups_claim = name.into();
+        self
+    }
+
+    /// The claim to put the permissions in. Defaults to `permissions`.
+    pub fn permissions_claim(mut self, name: impl Into<String>) -> Self {
+        self.permissions_claim = name.into();
+        self
+    }
+
+    /// How long resolved permissions are cached. Defaults to 5 minutes. A TTL
+    /// too long to represent as an `Instant` disables the cache.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Don't cache the permissions of more than `max_entries` users and
+    /// sets of groups. When full, the entries that expire first are
+    /// dropped. Defaults to 10000.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Put the permissions of the groups in `claims` in the permissions
+    /// claim, replacing it if present.
+    pub fn apply(&self, claims: &mut Map<String, Value>) {
+        let sub = claims.get("sub").and_then(Value::as_str).unwrap_or("");
+        let groups: Vec<String> = match claims.get(&self.groups_claim) {
+            Some(Value::String(g)) => vec![g.clone()],
+            Some(Value::Array(gs)) => gs
+                .iter()
+                .filter_map(|g| g.as_str().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let permissions = self.permissions(sub, &groups);
+        claims.insert(self.permissions_claim.clone(), permissions.into());
+    }
+
+    /// The permissions of `groups`, from the cache if they were resolved for
+    /// `sub` and the same groups less than the TTL ago.
+    pub fn permissions(&self, sub: &str, groups: &[String]) -> Vec<String> {
+        let key = (sub.to_string(), groups_hash(groups));
+        let now = Instant::now();
+        if let Some((expires, permissions)) = self.cache.lock().unwrap().permissions.get(&key) {
+            if *expires > now {
+                return permissions.clone();
+            }
+        }
+
+        // Don't hold the lock while resolving, it may be slow.
+        let permissions = (self.resolve)(sub, groups);
+        let expires = match now.checked_add(self.ttl) {
+            Some(expires) if self.max_entries > 0 => expires,
+            _ => return permissions,
+        };
+        let mut cache = self.cache.lock().unwrap();
+        if let Some((expires, _)) = cache.permissions.remove(&key) {
+            cache.by_expiry.remove(&(expires, key.clone()));
+        }
+        // Drop expired entries, then the ones that expire first, until
+        // there is room.
+        while let Some((expires, oldest)) = cache.by_expiry.iter().next().cloned() {
+            if expires > now && cache.permissions.len() < self.max_entries {
+                break;
+            }
+            cache.permissions.remove(&oldest);
+            cache.by_expiry.remove(&(expires, oldest));
+        }
+        cache.by_expiry.insert((expires, key.clone()));
+        cache
+            .permissions
+            .insert(key, (expires, permissions.clone()));
+        permissions
+    }
+
+    /// Forget all resolved permissions, e.g. after group permissions change.
+    pub fn clear(&self) {
+        *self.cache.lock().unwrap() = Cache::default();
+    }
+}
+
+impl fmt::Debug for RoleMapper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoleMapper")
+            .field("groups_claim", &self.groups_claim)
+            .field("permissions_claim", &self.permissions_claim)
+            .field("ttl", &self.ttl)
+            .field("max_entries", &self.max_entries)
+            .finish()
+    }
+}
+
+/// SHA-256 of the sorted groups, so that the order of the groups doesn't
+/// matter, and different sets can't collide.
+fn groups_hash(groups: &[String]) -> [u8; 32] {
+    let mut sorted: Vec<&str> = groups.iter().map(|g| g.as_str()).collect();
+    sorted.sort_unstable();
+    sorted.dedup();
+    let mut hasher = Hasher::new(MessageDigest::sha256()).unwrap();
+    for g in sorted {
+        hasher.update(&(g.len() as u64).to_be_bytes()).unwrap();
+        hasher.update(g.as_bytes()).unwrap();
+    }
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&hasher.finish().unwrap());
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn role_mapper() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let l = lookups.clone();
+        let mapper = RoleMapper::new(move |_, groups| {
+            l.fetch_add(1, Ordering::SeqCst);
+            groups.iter().map(|g| format!("{}:read", g)).collect()
+        })
+        .groups_claim("cognito:groups");
+
+        let mut claims = json!({ "sub": "alice", "cognito:groups": ["a", "b"] });
+        let claims = claims.as_object_mut().unwrap();
+        mapper.apply(claims);
+        assert_eq!(claims["permissions"], json!(["a:read", "b:read"]));
+        mapper.apply(claims);
+        assert_eq!(
+            mapper.permissions("alice", &["b".into(), "a".into()]),
+            ["a:read", "b:read"]
+        );
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        // Another user, other groups, or after the TTL.
+        mapper.permissions("bob", &["a".into(), "b".into()]);
+        mapper.permissions("alice", &["a".into()]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+        let mapper = mapper.ttl(Duration::ZERO);
+        mapper.permissions("alice", &["c".into()]);
+        mapper.permissions("alice", &["c".into()]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 5);
+
+        // When full, the entry that expires first is dropped.
+        let mapper = mapper.ttl(Duration::from_secs(60)).max_entries(2);
+        mapper.clear();
+        for sub in ["alice", "bob", "carol"] {
+            mapper.permissions(sub, &["a".into()]);
+        }
+        assert_eq!(mapper.cache.lock().unwrap().permissions.len(), 2);
+        mapper.permissions("carol", &["a".into()]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 8);
+        mapper.permissions("alice", &["a".into()]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 9);
+
+        let mapper = mapper.ttl(Duration::MAX);
+        mapper.permissions("dave", &["a".into()]);
+        mapper.permissions("dave", &["a".into()]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 11);
+
+        assert_ne!(
+            groups_hash(&["ab".into(), "c".into()]),
+            groups_hash(&["a".into(), "bc".into()])
+        );
+    }
+}
@@ -1,17 +1,26 @@
 /// RSASSA-PKCS1-v1_5 using SHA-256.
+#[cfg(feature = "rsa")]
 use openssl::{
     bn::BigNum,
     hash::MessageDigest,
-    pkey::{Id, PKey, Private, Public},
-    rsa::{Padding, Rsa},
-    sign::{RsaPssSaltlen, Signer, Verifier},
+    md::Md,
+    pkey::{HasPublic, Id, PKey, PKeyRef, Public},
+    pkey_ctx::{PkeyCtx, PkeyCtxRef},
+    rsa::{Padding, Rsa, RsaRef},
+    sign::{RsaPssSaltlen, Verifier},
 };
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+use openssl::{pkey::Private, sign::Signer};
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
 use smallvec::SmallVec;
+#[cfg(feature = "rsa")]
+use std::os::raw::c_int;
 
-use crate::{
-    jwk::Jwk, url_safe_trailing_bits, Error, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey,
-    VerificationKey,
-};
+#[cfg(feature = "rsa")]
+use crate::{b64, jwk::Jwk, stream::VerifyStream, Fingerprint, PublicKeyToJwk, VerificationKey};
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+use crate::{stream::SignStream, PrivateKeyToJwk, SigningKey};
+use crate::{Error, Result};
 
 /// RSA signature algorithms.
 #[non_exhaustive]
@@ -33,6 +42,7 @@ impl RsaAlgorithm {
         )
     }
 
+    #[cfg(feature = "rsa")]
     fn digest(self) -> MessageDigest {
         use RsaAlgorithm::*;
         match self {
@@ -67,18 +77,102 @@ impl RsaAlgorithm {
     }
 }
 
+/// Salt length of PSS signatures.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PssSaltLength {
+    /// The digest length, as required by RFC 7518. The default.
+    #[default]
+    DigestLength,
+    /// The maximum length the key size allows.
+    Maximum,
+    /// A fixed number of bytes.
+    Bytes(u16),
+    /// Accept any salt length on verification. Signing uses the digest
+    /// length.
+    Auto,
+}
+
+#[cfg(feature = "rsa")]
+impl PssSaltLength {
+    // OpenSSL's RSA_PSS_SALTLEN_AUTO and RSA_PSS_SALTLEN_MAX.
+    const AUTO: c_int = -2;
+    const MAX: c_int = -3;
+
+    #[cfg(not(feature = "verify-only"))]
+    fn for_signing(self) -> RsaPssSaltlen {
+        match self {
+            PssSaltLength::DigestLength | PssSaltLength::Auto => RsaPssSaltlen::DIGEST_LENGTH,
+            PssSaltLength::Maximum => RsaPssSaltlen::custom(Self::MAX),
+            PssSaltLength::Bytes(n) => RsaPssSaltlen::custom(n.into()),
+        }
+    }
+
+    fn for_verification(self) -> RsaPssSaltlen {
+        match self {
+            PssSaltLength::DigestLength => RsaPssSaltlen::DIGEST_LENGTH,
+            PssSaltLength::Maximum => RsaPssSaltlen::custom(Self::MAX),
+            PssSaltLength::Bytes(n) => RsaPssSaltlen::custom(n.into()),
+            PssSaltLength::Auto => RsaPssSaltlen::custom(Self::AUTO),
+        }
+    }
+}
+
+/// Restrictions on imported RSA keys.
+///
+/// Keys with an exponent of 1 or an even exponent are always rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RsaKeyPolicy {
+    /// Minimum modulus size in bits. Defaults to 2048.
+    pub min_bits: u32,
+    /// Maximum modulus size in bits. Defaults to no limit.
+    ///
+    /// Verification cost grows quickly with the modulus size, so set this
+    /// (e.g. to 8192) if keys come from sources you don't fully trust.
+    pub max_bits: Option<u32>,
+}
+
+impl Default for RsaKeyPolicy {
+    fn default() -> Self {
+        Self {
+            min_bits: 2048,
+            max_bits: None,
+        }
+    }
+}
+
+#[cfg(feature = "rsa")]
+impl RsaKeyPolicy {
+    pub(crate) fn check<T: HasPublic>(&self, rsa: &RsaRef<T>) -> Result<()> {
+        let bits = rsa.n().num_bits() as u32;
+        let e = rsa.e();
+        if bits < self.min_bits
+            || self.max_bits.is_some_and(|max| bits > max)
+            || e.num_bits() <= 1
+            || !e.is_bit_set(0)
+        {
+            return Err(Error::UnsupportedOrInvalidKey);
+        }
+        Ok(())
+    }
+}
+
 /// RSA Private Key.
 ///
 /// By default, it only verifies signatures generated by the same algorithm used
 /// for signing. If you want to verify signatures generated by any RSA
 /// algorithm, set `verify_any` to `true`.
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
 #[derive(Debug, Clone)]
 pub struct RsaPrivateKey {
     private_key: PKey<Private>,
     pub algorithm: RsaAlgorithm,
     pub verify_any: bool,
+    /// Salt length for PS256/PS384/PS512.
+    pub pss_salt_length: PssSaltLength,
 }
 
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
 impl RsaPrivateKey {
     /// bits >= 2048.
     pub fn generate(bits: u32, algorithm: RsaAlgorithm) -> Result<Self> {
@@ -90,17 +184,36 @@ impl RsaPrivateKey {
             private_key: PKey::from_rsa(Rsa::generate(bits)?)?,
             algorithm,
             verify_any: false,
+            pss_salt_length: PssSaltLength::default(),
         })
     }
 
     pub(crate) fn from_pkey(pkey: PKey<Private>, algorithm: RsaAlgorithm) -> Result<Self> {
-        if pkey.bits() < 2048 || !pkey.rsa()?.check_key()? {
+        let rsa = pkey.rsa()?;
+        RsaKeyPolicy::default().check(&rsa)?;
+        if !rsa.check_key()? {
+            return Err(Error::UnsupportedOrInvalidKey);
+        }
+        Ok(Self {
+            private_key: pkey,
+            algorithm,
+            verify_any: false,
+            pss_salt_length: PssSaltLength::default(),
+        })
+    }
+
+    /// A key that may be held by an OpenSSL provider, so only its size is
+    /// checked.
+    #[cfg(feature = "openssl-store")]
+    pub(crate) fn from_provider_pkey(pkey: PKey<Private>, algorithm: RsaAlgorithm) -> Result<Self> {
+        if pkey.bits() < RsaKeyPolicy::default().min_bits {
             return Err(Error::UnsupportedOrInvalidKey);
         }
         Ok(Self {
             private_key: pkey,
             algorithm,
             verify_any: false,
+            pss_salt_length: PssSaltLength::default(),
         })
     }
 
@@ -108,13 +221,12 @@ impl RsaPrivateKey {
         pkey: PKey<Private>,
         algorithm: RsaAlgorithm,
     ) -> Result<Self> {
-        if pkey.bits() < 2048 {
-            return Err(Error::UnsupportedOrInvalidKey);
-        }
+        RsaKeyPolicy::default().check(&*pkey.rsa()?)?;
         Ok(Self {
             private_key: pkey,
             algorithm,
             verify_any: false,
+            pss_salt_length: PssSaltLength::default(),
         })
     }
 
@@ -133,6 +245,16 @@ impl RsaPrivateKey {
         Ok(String::from_utf8(self.private_key.public_key_to_pem()?)?)
     }
 
+    /// PKCS#8 DER.
+    pub fn private_key_to_der_pkcs8(&self) -> Result<Vec<u8>> {
+        Ok(self.private_key.private_key_to_pkcs8()?)
+    }
+
+    /// SubjectPublicKeyInfo DER.
+    pub fn public_key_to_der(&self) -> Result<Vec<u8>> {
+        Ok(self.private_key.public_key_to_der()?)
+    }
+
     pub fn public_key_to_pem_pkcs1(&self) -> Result<String> {
         Ok(String::from_utf8(
             self.private_key.rsa()?.public_key_to_pem_pkcs1()?,
@@ -148,6 +270,7 @@ impl RsaPrivateKey {
     }
 }
 
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
 impl PrivateKeyToJwk for RsaPrivateKey {
     #[allow(clippy::many_single_char_names)]
     fn private_key_to_jwk(&self) -> Result<Jwk> {
@@ -161,7 +284,7 @@ impl PrivateKeyToJwk for RsaPrivateKey {
         let dq = rsa.dmq1().map(|dq| dq.to_vec());
         let qi = rsa.iqmp().map(|qi| qi.to_vec());
         fn encode(x: &[u8]) -> String {
-            base64::encode_config(x, url_safe_trailing_bits())
+            b64::encode_url(x)
         }
         Ok(Jwk {
             kty: "RSA".into(),
@@ -184,6 +307,7 @@ impl PrivateKeyToJwk for RsaPrivateKey {
     }
 }
 
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
 impl PublicKeyToJwk for RsaPrivateKey {
     fn public_key_to_jwk(&self) -> Result<Jwk> {
         Ok(Jwk {
@@ -194,37 +318,62 @@ impl PublicKeyToJwk for RsaPrivateKey {
                 Some(self.algorithm.name().into())
             },
             use_: Some("sig".into()),
-            n: Some(base64::encode_config(self.n()?, url_safe_trailing_bits())),
-            e: Some(base64::encode_config(self.e()?, url_safe_trailing_bits())),
+            n: Some(b64::encode_url(self.n()?)),
+            e: Some(b64::encode_url(self.e()?)),
             ..Jwk::default()
         })
     }
 }
 
 /// RSA Public Key.
-#[derive(Debug)]
+#[cfg(feature = "rsa")]
+#[derive(Debug, Clone)]
 pub struct RsaPublicKey {
     public_key: PKey<Public>,
     /// If this is `None`, this key verifies signatures generated by ANY RSA
     /// algorithms. Otherwise it ONLY verifies signatures generated by this
     /// algorithm.
     pub algorithm: Option<RsaAlgorithm>,
+    /// Salt length for PS256/PS384/PS512.
+    pub pss_salt_length: PssSaltLength,
 }
 
+#[cfg(feature = "rsa")]
 impl RsaPublicKey {
     pub(crate) fn from_pkey(pkey: PKey<Public>, algorithm: Option<RsaAlgorithm>) -> Result<Self> {
-        if pkey.id() != Id::RSA || pkey.bits() < 2048 {
+        Self::from_pkey_with_policy(pkey, algorithm, &RsaKeyPolicy::default())
+    }
+
+    fn from_pkey_with_policy(
+        pkey: PKey<Public>,
+        algorithm: Option<RsaAlgorithm>,
+        policy: &RsaKeyPolicy,
+    ) -> Result<Self> {
+        if pkey.id() != Id::RSA {
             return Err(Error::UnsupportedOrInvalidKey);
         }
+        policy.check(&*pkey.rsa()?)?;
         Ok(Self {
             public_key: pkey,
             algorithm,
+            pss_salt_length: PssSaltLength::default(),
         })
     }
 
+    /// See [`Fingerprint`].
+    pub fn fingerprint_sha256(&self) -> Result<Fingerprint> {
+        Fingerprint::of_spki_der(&self.public_key.public_key_to_der()?)
+    }
+
+    /// Whether `other` is the same key, i.e. has the same modulus and
+    /// exponent. `algorithm` and `pss_salt_length` are not compared.
+    pub fn key_eq(&self, other: &Self) -> bool {
+        self.public_key.public_eq(&other.public_key)
+    }
+
     /// Both `BEGIN PUBLIC KEY` and `BEGIN RSA PUBLIC KEY` are OK.
     pub fn from_pem(pem: &[u8], algorithm: Option<RsaAlgorithm>) -> Result<Self> {
-        if std::str::from_utf8(pem).map_or(false, |pem| pem.contains("BEGIN RSA")) {
+        if std::str::from_utf8(pem).is_ok_and(|pem| pem.contains("BEGIN RSA")) {
             let rsa = Rsa::public_key_from_pem_pkcs1(pem)?;
             Self::from_pkey(PKey::from_rsa(rsa)?, algorithm)
         } else {
@@ -234,8 +383,17 @@ impl RsaPublicKey {
     }
 
     pub fn from_components(n: &[u8], e: &[u8], algorithm: Option<RsaAlgorithm>) -> Result<Self> {
+        Self::from_components_with_policy(n, e, algorithm, &RsaKeyPolicy::default())
+    }
+
+    pub fn from_components_with_policy(
+        n: &[u8],
+        e: &[u8],
+        algorithm: Option<RsaAlgorithm>,
+        policy: &RsaKeyPolicy,
+    ) -> Result<Self> {
         let rsa = Rsa::from_public_components(BigNum::from_slice(n)?, BigNum::from_slice(e)?)?;
-        Self::from_pkey(PKey::from_rsa(rsa)?, algorithm)
+        Self::from_pkey_with_policy(PKey::from_rsa(rsa)?, algorithm, policy)
     }
 
     /// BEGIN PUBLIC KEY
@@ -259,27 +417,24 @@ impl RsaPublicKey {
     }
 }
 
+#[cfg(feature = "rsa")]
 impl PublicKeyToJwk for RsaPublicKey {
     fn public_key_to_jwk(&self) -> Result<Jwk> {
         Ok(Jwk {
             kty: "RSA".into(),
             alg: self.algorithm.map(|alg| alg.name().to_string()),
             use_: Some("sig".into()),
-            n: Some(base64::encode_config(self.n()?, url_safe_trailing_bits())),
-            e: Some(base64::encode_config(self.e()?, url_safe_trailing_bits())),
+            n: Some(b64::encode_url(self.n()?)),
+            e: Some(b64::encode_url(self.e()?)),
             ..Jwk::default()
         })
     }
 }
 
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
 impl SigningKey for RsaPrivateKey {
     fn sign(&self, v: &[u8]) -> Result<SmallVec<[u8; 64]>> {
-        let mut signer = Signer::new(self.algorithm.digest(), self.private_key.as_ref())?;
-        if self.algorithm.is_pss() {
-            signer.set_rsa_padding(Padding::PKCS1_PSS)?;
-            signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
-        }
-
+        let mut signer = self.signer()?;
         signer.update(v)?;
         Ok(signer.sign_to_vec()?.into())
     }
@@ -287,74 +442,194 @@ impl SigningKey for RsaPrivateKey {
     fn alg(&self) -> &'static str {
         self.algorithm.name()
     }
+
+    fn sign_stream(&self) -> Result<SignStream<'_>> {
+        Ok(SignStream::signer(self.signer()?))
+    }
 }
 
-impl VerificationKey for RsaPrivateKey {
-    fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
-        let alg = if self.verify_any {
-            RsaAlgorithm::from_name(alg)?
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+impl RsaPrivateKey {
+    fn signer(&self) -> Result<Signer<'_>> {
+        let mut signer = Signer::new(self.algorithm.digest(), self.private_key.as_ref())?;
+        if self.algorithm.is_pss() {
+            signer.set_rsa_padding(Padding::PKCS1_PSS)?;
+            signer.set_rsa_pss_saltlen(self.pss_salt_length.for_signing())?;
+        }
+        Ok(signer)
+    }
+
+    fn verification_algorithm(&self, alg: &str) -> Result<RsaAlgorithm> {
+        if self.verify_any {
+            RsaAlgorithm::from_name(alg)
+        } else if alg != self.algorithm.name() {
+            Err(Error::VerificationError)
         } else {
-            if alg != self.algorithm.name() {
-                return Err(Error::VerificationError);
-            }
-            self.algorithm
-        };
+            Ok(self.algorithm)
+        }
+    }
+
+    fn verifier(&self, alg: &str) -> Result<Verifier<'_>> {
+        let alg = self.verification_algorithm(alg)?;
+        rsa_verifier(alg, &self.private_key, self.pss_salt_length)
+    }
 
-        let mut verifier = Verifier::new(alg.digest(), self.private_key.as_ref())?;
-        if alg.is_pss() {
-            verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
-            verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+    /// Sign a digest of the JWS signing input computed elsewhere, e.g. the
+    /// SHA-256 digest for RS256 and PS256. The result is a valid signature
+    /// for [`verify`](VerificationKey::verify) of the signing input.
+    pub fn sign_digest(&self, digest: &[u8]) -> Result<SmallVec<[u8; 64]>> {
+        check_digest_len(self.algorithm, digest)?;
+        let mut ctx = PkeyCtx::new(&self.private_key)?;
+        ctx.sign_init()?;
+        set_digest_params(&mut ctx, self.algorithm, self.pss_salt_length.for_signing())?;
+        let mut sig = Vec::new();
+        ctx.sign_to_vec(digest, &mut sig)?;
+        Ok(sig.into())
+    }
+
+    /// Verify a signature over a digest of the JWS signing input, see
+    /// [`sign_digest`](Self::sign_digest).
+    pub fn verify_digest(&self, digest: &[u8], sig: &[u8], alg: &str) -> Result<()> {
+        let alg = self.verification_algorithm(alg)?;
+        rsa_verify_digest(alg, &self.private_key, self.pss_salt_length, digest, sig)
+    }
+}
+
+#[cfg(feature = "rsa")]
+impl RsaPublicKey {
+    fn verification_algorithm(&self, alg: &str) -> Result<RsaAlgorithm> {
+        match self.algorithm {
+            Some(self_alg) if self_alg.name() != alg => Err(Error::VerificationError),
+            Some(self_alg) => Ok(self_alg),
+            None => RsaAlgorithm::from_name(alg),
         }
-        if verifier.verify_oneshot(sig, v)? {
+    }
+
+    fn verifier(&self, alg: &str) -> Result<Verifier<'_>> {
+        let alg = self.verification_algorithm(alg)?;
+        rsa_verifier(alg, &self.public_key, self.pss_salt_length)
+    }
+
+    /// Verify a signature over a digest of the JWS signing input, see
+    /// [`RsaPrivateKey::sign_digest`].
+    pub fn verify_digest(&self, digest: &[u8], sig: &[u8], alg: &str) -> Result<()> {
+        let alg = self.verification_algorithm(alg)?;
+        rsa_verify_digest(alg, &self.public_key, self.pss_salt_length, digest, sig)
+    }
+}
+
+#[cfg(feature = "rsa")]
+fn check_digest_len(alg: RsaAlgorithm, digest: &[u8]) -> Result<()> {
+    if digest.len() == alg.digest().size() {
+        Ok(())
+    } else {
+        Err(Error::InvalidDigest)
+    }
+}
+
+/// Padding and digest algorithm of signatures over a digest.
+#[cfg(feature = "rsa")]
+fn set_digest_params<T>(
+    ctx: &mut PkeyCtxRef<T>,
+    alg: RsaAlgorithm,
+    pss_salt_length: RsaPssSaltlen,
+) -> Result<()> {
+    let md = Md::from_nid(alg.digest().type_()).ok_or(Error::UnsupportedOrInvalidKey)?;
+    ctx.set_signature_md(md)?;
+    if alg.is_pss() {
+        ctx.set_rsa_padding(Padding::PKCS1_PSS)?;
+        ctx.set_rsa_pss_saltlen(pss_salt_length)?;
+    } else {
+        ctx.set_rsa_padding(Padding::PKCS1)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "rsa")]
+fn rsa_verify_digest<T: HasPublic>(
+    alg: RsaAlgorithm,
+    key: &PKeyRef<T>,
+    pss_salt_length: PssSaltLength,
+    digest: &[u8],
+    sig: &[u8],
+) -> Result<()> {
+    check_digest_len(alg, digest)?;
+    let mut ctx = PkeyCtx::new(key)?;
+    ctx.verify_init()?;
+    set_digest_params(&mut ctx, alg, pss_salt_length.for_verification())?;
+    // OpenSSL reports some invalid signatures as errors instead of `false`.
+    if ctx.verify(digest, sig).unwrap_or(false) {
+        Ok(())
+    } else {
+        Err(Error::VerificationError)
+    }
+}
+
+#[cfg(feature = "rsa")]
+fn rsa_verifier<T: HasPublic>(
+    alg: RsaAlgorithm,
+    key: &PKeyRef<T>,
+    pss_salt_length: PssSaltLength,
+) -> Result<Verifier<'_>> {
+    let mut verifier = Verifier::new(alg.digest(), key)?;
+    if alg.is_pss() {
+        verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+        verifier.set_rsa_pss_saltlen(pss_salt_length.for_verification())?;
+    }
+    Ok(verifier)
+}
+
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+impl VerificationKey for RsaPrivateKey {
+    fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
+        if self.verifier(alg)?.verify_oneshot(sig, v)? {
             Ok(())
         } else {
             Err(Error::VerificationError)
         }
     }
+
+    fn verify_stream(&self, alg: &str) -> Result<VerifyStream<'_>> {
+        Ok(VerifyStream::verifier(self.verifier(alg)?))
+    }
 }
 
+#[cfg(feature = "rsa")]
 impl VerificationKey for RsaPublicKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> Result<()> {
-        let alg = if let Some(self_alg) = self.algorithm {
-            if self_alg.name() != alg {
-                return Err(Error::VerificationError);
-            }
-            self_alg
-        } else {
-            RsaAlgorithm::from_name(alg)?
-        };
-
-        let mut verifier = Verifier::new(alg.digest(), self.public_key.as_ref())?;
-        if alg.is_pss() {
-            verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
-            verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
-        }
-        if verifier.verify_oneshot(sig, v)? {
+        if self.verifier(alg)?.verify_oneshot(sig, v)? {
             Ok(())
         } else {
             Err(Error::VerificationError)
         }
     }
+
+    fn verify_stream(&self, alg: &str) -> Result<VerifyStream<'_>> {
+        Ok(VerifyStream::verifier(self.verifier(alg)?))
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "rsa", not(feature = "verify-only")))]
 mod tests {
-    use crate::{
-        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
-        SomePrivateKey,
-    };
+    #[cfg(feature = "ecdsa")]
+    use crate::ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey};
+    use crate::SomePrivateKey;
 
     use super::*;
 
     #[test]
+    #[allow(irrefutable_let_patterns)]
     fn conversion() -> Result<()> {
         let k = RsaPrivateKey::generate(2048, RsaAlgorithm::PS384)?;
         let pem = k.private_key_to_pem_pkcs8()?;
         RsaPrivateKey::from_pem(pem.as_bytes(), RsaAlgorithm::PS384)?;
 
-        let es256key_pem =
-            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?.private_key_to_pem_pkcs8()?;
-        assert!(RsaPrivateKey::from_pem(es256key_pem.as_bytes(), RsaAlgorithm::PS384).is_err());
+        #[cfg(feature = "ecdsa")]
+        {
+            let es256key_pem =
+                EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?.private_key_to_pem_pkcs8()?;
+            assert!(RsaPrivateKey::from_pem(es256key_pem.as_bytes(), RsaAlgorithm::PS384).is_err());
+        }
 
         let pk_pem = k.public_key_to_pem()?;
         let pk_pem_pkcs1 = k.public_key_to_pem_pkcs1()?;
@@ -405,6 +680,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn pss_salt_length() -> Result<()> {
+        let mut k = RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?;
+        let mut pk = RsaPublicKey::from_pem(k.public_key_to_pem()?.as_bytes(), None)?;
+
+        let digest_sig = k.sign(b"...")?;
+        k.pss_salt_length = PssSaltLength::Maximum;
+        let max_sig = k.sign(b"...")?;
+        k.pss_salt_length = PssSaltLength::Bytes(0);
+        let empty_sig = k.sign(b"...")?;
+
+        pk.verify(b"...", &digest_sig, "PS256")?;
+        assert!(pk.verify(b"...", &max_sig, "PS256").is_err());
+        assert!(pk.verify(b"...", &empty_sig, "PS256").is_err());
+
+        pk.pss_salt_length = PssSaltLength::Maximum;
+        pk.verify(b"...", &max_sig, "PS256")?;
+        assert!(pk.verify(b"...", &digest_sig, "PS256").is_err());
+
+        pk.pss_salt_length = PssSaltLength::Bytes(0);
+        pk.verify(b"...", &empty_sig, "PS256")?;
+
+        pk.pss_salt_length = PssSaltLength::Auto;
+        for sig in [&digest_sig, &max_sig, &empty_sig] {
+            pk.verify(b"...", sig, "PS256")?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn key_policy() -> Result<()> {
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::RS256)?;
+        let n = k.n()?;
+        RsaPublicKey::from_components(&n, &[3], None)?;
+        RsaPublicKey::from_components(&n, &k.e()?, None)?;
+        assert!(RsaPublicKey::from_components(&n, &[1], None).is_err());
+        assert!(RsaPublicKey::from_components(&n, &[1, 0, 0], None).is_err());
+
+        let policy = RsaKeyPolicy {
+            min_bits: 3072,
+            ..Default::default()
+        };
+        assert!(RsaPublicKey::from_components_with_policy(&n, &k.e()?, None, &policy).is_err());
+        let policy = RsaKeyPolicy {
+            min_bits: 1024,
+            max_bits: Some(1024),
+        };
+        assert!(RsaPublicKey::from_components_with_policy(&n, &k.e()?, None, &policy).is_err());
+
+        let weak = Rsa::generate(1024)?.public_key_to_pem()?;
+        assert!(RsaPublicKey::from_pem(&weak, None).is_err());
+        Ok(())
+    }
+
     #[test]
     fn sign_verify() -> Result<()> {
         for alg in [
@@ -426,4 +755,23 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn digest() -> Result<()> {
+        for alg in [RsaAlgorithm::RS384, RsaAlgorithm::PS256] {
+            let k = RsaPrivateKey::generate(2048, alg)?;
+            let pk = RsaPublicKey::from_pem(k.public_key_to_pem()?.as_bytes(), None)?;
+            let digest = openssl::hash::hash(alg.digest(), b"...")?;
+            let sig = k.sign_digest(&digest)?;
+            pk.verify(b"...", &sig, alg.name())?;
+            pk.verify_digest(&digest, &k.sign(b"...")?, alg.name())?;
+            k.verify_digest(&digest, &sig, alg.name())?;
+            assert!(pk.verify_digest(&digest, &sig[1..], alg.name()).is_err());
+            assert!(matches!(
+                k.sign_digest(&digest[1..]),
+                Err(Error::InvalidDigest)
+            ));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,101 @@
+//! Validating claims against a JSON Schema.
+//!
+//! ```
+//! # use jwtk::{schema::ClaimsSchema, ValidationOptions};
+//! let schema = ClaimsSchema::new(&serde_json::json!({
+//!     "type": "object",
+//!     "required": ["sub", "scope"],
+//!     "properties": {
+//!         "scope": { "type": "string" },
+//!     },
+//! }))?;
+//! let options = ValidationOptions::new().claims_schema(schema);
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use std::fmt;
+
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+use crate::{Error, Result};
+
+/// A compiled JSON Schema for claims.
+///
+/// Schemas can't reference other schemas by URL.
+pub struct ClaimsSchema {
+    schema: JSONSchema,
+}
+
+impl ClaimsSchema {
+    pub fn new(schema: &Value) -> Result<Self> {
+        let schema =
+            JSONSchema::compile(schema).map_err(|e| Error::InvalidSchema(e.to_string()))?;
+        Ok(Self { schema })
+    }
+
+    /// Validate `claims`, e.g. [`Claims`](crate::Claims) converted with
+    /// `serde_json::to_value`.
+    ///
+    /// The error names the first claim that doesn't match, and the schema
+    /// keyword it doesn't match, but not the claim's value.
+    pub fn validate(&self, claims: &Value) -> Result<()> {
+        match self.schema.validate(claims) {
+            Ok(()) => Ok(()),
+            Err(mut errors) => {
+                let e = errors.next().unwrap();
+                Err(Error::SchemaMismatch(format!(
+                    "claims at '{}' don't match '{}'",
+                    e.instance_path, e.schema_path
+                )))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ClaimsSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClaimsSchema").finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "eddsa", not(feature = "verify-only")))]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        eddsa::Ed25519PrivateKey, sign, verify_with_options, HeaderAndClaims, ValidationOptions,
+    };
+
+    use super::*;
+
+    #[test]
+    fn claims_schema() -> Result<()> {
+        let schema = ClaimsSchema::new(&json!({
+            "type": "object",
+            "required": ["sub", "scope"],
+            "properties": {
+                "scope": { "type": "string" },
+            },
+        }))?;
+        let options = ValidationOptions::new().claims_schema(schema);
+
+        let k = Ed25519PrivateKey::generate()?;
+        let mut token = HeaderAndClaims::new_dynamic();
+        token.set_sub("alice").insert("scope", "read");
+        let verified = verify_with_options::<Value>(&sign(&mut token, &k)?, &k, &options)?;
+        assert_eq!(verified.claims().extra["scope"], "read");
+
+        token.insert("scope", 42);
+        let e = verify_with_options::<Value>(&sign(&mut token, &k)?, &k, &options).unwrap_err();
+        assert!(
+            matches!(&e, Error::SchemaMismatch(m) if m.contains("/scope") && !m.contains("42"))
+        );
+
+        assert!(matches!(
+            ClaimsSchema::new(&json!({ "type": 1 })),
+            Err(Error::InvalidSchema(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+//! Access and refresh token pairs for sessions.
+//!
+//! [`SessionIssuer`] mints a short-lived access token and a long-lived
+//! refresh token that share a session id (`sid`). Refreshing rotates both:
+//! every refresh token can be used once, and reuse of one revokes the whole
+//! session, as recommended for refresh token rotation.
+//!
+//! ```
+//! # use jwtk::{ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey}, session::SessionIssuer};
+//! let issuer = SessionIssuer::new(
+//!     EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+//!     "https://auth.example.com",
+//! );
+//! let pair = issuer.issue("alice")?;
+//! let next = issuer.refresh(&pair.refresh_token)?;
+//! issuer.verify_access(&next.access_token)?;
+//!
+//! // Reusing a refresh token ends the session.
+//! assert!(issuer.refresh(&pair.refresh_token).is_err());
+//! assert!(issuer.verify_access(&next.access_token).is_err());
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use std::{collections::HashSet, fmt, sync::RwLock, time::Duration};
+
+use openssl::rand::rand_bytes;
+use serde_json::{Map, Value};
+
+use crate::{
+    b64, redact::RedactedToken, revocation::ReplayCache, sign, verify_with_options, Error,
+    HeaderAndClaims, Result, SigningKey, ValidationOptions, VerificationKey,
+};
+
+/// `typ` of access tokens, RFC 9068.
+pub const ACCESS_TOKEN_TYP: &str = "at+jwt";
+
+/// `typ` of refresh tokens.
+pub const REFRESH_TOKEN_TYP: &str = "refresh+jwt";
+
+/// A freshly minted access and refresh token.
+#[derive(Clone)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Lifetime of the access token, e.g. for `expires_in`.
+    pub expires_in: Duration,
+    pub sid: String,
+}
+
+impl fmt::Debug for TokenPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenPair")
+            .field("access_token", &RedactedToken(&self.access_token))
+            .field("refresh_token", &RedactedToken(&self.refresh_token))
+            .field("expires_in", &self.expires_in)
+            .field("sid", &self.sid)
+            .finish()
+    }
+}
+
+/// Issues and rotates access and refresh token pairs.
+///
+/// Both tokens are signed with the same key, and told apart by their `typ`.
+/// Used refresh tokens and revoked sessions are only kept in memory, so a
+/// restart forgets them.
+pub struct SessionIssuer<K> {
+    key: K,
+    issuer: String,
+    access_ttl: Duration,
+    refresh_ttl: Duration,
+    used: ReplayCache,
+    revoked: RwLock<HashSet<String>>,
+}
+
+fn random_id() -> Result<String> {
+    let mut id = [0u8; 16];
+    rand_bytes(&mut id)?;
+    Ok(b64::encode_url(id))
+}
+
+impl<K: SigningKey + VerificationKey> SessionIssuer<K> {
+    pub fn new(key: K, issuer: impl Into<String>) -> Self {
+        Self {
+            key,
+            issuer: issuer.into(),
+            access_ttl: Duration::from_secs(15 * 60),
+            refresh_ttl: Duration::from_secs(30 * 24 * 60 * 60),
+            used: ReplayCache::new(),
+            revoked: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// Access token lifetime. Defaults to 15 minutes.
+    pub fn set_access_ttl(&mut self, ttl: Duration) {
+        self.access_ttl = ttl;
+    }
+
+    /// Refresh token lifetime. Defaults to 30 days.
+    pub fn set_refresh_ttl(&mut self, ttl: Duration) {
+        self.refresh_ttl = ttl;
+    }
+
+    /// Start a session for `sub`.
+    pub fn issue(&self, sub: &str) -> Result<TokenPair> {
+        self.issue_for_session(sub, random_id()?)
+    }
+
+    fn issue_for_session(&self, sub: &str, sid: String) -> Result<TokenPair> {
+        let mint = |typ: &str, ttl: Duration| {
+            let mut token = HeaderAndClaims::new_dynamic();
+            token.header_mut().typ = Some(typ.into());
+            token
+                .set_iss(self.issuer.clone())
+                .set_sub(sub)
+                .set_jti(random_id()?)
+                .set_iat_now()
+                .set_exp_from_now(ttl)
+                .set_sid(sid.clone());
+            sign(&mut token, &self.key)
+        };
+        Ok(TokenPair {
+            access_token: mint(ACCESS_TOKEN_TYP, self.access_ttl)?,
+            refresh_token: mint(REFRESH_TOKEN_TYP, self.refresh_ttl)?,
+            expires_in: self.access_ttl,
+            sid,
+        })
+    }
+
+    fn verify_typ(&self, token: &str, typ: &str) -> Result<HeaderAndClaims<Map<String, Value>>> {
+        let options = ValidationOptions::new()
+            .require_typ(typ)
+            .require_iss(self.issuer.clone());
+        let verified = verify_with_options::<Map<String, Value>>(token, &self.key, &options)?;
+        match &verified.claims().sid {
+            Some(sid) if self.revoked.read().unwrap().contains(sid) => Err(Error::Revoked),
+            Some(_) => Ok(verified),
+            None => Err(Error::InvalidToken),
+        }
+    }
+
+    /// Verify an access token of a session that has not been revoked.
+    pub fn verify_access(&self, token: &str) -> Result<HeaderAndClaims<Map<String, Value>>> {
+        self.verify_typ(token, ACCESS_TOKEN_TYP)
+    }
+
+    /// Exchange a refresh token for a new pair in the same session.
+    ///
+    /// A refresh token that has already been used returns
+    /// `Error::Replayed` and revokes its session.
+    pub fn refresh(&self, refresh_token: &str) -> Result<TokenPair> {
+        let verified = self.verify_typ(refresh_token, REFRESH_TOKEN_TYP)?;
+        let claims = verified.claims();
+        let sid = claims.sid.as_deref().unwrap_or_default();
+        let (jti, exp, sub) = match (&claims.jti, claims.exp, &claims.sub) {
+            (Some(jti), Some(exp), Some(sub)) => (jti, exp, sub),
+            _ => return Err(Error::InvalidToken),
+        };
+        if let Err(e) = self.used.check_and_record(jti, exp) {
+            self.revoke_session(sid);
+            return Err(e);
+        }
+        self.issue_for_session(sub, sid.into())
+    }
+
+    /// Reject the access and refresh tokens of session `sid`.
+    pub fn revoke_session(&self, sid: &str) {
+        self.revoked.write().unwrap().insert(sid.into());
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa"))]
+mod tests {
+    use crate::ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey};
+
+    use super::*;
+
+    #[test]
+    fn rotation() -> Result<()> {
+        let issuer = SessionIssuer::new(
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+            "https://auth.test",
+        );
+        let pair = issuer.issue("alice")?;
+        let access = issuer.verify_access(&pair.access_token)?;
+        assert_eq!(access.claims().sub.as_deref(), Some("alice"));
+        assert_eq!(access.claims().sid.as_ref(), Some(&pair.sid));
+
+        // Access and refresh tokens can't be mixed up.
+        assert!(issuer.verify_access(&pair.refresh_token).is_err());
+        assert!(issuer.refresh(&pair.access_token).is_err());
+
+        let next = issuer.refresh(&pair.refresh_token)?;
+        assert_eq!(next.sid, pair.sid);
+        assert_ne!(next.refresh_token, pair.refresh_token);
+        issuer.verify_access(&next.access_token)?;
+
+        // Reuse revokes the session.
+        assert!(matches!(
+            issuer.refresh(&pair.refresh_token),
+            Err(Error::Replayed)
+        ));
+        assert!(matches!(
+            issuer.refresh(&next.refresh_token),
+            Err(Error::Revoked)
+        ));
+        assert!(matches!(
+            issuer.verify_access(&next.access_token),
+            Err(Error::Revoked)
+        ));
+
+        // Other sessions are unaffected.
+        let other = issuer.issue("bob")?;
+        issuer.refresh(&other.refresh_token)?;
+        Ok(())
+    }
+}
@@ -1,24 +1,44 @@
 //! Enum of HMAC / EC / RSA / Ed Keys.
 
-use openssl::pkey::{Id, PKey};
+use openssl::pkey::{Id, PKey, Public};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{
-    ecdsa::{EcdsaPrivateKey, EcdsaPublicKey},
-    eddsa::{Ed25519PrivateKey, Ed25519PublicKey},
-    jwk::Jwk,
-    rsa::{RsaAlgorithm, RsaPrivateKey, RsaPublicKey},
-    Error, PrivateKeyToJwk, PublicKeyToJwk, Result, SigningKey, VerificationKey,
-};
+#[cfg(feature = "ecdsa")]
+use crate::ecdsa::EcdsaPublicKey;
+#[cfg(feature = "eddsa")]
+use crate::eddsa::Ed25519PublicKey;
+#[cfg(feature = "rsa")]
+use crate::rsa::RsaPublicKey;
+use crate::{jwk::Jwk, Error, Fingerprint, PublicKeyToJwk, Result, VerificationKey};
+
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+use crate::ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey};
+#[cfg(all(feature = "eddsa", not(feature = "verify-only")))]
+use crate::eddsa::Ed25519PrivateKey;
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+use crate::rsa::RsaPrivateKey;
+#[cfg(not(feature = "verify-only"))]
+use crate::{b64, rsa::RsaAlgorithm, PrivateKeyToJwk, SigningKey};
+#[cfg(not(feature = "verify-only"))]
+use openssl::pkey::Private;
 
 /// An RSA, EC or Ed25519 private key.
 ///
 /// Use this if you just want to load SOME private key from an external pem
 /// file.
+///
+/// Serializes to and deserializes from the private JWK form, so it can be
+/// stored directly in configuration. An RSA JWK without `alg` deserializes to
+/// an RS256 key.
+#[cfg(not(feature = "verify-only"))]
 #[non_exhaustive]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SomePrivateKey {
+    #[cfg(feature = "eddsa")]
     Ed25519(Ed25519PrivateKey),
+    #[cfg(feature = "ecdsa")]
     Ecdsa(EcdsaPrivateKey),
+    #[cfg(feature = "rsa")]
     Rsa(RsaPrivateKey),
 }
 
@@ -26,56 +46,81 @@ pub enum SomePrivateKey {
 ///
 /// Use this if you just want to load SOME public key from an external pem file
 /// or JWK.
+///
+/// Like the individual key types, serializes to and deserializes from the
+/// JWK form.
+///
+/// Verification dispatches on the key type and checks the token's `alg`
+/// against it. EC and Ed25519 keys support exactly one algorithm each, which
+/// is determined by the curve. RSA keys without an algorithm, e.g. from PEM or
+/// [`from_jwk_any_alg`](Self::from_jwk_any_alg), verify signatures of any RSA
+/// algorithm.
 #[non_exhaustive]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SomePublicKey {
+    #[cfg(feature = "eddsa")]
     Ed25519(Ed25519PublicKey),
+    #[cfg(feature = "ecdsa")]
     Ecdsa(EcdsaPublicKey),
+    #[cfg(feature = "rsa")]
     Rsa(RsaPublicKey),
 }
 
+#[cfg(all(feature = "eddsa", not(feature = "verify-only")))]
 impl From<Ed25519PrivateKey> for SomePrivateKey {
     #[inline]
     fn from(k: Ed25519PrivateKey) -> SomePrivateKey {
+        #[cfg(feature = "eddsa")]
         SomePrivateKey::Ed25519(k)
     }
 }
 
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
 impl From<EcdsaPrivateKey> for SomePrivateKey {
     #[inline]
     fn from(k: EcdsaPrivateKey) -> SomePrivateKey {
+        #[cfg(feature = "ecdsa")]
         SomePrivateKey::Ecdsa(k)
     }
 }
 
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
 impl From<RsaPrivateKey> for SomePrivateKey {
     #[inline]
     fn from(k: RsaPrivateKey) -> SomePrivateKey {
+        #[cfg(feature = "rsa")]
         SomePrivateKey::Rsa(k)
     }
 }
 
+#[cfg(feature = "eddsa")]
 impl From<Ed25519PublicKey> for SomePublicKey {
     #[inline]
     fn from(k: Ed25519PublicKey) -> SomePublicKey {
+        #[cfg(feature = "eddsa")]
         SomePublicKey::Ed25519(k)
     }
 }
 
+#[cfg(feature = "ecdsa")]
 impl From<EcdsaPublicKey> for SomePublicKey {
     #[inline]
     fn from(k: EcdsaPublicKey) -> SomePublicKey {
+        #[cfg(feature = "ecdsa")]
         SomePublicKey::Ecdsa(k)
     }
 }
 
+#[cfg(feature = "rsa")]
 impl From<RsaPublicKey> for SomePublicKey {
     #[inline]
     fn from(k: RsaPublicKey) -> SomePublicKey {
+        #[cfg(feature = "rsa")]
         SomePublicKey::Rsa(k)
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl SomePrivateKey {
     /// Read an RSA/EC/Ed25519 private key from PEM.
     ///
@@ -85,16 +130,158 @@ impl SomePrivateKey {
     /// For an RSA private key, `if_rsa_algorithm` is used.
     pub fn from_pem(pem: &[u8], if_rsa_algorithm: RsaAlgorithm) -> Result<Self> {
         let pk = PKey::private_key_from_pem(pem)?;
+        Self::from_pkey(pk, if_rsa_algorithm)
+    }
+
+    /// Read an RSA/EC/Ed25519 private key from PKCS#8 or traditional DER.
+    ///
+    /// Algorithms are chosen as in [`from_pem`](Self::from_pem).
+    pub fn from_der(der: &[u8], if_rsa_algorithm: RsaAlgorithm) -> Result<Self> {
+        let pk = PKey::private_key_from_der(der)?;
+        Self::from_pkey(pk, if_rsa_algorithm)
+    }
+
+    /// Load a private key through OpenSSL's store API, e.g. from a
+    /// `pkcs11:` URI with the pkcs11 provider, or from a TPM with the tpm2
+    /// provider, configured in `openssl.cnf`.
+    ///
+    /// The key stays in the provider, which signs. Converting it to PEM or a
+    /// JWK only works if the provider allows exporting it. Requires the
+    /// `openssl-store` feature and OpenSSL 3.
+    #[cfg(feature = "openssl-store")]
+    #[cfg_attr(not(feature = "rsa"), allow(unused_variables))]
+    pub fn from_openssl_uri(uri: &str, if_rsa_algorithm: RsaAlgorithm) -> Result<Self> {
+        let pk = crate::store::load_private_key(uri)?;
+        match crate::store::key_type(&pk) {
+            #[cfg(feature = "rsa")]
+            Id::RSA => Ok(RsaPrivateKey::from_provider_pkey(pk, if_rsa_algorithm)?.into()),
+            #[cfg(feature = "ecdsa")]
+            Id::EC => Ok(EcdsaPrivateKey::from_provider_pkey(pk)?.into()),
+            #[cfg(feature = "eddsa")]
+            Id::ED25519 => Ok(Ed25519PrivateKey::from_provider_pkey(pk).into()),
+            _ => Err(Error::UnsupportedOrInvalidKey),
+        }
+    }
+
+    /// Same as [`Jwk::to_signing_key`].
+    #[inline]
+    pub fn from_jwk(jwk: &Jwk, if_rsa_algorithm: RsaAlgorithm) -> Result<Self> {
+        jwk.to_signing_key(if_rsa_algorithm)
+    }
 
+    /// Read a key from the environment variable `var`.
+    ///
+    /// The value may be:
+    ///
+    /// * PEM, also with literal `\n` instead of line breaks, as is common
+    ///   when the key has to fit on one line,
+    /// * base64 encoded PEM or DER, or
+    /// * a private JWK.
+    ///
+    /// RSA keys without an algorithm use `if_rsa_algorithm`. Errors are an
+    /// [`Error::EnvKey`] telling which format was detected and what went
+    /// wrong, without the key itself.
+    pub fn from_env(var: &str, if_rsa_algorithm: RsaAlgorithm) -> Result<Self> {
+        let value = std::env::var(var).map_err(|e| Error::EnvKey {
+            var: var.into(),
+            message: e.to_string(),
+        })?;
+        Self::from_text(&value, if_rsa_algorithm).map_err(|message| Error::EnvKey {
+            var: var.into(),
+            message,
+        })
+    }
+
+    /// Detect the format of `value` and load it, see `from_env`.
+    pub(crate) fn from_text(
+        value: &str,
+        if_rsa_algorithm: RsaAlgorithm,
+    ) -> std::result::Result<Self, String> {
+        let mut value = value.trim();
+        // Quotes kept by e.g. docker env files.
+        for quote in ['"', '\''] {
+            if let Some(v) = value
+                .strip_prefix(quote)
+                .and_then(|v| v.strip_suffix(quote))
+            {
+                value = v.trim();
+            }
+        }
+        if value.is_empty() {
+            return Err("empty".into());
+        }
+
+        if value.starts_with('{') {
+            let jwk: Jwk = serde_json::from_str(value)
+                .map_err(|e| format!("detected JWK JSON, but it is invalid: {}", e))?;
+            if jwk.d.is_none() {
+                return Err("detected JWK JSON, but it is a public key".into());
+            }
+            return Self::from_jwk(&jwk, if_rsa_algorithm)
+                .map_err(|e| format!("detected JWK JSON, but it is not a valid key: {}", e));
+        }
+
+        if value.contains("-----BEGIN") {
+            let pem = value.replace("\\r", "").replace("\\n", "\n");
+            if pem.contains("PUBLIC KEY-----") || pem.contains("CERTIFICATE-----") {
+                return Err("detected PEM, but it is not a private key".into());
+            }
+            return Self::from_pem(pem.as_bytes(), if_rsa_algorithm)
+                .map_err(|e| format!("detected PEM, but it is not a valid key: {}", e));
+        }
+
+        let compact: String = value.split_whitespace().collect();
+        let bytes = b64::decode(&compact)
+            .or_else(|_| b64::decode_url(&compact))
+            .map_err(|_| "not PEM, base64 or JWK JSON".to_string())?;
+        if bytes.starts_with(b"-----BEGIN") {
+            Self::from_pem(&bytes, if_rsa_algorithm).map_err(|e| {
+                format!(
+                    "detected base64 encoded PEM, but it is not a valid key: {}",
+                    e
+                )
+            })
+        } else {
+            Self::from_der(&bytes, if_rsa_algorithm)
+                .map_err(|_| "detected base64, but it is neither PEM nor a DER private key".into())
+        }
+    }
+
+    /// Generate a key for the JWS algorithm `alg`, e.g. `ES256`, `PS384` or
+    /// `EdDSA`. RSA keys are 2048 bits.
+    pub fn generate(alg: &str) -> Result<Self> {
+        Ok(match alg {
+            #[cfg(feature = "eddsa")]
+            "EdDSA" => Ed25519PrivateKey::generate()?.into(),
+            #[cfg(feature = "ecdsa")]
+            "ES256" => EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?.into(),
+            #[cfg(feature = "ecdsa")]
+            "ES256K" => EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256K)?.into(),
+            #[cfg(feature = "ecdsa")]
+            "ES384" => EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?.into(),
+            #[cfg(feature = "ecdsa")]
+            "ES512" => EcdsaPrivateKey::generate(EcdsaAlgorithm::ES512)?.into(),
+            #[cfg(feature = "rsa")]
+            alg => RsaPrivateKey::generate(2048, RsaAlgorithm::from_name(alg)?)?.into(),
+            #[cfg(not(feature = "rsa"))]
+            _ => return Err(Error::UnsupportedOrInvalidKey),
+        })
+    }
+
+    #[cfg_attr(not(feature = "rsa"), allow(unused_variables))]
+    fn from_pkey(pk: PKey<Private>, if_rsa_algorithm: RsaAlgorithm) -> Result<Self> {
         match pk.id() {
+            #[cfg(feature = "rsa")]
             Id::RSA => {
                 let k = RsaPrivateKey::from_pkey(pk, if_rsa_algorithm)?;
                 Ok(Self::Rsa(k))
             }
+            #[cfg(feature = "ecdsa")]
             Id::EC => {
                 let k = EcdsaPrivateKey::from_pkey(pk)?;
                 Ok(Self::Ecdsa(k))
             }
+            #[cfg(feature = "eddsa")]
             Id::ED25519 => {
                 let k = Ed25519PrivateKey::from_pkey(pk)?;
                 Ok(Self::Ed25519(k))
@@ -105,41 +292,158 @@ impl SomePrivateKey {
 
     pub fn private_key_to_pem_pkcs8(&self) -> Result<String> {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePrivateKey::Ed25519(ed) => ed.private_key_to_pem_pkcs8(),
+            #[cfg(feature = "ecdsa")]
             SomePrivateKey::Ecdsa(ec) => ec.private_key_to_pem_pkcs8(),
+            #[cfg(feature = "rsa")]
             SomePrivateKey::Rsa(rsa) => rsa.private_key_to_pem_pkcs8(),
         }
     }
 
     pub fn public_key_to_pem(&self) -> Result<String> {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePrivateKey::Ed25519(ed) => ed.public_key_to_pem(),
+            #[cfg(feature = "ecdsa")]
             SomePrivateKey::Ecdsa(ec) => ec.public_key_to_pem(),
+            #[cfg(feature = "rsa")]
             SomePrivateKey::Rsa(rsa) => rsa.public_key_to_pem(),
         }
     }
+
+    /// PKCS#8 DER.
+    pub fn private_key_to_der_pkcs8(&self) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "eddsa")]
+            SomePrivateKey::Ed25519(ed) => ed.private_key_to_der_pkcs8(),
+            #[cfg(feature = "ecdsa")]
+            SomePrivateKey::Ecdsa(ec) => ec.private_key_to_der_pkcs8(),
+            #[cfg(feature = "rsa")]
+            SomePrivateKey::Rsa(rsa) => rsa.private_key_to_der_pkcs8(),
+        }
+    }
+
+    /// SubjectPublicKeyInfo DER.
+    pub fn public_key_to_der(&self) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "eddsa")]
+            SomePrivateKey::Ed25519(ed) => ed.public_key_to_der(),
+            #[cfg(feature = "ecdsa")]
+            SomePrivateKey::Ecdsa(ec) => ec.public_key_to_der(),
+            #[cfg(feature = "rsa")]
+            SomePrivateKey::Rsa(rsa) => rsa.public_key_to_der(),
+        }
+    }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl PublicKeyToJwk for SomePrivateKey {
     fn public_key_to_jwk(&self) -> Result<Jwk> {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePrivateKey::Ed25519(ed) => ed.public_key_to_jwk(),
+            #[cfg(feature = "ecdsa")]
             SomePrivateKey::Ecdsa(ec) => ec.public_key_to_jwk(),
+            #[cfg(feature = "rsa")]
             SomePrivateKey::Rsa(rsa) => rsa.public_key_to_jwk(),
         }
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl PrivateKeyToJwk for SomePrivateKey {
     fn private_key_to_jwk(&self) -> Result<Jwk> {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePrivateKey::Ed25519(ed) => ed.private_key_to_jwk(),
+            #[cfg(feature = "ecdsa")]
             SomePrivateKey::Ecdsa(ec) => ec.private_key_to_jwk(),
+            #[cfg(feature = "rsa")]
             SomePrivateKey::Rsa(rsa) => rsa.private_key_to_jwk(),
         }
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
+impl Serialize for SomePrivateKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.private_key_to_jwk()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl<'de> Deserialize<'de> for SomePrivateKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let jwk = Jwk::deserialize(deserializer)?;
+        jwk.to_signing_key(RsaAlgorithm::RS256)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for SomePublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.public_key_to_jwk()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SomePublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let jwk = Jwk::deserialize(deserializer)?;
+        jwk.to_verification_key().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serialize and deserialize a key type in JWK form, via `SomePrivateKey` or
+/// `SomePublicKey`.
+macro_rules! impl_jwk_serde {
+    ($key:ident, $some:ident :: $variant:ident, $to_jwk:ident) => {
+        impl Serialize for $key {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                self.$to_jwk()
+                    .map_err(serde::ser::Error::custom)?
+                    .serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $key {
+            fn deserialize<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error> {
+                match $some::deserialize(deserializer)? {
+                    $some::$variant(k) => Ok(k),
+                    #[allow(unreachable_patterns)]
+                    _ => Err(serde::de::Error::custom(Error::UnsupportedOrInvalidKey)),
+                }
+            }
+        }
+    };
+}
+
+#[cfg(all(feature = "eddsa", not(feature = "verify-only")))]
+impl_jwk_serde!(
+    Ed25519PrivateKey,
+    SomePrivateKey::Ed25519,
+    private_key_to_jwk
+);
+#[cfg(all(feature = "ecdsa", not(feature = "verify-only")))]
+impl_jwk_serde!(EcdsaPrivateKey, SomePrivateKey::Ecdsa, private_key_to_jwk);
+#[cfg(all(feature = "rsa", not(feature = "verify-only")))]
+impl_jwk_serde!(RsaPrivateKey, SomePrivateKey::Rsa, private_key_to_jwk);
+#[cfg(feature = "eddsa")]
+impl_jwk_serde!(Ed25519PublicKey, SomePublicKey::Ed25519, public_key_to_jwk);
+#[cfg(feature = "ecdsa")]
+impl_jwk_serde!(EcdsaPublicKey, SomePublicKey::Ecdsa, public_key_to_jwk);
+#[cfg(feature = "rsa")]
+impl_jwk_serde!(RsaPublicKey, SomePublicKey::Rsa, public_key_to_jwk);
+
 impl SomePublicKey {
     /// Read an RSA/EC/Ed25519 public key from PEM.
     ///
@@ -150,15 +454,22 @@ impl SomePublicKey {
     /// verified.
     pub fn from_pem(pem: &[u8]) -> Result<Self> {
         let pk = PKey::public_key_from_pem(pem)?;
+        Self::from_pkey(pk)
+    }
+
+    pub(crate) fn from_pkey(pk: PKey<Public>) -> Result<Self> {
         match pk.id() {
+            #[cfg(feature = "rsa")]
             Id::RSA => {
                 let k = RsaPublicKey::from_pkey(pk, None)?;
                 Ok(Self::Rsa(k))
             }
+            #[cfg(feature = "ecdsa")]
             Id::EC => {
                 let k = EcdsaPublicKey::from_pkey(pk)?;
                 Ok(Self::Ecdsa(k))
             }
+            #[cfg(feature = "eddsa")]
             Id::ED25519 => {
                 let k = Ed25519PublicKey::from_pkey(pk)?;
                 Ok(Self::Ed25519(k))
@@ -167,59 +478,357 @@ impl SomePublicKey {
         }
     }
 
+    /// Same as [`Jwk::to_verification_key`]. RSA keys are restricted to the
+    /// JWK's `alg`, if present.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self> {
+        jwk.to_verification_key()
+    }
+
+    /// Like [`from_jwk`](Self::from_jwk), but ignores the JWK's `alg`, so an
+    /// RSA key verifies signatures of any RSA algorithm.
+    pub fn from_jwk_any_alg(jwk: &Jwk) -> Result<Self> {
+        if jwk.alg.is_none() {
+            return jwk.to_verification_key();
+        }
+        let mut jwk = jwk.clone();
+        jwk.alg = None;
+        jwk.to_verification_key()
+    }
+
+    /// See [`Fingerprint`].
+    pub fn fingerprint_sha256(&self) -> Result<Fingerprint> {
+        match self {
+            #[cfg(feature = "eddsa")]
+            SomePublicKey::Ed25519(ed) => ed.fingerprint_sha256(),
+            #[cfg(feature = "ecdsa")]
+            SomePublicKey::Ecdsa(ec) => ec.fingerprint_sha256(),
+            #[cfg(feature = "rsa")]
+            SomePublicKey::Rsa(rsa) => rsa.fingerprint_sha256(),
+        }
+    }
+
+    /// Whether `other` is the same key, regardless of e.g. the algorithms
+    /// an RSA key is restricted to.
+    ///
+    /// Useful to tell whether a JWK Set entry is a known key under a new
+    /// `kid`.
+    pub fn key_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            #[cfg(feature = "eddsa")]
+            (SomePublicKey::Ed25519(a), SomePublicKey::Ed25519(b)) => a.key_eq(b),
+            #[cfg(feature = "ecdsa")]
+            (SomePublicKey::Ecdsa(a), SomePublicKey::Ecdsa(b)) => a.key_eq(b),
+            #[cfg(feature = "rsa")]
+            (SomePublicKey::Rsa(a), SomePublicKey::Rsa(b)) => a.key_eq(b),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
     pub fn to_pem(&self) -> Result<String> {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePublicKey::Ed25519(ed) => ed.to_pem(),
+            #[cfg(feature = "ecdsa")]
             SomePublicKey::Ecdsa(ec) => ec.to_pem(),
+            #[cfg(feature = "rsa")]
             SomePublicKey::Rsa(rsa) => rsa.to_pem(),
         }
     }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl SigningKey for SomePrivateKey {
     fn alg(&self) -> &'static str {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePrivateKey::Ed25519(ed) => ed.alg(),
+            #[cfg(feature = "ecdsa")]
             SomePrivateKey::Ecdsa(ec) => ec.alg(),
+            #[cfg(feature = "rsa")]
             SomePrivateKey::Rsa(rsa) => rsa.alg(),
         }
     }
 
     fn sign(&self, v: &[u8]) -> crate::Result<smallvec::SmallVec<[u8; 64]>> {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePrivateKey::Ed25519(ed) => ed.sign(v),
+            #[cfg(feature = "ecdsa")]
             SomePrivateKey::Ecdsa(ec) => ec.sign(v),
+            #[cfg(feature = "rsa")]
             SomePrivateKey::Rsa(rsa) => rsa.sign(v),
         }
     }
+
+    fn sign_stream(&self) -> crate::Result<crate::stream::SignStream<'_>> {
+        match self {
+            #[cfg(feature = "eddsa")]
+            SomePrivateKey::Ed25519(ed) => ed.sign_stream(),
+            #[cfg(feature = "ecdsa")]
+            SomePrivateKey::Ecdsa(ec) => ec.sign_stream(),
+            #[cfg(feature = "rsa")]
+            SomePrivateKey::Rsa(rsa) => rsa.sign_stream(),
+        }
+    }
 }
 
+#[cfg(not(feature = "verify-only"))]
 impl VerificationKey for SomePrivateKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> crate::Result<()> {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePrivateKey::Ed25519(ed) => ed.verify(v, sig, alg),
+            #[cfg(feature = "ecdsa")]
             SomePrivateKey::Ecdsa(ec) => ec.verify(v, sig, alg),
+            #[cfg(feature = "rsa")]
             SomePrivateKey::Rsa(rsa) => rsa.verify(v, sig, alg),
         }
     }
+
+    fn verify_stream(&self, alg: &str) -> crate::Result<crate::stream::VerifyStream<'_>> {
+        match self {
+            #[cfg(feature = "eddsa")]
+            SomePrivateKey::Ed25519(ed) => ed.verify_stream(alg),
+            #[cfg(feature = "ecdsa")]
+            SomePrivateKey::Ecdsa(ec) => ec.verify_stream(alg),
+            #[cfg(feature = "rsa")]
+            SomePrivateKey::Rsa(rsa) => rsa.verify_stream(alg),
+        }
+    }
 }
 
 impl VerificationKey for SomePublicKey {
     fn verify(&self, v: &[u8], sig: &[u8], alg: &str) -> crate::Result<()> {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePublicKey::Ed25519(ed) => ed.verify(v, sig, alg),
+            #[cfg(feature = "ecdsa")]
             SomePublicKey::Ecdsa(ec) => ec.verify(v, sig, alg),
+            #[cfg(feature = "rsa")]
             SomePublicKey::Rsa(rsa) => rsa.verify(v, sig, alg),
         }
     }
+
+    fn verify_stream(&self, alg: &str) -> crate::Result<crate::stream::VerifyStream<'_>> {
+        match self {
+            #[cfg(feature = "eddsa")]
+            SomePublicKey::Ed25519(ed) => ed.verify_stream(alg),
+            #[cfg(feature = "ecdsa")]
+            SomePublicKey::Ecdsa(ec) => ec.verify_stream(alg),
+            #[cfg(feature = "rsa")]
+            SomePublicKey::Rsa(rsa) => rsa.verify_stream(alg),
+        }
+    }
 }
 
 impl PublicKeyToJwk for SomePublicKey {
     fn public_key_to_jwk(&self) -> Result<Jwk> {
         match self {
+            #[cfg(feature = "eddsa")]
             SomePublicKey::Ed25519(ed) => ed.public_key_to_jwk(),
+            #[cfg(feature = "ecdsa")]
             SomePublicKey::Ecdsa(ec) => ec.public_key_to_jwk(),
+            #[cfg(feature = "rsa")]
             SomePublicKey::Rsa(rsa) => rsa.public_key_to_jwk(),
         }
     }
 }
+
+#[cfg(all(
+    test,
+    feature = "rsa",
+    feature = "ecdsa",
+    feature = "eddsa",
+    not(feature = "verify-only")
+))]
+mod tests {
+    use crate::{
+        ecdsa::EcdsaAlgorithm, eddsa::Ed25519PrivateKey, jwk::WithKid, sign, verify,
+        HeaderAndClaims,
+    };
+
+    use super::*;
+
+    #[test]
+    fn private_key_constructors() -> Result<()> {
+        for alg in ["EdDSA", "ES256", "ES256K", "ES384", "ES512", "PS256"] {
+            let k = SomePrivateKey::generate(alg)?;
+            assert_eq!(k.alg(), alg);
+            let pk = SomePublicKey::from_pem(k.public_key_to_pem()?.as_bytes())?;
+
+            let der =
+                SomePrivateKey::from_der(&k.private_key_to_der_pkcs8()?, RsaAlgorithm::PS256)?;
+            let jwk = SomePrivateKey::from_jwk(&k.private_key_to_jwk()?, RsaAlgorithm::PS256)?;
+            let json = serde_json::to_string(&k)?;
+            let de: SomePrivateKey = serde_json::from_str(&json)?;
+            assert_eq!(serde_json::to_string(&de)?, json);
+
+            for k in [der, jwk, de] {
+                let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+                verify::<()>(&token, &pk)?;
+            }
+        }
+        assert!(SomePrivateKey::generate("HS256").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn from_jwk_any_alg() -> Result<()> {
+        let k = RsaPrivateKey::generate(2048, RsaAlgorithm::PS384)?;
+        let mut jwk = k.public_key_to_jwk()?;
+        jwk.alg = Some("RS256".into());
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+
+        assert!(verify::<()>(&token, &SomePublicKey::from_jwk(&jwk)?).is_err());
+        verify::<()>(&token, &SomePublicKey::from_jwk_any_alg(&jwk)?)?;
+
+        let keys: Vec<SomePrivateKey> = vec![
+            Ed25519PrivateKey::generate()?.into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES384)?.into(),
+            k.into(),
+        ];
+        for k in keys {
+            let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+            let pk = SomePublicKey::from_jwk_any_alg(&k.public_key_to_jwk()?)?;
+            verify::<()>(&token, &pk)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn key_eq() -> Result<()> {
+        let keys = [
+            SomePrivateKey::generate("EdDSA")?,
+            SomePrivateKey::generate("ES256")?,
+            SomePrivateKey::generate("ES256")?,
+            SomePrivateKey::generate("RS256")?,
+        ];
+        for (i, a) in keys.iter().enumerate() {
+            let mut jwk = a.public_key_to_jwk()?;
+            let pa = SomePublicKey::from_jwk(&jwk)?;
+            jwk.kid = Some("renamed".into());
+            jwk.alg = None;
+            assert!(pa.key_eq(&SomePublicKey::from_jwk(&jwk)?));
+            for b in &keys[i + 1..] {
+                assert!(!pa.key_eq(&SomePublicKey::from_jwk(&b.public_key_to_jwk()?)?));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn clone_across_threads() -> Result<()> {
+        let k = WithKid::new("k".into(), SomePrivateKey::generate("ES256")?);
+        let pk = SomePublicKey::from_jwk(&k.public_key_to_jwk()?)?;
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let (k, pk) = (k.clone(), pk.clone());
+                std::thread::spawn(move || -> Result<()> {
+                    let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+                    verify::<()>(&token, &pk)?;
+                    Ok(())
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap()?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "openssl-store")]
+    #[test]
+    fn from_openssl_uri() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("jwtk-store-{}.pem", std::process::id()));
+        for alg in ["EdDSA", "ES256K", "ES384", "RS256"] {
+            let k = SomePrivateKey::generate(alg)?;
+            std::fs::write(&path, k.private_key_to_pem_pkcs8()?)?;
+            let loaded = SomePrivateKey::from_openssl_uri(
+                &format!("file:{}", path.display()),
+                RsaAlgorithm::RS256,
+            )?;
+            assert_eq!(loaded.alg(), alg);
+            let token = sign(&mut HeaderAndClaims::new_dynamic(), &loaded)?;
+            verify::<serde_json::Value>(&token, &k)?;
+        }
+        // Nothing loadable.
+        std::fs::write(&path, "not a key")?;
+        assert!(SomePrivateKey::from_openssl_uri(
+            &format!("file:{}", path.display()),
+            RsaAlgorithm::RS256
+        )
+        .is_err());
+        std::fs::remove_file(&path)?;
+        assert!(
+            SomePrivateKey::from_openssl_uri("file:/nonexistent", RsaAlgorithm::RS256).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn from_env() -> Result<()> {
+        let k = SomePrivateKey::generate("ES256")?;
+        let pem = k.private_key_to_pem_pkcs8()?;
+        let jwk = serde_json::to_string(&k.private_key_to_jwk()?)?;
+        for value in [
+            pem.replace('\n', "\\n"),
+            format!("\"{}\"", pem),
+            b64::encode(&pem),
+            b64::encode(k.private_key_to_der_pkcs8()?),
+            jwk,
+        ] {
+            let k1 = SomePrivateKey::from_text(&value, RsaAlgorithm::RS256).unwrap();
+            assert_eq!(k1.public_key_to_pem()?, k.public_key_to_pem()?);
+        }
+
+        std::env::set_var("JWTK_TEST_FROM_ENV", k.public_key_to_pem()?);
+        let e = SomePrivateKey::from_env("JWTK_TEST_FROM_ENV", RsaAlgorithm::RS256).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "key in $JWTK_TEST_FROM_ENV: detected PEM, but it is not a private key"
+        );
+        std::env::set_var("JWTK_TEST_FROM_ENV", "{\"kty\":\"EC\"}");
+        let e = SomePrivateKey::from_env("JWTK_TEST_FROM_ENV", RsaAlgorithm::RS256).unwrap_err();
+        assert!(e.to_string().contains("detected JWK JSON"), "{}", e);
+        std::env::remove_var("JWTK_TEST_FROM_ENV");
+        assert!(SomePrivateKey::from_env("JWTK_TEST_FROM_ENV", RsaAlgorithm::RS256).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn keys_in_config() -> Result<()> {
+        #[derive(Serialize, Deserialize)]
+        struct Config {
+            signing: EcdsaPrivateKey,
+            trusted: Vec<SomePublicKey>,
+            legacy: RsaPublicKey,
+        }
+
+        let signing = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let rsa = RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?;
+        let json = serde_json::json!({
+            "signing": signing.private_key_to_jwk()?,
+            "trusted": [signing.public_key_to_jwk()?, rsa.public_key_to_jwk()?],
+            "legacy": rsa.public_key_to_jwk()?,
+        });
+        let config: Config = serde_json::from_value(json)?;
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &config.signing)?;
+        verify::<()>(&token, &config.trusted[0])?;
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &rsa)?;
+        verify::<()>(&token, &config.trusted[1])?;
+        verify::<()>(&token, &config.legacy)?;
+
+        let config: Config = serde_json::from_str(&serde_json::to_string(&config)?)?;
+        verify::<()>(&token, &config.legacy)?;
+
+        // Wrong key type.
+        let json = serde_json::json!({
+            "signing": rsa.private_key_to_jwk()?,
+            "trusted": [],
+            "legacy": rsa.public_key_to_jwk()?,
+        });
+        assert!(serde_json::from_value::<Config>(json).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+//! SPIFFE JWT-SVIDs.
+//!
+//! A JWT-SVID is a token whose `sub` is the SPIFFE ID of a workload. It is
+//! verified with the JWT authorities in the SPIFFE bundle of the ID's trust
+//! domain, must be for the verifying audience and must expire.
+//!
+//! ```
+//! # use jwtk::{ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey}, jwk::{JwkSet, WithKid}, PublicKeyToJwk};
+//! use jwtk::spiffe::{self, JwtSvidVerifier, SpiffeId};
+//! use std::time::Duration;
+//!
+//! # let key = WithKid::new_with_thumbprint_id(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?)?;
+//! # let mut jwk = key.public_key_to_jwk()?;
+//! # jwk.use_ = Some("jwt-svid".into());
+//! # let bundle = JwkSet { keys: vec![jwk] };
+//! let id: SpiffeId = "spiffe://example.org/ns/default/sa/web".parse()?;
+//! let svid = spiffe::mint(&key, &id, &["db"], Duration::from_secs(300))?;
+//!
+//! let mut verifier = JwtSvidVerifier::new();
+//! verifier.add_bundle("example.org", &bundle);
+//! let verified = verifier.verify::<serde_json::Value>(&svid, "db")?;
+//! assert_eq!(verified.id, id);
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{
+    decode_without_verify,
+    jwk::{JwkSet, JwkSetVerifier},
+    Error, HeaderAndClaims, Result, ValidationOptions,
+};
+#[cfg(not(feature = "verify-only"))]
+use crate::{sign, SigningKey};
+
+/// Algorithms allowed for JWT-SVIDs.
+const ALGORITHMS: &[&str] = &[
+    "RS256", "RS384", "RS512", "ES256", "ES384", "ES512", "PS256", "PS384", "PS512",
+];
+
+/// A SPIFFE ID, e.g. `spiffe://example.org/ns/default/sa/web`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpiffeId {
+    trust_domain: String,
+    path: String,
+}
+
+impl SpiffeId {
+    /// `path` is empty or starts with `/`.
+    pub fn new(trust_domain: &str, path: &str) -> Result<Self> {
+        format!("spiffe://{}{}", trust_domain, path).parse()
+    }
+
+    pub fn trust_domain(&self) -> &str {
+        &self.trust_domain
+    }
+
+    /// Empty, or starting with `/`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl FromStr for SpiffeId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rest = s.strip_prefix("spiffe://").ok_or(Error::InvalidToken)?;
+        let (trust_domain, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+        let valid_trust_domain = !trust_domain.is_empty()
+            && trust_domain.bytes().all(|b| {
+                b.is_ascii_lowercase() || b.is_ascii_digit() || matches!(b, b'.' | b'-' | b'_')
+            });
+        let valid_path = path.is_empty()
+            || path[1..].split('/').all(|segment| {
+                !matches!(segment, "" | "." | "..")
+                    && segment
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_'))
+            });
+        if !(valid_trust_domain && valid_path) {
+            return Err(Error::InvalidToken);
+        }
+        Ok(Self {
+            trust_domain: trust_domain.into(),
+            path: path.into(),
+        })
+    }
+}
+
+impl fmt::Display for SpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "spiffe://{}{}", self.trust_domain, self.path)
+    }
+}
+
+/// A verified JWT-SVID.
+pub struct JwtSvid<ExtraClaims> {
+    /// The SPIFFE ID in `sub`.
+    pub id: SpiffeId,
+    pub token: HeaderAndClaims<ExtraClaims>,
+}
+
+impl<ExtraClaims: fmt::Debug> fmt::Debug for JwtSvid<ExtraClaims> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtSvid")
+            .field("id", &self.id)
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+/// Verifies JWT-SVIDs against the bundles of one or more trust domains.
+pub struct JwtSvidVerifier {
+    bundles: HashMap<String, JwkSetVerifier>,
+    max_lifetime: Option<Duration>,
+}
+
+impl Default for JwtSvidVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JwtSvidVerifier {
+    pub fn new() -> Self {
+        Self {
+            bundles: HashMap::new(),
+            max_lifetime: Some(Duration::from_secs(60 * 60)),
+        }
+    }
+
+    /// Trust the JWT authorities in `bundle` for `trust_domain`, replacing
+    /// any previous bundle of the trust domain.
+    ///
+    /// Keys with `use` `jwt-svid`, or without `use`, are JWT authorities.
+    /// Others, e.g. X.509 authorities, are ignored.
+    pub fn add_bundle(&mut self, trust_domain: &str, bundle: &JwkSet) {
+        let keys = bundle
+            .keys
+            .iter()
+            .filter(|k| matches!(k.use_.as_deref(), None | Some("jwt-svid")))
+            .map(|k| {
+                let mut k = k.clone();
+                k.use_ = None;
+                k
+            })
+            .collect();
+        self.bundles
+            .insert(trust_domain.into(), JwkSet { keys }.verifier());
+    }
+
+    /// Reject tokens valid for longer than `lifetime`, measured from `iat` if
+    /// present, otherwise from now. Defaults to one hour. `None` disables the
+    /// check.
+    pub fn set_max_lifetime(&mut self, lifetime: Option<Duration>) {
+        self.max_lifetime = lifetime;
+    }
+
+    /// Verify a JWT-SVID for `audience`.
+    pub fn verify<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+        audience: &str,
+    ) -> Result<JwtSvid<ExtraClaims>> {
+        let unverified = decode_without_verify::<Map<String, Value>>(token)?;
+        let id: SpiffeId = unverified
+            .claims()
+            .sub
+            .as_deref()
+            .ok_or(Error::InvalidToken)?
+            .parse()?;
+        let bundle = self.bundles.get(id.trust_domain()).ok_or(Error::NoKey)?;
+
+        if !ALGORITHMS.contains(&&*unverified.header().alg) {
+            return Err(Error::AlgMismatch);
+        }
+        if !matches!(
+            unverified.header().typ.as_deref(),
+            None | Some("JWT" | "JOSE")
+        ) {
+            return Err(Error::TypMismatch);
+        }
+
+        let token = bundle.verify_with_options::<ExtraClaims>(
+            token,
+            &ValidationOptions::new().require_aud(audience),
+        )?;
+        let exp = token.claims().exp.ok_or(Error::InvalidToken)?;
+        if let Some(max_lifetime) = self.max_lifetime {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            let start = token.claims().iat.unwrap_or(now);
+            if exp.saturating_sub(start) > max_lifetime {
+                return Err(Error::InvalidToken);
+            }
+        }
+        Ok(JwtSvid { id, token })
+    }
+}
+
+/// Mint a JWT-SVID for `id` and `audiences`, valid for `ttl`.
+#[cfg(not(feature = "verify-only"))]
+pub fn mint(
+    k: &dyn SigningKey,
+    id: &SpiffeId,
+    audiences: &[&str],
+    ttl: Duration,
+) -> Result<String> {
+    let mut token = HeaderAndClaims::new_dynamic();
+    token.header_mut().typ = Some("JWT".into());
+    token
+        .set_sub(id.to_string())
+        .set_auds(audiences.iter().map(|a| a.to_string()).collect())
+        .set_iat_now()
+        .set_exp_from_now(ttl);
+    sign(&mut token, k)
+}
+
+#[cfg(all(
+    test,
+    feature = "ecdsa",
+    feature = "eddsa",
+    not(feature = "verify-only")
+))]
+mod tests {
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        eddsa::Ed25519PrivateKey,
+        jwk::WithKid,
+        PublicKeyToJwk,
+    };
+
+    use super::*;
+
+    #[test]
+    fn spiffe_id() {
+        for id in [
+            "spiffe://example.org",
+            "spiffe://example.org/ns/default/sa/web",
+            "spiffe://a-b_c.d/x.y-z_0",
+        ] {
+            assert_eq!(id.parse::<SpiffeId>().unwrap().to_string(), id);
+        }
+        for id in [
+            "https://example.org/web",
+            "spiffe://",
+            "spiffe://Example.org/web",
+            "spiffe://example.org:8080/web",
+            "spiffe://user@example.org/web",
+            "spiffe://example.org/",
+            "spiffe://example.org/web/",
+            "spiffe://example.org//web",
+            "spiffe://example.org/a/../b",
+            "spiffe://example.org/web?x=1",
+        ] {
+            assert!(id.parse::<SpiffeId>().is_err(), "{}", id);
+        }
+        let id = SpiffeId::new("example.org", "/web").unwrap();
+        assert_eq!(id.trust_domain(), "example.org");
+        assert_eq!(id.path(), "/web");
+    }
+
+    #[test]
+    fn jwt_svid() -> Result<()> {
+        let k = WithKid::new_with_thumbprint_id(EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?)?;
+        let mut jwk = k.public_key_to_jwk()?;
+        jwk.use_ = Some("jwt-svid".into());
+        let bundle = JwkSet { keys: vec![jwk] };
+        let mut verifier = JwtSvidVerifier::new();
+        verifier.add_bundle("example.org", &bundle);
+
+        let id = SpiffeId::new("example.org", "/web")?;
+        let svid = mint(&k, &id, &["db", "cache"], Duration::from_secs(300))?;
+        let verified = verifier.verify::<Value>(&svid, "cache")?;
+        assert_eq!(verified.id, id);
+        assert!(matches!(
+            verifier.verify::<Value>(&svid, "other"),
+            Err(Error::AudMismatch)
+        ));
+
+        // Other trust domain.
+        let other = SpiffeId::new("other.org", "/web")?;
+        let svid = mint(&k, &other, &["db"], Duration::from_secs(300))?;
+        assert!(matches!(
+            verifier.verify::<Value>(&svid, "db"),
+            Err(Error::NoKey)
+        ));
+
+        // Too long lived.
+        let svid = mint(&k, &id, &["db"], Duration::from_secs(2 * 60 * 60))?;
+        assert!(verifier.verify::<Value>(&svid, "db").is_err());
+        verifier.set_max_lifetime(None);
+        verifier.verify::<Value>(&svid, "db")?;
+
+        // No exp.
+        let mut token = HeaderAndClaims::new_dynamic();
+        token.set_sub(id.to_string()).add_aud("db");
+        let svid = sign(&mut token, &k)?;
+        assert!(verifier.verify::<Value>(&svid, "db").is_err());
+
+        // EdDSA is not allowed.
+        let ed = WithKid::new_with_thumbprint_id(Ed25519PrivateKey::generate()?)?;
+        let mut jwk = ed.public_key_to_jwk()?;
+        jwk.use_ = None;
+        verifier.add_bundle("example.org", &JwkSet { keys: vec![jwk] });
+        let svid = mint(&ed, &id, &["db"], Duration::from_secs(300))?;
+        assert!(matches!(
+            verifier.verify::<Value>(&svid, "db"),
+            Err(Error::AlgMismatch)
+        ));
+        Ok(())
+    }
+}
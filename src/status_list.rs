@@ -0,0 +1,351 @@
+//! Token Status List (draft-ietf-oauth-status-list).
+//!
+//! An issuer keeps the status of its tokens in a compressed bit array, which
+//! is published as a signed status list token. Each referenced token carries
+//! a `status.status_list` claim pointing into that list.
+
+use std::io::{Read, Write};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    b64, verify_with_options, Error, HeaderAndClaims, Result, ValidationOptions, VerificationKey,
+};
+#[cfg(not(feature = "verify-only"))]
+use crate::{sign, SigningKey};
+
+/// The token is valid.
+pub const STATUS_VALID: u8 = 0x00;
+/// The token has been revoked.
+pub const STATUS_INVALID: u8 = 0x01;
+/// The token is temporarily suspended.
+pub const STATUS_SUSPENDED: u8 = 0x02;
+
+/// `typ` header of status list tokens.
+pub const STATUS_LIST_TYP: &str = "statuslist+jwt";
+
+/// Maximum size of a decompressed status list, 16 MiB, i.e. 128Mi statuses of
+/// one bit.
+const MAX_LIST_BYTES: u64 = 16 * 1024 * 1024;
+
+/// The `status` claim of a referenced token.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Status {
+    pub status_list: StatusListReference,
+}
+
+/// Index of a referenced token in the status list served at `uri`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StatusListReference {
+    pub idx: usize,
+    pub uri: String,
+}
+
+/// The `status_list` claim of a status list token.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StatusListClaim {
+    pub bits: u8,
+    /// Base64url encoded, zlib compressed status bytes.
+    pub lst: String,
+}
+
+/// Extra claims of a status list token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusListTokenClaims {
+    pub status_list: StatusListClaim,
+    /// Recommended maximum time in seconds to cache the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+}
+
+/// A decompressed status list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusList {
+    bits: u8,
+    lst: Vec<u8>,
+}
+
+impl StatusList {
+    /// Create a list that holds `len` statuses of `bits` bits each, all set to
+    /// `STATUS_VALID`.
+    ///
+    /// `bits` must be 1, 2, 4 or 8.
+    pub fn new(bits: u8, len: usize) -> Result<Self> {
+        if !matches!(bits, 1 | 2 | 4 | 8) {
+            return Err(Error::InvalidToken);
+        }
+        // Round up to whole bytes.
+        let bytes = len
+            .checked_mul(bits as usize)
+            .and_then(|n| n.checked_add(7))
+            .ok_or(Error::InvalidToken)?
+            >> 3;
+        Ok(Self {
+            bits,
+            lst: vec![0; bytes],
+        })
+    }
+
+    #[inline]
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// Number of statuses the list can hold.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lst.len() * 8 / self.bits as usize
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lst.is_empty()
+    }
+
+    /// Status at `idx`, or `None` if out of range.
+    pub fn get(&self, idx: usize) -> Option<u8> {
+        if idx >= self.len() {
+            return None;
+        }
+        let (byte, shift) = self.position(idx);
+        Some((self.lst[byte] >> shift) & self.mask())
+    }
+
+    pub fn set(&mut self, idx: usize, status: u8) -> Result<()> {
+        if idx >= self.len() || status > self.mask() {
+            return Err(Error::InvalidToken);
+        }
+        let (byte, shift) = self.position(idx);
+        self.lst[byte] &= !(self.mask() << shift);
+        self.lst[byte] |= status << shift;
+        Ok(())
+    }
+
+    // Statuses are packed starting from the least significant bit.
+    fn position(&self, idx: usize) -> (usize, usize) {
+        let bit = idx * self.bits as usize;
+        (bit / 8, bit % 8)
+    }
+
+    fn mask(&self) -> u8 {
+        ((1u16 << self.bits) - 1) as u8
+    }
+
+    pub fn to_claim(&self) -> Result<StatusListClaim> {
+        let mut w = ZlibEncoder::new(Vec::new(), Compression::best());
+        w.write_all(&self.lst)?;
+        Ok(StatusListClaim {
+            bits: self.bits,
+            lst: b64::encode_url(w.finish()?),
+        })
+    }
+
+    pub fn from_claim(claim: &StatusListClaim) -> Result<Self> {
+        if !matches!(claim.bits, 1 | 2 | 4 | 8) {
+            return Err(Error::InvalidToken);
+        }
+        let compressed = b64::decode_url(&claim.lst)?;
+        let mut lst = Vec::new();
+        ZlibDecoder::new(&compressed[..])
+            .take(MAX_LIST_BYTES + 1)
+            .read_to_end(&mut lst)?;
+        if lst.len() as u64 > MAX_LIST_BYTES {
+            return Err(Error::InvalidToken);
+        }
+        Ok(Self {
+            bits: claim.bits,
+            lst,
+        })
+    }
+}
+
+/// Sign a status list token for the list served at `uri`.
+///
+/// `typ`, `sub` and `iat` are set automatically. Use `header_and_claims` to
+/// set other claims, e.g. `exp`.
+#[cfg(not(feature = "verify-only"))]
+pub fn sign_status_list_token(
+    header_and_claims: &mut HeaderAndClaims<StatusListTokenClaims>,
+    uri: &str,
+    k: &dyn SigningKey,
+) -> Result<String> {
+    header_and_claims.header_mut().typ = Some(STATUS_LIST_TYP.into());
+    header_and_claims.set_sub(uri).set_iat_now();
+    sign(header_and_claims, k)
+}
+
+/// Verify a status list token that should be served at `uri`, and decompress
+/// its status list.
+pub fn verify_status_list_token(
+    token: &str,
+    uri: &str,
+    k: &dyn VerificationKey,
+) -> Result<(HeaderAndClaims<StatusListTokenClaims>, StatusList)> {
+    let options = ValidationOptions::new().require_typ(STATUS_LIST_TYP);
+    let verified = verify_with_options::<StatusListTokenClaims>(token, k, &options)?;
+    if verified.claims().sub.as_deref() != Some(uri) {
+        return Err(Error::InvalidToken);
+    }
+    let list = StatusList::from_claim(&verified.claims().extra.status_list)?;
+    Ok((verified, list))
+}
+
+#[cfg(feature = "remote-jwks")]
+struct CachedStatusList {
+    list: StatusList,
+    valid_until: std::time::Instant,
+}
+
+/// Fetches, verifies and caches status list tokens, and checks the status of
+/// referenced tokens against them.
+#[cfg(feature = "remote-jwks")]
+pub struct RemoteStatusListResolver {
+    key: Box<dyn VerificationKey + Send + Sync>,
+    client: reqwest::Client,
+    cache_duration: std::time::Duration,
+    cache: tokio::sync::RwLock<std::collections::HashMap<String, CachedStatusList>>,
+}
+
+#[cfg(feature = "remote-jwks")]
+impl RemoteStatusListResolver {
+    /// Status list tokens must be signed by `key`. They are cached for
+    /// `cache_duration`, or for their `ttl` if that is shorter.
+    pub fn new(
+        key: Box<dyn VerificationKey + Send + Sync>,
+        client: Option<reqwest::Client>,
+        cache_duration: std::time::Duration,
+    ) -> Self {
+        Self {
+            key,
+            client: client.unwrap_or_default(),
+            cache_duration,
+            cache: tokio::sync::RwLock::new(std::collections::HashMap::new()),
+        }
+    }
+
+    /// Get the status of the referenced token.
+    pub async fn status(&self, reference: &StatusListReference) -> Result<u8> {
+        let now = std::time::Instant::now();
+        if let Some(c) = self.cache.read().await.get(&reference.uri) {
+            if c.valid_until > now {
+                return c.list.get(reference.idx).ok_or(Error::InvalidToken);
+            }
+        }
+
+        let token = self
+            .client
+            .get(&reference.uri)
+            .header("accept", "application/statuslist+jwt")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let (verified, list) = verify_status_list_token(&token, &reference.uri, &*self.key)?;
+        let cache_duration = match verified.claims().extra.ttl {
+            Some(ttl) => self.cache_duration.min(std::time::Duration::from_secs(ttl)),
+            None => self.cache_duration,
+        };
+        let status = list.get(reference.idx).ok_or(Error::InvalidToken);
+
+        self.cache.write().await.insert(
+            reference.uri.clone(),
+            CachedStatusList {
+                list,
+                valid_until: now + cache_duration,
+            },
+        );
+
+        status
+    }
+
+    /// Return `Error::Revoked` unless the referenced token has status
+    /// `STATUS_VALID`.
+    pub async fn check(&self, status: &Status) -> Result<()> {
+        match self.status(&status.status_list).await? {
+            STATUS_VALID => Ok(()),
+            _ => Err(Error::Revoked),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+mod tests {
+    use crate::ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey};
+
+    use super::*;
+
+    #[test]
+    fn draft_example() -> Result<()> {
+        let list = StatusList::from_claim(&StatusListClaim {
+            bits: 1,
+            lst: "eNrbuRgAAhcBXQ".into(),
+        })?;
+        let expected = [1, 0, 0, 1, 1, 1, 0, 1, 1, 1, 0, 0, 0, 1, 0, 1];
+        assert_eq!(list.len(), expected.len());
+        for (idx, status) in expected.iter().enumerate() {
+            assert_eq!(list.get(idx), Some(*status));
+        }
+        assert_eq!(list.get(16), None);
+        Ok(())
+    }
+
+    #[test]
+    fn set_and_sign() -> Result<()> {
+        let mut list = StatusList::new(2, 10)?;
+        list.set(3, STATUS_SUSPENDED)?;
+        list.set(9, STATUS_INVALID)?;
+        assert!(list.set(9, 4).is_err());
+        assert!(list.set(12, STATUS_INVALID).is_err());
+
+        let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        let uri = "https://example.com/statuslists/1";
+        let token = sign_status_list_token(
+            &mut HeaderAndClaims::with_claims(StatusListTokenClaims {
+                status_list: list.to_claim()?,
+                ttl: None,
+            }),
+            uri,
+            &k,
+        )?;
+
+        let (_, list1) = verify_status_list_token(&token, uri, &k)?;
+        assert_eq!(list1, list);
+        assert_eq!(list1.get(3), Some(STATUS_SUSPENDED));
+        assert_eq!(list1.get(9), Some(STATUS_INVALID));
+        assert_eq!(list1.get(0), Some(STATUS_VALID));
+
+        assert!(verify_status_list_token(&token, "https://example.com/other", &k).is_err());
+
+        let mut header_and_claims = HeaderAndClaims::with_claims(StatusListTokenClaims {
+            status_list: list.to_claim()?,
+            ttl: None,
+        });
+        header_and_claims.set_sub(uri);
+        header_and_claims.header_mut().typ = Some("application/statuslist+jwt".into());
+        let token = sign(&mut header_and_claims, &k)?;
+        assert!(verify_status_list_token(&token, uri, &k).is_ok());
+        header_and_claims.header_mut().typ = Some("JWT".into());
+        let token = sign(&mut header_and_claims, &k)?;
+        assert!(verify_status_list_token(&token, uri, &k).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn limits() -> Result<()> {
+        assert!(StatusList::new(8, usize::MAX).is_err());
+
+        let mut w = ZlibEncoder::new(Vec::new(), Compression::best());
+        w.write_all(&vec![0; MAX_LIST_BYTES as usize + 1])?;
+        let claim = StatusListClaim {
+            bits: 1,
+            lst: b64::encode_url(w.finish()?),
+        };
+        assert!(matches!(
+            StatusList::from_claim(&claim),
+            Err(Error::InvalidToken)
+        ));
+        Ok(())
+    }
+}
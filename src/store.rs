@@ -0,0 +1,124 @@
+//! Private keys loaded by OpenSSL from a URI, see
+//! [`SomePrivateKey::from_openssl_uri`](crate::SomePrivateKey::from_openssl_uri).
+
+use std::{
+    ffi::CString,
+    os::raw::{c_char, c_int, c_void},
+    ptr,
+};
+
+use foreign_types::{ForeignType, ForeignTypeRef};
+#[cfg(feature = "ecdsa")]
+use openssl::nid::Nid;
+use openssl::{
+    error::ErrorStack,
+    pkey::{Id, PKey, PKeyRef, Private},
+};
+
+use crate::{Error, Result};
+
+#[allow(non_camel_case_types)]
+enum OSSL_STORE_CTX {}
+#[allow(non_camel_case_types)]
+enum OSSL_STORE_INFO {}
+
+const OSSL_STORE_INFO_PKEY: c_int = 4;
+
+// Not bound by openssl-sys. OpenSSL 3.0 and later.
+extern "C" {
+    fn OSSL_STORE_open(
+        uri: *const c_char,
+        ui_method: *const c_void,
+        ui_data: *mut c_void,
+        post_process: *const c_void,
+        post_process_data: *mut c_void,
+    ) -> *mut OSSL_STORE_CTX;
+    fn OSSL_STORE_load(ctx: *mut OSSL_STORE_CTX) -> *mut OSSL_STORE_INFO;
+    fn OSSL_STORE_eof(ctx: *mut OSSL_STORE_CTX) -> c_int;
+    fn OSSL_STORE_error(ctx: *mut OSSL_STORE_CTX) -> c_int;
+    fn OSSL_STORE_close(ctx: *mut OSSL_STORE_CTX) -> c_int;
+    fn OSSL_STORE_INFO_get_type(info: *const OSSL_STORE_INFO) -> c_int;
+    fn OSSL_STORE_INFO_get1_PKEY(info: *const OSSL_STORE_INFO) -> *mut openssl_sys::EVP_PKEY;
+    fn OSSL_STORE_INFO_free(info: *mut OSSL_STORE_INFO);
+}
+
+/// The first private key at `uri`, e.g. `file:/path/key.pem` or, with the
+/// pkcs11 provider configured, `pkcs11:token=...;object=...`.
+pub(crate) fn load_private_key(uri: &str) -> Result<PKey<Private>> {
+    let uri = CString::new(uri).map_err(|_| Error::UnsupportedOrInvalidKey)?;
+    unsafe {
+        let ctx = OSSL_STORE_open(
+            uri.as_ptr(),
+            ptr::null(),
+            ptr::null_mut(),
+            ptr::null(),
+            ptr::null_mut(),
+        );
+        if ctx.is_null() {
+            return Err(ErrorStack::get().into());
+        }
+        let mut pkey = None;
+        while pkey.is_none() && OSSL_STORE_eof(ctx) == 0 {
+            let info = OSSL_STORE_load(ctx);
+            if info.is_null() {
+                // Loading failed, or nothing more can be loaded without
+                // the store reporting eof: either way trying again won't
+                // help.
+                if OSSL_STORE_error(ctx) == 1 {
+                    let e = ErrorStack::get();
+                    OSSL_STORE_close(ctx);
+                    return Err(e.into());
+                }
+                break;
+            }
+            if OSSL_STORE_INFO_get_type(info) == OSSL_STORE_INFO_PKEY {
+                let p = OSSL_STORE_INFO_get1_PKEY(info);
+                if !p.is_null() {
+                    pkey = Some(PKey::from_ptr(p));
+                }
+            }
+            OSSL_STORE_INFO_free(info);
+        }
+        OSSL_STORE_close(ctx);
+        // Errors of entries that were skipped.
+        let _ = ErrorStack::get();
+        pkey.ok_or(Error::NoKey)
+    }
+}
+
+/// Type of a key. `PKey::id` doesn't work for keys of providers other than
+/// the default one.
+pub(crate) fn key_type(pkey: &PKeyRef<Private>) -> Id {
+    for (name, id) in [
+        (&b"RSA\0"[..], Id::RSA),
+        (b"EC\0", Id::EC),
+        (b"ED25519\0", Id::ED25519),
+    ] {
+        if unsafe { openssl_sys::EVP_PKEY_is_a(pkey.as_ptr(), name.as_ptr() as *const c_char) } == 1
+        {
+            return id;
+        }
+    }
+    pkey.id()
+}
+
+/// Curve of an EC key, without exporting the key from its provider.
+#[cfg(feature = "ecdsa")]
+pub(crate) fn curve(pkey: &PKeyRef<Private>) -> Result<Nid> {
+    let mut name = [0 as c_char; 64];
+    let mut len = 0;
+    unsafe {
+        if openssl_sys::EVP_PKEY_get_utf8_string_param(
+            pkey.as_ptr(),
+            b"group\0".as_ptr() as *const c_char,
+            name.as_mut_ptr(),
+            name.len(),
+            &mut len,
+        ) != 1
+        {
+            return Err(ErrorStack::get().into());
+        }
+        let nid = openssl_sys::OBJ_sn2nid(name.as_ptr());
+        Ok(Nid::from_raw(nid))
+    }
+}
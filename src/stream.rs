@@ -0,0 +1,263 @@
+//! Detached signatures over payloads too large to load into memory.
+//!
+//! A detached JWS is a compact JWS with an empty payload segment, e.g.
+//! `eyJhbGciOiJFUzI1NiJ9..MEUCIQ…`, that is transmitted separately from the
+//! payload (RFC 7515 appendix F). [`sign_detached`] and [`verify_detached`]
+//! read the payload from an [`io::Read`](std::io::Read), e.g. a file, and
+//! feed it to the signature algorithm as they go.
+//!
+//! Streaming is supported by RSA and ECDSA keys. Ed25519 signatures are over
+//! the whole message at once, and return `Error::UnsupportedOrInvalidKey`, as
+//! do HMAC keys.
+//!
+//! ```
+//! # use jwtk::{ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey}, ValidationOptions};
+//! use jwtk::stream::{sign_detached, verify_detached};
+//!
+//! let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+//! let artifact = vec![7u8; 1 << 20];
+//! let signature = sign_detached(&artifact[..], &k)?;
+//! verify_detached(&signature, &artifact[..], &k, &ValidationOptions::new())?;
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use std::io::{self, Read, Write};
+
+#[cfg(not(feature = "verify-only"))]
+use openssl::sign::Signer;
+use openssl::{hash::Hasher, sign::Verifier};
+#[cfg(not(feature = "verify-only"))]
+use smallvec::SmallVec;
+
+#[cfg(not(feature = "verify-only"))]
+use crate::SigningKey;
+use crate::{b64, decode_header, Error, Header, Result, ValidationOptions, VerificationKey};
+
+type VerifyDigest<'a> = Box<dyn FnOnce(&[u8], &[u8]) -> Result<()> + 'a>;
+#[cfg(not(feature = "verify-only"))]
+type SignDigest<'a> = Box<dyn FnOnce(&[u8]) -> Result<SmallVec<[u8; 64]>> + 'a>;
+
+// Signers are used by RSA keys, digests by ECDSA keys.
+#[cfg(not(feature = "verify-only"))]
+#[cfg_attr(not(all(feature = "rsa", feature = "ecdsa")), allow(dead_code))]
+enum SignInner<'a> {
+    Signer(Signer<'a>),
+    Digest(Hasher, SignDigest<'a>),
+}
+
+/// A signature being computed over input written to it, see
+/// [`SigningKey::sign_stream`].
+#[cfg(not(feature = "verify-only"))]
+pub struct SignStream<'a> {
+    inner: SignInner<'a>,
+}
+
+#[cfg(not(feature = "verify-only"))]
+#[cfg_attr(not(all(feature = "rsa", feature = "ecdsa")), allow(dead_code))]
+impl<'a> SignStream<'a> {
+    pub(crate) fn signer(signer: Signer<'a>) -> Self {
+        Self {
+            inner: SignInner::Signer(signer),
+        }
+    }
+
+    /// Hash the input with `hasher`, then sign the digest.
+    pub(crate) fn digest(
+        hasher: Hasher,
+        sign: impl FnOnce(&[u8]) -> Result<SmallVec<[u8; 64]>> + 'a,
+    ) -> Self {
+        Self {
+            inner: SignInner::Digest(hasher, Box::new(sign)),
+        }
+    }
+
+    /// The signature over everything written.
+    pub fn finish(self) -> Result<SmallVec<[u8; 64]>> {
+        match self.inner {
+            SignInner::Signer(signer) => Ok(signer.sign_to_vec()?.into()),
+            SignInner::Digest(mut hasher, sign) => sign(&hasher.finish()?),
+        }
+    }
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl Write for SignStream<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            SignInner::Signer(signer) => signer.update(buf)?,
+            SignInner::Digest(hasher, _) => hasher.update(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg_attr(not(all(feature = "rsa", feature = "ecdsa")), allow(dead_code))]
+enum VerifyInner<'a> {
+    Verifier(Verifier<'a>),
+    Digest(Hasher, VerifyDigest<'a>),
+}
+
+/// A signature being verified over input written to it, see
+/// [`VerificationKey::verify_stream`].
+pub struct VerifyStream<'a> {
+    inner: VerifyInner<'a>,
+}
+
+#[cfg_attr(not(all(feature = "rsa", feature = "ecdsa")), allow(dead_code))]
+impl<'a> VerifyStream<'a> {
+    pub(crate) fn verifier(verifier: Verifier<'a>) -> Self {
+        Self {
+            inner: VerifyInner::Verifier(verifier),
+        }
+    }
+
+    /// Hash the input with `hasher`, then verify the signature over the
+    /// digest with `verify(digest, sig)`.
+    pub(crate) fn digest(
+        hasher: Hasher,
+        verify: impl FnOnce(&[u8], &[u8]) -> Result<()> + 'a,
+    ) -> Self {
+        Self {
+            inner: VerifyInner::Digest(hasher, Box::new(verify)),
+        }
+    }
+
+    /// Check that `sig` is a signature over everything written.
+    pub fn finish(self, sig: &[u8]) -> Result<()> {
+        match self.inner {
+            VerifyInner::Verifier(verifier) => {
+                if verifier.verify(sig)? {
+                    Ok(())
+                } else {
+                    Err(Error::VerificationError)
+                }
+            }
+            VerifyInner::Digest(mut hasher, verify) => verify(&hasher.finish()?, sig),
+        }
+    }
+}
+
+impl Write for VerifyStream<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            VerifyInner::Verifier(verifier) => verifier.update(buf)?,
+            VerifyInner::Digest(hasher, _) => hasher.update(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Write `header.` and then the base64url encoded `payload` to `w`.
+fn write_signing_input(w: &mut impl Write, header: &str, payload: impl Read) -> Result<()> {
+    w.write_all(header.as_bytes())?;
+    w.write_all(b".")?;
+    let mut encoder = base64::write::EncoderWriter::new(w, &b64::URL_SAFE);
+    io::copy(&mut { payload }, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// Sign `payload` and return a detached JWS, i.e. `header..signature`.
+#[cfg(not(feature = "verify-only"))]
+pub fn sign_detached(payload: impl Read, k: &dyn SigningKey) -> Result<String> {
+    let header = Header {
+        alg: k.alg().into(),
+        kid: k.kid().map(Into::into),
+        ..Header::default()
+    };
+    let header = b64::encode_url(serde_json::to_vec(&header)?);
+
+    let mut stream = k.sign_stream()?;
+    write_signing_input(&mut stream, &header, payload)?;
+    let sig = stream.finish()?;
+
+    Ok(format!("{}..{}", header, b64::encode_url(sig)))
+}
+
+/// Verify detached JWS `token` over `payload`, and return its header.
+///
+/// The header is validated according to `options`, e.g. its `alg` and
+/// `typ`. A token with a non-empty payload segment is rejected.
+pub fn verify_detached(
+    token: &str,
+    payload: impl Read,
+    k: &dyn VerificationKey,
+    options: &ValidationOptions,
+) -> Result<Header> {
+    options.check_token_len(token)?;
+    let (header_segment, sig) = match token.split_once("..") {
+        Some((header, sig)) if !sig.contains('.') => (header, sig),
+        _ => return Err(Error::InvalidToken),
+    };
+    let header = decode_header(header_segment, options)?;
+    options.check_alg(&header.alg)?;
+    let sig = b64::decode_url(sig)?;
+
+    let mut stream = k.verify_stream(&header.alg)?;
+    write_signing_input(&mut stream, header_segment, payload)?;
+    stream.finish(&sig)?;
+
+    options.check_typ(header.typ.as_deref())?;
+    Ok(header)
+}
+
+#[cfg(all(
+    test,
+    feature = "rsa",
+    feature = "ecdsa",
+    feature = "eddsa",
+    not(feature = "verify-only")
+))]
+mod tests {
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        eddsa::Ed25519PrivateKey,
+        jwk::WithKid,
+        rsa::{RsaAlgorithm, RsaPrivateKey},
+        SomePrivateKey,
+    };
+
+    use super::*;
+
+    #[test]
+    fn detached() -> Result<()> {
+        let payload: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        let keys: Vec<SomePrivateKey> = vec![
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?.into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES512)?.into(),
+            RsaPrivateKey::generate(2048, RsaAlgorithm::RS384)?.into(),
+            RsaPrivateKey::generate(2048, RsaAlgorithm::PS256)?.into(),
+        ];
+        let options = ValidationOptions::new();
+        for k in keys {
+            let k = WithKid::new("k".into(), k);
+            let token = sign_detached(&payload[..], &k)?;
+            let header = verify_detached(&token, &payload[..], &k, &options)?;
+            assert_eq!(header.kid.as_deref(), Some("k"));
+
+            // Same as an ordinary JWS with the payload put back in.
+            let (h, s) = token.split_once("..").unwrap();
+            let attached = format!("{}.{}.{}", h, b64::encode_url(&payload), s);
+            crate::verify_raw(&attached, &k, &options.clone().max_token_len(1 << 20))?;
+
+            let mut other = payload.clone();
+            other[50_000] ^= 1;
+            assert!(verify_detached(&token, &other[..], &k, &options).is_err());
+        }
+
+        let ed = Ed25519PrivateKey::generate()?;
+        assert!(matches!(
+            sign_detached(&payload[..], &ed),
+            Err(Error::UnsupportedOrInvalidKey)
+        ));
+        Ok(())
+    }
+}
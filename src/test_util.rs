@@ -0,0 +1,244 @@
+//! Utilities for testing code that verifies tokens.
+//!
+//! [`MockIssuer`] generates a key, mints tokens with arbitrary claims and
+//! serves its JWK Set, either in process or over HTTP on a local port.
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    jwk::{JwkSet, JwkSetVerifier, WithKid},
+    keygen::KeyGen,
+    sign, HeaderAndClaims, PublicKeyToJwk, Result, SomePrivateKey,
+};
+
+/// A token issuer for tests.
+///
+/// ```
+/// # use jwtk::test_util::MockIssuer;
+/// # use serde_json::{Map, Value};
+/// let issuer = MockIssuer::new()?;
+/// let token = issuer.mint(|t| {
+///     t.set_sub("alice").insert("admin", true);
+/// })?;
+/// let verified = issuer.verifier()?.verify::<Map<String, Value>>(&token)?;
+/// assert_eq!(verified.claims().extra["admin"], true);
+/// # Ok::<_, jwtk::Error>(())
+/// ```
+pub struct MockIssuer {
+    key: WithKid<SomePrivateKey>,
+    issuer: String,
+    server: Option<JwksServer>,
+}
+
+impl MockIssuer {
+    /// An issuer with a fresh ES256 key.
+    pub fn new() -> Result<Self> {
+        Self::with_alg("ES256")
+    }
+
+    /// An issuer with a fresh key for `alg`, see [`KeyGen::new`].
+    pub fn with_alg(alg: &str) -> Result<Self> {
+        let generated = KeyGen::new(alg).kid_from_thumbprint().generate()?;
+        Ok(Self {
+            key: WithKid::new(generated.kid.unwrap(), generated.key),
+            issuer: "https://issuer.test".into(),
+            server: None,
+        })
+    }
+
+    /// `iss` of minted tokens. Defaults to `https://issuer.test`.
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    pub fn set_issuer(&mut self, issuer: impl Into<String>) -> &mut Self {
+        self.issuer = issuer.into();
+        self
+    }
+
+    pub fn key(&self) -> &WithKid<SomePrivateKey> {
+        &self.key
+    }
+
+    pub fn jwks(&self) -> Result<JwkSet> {
+        Ok(JwkSet {
+            keys: vec![self.key.public_key_to_jwk()?],
+        })
+    }
+
+    /// A verifier for the tokens of this issuer, for in process use.
+    pub fn verifier(&self) -> Result<JwkSetVerifier> {
+        Ok(self.jwks()?.verifier())
+    }
+
+    /// Mint a token with `iss`, `iat` and a 5 minute `exp` preset. `f` can
+    /// set or override any header field or claim.
+    pub fn mint(&self, f: impl FnOnce(&mut HeaderAndClaims<Map<String, Value>>)) -> Result<String> {
+        let mut token = HeaderAndClaims::new_dynamic();
+        token
+            .set_iss(self.issuer.clone())
+            .set_iat_now()
+            .set_exp_from_now(Duration::from_secs(300));
+        f(&mut token);
+        sign(&mut token, &self.key)
+    }
+
+    /// Mint a token with exactly these header and claims.
+    pub fn mint_claims<ExtraClaims: Serialize>(
+        &self,
+        token: &mut HeaderAndClaims<ExtraClaims>,
+    ) -> Result<String> {
+        sign(token, &self.key)
+    }
+
+    /// Serve the JWK Set at `http://127.0.0.1:<port>/jwks`, and an OpenID
+    /// provider configuration at `/.well-known/openid-configuration`.
+    ///
+    /// Sets the issuer to the server's base url. The server is stopped when
+    /// the issuer is dropped. Returns the JWK Set url.
+    pub fn serve(&mut self) -> Result<String> {
+        if self.server.is_none() {
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            let base = format!("http://{}", listener.local_addr()?);
+            self.issuer = base.clone();
+            let jwks = serde_json::to_string(&self.jwks()?)?;
+            let discovery = serde_json::to_string(&serde_json::json!({
+                "issuer": base,
+                "jwks_uri": format!("{}/jwks", base),
+            }))?;
+            self.server = Some(JwksServer::start(listener, jwks, discovery)?);
+        }
+        Ok(self.jwks_url().unwrap())
+    }
+
+    /// The JWK Set url, if the issuer is being served.
+    pub fn jwks_url(&self) -> Option<String> {
+        self.server
+            .as_ref()
+            .map(|_| format!("{}/jwks", self.issuer))
+    }
+
+    /// How many times the JWK Set was fetched from the server, e.g. to test
+    /// caching.
+    pub fn jwks_fetches(&self) -> usize {
+        self.server
+            .as_ref()
+            .map_or(0, |s| s.jwks_fetches.load(Ordering::SeqCst))
+    }
+}
+
+struct JwksServer {
+    addr: std::net::SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    jwks_fetches: Arc<AtomicUsize>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl JwksServer {
+    fn start(listener: TcpListener, jwks: String, discovery: String) -> Result<Self> {
+        let addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let jwks_fetches = Arc::new(AtomicUsize::new(0));
+        let thread = std::thread::spawn({
+            let shutdown = shutdown.clone();
+            let jwks_fetches = jwks_fetches.clone();
+            move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let _ = respond(stream, &jwks, &discovery, &jwks_fetches);
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            addr,
+            shutdown,
+            jwks_fetches,
+            thread: Some(thread),
+        })
+    }
+}
+
+fn respond(
+    mut stream: TcpStream,
+    jwks: &str,
+    discovery: &str,
+    jwks_fetches: &AtomicUsize,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 16 * 1024 {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split(' ').nth(1).unwrap_or("");
+    let (status, body) = match path {
+        "/jwks" => {
+            jwks_fetches.fetch_add(1, Ordering::SeqCst);
+            ("200 OK", jwks)
+        }
+        "/.well-known/openid-configuration" => ("200 OK", discovery),
+        _ => ("404 Not Found", "{}"),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+impl Drop for JwksServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake up the accept loop.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "remote-jwks"))]
+mod tests {
+    use crate::jwk::RemoteJwksVerifier;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serve_jwks() -> Result<()> {
+        let mut issuer = MockIssuer::with_alg("EdDSA")?;
+        let url = issuer.serve()?;
+        let token = issuer.mint(|t| {
+            t.set_sub("alice");
+        })?;
+
+        let verifier = RemoteJwksVerifier::new(url, None, Duration::from_secs(60));
+        let verified = verifier.verify::<Map<String, Value>>(&token).await?;
+        assert_eq!(verified.claims().sub.as_deref(), Some("alice"));
+        assert_eq!(verified.claims().iss.as_deref(), Some(issuer.issuer()));
+        assert_eq!(issuer.jwks_fetches(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! Test vectors from the JOSE RFC appendices.
+//!
+//! The examples of RFC 7516 and RFC 7518 are for encryption, which jwtk
+//! doesn't implement, so the JWS algorithms of RFC 7518 are covered by the
+//! examples of RFC 7515 and the JOSE cookbook, RFC 7520.
+//!
+//! Exposed with the `test-vectors` feature so that downstream crates, e.g.
+//! custom `SigningKey` implementations, can check themselves against the
+//! same data.
+
+/// A JWS signature test vector.
+#[derive(Debug, Clone, Copy)]
+pub struct JwsVector {
+    /// Where the vector comes from.
+    pub source: &'static str,
+    pub alg: &'static str,
+    /// The key as a JWK. Only signing vectors include the private key.
+    pub jwk: &'static str,
+    /// Encoded header and payload, joined with `.`.
+    pub signing_input: &'static str,
+    /// Base64url encoded signature.
+    pub signature: &'static str,
+    /// Whether signing `signing_input` with `jwk` reproduces `signature`
+    /// exactly.
+    pub deterministic: bool,
+}
+
+impl JwsVector {
+    /// The compact serialization.
+    pub fn token(&self) -> String {
+        format!("{}.{}", self.signing_input, self.signature)
+    }
+}
+
+pub const JWS_VECTORS: &[JwsVector] = &[
+    JwsVector {
+        source: "RFC 7515 Appendix A.1",
+        alg: "HS256",
+        jwk: r#"{"kty":"oct","k":"AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow"}"#,
+        signing_input: "eyJ0eXAiOiJKV1QiLA0KICJhbGciOiJIUzI1NiJ9.eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ",
+        signature: "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk",
+        deterministic: true,
+    },
+    JwsVector {
+        source: "RFC 7515 Appendix A.2",
+        alg: "RS256",
+        jwk: r#"{"kty":"RSA","n":"ofgWCuLjybRlzo0tZWJjNiuSfb4p4fAkd_wWJcyQoTbji9k0l8W26mPddxHmfHQp-Vaw-4qPCJrcS2mJPMEzP1Pt0Bm4d4QlL-yRT-SFd2lZS-pCgNMsD1W_YpRPEwOWvG6b32690r2jZ47soMZo9wGzjb_7OMg0LOL-bSf63kpaSHSXndS5z5rexMdbBYUsLA9e-KXBdQOS-UTo7WTBEMa2R2CapHg665xsmtdVMTBQY4uDZlxvb3qCo5ZwKh9kG4LT6_I5IhlJH7aGhyxXFvUK-DWNmoudF8NAco9_h9iaGNj8q2ethFkMLs91kzk2PAcDTW9gb54h4FRWyuXpoQ","e":"AQAB","d":"Eq5xpGnNCivDflJsRQBXHx1hdR1k6Ulwe2JZD50LpXyWPEAeP88vLNO97IjlA7_GQ5sLKMgvfTeXZx9SE-7YwVol2NXOoAJe46sui395IW_GO-pWJ1O0BkTGoVEn2bKVRUCgu-GjBVaYLU6f3l9kJfFNS3E0QbVdxzubSu3Mkqzjkn439X0M_V51gfpRLI9JYanrC4D4qAdGcopV_0ZHHzQlBjudU2QvXt4ehNYTCBr6XCLQUShb1juUO1ZdiYoFaFQT5Tw8bGUl_x_jTj3ccPDVZFD9pIuhLhBOneufuBiB4cS98l2SR_RQyGWSeWjnczT0QU91p1DhOVRuOopznQ","p":"4BzEEOtIpmVdVEZNCqS7baC4crd0pqnRH_5IB3jw3bcxGn6QLvnEtfdUdiYrqBdss1l58BQ3KhooKeQTa9AB0Hw_Py5PJdTJNPY8cQn7ouZ2KKDcmnPGBY5t7yLc1QlQ5xHdwW1VhvKn-nXqhJTBgIPgtldC-KDV5z-y2XDwGUc","q":"uQPEfgmVtjL0Uyyx88GZFF1fOunH3-7cepKmtH4pxhtCoHqpWmT8YAmZxaewHgHAjLYsp1ZSe7zFYHj7C6ul7TjeLQeZD_YwD66t62wDmpe_HlB-TnBA-njbglfIsRLtXlnDzQkv5dTltRJ11BKBBypeeF6689rjcJIDEz9RWdc","dp":"BwKfV3Akq5_MFZDFZCnW-wzl-CCo83WoZvnLQwCTeDv8uzluRSnm71I3QCLdhrqE2e9YkxvuxdBfpT_PI7Yz-FOKnu1R6HsJeDCjn12Sk3vmAktV2zb34MCdy7cpdTh_YVr7tss2u6vneTwrA86rZtu5Mbr1C1XsmvkxHQAdYo0","dq":"h_96-mK1R_7glhsum81dZxjTnYynPbZpHziZjeeHcXYsXaaMwkOlODsWa7I9xXDoRwbKgB719rrmI2oKr6N3Do9U0ajaHF-NKJnwgjMd2w9cjz3_-kyNlxAr2v4IKhGNpmM5iIgOS1VZnOZ68m6_pbLBSp3nssTdlqvd0tIiTHU","qi":"IYd7DHOhrWvxkwPQsRM2tOgrjbcrfvtQJipd-DlcxyVuuM9sQLdgjVk2oy26F0EmpScGLq2MowX7fhd_QJQ3ydy5cY7YIBi87w93IKLEdfnbJtoOPLUW0ITrJReOgo1cq9SbsxYawBgfp_gh6A5603k2-ZQwVK0JKSHuLFkuQ3U"}"#,
+        signing_input: "eyJhbGciOiJSUzI1NiJ9.eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ",
+        signature: "cC4hiUPoj9Eetdgtv3hF80EGrhuB__dzERat0XF9g2VtQgr9PJbu3XOiZj5RZmh7AAuHIm4Bh-0Qc_lF5YKt_O8W2Fp5jujGbds9uJdbF9CUAr7t1dnZcAcQjbKBYNX4BAynRFdiuB--f_nZLgrnbyTyWzO75vRK5h6xBArLIARNPvkSjtQBMHlb1L07Qe7K0GarZRmB_eSN9383LcOLn6_dO--xi12jzDwusC-eOkHWEsqtFZESc6BfI7noOPqvhJ1phCnvWh6IeYI2w9QOYEUipUTI8np6LbgGY9Fs98rqVt5AXLIhWkWywlVmtVrBp0igcN_IoypGlUPQGe77Rw",
+        deterministic: true,
+    },
+    JwsVector {
+        source: "RFC 7515 Appendix A.3",
+        alg: "ES256",
+        jwk: r#"{"kty":"EC","crv":"P-256","x":"f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU","y":"x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0"}"#,
+        signing_input: "eyJhbGciOiJFUzI1NiJ9.eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ",
+        signature: "DtEhU3ljbEg8L38VWAfUAqOyKAM6-Xx-F4GawxaepmXFCgfTjDxw5djxLa8ISlSApmWQxfKTUJqPP3-Kg6NU1Q",
+        deterministic: false,
+    },
+    JwsVector {
+        source: "RFC 7515 Appendix A.4",
+        alg: "ES512",
+        jwk: r#"{"kty":"EC","crv":"P-521","x":"AekpBQ8ST8a8VcfVOTNl353vSrDCLLJXmPk06wTjxrrjcBpXp5EOnYG_NjFZ6OvLFV1jSfS9tsz4qUxcWceqwQGk","y":"ADSmRA43Z1DSNx_RvcLI87cdL07l6jQyyBXMoxVg_l2Th-x3S1WDhjDly79ajL4Kkd0AZMaZmh9ubmf63e3kyMj2","d":"AY5pb7A0UFiB3RELSD64fTLOSV_jazdF7fLYyuTw8lOfRhWg6Y6rUrPAxerEzgdRhajnu0ferB0d53vM9mE15j2C"}"#,
+        signing_input: "eyJhbGciOiJFUzUxMiJ9.UGF5bG9hZA",
+        signature: "AdwMgeerwtHoh-l192l60hp9wAHZFVJbLfD_UxMi70cwnZOYaRI1bKPWROc-mZZqwqT2SI-KGDKB34XO0aw_7XdtAG8GaSwFKdCAPZgoXD2YBJZCPEX3xKpRwcdOO8KpEHwJjyqOgzDO7iKvU8vcnwNrmxYbSW9ERBXukOXolLzeO_Jn",
+        deterministic: false,
+    },
+    JwsVector {
+        source: "RFC 7520 Section 4.4",
+        alg: "HS256",
+        jwk: r#"{"kty":"oct","kid":"018c0ae5-4d9b-471b-bfd6-eef314bc7037","use":"sig","alg":"HS256","k":"hJtXIZ2uSN5kbQfbtTNWbpdmhkV8FJG-Onbc6mxCcYg"}"#,
+        signing_input: "eyJhbGciOiJIUzI1NiIsImtpZCI6IjAxOGMwYWU1LTRkOWItNDcxYi1iZmQ2LWVlZjMxNGJjNzAzNyJ9.SXTigJlzIGEgZGFuZ2Vyb3VzIGJ1c2luZXNzLCBGcm9kbywgZ29pbmcgb3V0IHlvdXIgZG9vci4gWW91IHN0ZXAgb250byB0aGUgcm9hZCwgYW5kIGlmIHlvdSBkb24ndCBrZWVwIHlvdXIgZmVldCwgdGhlcmXigJlzIG5vIGtub3dpbmcgd2hlcmUgeW91IG1pZ2h0IGJlIHN3ZXB0IG9mZiB0by4",
+        signature: "s0h6KThzkfBBBkLspW1h84VsJZFTsPPqMDA7g1Md7p0",
+        deterministic: true,
+    },
+    JwsVector {
+        source: "RFC 8037 Appendix A.4",
+        alg: "EdDSA",
+        jwk: r#"{"kty":"OKP","crv":"Ed25519","d":"nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#,
+        signing_input: "eyJhbGciOiJFZERTQSJ9.RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc",
+        signature: "hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg",
+        deterministic: true,
+    },
+];
+
+/// A JWK thumbprint (RFC 7638) test vector.
+#[derive(Debug, Clone, Copy)]
+pub struct ThumbprintVector {
+    pub source: &'static str,
+    pub jwk: &'static str,
+    /// Base64url encoded SHA-256 thumbprint.
+    pub thumbprint_sha256: &'static str,
+}
+
+pub const THUMBPRINT_VECTORS: &[ThumbprintVector] = &[
+    ThumbprintVector {
+        source: "RFC 7638 Section 3.1",
+        jwk: r#"{"kty":"RSA","n":"0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw","e":"AQAB","alg":"RS256","kid":"2011-04-29"}"#,
+        thumbprint_sha256: "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs",
+    },
+    ThumbprintVector {
+        source: "RFC 8037 Appendix A.3",
+        jwk: r#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#,
+        thumbprint_sha256: "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k",
+    },
+];
+
+#[cfg(all(
+    test,
+    feature = "rsa",
+    feature = "ecdsa",
+    feature = "eddsa",
+    feature = "hmac",
+    not(feature = "verify-only")
+))]
+mod tests {
+    use crate::{
+        b64,
+        hmac::{HmacAlgorithm, HmacKey},
+        jwk::Jwk,
+        rsa::RsaAlgorithm,
+        Result, SigningKey, VerificationKey,
+    };
+
+    use super::*;
+
+    type Keys = (Option<Box<dyn SigningKey>>, Box<dyn VerificationKey>);
+
+    fn keys(v: &JwsVector) -> Result<Keys> {
+        let jwk: Jwk = serde_json::from_str(v.jwk)?;
+        if jwk.kty == "oct" {
+            let k: serde_json::Value = serde_json::from_str(v.jwk)?;
+            let k = b64::decode_url(k["k"].as_str().unwrap())?;
+            let alg = match v.alg {
+                "HS256" => HmacAlgorithm::HS256,
+                "HS384" => HmacAlgorithm::HS384,
+                _ => HmacAlgorithm::HS512,
+            };
+            return Ok((
+                Some(Box::new(HmacKey::from_bytes(&k, alg))),
+                Box::new(HmacKey::from_bytes(&k, alg)),
+            ));
+        }
+        let sk = match jwk.d {
+            Some(_) => {
+                Some(Box::new(jwk.to_signing_key(RsaAlgorithm::RS256)?) as Box<dyn SigningKey>)
+            }
+            None => None,
+        };
+        Ok((sk, Box::new(jwk.to_verification_key()?)))
+    }
+
+    #[test]
+    fn jws_vectors() -> Result<()> {
+        for v in JWS_VECTORS {
+            let (sk, vk) = keys(v)?;
+            let sig = b64::decode_url(v.signature)?;
+            vk.verify(v.signing_input.as_bytes(), &sig, v.alg)?;
+
+            if let Some(sk) = sk {
+                assert_eq!(sk.alg(), v.alg, "{}", v.source);
+                let sig1 = sk.sign(v.signing_input.as_bytes())?;
+                if v.deterministic {
+                    assert_eq!(&sig1[..], &sig[..], "{}", v.source);
+                } else {
+                    vk.verify(v.signing_input.as_bytes(), &sig1, v.alg)?;
+                }
+            }
+
+            let mut tampered = sig.clone();
+            tampered[0] ^= 1;
+            assert!(vk
+                .verify(v.signing_input.as_bytes(), &tampered, v.alg)
+                .is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn thumbprint_vectors() -> Result<()> {
+        for v in THUMBPRINT_VECTORS {
+            let jwk: Jwk = serde_json::from_str(v.jwk)?;
+            assert_eq!(
+                jwk.get_thumbprint_sha256_base64()?,
+                v.thumbprint_sha256,
+                "{}",
+                v.source
+            );
+        }
+        Ok(())
+    }
+}
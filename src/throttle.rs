@@ -0,0 +1,230 @@
+//! Tracking verification failures per client.
+//!
+//! A client presenting a stream of forged or garbled tokens is either broken
+//! or probing. [`FailureTracker`] counts such failures per source, e.g. a
+//! client IP address or API key, so that a gateway can throttle it.
+//!
+//! ```
+//! # use jwtk::{throttle::FailureTracker, verify, hmac::{HmacKey, HmacAlgorithm}};
+//! # use std::time::Duration;
+//! # let k = HmacKey::generate(HmacAlgorithm::HS256)?;
+//! let tracker = FailureTracker::new(Duration::from_secs(60));
+//! let source = "203.0.113.7";
+//! if tracker.failures(source) >= 10 {
+//!     // Reject with 429 without even looking at the token.
+//! }
+//! let result = tracker.track(source, verify::<()>("a.b.c", &k));
+//! assert!(result.is_err());
+//! assert_eq!(tracker.failures(source), 1);
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{Error, Result};
+
+struct Window {
+    start: Instant,
+    failures: u32,
+}
+
+#[derive(Default)]
+struct Sources {
+    windows: HashMap<String, Window>,
+    /// The same sources, ordered by the start of their window, for eviction.
+    by_start: BTreeSet<(Instant, String)>,
+}
+
+impl Sources {
+    fn remove(&mut self, source: &str) {
+        if let Some(w) = self.windows.remove(source) {
+            self.by_start.remove(&(w.start, source.to_string()));
+        }
+    }
+}
+
+/// Counts verification failures per source in fixed time windows.
+///
+/// Only failures that a legitimate client wouldn't cause count, i.e. invalid
+/// signatures, malformed tokens and unknown keys or algorithms. Expired
+/// tokens and failed claim checks don't.
+pub struct FailureTracker {
+    window: Duration,
+    max_entries: usize,
+    sources: Mutex<Sources>,
+}
+
+impl FailureTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            max_entries: 10_000,
+            sources: Mutex::new(Sources::default()),
+        }
+    }
+
+    /// Don't track more than `max_entries` sources. When full, the sources
+    /// whose windows started first are forgotten. Defaults to 10000.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Whether `error` counts as a failure.
+    pub fn is_suspicious(error: &Error) -> bool {
+        matches!(
+            error,
+            Error::VerificationError
+                | Error::InvalidToken
+                | Error::AlgMismatch
+                | Error::NoKid
+                | Error::NoKey
+                | Error::TooLarge
+                | Error::Decode(_)
+                | Error::SerdeJson(_)
+                | Error::Utf8(_)
+        )
+    }
+
+    /// Record a failure of `source`, if `result` is one, and pass `result`
+    /// on.
+    pub fn track<T>(&self, source: &str, result: Result<T>) -> Result<T> {
+        if let Err(e) = &result {
+            if Self::is_suspicious(e) {
+                self.record_failure(source);
+            }
+        }
+        result
+    }
+
+    /// Record a failure of `source`, and return its failures in the current
+    /// window.
+    pub fn record_failure(&self, source: &str) -> u32 {
+        self.record_failure_at(source, Instant::now())
+    }
+
+    fn record_failure_at(&self, source: &str, now: Instant) -> u32 {
+        let mut sources = self.sources.lock().unwrap();
+        // Drop ended windows, then the ones that started first, until there
+        // is room.
+        while let Some((start, oldest)) = sources.by_start.iter().next().cloned() {
+            let ended = now.duration_since(start) >= self.window;
+            if !ended
+                && (sources.windows.contains_key(source)
+                    || sources.windows.len() < self.max_entries)
+            {
+                break;
+            }
+            sources.windows.remove(&oldest);
+            sources.by_start.remove(&(start, oldest));
+        }
+        if let Some(w) = sources.windows.get_mut(source) {
+            w.failures = w.failures.saturating_add(1);
+            return w.failures;
+        }
+        sources.by_start.insert((now, source.into()));
+        sources.windows.insert(
+            source.into(),
+            Window {
+                start: now,
+                failures: 1,
+            },
+        );
+        1
+    }
+
+    /// Failures of `source` in the current window.
+    pub fn failures(&self, source: &str) -> u32 {
+        self.failures_at(source, Instant::now())
+    }
+
+    fn failures_at(&self, source: &str, now: Instant) -> u32 {
+        match self.sources.lock().unwrap().windows.get(source) {
+            Some(w) if now.duration_since(w.start) < self.window => w.failures,
+            _ => 0,
+        }
+    }
+
+    /// When the current window of `source` ends, if it has any failures.
+    pub fn window_end(&self, source: &str) -> Option<Instant> {
+        match self.sources.lock().unwrap().windows.get(source) {
+            Some(w) if w.start.elapsed() < self.window => Some(w.start + self.window),
+            _ => None,
+        }
+    }
+
+    /// Forget the failures of `source`, e.g. after it authenticated in
+    /// another way.
+    pub fn reset(&self, source: &str) {
+        self.sources.lock().unwrap().remove(source);
+    }
+}
+
+#[cfg(all(test, feature = "eddsa", not(feature = "verify-only")))]
+mod tests {
+    use crate::{eddsa::Ed25519PrivateKey, sign, verify, HeaderAndClaims};
+
+    use super::*;
+
+    #[test]
+    fn failure_tracker() -> Result<()> {
+        let k = Ed25519PrivateKey::generate()?;
+        let other = Ed25519PrivateKey::generate()?;
+        let forged = sign(&mut HeaderAndClaims::new_dynamic(), &other)?;
+        let mut expired = HeaderAndClaims::new_dynamic();
+        expired.claims_mut().exp = Some(Duration::from_secs(1));
+        let expired = sign(&mut expired, &k)?;
+
+        let tracker = FailureTracker::new(Duration::from_secs(60));
+        assert!(tracker
+            .track("a", verify::<serde_json::Value>(&forged, &k))
+            .is_err());
+        assert!(tracker.track("a", verify::<()>("garbage", &k)).is_err());
+        assert!(matches!(
+            tracker.track("a", verify::<serde_json::Value>(&expired, &k)),
+            Err(Error::Expired)
+        ));
+        assert_eq!(tracker.failures("a"), 2);
+        assert_eq!(tracker.failures("b"), 0);
+        assert!(tracker.window_end("a").is_some());
+        tracker.reset("a");
+        assert_eq!(tracker.failures("a"), 0);
+
+        let tracker = FailureTracker::new(Duration::ZERO);
+        assert_eq!(tracker.record_failure("a"), 1);
+        assert_eq!(tracker.failures("a"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn failure_windows() {
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+        let tracker = FailureTracker::new(Duration::from_secs(60)).max_entries(2);
+
+        assert_eq!(tracker.record_failure_at("a", at(0)), 1);
+        assert_eq!(tracker.record_failure_at("a", at(59)), 2);
+        assert_eq!(tracker.failures_at("a", at(59)), 2);
+        assert_eq!(tracker.failures_at("a", at(60)), 0);
+        // A new window.
+        assert_eq!(tracker.record_failure_at("a", at(60)), 1);
+
+        // Full: the window that started first is dropped.
+        assert_eq!(tracker.record_failure_at("b", at(61)), 1);
+        assert_eq!(tracker.record_failure_at("c", at(62)), 1);
+        assert_eq!(tracker.failures_at("a", at(62)), 0);
+        assert_eq!(tracker.record_failure_at("b", at(62)), 2);
+        assert_eq!(tracker.sources.lock().unwrap().windows.len(), 2);
+
+        // Ended windows are dropped first.
+        assert_eq!(tracker.record_failure_at("d", at(121)), 1);
+        let sources = tracker.sources.lock().unwrap();
+        assert_eq!(sources.windows.len(), 2);
+        assert_eq!(sources.by_start.len(), 2);
+        assert!(sources.windows.contains_key("c"));
+    }
+}
@@ -0,0 +1,298 @@
+//! Cache of verified tokens.
+//!
+//! A gateway that sees the same bearer token many times a minute can keep
+//! the verified header and claims in a [`TokenCache`], keyed by the SHA-256
+//! of the token, instead of checking the signature every time. Entries
+//! expire after the TTL of the cache or at the `exp` of the token,
+//! whichever is first.
+//!
+//! Each cache has the one verification function it was created with, so a
+//! token accepted under one set of keys or [`ValidationOptions`] is never
+//! returned for another. Use one cache per verifier.
+//!
+//! A cached token stays valid until it expires, so install the
+//! [`RevocationCheck`] of the verifier on the cache too. It is consulted on
+//! every hit, and [`purge_revoked`](TokenCache::purge_revoked) drops revoked
+//! entries at once.
+//!
+//! ```
+//! # use jwtk::{hmac::{HmacAlgorithm, HmacKey}, sign, token_cache::TokenCache, verify, HeaderAndClaims};
+//! # use serde_json::{Map, Value};
+//! # use std::time::Duration;
+//! # let k = HmacKey::generate(HmacAlgorithm::HS256)?;
+//! # let token = sign(HeaderAndClaims::new_dynamic().set_exp_from_now(Duration::from_secs(60)), &k)?;
+//! let cache = TokenCache::new(Duration::from_secs(30), move |token| {
+//!     verify::<Map<String, Value>>(token, &k)
+//! });
+//! let verified = cache.verify(&token)?;
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+//!
+//! [`ValidationOptions`]: crate::ValidationOptions
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use openssl::hash::{hash, MessageDigest};
+
+use crate::{
+    revocation::{check_revocation, RevocationCheck},
+    HeaderAndClaims, Result,
+};
+
+type Verify<ExtraClaims> = Box<dyn Fn(&str) -> Result<HeaderAndClaims<ExtraClaims>> + Send + Sync>;
+
+struct Entries<ExtraClaims> {
+    /// Tokens and when they expire, in seconds since the epoch.
+    tokens: HashMap<[u8; 32], (Duration, Arc<HeaderAndClaims<ExtraClaims>>)>,
+    /// The same, ordered by expiry, for eviction.
+    by_expiry: BTreeSet<(Duration, [u8; 32])>,
+}
+
+impl<ExtraClaims> Entries<ExtraClaims> {
+    fn remove(&mut self, key: &[u8; 32]) {
+        if let Some((expires, _)) = self.tokens.remove(key) {
+            self.by_expiry.remove(&(expires, *key));
+        }
+    }
+
+    /// Drop expired entries, then the ones that expire first, until there
+    /// is room for another one.
+    fn make_room(&mut self, now: Duration, max_entries: usize) {
+        while let Some(&(expires, key)) = self.by_expiry.iter().next() {
+            if expires > now && self.tokens.len() < max_entries {
+                break;
+            }
+            self.by_expiry.remove(&(expires, key));
+            self.tokens.remove(&key);
+        }
+    }
+}
+
+/// Tokens verified with one verification function, by the SHA-256 of their
+/// compact serialization, see the [module docs](self).
+pub struct TokenCache<ExtraClaims> {
+    verify: Verify<ExtraClaims>,
+    entries: Mutex<Entries<ExtraClaims>>,
+    ttl: Duration,
+    max_entries: usize,
+    revocation_check: Option<Arc<dyn RevocationCheck>>,
+}
+
+impl<ExtraClaims> TokenCache<ExtraClaims> {
+    /// Cache tokens accepted by `verify` for at most `ttl`.
+    pub fn new(
+        ttl: Duration,
+        verify: impl Fn(&str) -> Result<HeaderAndClaims<ExtraClaims>> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            verify: Box::new(verify),
+            entries: Mutex::new(Entries {
+                tokens: HashMap::new(),
+                by_expiry: BTreeSet::new(),
+            }),
+            ttl,
+            max_entries: 10_000,
+            revocation_check: None,
+        }
+    }
+
+    /// Don't cache more than `max_entries` tokens. When full, the tokens
+    /// that expire first are dropped. Defaults to 10000.
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries;
+    }
+
+    /// Reject cached tokens that `check` considers revoked.
+    pub fn set_revocation_check(&mut self, check: Arc<dyn RevocationCheck>) {
+        self.revocation_check = Some(check);
+    }
+
+    /// The cached header and claims of `token`, or else the result of the
+    /// verification function, which is cached if it succeeds.
+    ///
+    /// Returns `Error::Revoked`, and forgets the token, if the revocation
+    /// check considers a cached token revoked.
+    pub fn verify(&self, token: &str) -> Result<Arc<HeaderAndClaims<ExtraClaims>>> {
+        let key = token_hash(token)?;
+        if let Some(cached) = self.get(&key)? {
+            return Ok(cached);
+        }
+        let verified = Arc::new((self.verify)(token)?);
+        self.insert(key, verified.clone());
+        Ok(verified)
+    }
+
+    fn get(&self, key: &[u8; 32]) -> Result<Option<Arc<HeaderAndClaims<ExtraClaims>>>> {
+        let mut entries = self.entries.lock().unwrap();
+        let cached = match entries.tokens.get(key) {
+            Some((expires, token)) if *expires > now() => token.clone(),
+            Some(_) => {
+                entries.remove(key);
+                return Ok(None);
+            }
+            None => return Ok(None),
+        };
+        if let Some(check) = &self.revocation_check {
+            if let Err(e) = check_revocation(&**check, cached.claims()) {
+                entries.remove(key);
+                return Err(e);
+            }
+        }
+        Ok(Some(cached))
+    }
+
+    fn insert(&self, key: [u8; 32], verified: Arc<HeaderAndClaims<ExtraClaims>>) {
+        let now = now();
+        let mut expires = now.saturating_add(self.ttl);
+        if let Some(exp) = verified.claims().exp {
+            expires = expires.min(exp);
+        }
+        if expires <= now || self.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&key);
+        entries.make_room(now, self.max_entries);
+        entries.by_expiry.insert((expires, key));
+        entries.tokens.insert(key, (expires, verified));
+    }
+
+    /// Forget the tokens that `check` considers revoked, e.g. after revoking
+    /// a `sub`.
+    pub fn purge_revoked(&self, check: &dyn RevocationCheck) {
+        let mut entries = self.entries.lock().unwrap();
+        let revoked: Vec<[u8; 32]> = entries
+            .tokens
+            .iter()
+            .filter(|(_, (_, token))| check_revocation(check, token.claims()).is_err())
+            .map(|(key, _)| *key)
+            .collect();
+        for key in revoked {
+            entries.remove(&key);
+        }
+    }
+
+    /// Forget all tokens, e.g. after rotating keys.
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.tokens.clear();
+        entries.by_expiry.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<ExtraClaims> fmt::Debug for TokenCache<ExtraClaims> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenCache")
+            .field("len", &self.len())
+            .field("ttl", &self.ttl)
+            .field("max_entries", &self.max_entries)
+            .finish()
+    }
+}
+
+fn token_hash(token: &str) -> Result<[u8; 32]> {
+    let mut h = [0; 32];
+    h.copy_from_slice(&hash(MessageDigest::sha256(), token.as_bytes())?);
+    Ok(h)
+}
+
+fn now() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+}
+
+#[cfg(all(test, feature = "hmac", not(feature = "verify-only")))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::{Map, Value};
+
+    use crate::{
+        hmac::{HmacAlgorithm, HmacKey},
+        revocation::RevocationList,
+        sign, verify, Error,
+    };
+
+    use super::*;
+
+    #[test]
+    fn token_cache() -> Result<()> {
+        let k = Arc::new(HmacKey::generate(HmacAlgorithm::HS256)?);
+        let token = |sub: &str, exp: u64| {
+            sign(
+                HeaderAndClaims::new_dynamic()
+                    .set_sub(sub)
+                    .set_exp_from_now(Duration::from_secs(exp)),
+                &*k,
+            )
+        };
+        let alice = token("alice", 60)?;
+        let verifications = Arc::new(AtomicUsize::new(0));
+        let new_cache = |ttl| {
+            let (k, verifications) = (k.clone(), verifications.clone());
+            TokenCache::new(ttl, move |token| {
+                verifications.fetch_add(1, Ordering::SeqCst);
+                verify::<Map<String, Value>>(token, &*k)
+            })
+        };
+
+        let list = Arc::new(RevocationList::new());
+        let mut cache = new_cache(Duration::from_secs(30));
+        cache.set_revocation_check(list.clone());
+        for _ in 0..3 {
+            let verified = cache.verify(&alice)?;
+            assert_eq!(verified.claims().sub.as_deref(), Some("alice"));
+        }
+        assert_eq!(verifications.load(Ordering::SeqCst), 1);
+
+        // Not cached.
+        assert!(cache.verify("a.b.c").is_err());
+        assert_eq!(cache.len(), 1);
+
+        list.revoke_sub("alice");
+        assert!(matches!(cache.verify(&alice), Err(Error::Revoked)));
+        assert!(cache.is_empty());
+        list.unrevoke_sub("alice");
+        cache.verify(&alice)?;
+        cache.purge_revoked(&|_: Option<&str>, sub: Option<&str>| sub == Some("alice"));
+        assert!(cache.is_empty());
+
+        // When full, the token that expires first is dropped.
+        cache.set_max_entries(2);
+        let (bob, carol) = (token("bob", 120)?, token("carol", 90)?);
+        cache.verify(&alice)?;
+        cache.verify(&bob)?;
+        cache.verify(&carol)?;
+        assert_eq!(cache.len(), 2);
+        let before = verifications.load(Ordering::SeqCst);
+        cache.verify(&bob)?;
+        cache.verify(&carol)?;
+        assert_eq!(verifications.load(Ordering::SeqCst), before);
+        cache.verify(&alice)?;
+        assert_eq!(verifications.load(Ordering::SeqCst), before + 1);
+
+        // Expired, or a zero TTL.
+        let expired = token("dave", 0)?;
+        let _ = cache.verify(&expired);
+        assert_eq!(cache.len(), 2);
+        let cache = new_cache(Duration::ZERO);
+        let before = verifications.load(Ordering::SeqCst);
+        cache.verify(&alice)?;
+        cache.verify(&alice)?;
+        assert_eq!(verifications.load(Ordering::SeqCst), before + 2);
+        assert!(cache.is_empty());
+        Ok(())
+    }
+}
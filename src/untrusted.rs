@@ -0,0 +1,127 @@
+//! Looking at tokens before they are verified.
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    decode_claims, decode_header, redact::RedactedToken, split_token, verify_with_options, Claims,
+    Header, HeaderAndClaims, Result, ValidationOptions, VerificationKey,
+};
+
+/// A value from a token whose signature has not been verified.
+///
+/// Anyone can put anything in it. It's fine for deciding e.g. which issuer's
+/// keys to verify the token with, but not for authorization. There is
+/// deliberately no `Deref`, so that it can't be passed where verified claims
+/// are expected.
+#[derive(Debug, Clone)]
+pub struct Untrusted<T>(T);
+
+impl<T> Untrusted<T> {
+    /// The unverified value.
+    pub fn peek(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_unverified(self) -> T {
+        self.0
+    }
+}
+
+/// A decoded, but not yet verified, token.
+///
+/// ```
+/// # use jwtk::{ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey}, sign, HeaderAndClaims, UntrustedToken, ValidationOptions};
+/// # let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+/// # let token = sign(HeaderAndClaims::new_dynamic().set_iss("https://a.example"), &k)?;
+/// let untrusted = UntrustedToken::parse(&token)?;
+/// let iss = untrusted.claims::<()>()?.peek().iss.clone();
+/// // Pick the key for `iss`, then:
+/// let verified = untrusted.verify::<()>(&k, &ValidationOptions::new())?;
+/// # assert_eq!(iss, verified.claims().iss);
+/// # Ok::<_, jwtk::Error>(())
+/// ```
+pub struct UntrustedToken<'a> {
+    token: &'a str,
+    header: Untrusted<Header>,
+}
+
+impl<'a> UntrustedToken<'a> {
+    /// Decode the header of `token`, within the size limits of the default
+    /// [`ValidationOptions`].
+    pub fn parse(token: &'a str) -> Result<Self> {
+        let options = ValidationOptions::default();
+        options.check_token_len(token)?;
+        let (header, _, _) = split_token(token)?;
+        Ok(Self {
+            token,
+            header: Untrusted(decode_header(header, &options)?),
+        })
+    }
+
+    /// The token as it was parsed, e.g. to forward it unchanged.
+    pub fn as_str(&self) -> &'a str {
+        self.token
+    }
+
+    pub fn header(&self) -> &Untrusted<Header> {
+        &self.header
+    }
+
+    /// Decode the claims, without checking the signature, `exp` or anything
+    /// else.
+    pub fn claims<ExtraClaims: DeserializeOwned>(&self) -> Result<Untrusted<Claims<ExtraClaims>>> {
+        let (_, payload, _) = split_token(self.token)?;
+        Ok(Untrusted(decode_claims(
+            payload,
+            &ValidationOptions::default(),
+        )?))
+    }
+
+    /// Verify the token, see [`verify_with_options`].
+    pub fn verify<ExtraClaims: DeserializeOwned>(
+        &self,
+        k: &dyn VerificationKey,
+        options: &ValidationOptions,
+    ) -> Result<HeaderAndClaims<ExtraClaims>> {
+        verify_with_options(self.token, k, options)
+    }
+}
+
+impl fmt::Debug for UntrustedToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UntrustedToken")
+            .field("token", &RedactedToken(self.token))
+            .field("header", &self.header)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "eddsa", not(feature = "verify-only")))]
+mod tests {
+    use serde_json::{Map, Value};
+
+    use crate::{eddsa::Ed25519PrivateKey, jwk::WithKid, sign, Error};
+
+    use super::*;
+
+    #[test]
+    fn untrusted() -> Result<()> {
+        let k = WithKid::new("k".into(), Ed25519PrivateKey::generate()?);
+        let token = sign(HeaderAndClaims::new_dynamic().set_sub("alice"), &k)?;
+        let untrusted = UntrustedToken::parse(&token)?;
+        assert_eq!(untrusted.header().peek().kid.as_deref(), Some("k"));
+        let claims = untrusted.claims::<Map<String, Value>>()?;
+        assert_eq!(claims.peek().sub.as_deref(), Some("alice"));
+        untrusted.verify::<Map<String, Value>>(&k, &ValidationOptions::new())?;
+
+        let other = Ed25519PrivateKey::generate()?;
+        assert!(matches!(
+            untrusted.verify::<Map<String, Value>>(&other, &ValidationOptions::new()),
+            Err(Error::VerificationError)
+        ));
+        assert!(UntrustedToken::parse("a.b").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,1035 @@
+//! Options for decoding and validating tokens.
+
+use std::{collections::HashSet, fmt, sync::Arc, time::Duration};
+
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Number, Value};
+
+#[cfg(feature = "json-schema")]
+use crate::schema::ClaimsSchema;
+use crate::{roles::RoleMapper, Claims, Error, OneOrMany, Result};
+
+/// Options for decoding and validating tokens.
+///
+/// The defaults are used by [`verify`](crate::verify),
+/// [`verify_only`](crate::verify_only) and
+/// [`decode_without_verify`](crate::decode_without_verify). Use
+/// [`verify_with_options`](crate::verify_with_options), or
+/// `set_validation_options` on the JWK Set verifiers, to change them.
+///
+/// ```
+/// # use jwtk::ValidationOptions;
+/// let options = ValidationOptions::new()
+///     .max_token_len(16 * 1024)
+///     .max_depth(8);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ValidationOptions {
+    pub(crate) max_token_len: usize,
+    pub(crate) max_header_len: usize,
+    pub(crate) max_claims_len: usize,
+    pub(crate) max_depth: usize,
+    pub(crate) reject_duplicate_header_keys: bool,
+    pub(crate) reject_duplicate_claim_keys: bool,
+    pub(crate) typ: Option<String>,
+    pub(crate) issuers: Vec<String>,
+    pub(crate) audiences: Vec<String>,
+    pub(crate) algorithms: Vec<String>,
+    /// Algorithms allowed by a [`Profile`], which `allow_alg` can't widen.
+    pub(crate) profile_algorithms: Option<Vec<String>>,
+    pub(crate) acrs: Vec<String>,
+    pub(crate) amrs: Vec<String>,
+    pub(crate) max_auth_age: Option<Duration>,
+    pub(crate) max_lifetime: Option<Duration>,
+    pub(crate) required_claims: Vec<String>,
+    pub(crate) sender_constrained: bool,
+    pub(crate) monitored: Vec<Rule>,
+    pub(crate) on_violation: Option<Hook<ViolationHook>>,
+    pub(crate) claims_mappers: Vec<Hook<ClaimsMapper>>,
+    pub(crate) leeway: Duration,
+    pub(crate) coercions: Vec<(String, Coercion)>,
+    #[cfg(feature = "json-schema")]
+    pub(crate) claims_schema: Option<Arc<ClaimsSchema>>,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self {
+            max_token_len: 64 * 1024,
+            max_header_len: 8 * 1024,
+            max_claims_len: 48 * 1024,
+            max_depth: 32,
+            reject_duplicate_header_keys: true,
+            reject_duplicate_claim_keys: false,
+            typ: None,
+            issuers: Vec::new(),
+            audiences: Vec::new(),
+            algorithms: Vec::new(),
+            profile_algorithms: None,
+            acrs: Vec::new(),
+            amrs: Vec::new(),
+            max_auth_age: None,
+            max_lifetime: None,
+            required_claims: Vec::new(),
+            sender_constrained: false,
+            monitored: Vec::new(),
+            on_violation: None,
+            claims_mappers: Vec::new(),
+            leeway: Duration::ZERO,
+            coercions: Vec::new(),
+            #[cfg(feature = "json-schema")]
+            claims_schema: None,
+        }
+    }
+}
+
+impl ValidationOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum length of the compact serialization. Defaults to 64 KiB.
+    pub fn max_token_len(mut self, len: usize) -> Self {
+        self.max_token_len = len;
+        self
+    }
+
+    /// Maximum size of the decoded header JSON. Defaults to 8 KiB.
+    pub fn max_header_len(mut self, len: usize) -> Self {
+        self.max_header_len = len;
+        self
+    }
+
+    /// Maximum size of the decoded claims JSON. Defaults to 48 KiB.
+    pub fn max_claims_len(mut self, len: usize) -> Self {
+        self.max_claims_len = len;
+        self
+    }
+
+    /// Maximum nesting depth of arrays and objects in the header and claims
+    /// JSON. The top level object counts as 1. Defaults to 32.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Reject headers in which an object has duplicate member names.
+    /// Defaults to `true`.
+    ///
+    /// JSON parsers disagree on which duplicate wins, so a header like
+    /// `{"alg":"RS256","alg":"none"}` may be read differently by different
+    /// parties, see RFC 8725 section 3.
+    pub fn reject_duplicate_header_keys(mut self, reject: bool) -> Self {
+        self.reject_duplicate_header_keys = reject;
+        self
+    }
+
+    /// Reject claims in which an object has duplicate member names. Defaults
+    /// to `false`.
+    pub fn reject_duplicate_claim_keys(mut self, reject: bool) -> Self {
+        self.reject_duplicate_claim_keys = reject;
+        self
+    }
+
+    /// Require the `typ` header to be `typ`, e.g. `JWT`, `at+jwt` or
+    /// `dpop+jwt`.
+    ///
+    /// Compared case-insensitively, and with an optional `application/`
+    /// prefix on either side, per RFC 7515 section 4.1.9. This keeps tokens
+    /// issued for one purpose, e.g. ID tokens, from being accepted for
+    /// another, e.g. as access tokens.
+    pub fn require_typ(mut self, typ: impl Into<String>) -> Self {
+        self.typ = Some(typ.into());
+        self
+    }
+
+    /// Require the `iss` claim to be `iss`. Call more than once to accept any
+    /// of several issuers.
+    pub fn require_iss(mut self, iss: impl Into<String>) -> Self {
+        self.issuers.push(iss.into());
+        self
+    }
+
+    /// Require the `aud` claim to contain `aud`. Call more than once to
+    /// accept tokens for any of several audiences.
+    pub fn require_aud(mut self, aud: impl Into<String>) -> Self {
+        self.audiences.push(aud.into());
+        self
+    }
+
+    /// Only accept tokens signed with `alg`. Call more than once to allow
+    /// several algorithms. By default any algorithm the key supports is
+    /// accepted. With a [`profile`](Self::profile) only the algorithms it
+    /// allows can be allowed.
+    pub fn allow_alg(mut self, alg: impl Into<String>) -> Self {
+        self.algorithms.push(alg.into());
+        self
+    }
+
+    /// Require the `acr` claim to be one of `acrs`, e.g.
+    /// `&["urn:mace:incommon:iap:silver"]`, to enforce an authentication
+    /// level. Call more than once to accept more levels.
+    pub fn require_acr(mut self, acrs: &[&str]) -> Self {
+        self.acrs.extend(acrs.iter().map(|acr| acr.to_string()));
+        self
+    }
+
+    /// Require the `amr` claim to contain any of `methods`, e.g.
+    /// `&["mfa", "hwk"]` for step-up authentication. Call more than once to
+    /// accept more methods.
+    pub fn require_amr_any(mut self, methods: &[&str]) -> Self {
+        self.amrs.extend(methods.iter().map(|m| m.to_string()));
+        self
+    }
+
+    /// Require the `auth_time` claim, and reject tokens whose end user
+    /// authenticated more than `max_age` ago, regardless of `exp`. Use this
+    /// when the authentication request had a `max_age`.
+    pub fn max_auth_age(mut self, max_age: Duration) -> Self {
+        self.max_auth_age = Some(max_age);
+        self
+    }
+
+    /// Require the `iat` and `exp` claims, and reject tokens that are valid
+    /// for more than `max_lifetime` from `iat` to `exp`.
+    pub fn max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = Some(max_lifetime);
+        self
+    }
+
+    /// Require the claim `name`, with any value but `null`.
+    pub fn require_claim(mut self, name: impl Into<String>) -> Self {
+        self.required_claims.push(name.into());
+        self
+    }
+
+    /// Require the token to be sender-constrained, i.e. to have a `cnf`
+    /// claim with the JWK thumbprint of a DPoP key (`jkt`, RFC 9449) or the
+    /// thumbprint of a client certificate (`x5t#S256`, RFC 8705). Checking
+    /// the proof of possession is up to the caller.
+    pub fn require_sender_constrained(mut self) -> Self {
+        self.sender_constrained = true;
+        self
+    }
+
+    /// Apply the constraints of `profile` on top of these options. Its
+    /// algorithms limit those allowed with [`allow_alg`](Self::allow_alg),
+    /// before or after this call.
+    ///
+    /// ```
+    /// # use jwtk::{Profile, ValidationOptions};
+    /// let options = ValidationOptions::new()
+    ///     .profile(Profile::Fapi2)
+    ///     .require_iss("https://as.example.com")
+    ///     .require_aud("https://rs.example.com");
+    /// ```
+    pub fn profile(self, profile: Profile) -> Self {
+        match profile {
+            Profile::Fapi2 => self
+                .limit_algs(&["PS256", "ES256"])
+                .max_lifetime(Duration::from_secs(60 * 60))
+                .require_claim("iss")
+                .require_claim("aud")
+                .require_sender_constrained(),
+        }
+    }
+
+    fn limit_algs(mut self, algs: &[&str]) -> Self {
+        let allowed = algs
+            .iter()
+            .map(|a| a.to_string())
+            .filter(|a| match &self.profile_algorithms {
+                Some(limit) => limit.contains(a),
+                None => true,
+            })
+            .collect();
+        self.profile_algorithms = Some(allowed);
+        self
+    }
+
+    /// Don't reject tokens that break `rule`, but report them to the
+    /// [`on_violation`](Self::on_violation) hook, e.g. to roll out a new
+    /// audience requirement and see what it would reject before enforcing
+    /// it. Call more than once to monitor several rules.
+    ///
+    /// ```
+    /// # use jwtk::{Rule, ValidationOptions};
+    /// # use std::sync::Arc;
+    /// let options = ValidationOptions::new()
+    ///     .require_aud("https://api.example.com")
+    ///     .monitor(Rule::Aud)
+    ///     .on_violation(Arc::new(|rule, e| eprintln!("would reject: {:?}: {}", rule, e)));
+    /// ```
+    pub fn monitor(mut self, rule: Rule) -> Self {
+        self.monitored.push(rule);
+        self
+    }
+
+    /// Call `on_violation` with the rule and the error it would have caused
+    /// when a token breaks a [`monitor`](Self::monitor)ed rule. It is called
+    /// during verification, so it should not block.
+    pub fn on_violation(mut self, on_violation: ViolationHook) -> Self {
+        self.on_violation = Some(Hook(on_violation));
+        self
+    }
+
+    /// Move the claim `from`, if present, to `to`, e.g. `cognito:groups` to
+    /// `roles`, after the claims are checked and before they are
+    /// deserialized. With
+    /// [`map_claims`](Self::map_claims) this maps the claims of an identity
+    /// provider to the claims struct of the application.
+    ///
+    /// ```
+    /// # use jwtk::ValidationOptions;
+    /// # use serde::Deserialize;
+    /// #[derive(Deserialize)]
+    /// struct AppClaims {
+    ///     #[serde(default)]
+    ///     roles: Vec<String>,
+    ///     username: Option<String>,
+    /// }
+    ///
+    /// let cognito = ValidationOptions::new()
+    ///     .rename_claim("cognito:groups", "roles")
+    ///     .rename_claim("cognito:username", "username");
+    /// let keycloak = ValidationOptions::new()
+    ///     .rename_claim("preferred_username", "username");
+    /// ```
+    pub fn rename_claim(self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        let (from, to) = (from.into(), to.into());
+        self.map_claims(Arc::new(move |claims| {
+            if let Some(v) = claims.remove(&from) {
+                claims.insert(to.clone(), v);
+            }
+        }))
+    }
+
+    /// Put the permissions of the groups of the token in a claim, with
+    /// `mapper`, see [`roles`](crate::roles).
+    pub fn map_roles(self, mapper: Arc<RoleMapper>) -> Self {
+        self.map_claims(Arc::new(move |claims| mapper.apply(claims)))
+    }
+
+    /// Change the claims with `mapper` before they are deserialized, e.g. to
+    /// move roles out of a nested object. Mappers run in the order they are
+    /// added, only for tokens that passed all other checks, including `exp`
+    /// and [`require_claim`](Self::require_claim), so changing the registered
+    /// claims in a mapper doesn't affect validation.
+    pub fn map_claims(mut self, mapper: ClaimsMapper) -> Self {
+        self.claims_mappers.push(Hook(mapper));
+        self
+    }
+
+    /// Tolerance for clock skew in the `exp`, `nbf` and `auth_time` checks. Defaults to
+    /// zero.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Convert the claim `name` with `coercion` before the claims are
+    /// checked and deserialized, for issuers that e.g. encode `exp` as a
+    /// string. Values that can't be converted are left as they are.
+    ///
+    /// ```
+    /// # use jwtk::{Coercion, ValidationOptions};
+    /// let options = ValidationOptions::new()
+    ///     .coerce("exp", Coercion::Number)
+    ///     .coerce("email_verified", Coercion::Bool);
+    /// ```
+    pub fn coerce(mut self, name: impl Into<String>, coercion: Coercion) -> Self {
+        self.coercions.push((name.into(), coercion));
+        self
+    }
+
+    /// Require the claims to match `schema`. Checked after the signature,
+    /// and before the claims are deserialized.
+    #[cfg(feature = "json-schema")]
+    pub fn claims_schema(mut self, schema: ClaimsSchema) -> Self {
+        self.claims_schema = Some(Arc::new(schema));
+        self
+    }
+
+    /// Whether the claims must be parsed as a `Value` and passed to
+    /// [`prepare_claims`](Self::prepare_claims) before they are deserialized.
+    pub(crate) fn inspects_claims(&self) -> bool {
+        #[cfg(feature = "json-schema")]
+        if self.claims_schema.is_some() {
+            return true;
+        }
+        !self.coercions.is_empty()
+            || !self.claims_mappers.is_empty()
+            || !self.required_claims.is_empty()
+            || self.sender_constrained
+    }
+
+    pub(crate) fn prepare_claims(&self, claims: &mut Value) -> Result<()> {
+        if let Value::Object(claims) = claims {
+            for (name, coercion) in &self.coercions {
+                if let Some(v) = claims.get_mut(name) {
+                    coercion.apply(v);
+                }
+            }
+            for name in &self.required_claims {
+                if let None | Some(Value::Null) = claims.get(name) {
+                    self.enforce(Rule::RequiredClaims, Err(Error::MissingClaim(name.clone())))?;
+                }
+            }
+            if self.sender_constrained {
+                let constrained = claims
+                    .get("cnf")
+                    .and_then(Value::as_object)
+                    .is_some_and(|cnf| {
+                        ["jkt", "x5t#S256"]
+                            .iter()
+                            .any(|m| cnf.get(*m).is_some_and(Value::is_string))
+                    });
+                if !constrained {
+                    self.enforce(
+                        Rule::SenderConstrained,
+                        Err(Error::MissingClaim("cnf".into())),
+                    )?;
+                }
+            }
+        }
+        #[cfg(feature = "json-schema")]
+        if let Some(schema) = &self.claims_schema {
+            schema.validate(claims)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn maps_claims(&self) -> bool {
+        !self.claims_mappers.is_empty()
+    }
+
+    /// Run the claims mappers, after the claims have been checked.
+    pub(crate) fn apply_claims_mappers(&self, claims: &mut Value) {
+        if let Value::Object(claims) = claims {
+            for mapper in &self.claims_mappers {
+                (mapper.0)(claims);
+            }
+        }
+    }
+
+    /// `result` of checking `rule`, or `Ok` after reporting the error if
+    /// `rule` is only monitored.
+    fn enforce(&self, rule: Rule, result: Result<()>) -> Result<()> {
+        match result {
+            Err(e) if self.monitored.contains(&rule) => {
+                if let Some(on_violation) = &self.on_violation {
+                    (on_violation.0)(rule, &e);
+                }
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    pub(crate) fn check_alg(&self, alg: &str) -> Result<()> {
+        let allowed = self.algorithms.is_empty() || self.algorithms.iter().any(|a| a == alg);
+        let in_profile = match &self.profile_algorithms {
+            Some(limit) => limit.iter().any(|a| a == alg),
+            None => true,
+        };
+        if allowed && in_profile {
+            return Ok(());
+        }
+        self.enforce(Rule::Alg, Err(Error::AlgMismatch))
+    }
+
+    pub(crate) fn check_iss(&self, iss: Option<&str>) -> Result<()> {
+        if self.issuers.is_empty() || iss.is_some_and(|iss| self.issuers.iter().any(|x| x == iss)) {
+            return Ok(());
+        }
+        Err(Error::IssMismatch)
+    }
+
+    pub(crate) fn check_aud(&self, aud: &OneOrMany<String>) -> Result<()> {
+        if self.audiences.is_empty() {
+            return Ok(());
+        }
+        let found = match aud {
+            OneOrMany::One(aud) => self.audiences.contains(aud),
+            OneOrMany::Vec(auds) => auds.iter().any(|aud| self.audiences.contains(aud)),
+        };
+        if found {
+            Ok(())
+        } else {
+            Err(Error::AudMismatch)
+        }
+    }
+
+    pub(crate) fn check_acr(&self, acr: Option<&str>) -> Result<()> {
+        if self.acrs.is_empty() || acr.is_some_and(|acr| self.acrs.iter().any(|x| x == acr)) {
+            return Ok(());
+        }
+        Err(Error::AcrMismatch)
+    }
+
+    pub(crate) fn check_amr(&self, amr: &[String]) -> Result<()> {
+        if self.amrs.is_empty() || amr.iter().any(|m| self.amrs.contains(m)) {
+            return Ok(());
+        }
+        Err(Error::AmrMismatch)
+    }
+
+    /// Check the claims that are compared to the options, i.e. not the
+    /// times.
+    pub(crate) fn check_claims<ExtraClaims>(&self, claims: &Claims<ExtraClaims>) -> Result<()> {
+        self.enforce(Rule::Iss, self.check_iss(claims.iss.as_deref()))?;
+        self.enforce(Rule::Aud, self.check_aud(&claims.aud))?;
+        self.enforce(Rule::Acr, self.check_acr(claims.acr.as_deref()))?;
+        self.enforce(Rule::Amr, self.check_amr(&claims.amr))?;
+        self.enforce(Rule::Lifetime, self.check_lifetime(claims.iat, claims.exp))
+    }
+
+    pub(crate) fn check_lifetime(
+        &self,
+        iat: Option<Duration>,
+        exp: Option<Duration>,
+    ) -> Result<()> {
+        let max_lifetime = match self.max_lifetime {
+            Some(max_lifetime) => max_lifetime,
+            None => return Ok(()),
+        };
+        match (iat, exp) {
+            (None, _) => Err(Error::MissingClaim("iat".into())),
+            (_, None) => Err(Error::MissingClaim("exp".into())),
+            (Some(iat), Some(exp)) if exp.saturating_sub(iat) > max_lifetime => {
+                Err(Error::LifetimeTooLong)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `now` is the time since the epoch.
+    pub(crate) fn check_auth_time(&self, auth_time: Option<Duration>, now: Duration) -> Result<()> {
+        let result = match (self.max_auth_age, auth_time) {
+            (None, _) => Ok(()),
+            (Some(_), None) => Err(Error::MissingClaim("auth_time".into())),
+            (Some(max_age), Some(auth_time)) => {
+                let age = now.saturating_sub(auth_time);
+                if age > max_age.saturating_add(self.leeway) {
+                    Err(Error::AuthTooOld)
+                } else {
+                    Ok(())
+                }
+            }
+        };
+        self.enforce(Rule::AuthAge, result)
+    }
+
+    pub(crate) fn check_typ(&self, typ: Option<&str>) -> Result<()> {
+        fn strip(typ: &str) -> &str {
+            match typ.get(..12) {
+                Some(prefix) if prefix.eq_ignore_ascii_case("application/") => &typ[12..],
+                _ => typ,
+            }
+        }
+        match (&self.typ, typ) {
+            (None, _) => Ok(()),
+            (Some(expected), Some(typ)) if strip(expected).eq_ignore_ascii_case(strip(typ)) => {
+                Ok(())
+            }
+            _ => self.enforce(Rule::Typ, Err(Error::TypMismatch)),
+        }
+    }
+
+    pub(crate) fn check_token_len(&self, token: &str) -> Result<()> {
+        if token.len() > self.max_token_len {
+            return Err(Error::TooLarge);
+        }
+        Ok(())
+    }
+}
+
+/// Rules of [`ValidationOptions`] that can be
+/// [`monitor`](ValidationOptions::monitor)ed instead of enforced. `exp`,
+/// `nbf` and the signature are always enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Rule {
+    /// [`ValidationOptions::allow_alg`].
+    Alg,
+    /// [`ValidationOptions::require_typ`].
+    Typ,
+    /// [`ValidationOptions::require_iss`].
+    Iss,
+    /// [`ValidationOptions::require_aud`].
+    Aud,
+    /// [`ValidationOptions::require_acr`].
+    Acr,
+    /// [`ValidationOptions::require_amr_any`].
+    Amr,
+    /// [`ValidationOptions::max_auth_age`].
+    AuthAge,
+    /// [`ValidationOptions::max_lifetime`].
+    Lifetime,
+    /// [`ValidationOptions::require_claim`].
+    RequiredClaims,
+    /// [`ValidationOptions::require_sender_constrained`].
+    SenderConstrained,
+}
+
+type ViolationHook = Arc<dyn Fn(Rule, &Error) + Send + Sync>;
+
+type ClaimsMapper = Arc<dyn Fn(&mut Map<String, Value>) + Send + Sync>;
+
+/// A callback of [`ValidationOptions`], which can't derive `Debug` through
+/// it.
+#[derive(Clone)]
+pub(crate) struct Hook<F>(F);
+
+impl<F> fmt::Debug for Hook<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Hook")
+    }
+}
+
+/// Sets of constraints of security profiles, see
+/// [`ValidationOptions::profile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Profile {
+    /// The FAPI 2.0 Security Profile: only `PS256` and `ES256` signatures,
+    /// `iss`, `aud`, `iat` and `exp` required, at most an hour from `iat` to
+    /// `exp`, and sender-constrained tokens only.
+    Fapi2,
+}
+
+/// How [`ValidationOptions::coerce`] converts a claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Coercion {
+    /// Strings of numbers, e.g. `"1700000000"`, to numbers.
+    Number,
+    /// `"true"` and `"false"` to booleans.
+    Bool,
+    /// Numbers and booleans to strings.
+    String,
+    /// Anything but an array to an array of one element.
+    Array,
+}
+
+impl Coercion {
+    fn apply(self, v: &mut Value) {
+        let coerced = match (self, &*v) {
+            (Coercion::Number, Value::String(s)) => {
+                let s = s.trim();
+                if let Ok(n) = s.parse::<u64>() {
+                    n.into()
+                } else if let Ok(n) = s.parse::<i64>() {
+                    n.into()
+                } else if let Some(n) = s.parse().ok().and_then(Number::from_f64) {
+                    Value::Number(n)
+                } else {
+                    return;
+                }
+            }
+            (Coercion::Bool, Value::String(s)) if s == "true" => true.into(),
+            (Coercion::Bool, Value::String(s)) if s == "false" => false.into(),
+            (Coercion::String, Value::Number(n)) => n.to_string().into(),
+            (Coercion::String, Value::Bool(b)) => b.to_string().into(),
+            (Coercion::Array, Value::Array(_)) => return,
+            (Coercion::Array, _) => Value::Array(vec![v.take()]),
+            _ => return,
+        };
+        *v = coerced;
+    }
+}
+
+/// Check that `segment` decodes to at most `max_len` bytes, without decoding
+/// it.
+pub(crate) fn check_encoded_len(segment: &str, max_len: usize) -> Result<()> {
+    let len = segment.len();
+    // Every 4 base64 characters encode 3 bytes.
+    if len / 4 * 3 + len % 4 * 3 / 4 > max_len {
+        return Err(Error::TooLarge);
+    }
+    Ok(())
+}
+
+/// Check that arrays and objects in `json` are nested at most `max_depth`
+/// levels deep.
+///
+/// `json` doesn't have to be valid, this only tracks brackets outside of
+/// strings.
+pub(crate) fn check_depth(json: &[u8], max_depth: usize) -> Result<()> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for &b in json {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if b == b'\\' {
+                escaped = true;
+            } else if b == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(Error::TooLarge);
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Check that no object in `json` has duplicate member names.
+pub(crate) fn check_duplicate_keys(json: &[u8]) -> Result<()> {
+    let mut de = serde_json::Deserializer::from_slice(json);
+    NoDuplicates.deserialize(&mut de)?;
+    de.end()?;
+    Ok(())
+}
+
+/// Walks a JSON value, failing on the first duplicate member name.
+#[derive(Clone, Copy)]
+struct NoDuplicates;
+
+impl<'de> DeserializeSeed<'de> for NoDuplicates {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NoDuplicates {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        while seq.next_element_seed(self)?.is_some() {}
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<(), A::Error> {
+        let mut keys = HashSet::new();
+        while let Some(key) = map.next_key::<std::borrow::Cow<'de, str>>()? {
+            if !keys.insert(key.clone()) {
+                return Err(serde::de::Error::custom(format_args!(
+                    "duplicate member name `{}`",
+                    key
+                )));
+            }
+            map.next_value_seed(self)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::b64;
+
+    #[test]
+    fn depth() {
+        assert!(check_depth(br#"{"a":[{"b":1}]}"#, 3).is_ok());
+        assert!(check_depth(br#"{"a":[{"b":1}]}"#, 2).is_err());
+        assert!(check_depth(br#"{"a":"[[[[\"[[["}"#, 1).is_ok());
+        assert!(check_depth(br#"{}{}{}[]"#, 1).is_ok());
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        assert!(check_duplicate_keys(br#"{"a":1,"b":{"a":2},"c":[{"a":3}]}"#).is_ok());
+        assert!(check_duplicate_keys(br#"{"a":1,"a":2}"#).is_err());
+        assert!(check_duplicate_keys(br#"{"a":1,"b":[{"c":1,"c":1}]}"#).is_err());
+        assert!(check_duplicate_keys(br#"{"a":1,"\u0061":2}"#).is_err());
+    }
+
+    #[test]
+    fn typ() {
+        let options = ValidationOptions::new().require_typ("at+jwt");
+        assert!(options.check_typ(Some("at+jwt")).is_ok());
+        assert!(options.check_typ(Some("AT+JWT")).is_ok());
+        assert!(options.check_typ(Some("application/at+JWT")).is_ok());
+        assert!(options.check_typ(Some("JWT")).is_err());
+        assert!(options.check_typ(None).is_err());
+        assert!(ValidationOptions::new().check_typ(None).is_ok());
+    }
+
+    #[test]
+    fn iss_and_aud() {
+        let options = ValidationOptions::new()
+            .require_iss("a")
+            .require_iss("b")
+            .require_aud("x");
+        assert!(options.check_iss(Some("b")).is_ok());
+        assert!(options.check_iss(Some("c")).is_err());
+        assert!(options.check_iss(None).is_err());
+        assert!(options.check_aud(&OneOrMany::One("x".into())).is_ok());
+        assert!(options
+            .check_aud(&OneOrMany::Vec(vec!["y".into(), "x".into()]))
+            .is_ok());
+        assert!(options.check_aud(&OneOrMany::One("y".into())).is_err());
+        assert!(options.check_aud(&OneOrMany::default()).is_err());
+
+        let options = ValidationOptions::new();
+        assert!(options.check_iss(None).is_ok());
+        assert!(options.check_aud(&OneOrMany::default()).is_ok());
+    }
+
+    #[test]
+    fn acr_and_amr() {
+        let options = ValidationOptions::new()
+            .require_acr(&["urn:mace:incommon:iap:silver"])
+            .require_amr_any(&["mfa", "hwk"]);
+        assert!(options
+            .check_acr(Some("urn:mace:incommon:iap:silver"))
+            .is_ok());
+        assert!(matches!(
+            options.check_acr(Some("urn:mace:incommon:iap:bronze")),
+            Err(Error::AcrMismatch)
+        ));
+        assert!(options.check_acr(None).is_err());
+        assert!(options.check_amr(&["pwd".into(), "hwk".into()]).is_ok());
+        assert!(matches!(
+            options.check_amr(&["pwd".into()]),
+            Err(Error::AmrMismatch)
+        ));
+        assert!(options.check_amr(&[]).is_err());
+
+        let options = ValidationOptions::new();
+        assert!(options.check_acr(None).is_ok());
+        assert!(options.check_amr(&[]).is_ok());
+    }
+
+    #[test]
+    fn auth_age() {
+        let now = Duration::from_secs(1700000000);
+        let options = ValidationOptions::new().max_auth_age(Duration::from_secs(300));
+        assert!(options
+            .check_auth_time(Some(now - Duration::from_secs(300)), now)
+            .is_ok());
+        assert!(matches!(
+            options.check_auth_time(Some(now - Duration::from_secs(301)), now),
+            Err(Error::AuthTooOld)
+        ));
+        assert!(matches!(
+            options.check_auth_time(None, now),
+            Err(Error::MissingClaim(c)) if c == "auth_time"
+        ));
+        let options = options.leeway(Duration::from_secs(5));
+        assert!(options
+            .check_auth_time(Some(now - Duration::from_secs(301)), now)
+            .is_ok());
+        assert!(ValidationOptions::new().check_auth_time(None, now).is_ok());
+    }
+
+    #[test]
+    fn encoded_len() {
+        for len in 0..20 {
+            let encoded = b64::encode_url(vec![0; len]);
+            assert!(check_encoded_len(&encoded, len).is_ok());
+            if len > 0 {
+                assert!(check_encoded_len(&encoded, len - 1).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn coercion() -> Result<()> {
+        let options = ValidationOptions::new()
+            .coerce("exp", Coercion::Number)
+            .coerce("nbf", Coercion::Number)
+            .coerce("verified", Coercion::Bool)
+            .coerce("sub", Coercion::String)
+            .coerce("roles", Coercion::Array)
+            .coerce("missing", Coercion::Number);
+        assert!(options.inspects_claims());
+        assert!(!ValidationOptions::new().inspects_claims());
+
+        let mut claims = serde_json::json!({
+            "exp": "1700000000",
+            "nbf": "soon",
+            "verified": "true",
+            "sub": 42,
+            "roles": "admin",
+        });
+        options.prepare_claims(&mut claims)?;
+        assert_eq!(
+            claims,
+            serde_json::json!({
+                "exp": 1700000000,
+                "nbf": "soon",
+                "verified": true,
+                "sub": "42",
+                "roles": ["admin"],
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fapi2() -> Result<()> {
+        let options = ValidationOptions::new().profile(Profile::Fapi2);
+        assert!(options.check_alg("PS256").is_ok());
+        assert!(options.check_alg("ES256").is_ok());
+        assert!(options.check_alg("RS256").is_err());
+        assert!(options.check_alg("none").is_err());
+        // Allowing other algorithms, before or after, doesn't widen the set.
+        let widened = ValidationOptions::new()
+            .allow_alg("RS256")
+            .allow_alg("PS256")
+            .profile(Profile::Fapi2)
+            .allow_alg("HS256");
+        assert!(widened.check_alg("PS256").is_ok());
+        assert!(widened.check_alg("ES256").is_err());
+        assert!(widened.check_alg("RS256").is_err());
+        assert!(widened.check_alg("HS256").is_err());
+
+        let mut claims = serde_json::json!({
+            "iss": "https://as.example.com",
+            "aud": "https://rs.example.com",
+            "cnf": { "jkt": "0ZcOCORZNYy-DWpqq30jZyJGHTN0d2HglBV3uiguA4I" },
+        });
+        options.prepare_claims(&mut claims)?;
+        claims["cnf"] =
+            serde_json::json!({ "x5t#S256": "bwcK0esc3ACC3DB2Y5_lESsXE8o9ltc05O89jdN-dg2" });
+        options.prepare_claims(&mut claims)?;
+        claims["cnf"] = serde_json::json!({ "kid": "k1" });
+        assert!(matches!(
+            options.prepare_claims(&mut claims),
+            Err(Error::MissingClaim(c)) if c == "cnf"
+        ));
+        claims.as_object_mut().unwrap().remove("aud");
+        assert!(matches!(
+            options.prepare_claims(&mut claims),
+            Err(Error::MissingClaim(c)) if c == "aud"
+        ));
+
+        let iat = Duration::from_secs(1700000000);
+        let hour = Duration::from_secs(3600);
+        assert!(options.check_lifetime(Some(iat), Some(iat + hour)).is_ok());
+        assert!(matches!(
+            options.check_lifetime(Some(iat), Some(iat + hour + Duration::from_secs(1))),
+            Err(Error::LifetimeTooLong)
+        ));
+        assert!(matches!(
+            options.check_lifetime(None, Some(iat)),
+            Err(Error::MissingClaim(c)) if c == "iat"
+        ));
+        assert!(ValidationOptions::new().check_lifetime(None, None).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn monitor() {
+        let violations = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let v = violations.clone();
+        let options = ValidationOptions::new()
+            .require_aud("new")
+            .require_iss("idp")
+            .require_typ("at+jwt")
+            .monitor(Rule::Aud)
+            .monitor(Rule::Typ)
+            .on_violation(Arc::new(move |rule, e| {
+                v.lock().unwrap().push((rule, e.to_string()))
+            }));
+        let mut claims = Claims::<()> {
+            iss: Some("idp".into()),
+            aud: OneOrMany::One("old".into()),
+            ..Default::default()
+        };
+        assert!(options.check_claims(&claims).is_ok());
+        assert!(options.check_typ(Some("JWT")).is_ok());
+        claims.aud = OneOrMany::One("new".into());
+        assert!(options.check_claims(&claims).is_ok());
+        assert_eq!(
+            violations.lock().unwrap()[..],
+            [
+                (Rule::Aud, Error::AudMismatch.to_string()),
+                (Rule::Typ, Error::TypMismatch.to_string()),
+            ]
+        );
+
+        claims.iss = Some("other".into());
+        assert!(matches!(
+            options.check_claims(&claims),
+            Err(Error::IssMismatch)
+        ));
+        assert_eq!(violations.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn map_claims() -> Result<()> {
+        let options = ValidationOptions::new()
+            .rename_claim("cognito:groups", "roles")
+            .rename_claim("missing", "other")
+            .map_claims(Arc::new(|claims| {
+                if let Some(Value::String(scope)) = claims.remove("scope") {
+                    let scopes: Vec<Value> = scope.split(' ').map(Into::into).collect();
+                    claims.insert("scopes".into(), scopes.into());
+                }
+            }))
+            .require_claim("cognito:groups");
+        assert!(options.inspects_claims());
+        assert!(options.maps_claims());
+
+        let mut claims = serde_json::json!({
+            "sub": "alice",
+            "cognito:groups": ["admins"],
+            "scope": "read write",
+        });
+        options.prepare_claims(&mut claims)?;
+        options.apply_claims_mappers(&mut claims);
+        assert_eq!(
+            claims,
+            serde_json::json!({
+                "sub": "alice",
+                "roles": ["admins"],
+                "scopes": ["read", "write"],
+            })
+        );
+        let mut claims = serde_json::json!({ "sub": "alice" });
+        assert!(matches!(
+            options.prepare_claims(&mut claims),
+            Err(Error::MissingClaim(c)) if c == "cognito:groups"
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+//! Extra claims of common identity providers.
+//!
+//! Use these as the `ExtraClaims` type when verifying tokens, e.g.
+//! `verify::<KeycloakClaims>(token, key)`. Claims not covered by a struct end
+//! up in its `extra` map. Registered claims, e.g. `azp` and `sid`, are fields
+//! of [`Claims`](crate::Claims).
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::skip_serializing_none;
+
+fn has_scope(scope: &Option<String>, s: &str) -> bool {
+    scope
+        .as_deref()
+        .is_some_and(|scope| scope.split(' ').any(|x| x == s))
+}
+
+/// Keycloak roles, as in `realm_access` and `resource_access`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeycloakRoles {
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+/// Keycloak access and ID token claims.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeycloakClaims {
+    pub scope: Option<String>,
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub realm_access: Option<KeycloakRoles>,
+    /// Client roles, by client id.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub resource_access: HashMap<String, KeycloakRoles>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl KeycloakClaims {
+    pub fn has_realm_role(&self, role: &str) -> bool {
+        self.realm_access
+            .as_ref()
+            .is_some_and(|r| r.roles.iter().any(|x| x == role))
+    }
+
+    pub fn has_client_role(&self, client: &str, role: &str) -> bool {
+        self.resource_access
+            .get(client)
+            .is_some_and(|r| r.roles.iter().any(|x| x == role))
+    }
+
+    /// Whether the space separated `scope` claim contains `scope`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        has_scope(&self.scope, scope)
+    }
+}
+
+/// Auth0 access token claims.
+///
+/// Custom claims in Auth0 are usually namespaced, e.g.
+/// `https://example.com/roles`, and are found in `extra`.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Auth0Claims {
+    pub scope: Option<String>,
+    /// Present when RBAC is enabled for the API.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<String>,
+    pub org_id: Option<String>,
+    pub org_name: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Auth0Claims {
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|x| x == permission)
+    }
+
+    /// Whether the space separated `scope` claim contains `scope`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        has_scope(&self.scope, scope)
+    }
+}
+
+/// Azure AD (Microsoft Entra ID) access and ID token claims.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AzureAdClaims {
+    /// Tenant id.
+    pub tid: Option<String>,
+    /// Object id of the user or service principal.
+    pub oid: Option<String>,
+    /// App roles.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    /// Group object ids.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    /// Delegated scopes, space separated.
+    pub scp: Option<String>,
+    pub appid: Option<String>,
+    pub preferred_username: Option<String>,
+    pub upn: Option<String>,
+    pub name: Option<String>,
+    /// Token version, `1.0` or `2.0`.
+    pub ver: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl AzureAdClaims {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|x| x == role)
+    }
+
+    /// Whether the space separated `scp` claim contains `scope`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        has_scope(&self.scp, scope)
+    }
+}
+
+/// Amazon Cognito user pool access and ID token claims.
+#[skip_serializing_none]
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CognitoClaims {
+    /// `access` or `id`.
+    pub token_use: Option<String>,
+    /// Present in access tokens.
+    pub client_id: Option<String>,
+    /// Present in access tokens.
+    pub username: Option<String>,
+    /// Present in ID tokens.
+    #[serde(rename = "cognito:username")]
+    pub cognito_username: Option<String>,
+    #[serde(
+        rename = "cognito:groups",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub cognito_groups: Vec<String>,
+    pub scope: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl CognitoClaims {
+    pub fn in_group(&self, group: &str) -> bool {
+        self.cognito_groups.iter().any(|x| x == group)
+    }
+
+    /// Whether the space separated `scope` claim contains `scope`.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        has_scope(&self.scope, scope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{Claims, Result};
+
+    use super::*;
+
+    #[test]
+    fn vendor_claims() -> Result<()> {
+        let keycloak: Claims<KeycloakClaims> = serde_json::from_value(json!({
+            "sub": "f:1",
+            "azp": "app",
+            "scope": "openid email",
+            "realm_access": { "roles": ["admin"] },
+            "resource_access": { "account": { "roles": ["view-profile"] } },
+            "sid": "s",
+        }))?;
+        assert_eq!(keycloak.sub.as_deref(), Some("f:1"));
+        assert!(keycloak.extra.has_realm_role("admin"));
+        assert!(!keycloak.extra.has_realm_role("user"));
+        assert!(keycloak.extra.has_client_role("account", "view-profile"));
+        assert!(!keycloak.extra.has_client_role("other", "view-profile"));
+        assert!(keycloak.extra.has_scope("email"));
+        assert_eq!(keycloak.azp.as_deref(), Some("app"));
+        assert_eq!(keycloak.sid.as_deref(), Some("s"));
+
+        let azure: Claims<AzureAdClaims> = serde_json::from_value(json!({
+            "tid": "t", "oid": "o", "roles": ["Task.Write"], "scp": "User.Read", "ver": "2.0",
+        }))?;
+        assert_eq!(azure.extra.tid.as_deref(), Some("t"));
+        assert!(azure.extra.has_role("Task.Write"));
+        assert!(azure.extra.has_scope("User.Read"));
+
+        let cognito: Claims<CognitoClaims> = serde_json::from_value(json!({
+            "token_use": "id", "cognito:username": "bob", "cognito:groups": ["admins"],
+        }))?;
+        assert_eq!(cognito.extra.cognito_username.as_deref(), Some("bob"));
+        assert!(cognito.extra.in_group("admins"));
+        assert_eq!(
+            serde_json::to_value(&cognito.extra)?,
+            json!({ "token_use": "id", "cognito:username": "bob", "cognito:groups": ["admins"] })
+        );
+
+        let auth0: Claims<Auth0Claims> = serde_json::from_value(json!({
+            "permissions": ["read:messages"],
+            "https://example.com/roles": ["admin"],
+        }))?;
+        assert!(auth0.extra.has_permission("read:messages"));
+        assert_eq!(
+            auth0.extra.extra["https://example.com/roles"],
+            json!(["admin"])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,484 @@
+//! Verifiers built from configuration.
+//!
+//! [`VerifierConfig`] describes which keys to trust and how to validate
+//! tokens. It deserializes from any serde format, e.g. in YAML:
+//!
+//! ```yaml
+//! issuers: ["https://auth.example.com"]
+//! audiences: ["api"]
+//! jwks_urls: ["https://auth.example.com/.well-known/jwks.json"]
+//! algorithms: ["ES256", "RS256"]
+//! leeway: 30
+//! ```
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::{
+    jwk::{Jwk, JwkSet, JwkSetVerifier},
+    Error, HeaderAndClaims, Result, ValidationOptions,
+};
+
+/// Trust configuration of a [`Verifier`].
+///
+/// Empty `issuers`, `audiences` or `algorithms` don't restrict anything.
+#[serde_as]
+#[non_exhaustive]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerifierConfig {
+    pub issuers: Vec<String>,
+    pub audiences: Vec<String>,
+    /// Urls of JWK Sets. Requires the `remote-jwks` feature.
+    pub jwks_urls: Vec<String>,
+    /// Inline keys. Each must have a `kid`.
+    pub keys: Vec<Jwk>,
+    /// Pinned JWK Set snapshots, read once by [`Verifier::from_config`], see
+    /// [`JwkSet::pinned`].
+    pub jwks_files: Vec<PathBuf>,
+    pub algorithms: Vec<String>,
+    /// Required `typ` header, see [`ValidationOptions::require_typ`].
+    pub typ: Option<String>,
+    /// Clock skew tolerance in seconds.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub leeway: Duration,
+    /// How long to cache fetched JWK Sets, in seconds. Defaults to 5 minutes.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub cache_duration: Duration,
+}
+
+impl Default for VerifierConfig {
+    fn default() -> Self {
+        Self {
+            issuers: Vec::new(),
+            audiences: Vec::new(),
+            jwks_urls: Vec::new(),
+            keys: Vec::new(),
+            jwks_files: Vec::new(),
+            algorithms: Vec::new(),
+            typ: None,
+            leeway: Duration::ZERO,
+            cache_duration: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl VerifierConfig {
+    /// The validation rules of this configuration.
+    pub fn validation_options(&self) -> ValidationOptions {
+        let mut options = ValidationOptions::new().leeway(self.leeway);
+        for iss in &self.issuers {
+            options = options.require_iss(iss.clone());
+        }
+        for aud in &self.audiences {
+            options = options.require_aud(aud.clone());
+        }
+        for alg in &self.algorithms {
+            options = options.allow_alg(alg.clone());
+        }
+        if let Some(typ) = &self.typ {
+            options = options.require_typ(typ.clone());
+        }
+        options
+    }
+}
+
+/// Verifies tokens with inline keys and remote JWK Sets.
+///
+/// Tokens must have a `kid`. Inline keys are tried first, then the JWK Sets
+/// in order, until one of them has the key.
+pub struct Verifier {
+    local: Option<JwkSetVerifier>,
+    #[cfg(feature = "remote-jwks")]
+    remote: Vec<crate::jwk::RemoteJwksVerifier>,
+}
+
+impl Verifier {
+    /// Build a verifier from `config`.
+    ///
+    /// Fails if an inline key is invalid or has no `kid`, if a JWK Set file
+    /// can't be read or isn't a valid pinned snapshot, if there are no keys
+    /// at all, or if `jwks_urls` is set without the `remote-jwks` feature.
+    pub fn from_config(config: &VerifierConfig) -> Result<Self> {
+        let options = config.validation_options();
+
+        for k in &config.keys {
+            k.kid.as_ref().ok_or(Error::NoKid)?;
+            k.to_verification_key()?;
+        }
+        let mut keys = config.keys.clone();
+        for path in &config.jwks_files {
+            keys.extend(JwkSet::pinned(&std::fs::read_to_string(path)?)?.keys);
+        }
+        let local = if keys.is_empty() {
+            None
+        } else {
+            let mut v = JwkSet { keys }.verifier();
+            v.set_validation_options(options.clone());
+            Some(v)
+        };
+
+        #[cfg(feature = "remote-jwks")]
+        let remote: Vec<_> = config
+            .jwks_urls
+            .iter()
+            .map(|url| {
+                let mut v =
+                    crate::jwk::RemoteJwksVerifier::new(url.clone(), None, config.cache_duration);
+                v.set_validation_options(options.clone());
+                v
+            })
+            .collect();
+        #[cfg(not(feature = "remote-jwks"))]
+        if !config.jwks_urls.is_empty() {
+            return Err(Error::UnsupportedOrInvalidKey);
+        }
+
+        let verifier = Self {
+            local,
+            #[cfg(feature = "remote-jwks")]
+            remote,
+        };
+        if !verifier.has_keys() {
+            return Err(Error::NoKey);
+        }
+        Ok(verifier)
+    }
+
+    /// Accept tokens that any of `verifiers` accepts, e.g. while migrating
+    /// to another identity provider or signing algorithm.
+    ///
+    /// ```
+    /// # use jwtk::{jwk::{JwkSet, WithKid}, eddsa::Ed25519PrivateKey, sign, HeaderAndClaims, PublicKeyToJwk, ValidationOptions, Verifier};
+    /// # async fn f() -> jwtk::Result<()> {
+    /// # let k = WithKid::new("k".into(), Ed25519PrivateKey::generate()?);
+    /// # let jwks = JwkSet { keys: vec![k.public_key_to_jwk()?] };
+    /// let mut old = jwks.verifier();
+    /// old.set_validation_options(ValidationOptions::new().require_iss("https://old.example.com"));
+    /// let mut new = jwks.verifier();
+    /// new.set_validation_options(ValidationOptions::new().require_iss("https://new.example.com"));
+    /// let verifier = Verifier::any_of([Verifier::from(old), Verifier::from(new)]);
+    ///
+    /// let token = sign(HeaderAndClaims::new_dynamic().set_iss("https://new.example.com"), &k)?;
+    /// let (matched, _) = verifier.verify::<serde_json::Value>(&token).await?;
+    /// assert_eq!(matched, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn any_of(verifiers: impl IntoIterator<Item = Verifier>) -> AnyOfVerifier {
+        AnyOfVerifier {
+            verifiers: verifiers.into_iter().collect(),
+        }
+    }
+
+    fn has_keys(&self) -> bool {
+        #[cfg(feature = "remote-jwks")]
+        if !self.remote.is_empty() {
+            return true;
+        }
+        self.local.is_some()
+    }
+
+    fn empty() -> Self {
+        Self {
+            local: None,
+            #[cfg(feature = "remote-jwks")]
+            remote: Vec::new(),
+        }
+    }
+
+    /// Decode, verify and validate `token`.
+    pub async fn verify<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<HeaderAndClaims<ExtraClaims>> {
+        // A failed fetch of one JWK Set shouldn't prevent verification with
+        // another that has the key.
+        #[cfg_attr(not(feature = "remote-jwks"), allow(unused_mut))]
+        let mut error = Error::NoKey;
+        if let Some(local) = &self.local {
+            match local.verify(token) {
+                Err(Error::NoKey) => {}
+                result => return result,
+            }
+        }
+        #[cfg(feature = "remote-jwks")]
+        for remote in &self.remote {
+            match remote.verify(token).await {
+                Err(e @ (Error::NoKey | Error::Reqwest(_))) => error = e,
+                result => return result,
+            }
+        }
+        Err(error)
+    }
+}
+
+impl From<JwkSetVerifier> for Verifier {
+    fn from(v: JwkSetVerifier) -> Self {
+        let mut verifier = Self::empty();
+        verifier.local = Some(v);
+        verifier
+    }
+}
+
+#[cfg(feature = "remote-jwks")]
+impl From<crate::jwk::RemoteJwksVerifier> for Verifier {
+    fn from(v: crate::jwk::RemoteJwksVerifier) -> Self {
+        let mut verifier = Self::empty();
+        verifier.remote.push(v);
+        verifier
+    }
+}
+
+/// Verifies tokens with the first of several [`Verifier`]s that accepts
+/// them, see [`Verifier::any_of`].
+pub struct AnyOfVerifier {
+    verifiers: Vec<Verifier>,
+}
+
+impl AnyOfVerifier {
+    /// Decode, verify and validate `token`, and return the index of the
+    /// verifier that accepted it.
+    ///
+    /// If none does, returns the first error other than `Error::NoKey`,
+    /// e.g. `Error::IssMismatch` from the verifier that has the key, or
+    /// `Error::NoKey` if none has it.
+    pub async fn verify<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<(usize, HeaderAndClaims<ExtraClaims>)> {
+        let mut error = None;
+        for (i, v) in self.verifiers.iter().enumerate() {
+            match v.verify(token).await {
+                Ok(token) => return Ok((i, token)),
+                Err(Error::NoKey) => {}
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        Err(error.unwrap_or(Error::NoKey))
+    }
+}
+
+/// A [`Verifier`] that can be cloned into every worker or request handler
+/// and replaced for all of them at once.
+///
+/// Remote JWK Sets are refreshed inside the verifier. Use
+/// [`replace`](Self::replace) for everything else, e.g. a changed
+/// configuration or rotated inline keys. Verifications in progress finish
+/// with the verifier they started with.
+#[derive(Clone)]
+pub struct SharedVerifier {
+    current: Arc<RwLock<Arc<Verifier>>>,
+}
+
+impl SharedVerifier {
+    pub fn new(verifier: impl Into<Verifier>) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(verifier.into()))),
+        }
+    }
+
+    /// Use `verifier` from now on, in all clones of this handle.
+    pub fn replace(&self, verifier: impl Into<Verifier>) {
+        *self.current.write().unwrap() = Arc::new(verifier.into());
+    }
+
+    /// The verifier currently in use.
+    pub fn current(&self) -> Arc<Verifier> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Verify `token` with the current verifier, see [`Verifier::verify`].
+    pub async fn verify<ExtraClaims: DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<HeaderAndClaims<ExtraClaims>> {
+        self.current().verify(token).await
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "ecdsa",
+    feature = "eddsa",
+    not(feature = "verify-only")
+))]
+mod tests {
+    use serde_json::{json, Map, Value};
+
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        eddsa::Ed25519PrivateKey,
+        jwk::WithKid,
+        sign, PublicKeyToJwk,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn from_config() -> Result<()> {
+        let es = WithKid::new(
+            "es".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        let ed = WithKid::new("ed".into(), Ed25519PrivateKey::generate()?);
+        let config: VerifierConfig = serde_json::from_value(json!({
+            "issuers": ["https://auth.test"],
+            "audiences": ["api"],
+            "keys": [es.public_key_to_jwk()?, ed.public_key_to_jwk()?],
+            "algorithms": ["ES256"],
+            "leeway": 60,
+        }))?;
+        assert_eq!(config.cache_duration, Duration::from_secs(300));
+        let verifier = Verifier::from_config(&config)?;
+
+        let mut token = HeaderAndClaims::new_dynamic();
+        token
+            .set_iss("https://auth.test")
+            .add_aud("api")
+            .set_exp_from_now(Duration::from_secs(0))
+            .set_nbf_from_now(Duration::from_secs(0));
+        let jwt = sign(&mut token, &es)?;
+        verifier.verify::<Map<String, Value>>(&jwt).await?;
+
+        // Not an allowed algorithm.
+        let jwt = sign(&mut token, &ed)?;
+        assert!(matches!(
+            verifier.verify::<Map<String, Value>>(&jwt).await,
+            Err(Error::AlgMismatch)
+        ));
+
+        // Unknown key.
+        let other = WithKid::new(
+            "other".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        let jwt = sign(&mut token, &other)?;
+        assert!(matches!(
+            verifier.verify::<Map<String, Value>>(&jwt).await,
+            Err(Error::NoKey)
+        ));
+
+        token.set_iss("https://other.test");
+        let jwt = sign(&mut token, &es)?;
+        assert!(matches!(
+            verifier.verify::<Map<String, Value>>(&jwt).await,
+            Err(Error::IssMismatch)
+        ));
+
+        assert!(serde_json::from_value::<VerifierConfig>(json!({ "issuer": "x" })).is_err());
+        assert!(matches!(
+            Verifier::from_config(&VerifierConfig::default()),
+            Err(Error::NoKey)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn jwks_files() -> Result<()> {
+        let k = WithKid::new("pinned".into(), Ed25519PrivateKey::generate()?);
+        let path = std::env::temp_dir().join(format!("jwtk-pinned-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            serde_json::to_string(&JwkSet {
+                keys: vec![k.public_key_to_jwk()?],
+            })?,
+        )?;
+        let config: VerifierConfig = serde_json::from_value(json!({ "jwks_files": [path] }))?;
+        let verifier = Verifier::from_config(&config);
+        std::fs::remove_file(&path)?;
+        let token = sign(&mut HeaderAndClaims::new_dynamic(), &k)?;
+        verifier?.verify::<Value>(&token).await?;
+
+        assert!(matches!(
+            Verifier::from_config(&config),
+            Err(Error::IoError(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn shared() -> Result<()> {
+        let old = WithKid::new("old".into(), Ed25519PrivateKey::generate()?);
+        let new = WithKid::new("new".into(), Ed25519PrivateKey::generate()?);
+        let jwks = |k: &WithKid<Ed25519PrivateKey>| -> Result<JwkSetVerifier> {
+            Ok(JwkSet {
+                keys: vec![k.public_key_to_jwk()?],
+            }
+            .verifier())
+        };
+        let shared = SharedVerifier::new(jwks(&old)?);
+        let worker = shared.clone();
+        let old_token = sign(&mut HeaderAndClaims::new_dynamic(), &old)?;
+        let new_token = sign(&mut HeaderAndClaims::new_dynamic(), &new)?;
+        worker.verify::<Value>(&old_token).await?;
+
+        shared.replace(jwks(&new)?);
+        worker.verify::<Value>(&new_token).await?;
+        assert!(worker.verify::<Value>(&old_token).await.is_err());
+
+        let handle = tokio::spawn(async move { worker.verify::<Value>(&new_token).await });
+        handle.await.unwrap()?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn any_of() -> Result<()> {
+        let old = WithKid::new("old".into(), Ed25519PrivateKey::generate()?);
+        let new = WithKid::new(
+            "new".into(),
+            EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?,
+        );
+        let verifier = |jwk: Jwk, iss: &str| {
+            let mut v = JwkSet { keys: vec![jwk] }.verifier();
+            v.set_validation_options(ValidationOptions::new().require_iss(iss));
+            Verifier::from(v)
+        };
+        let verifier = Verifier::any_of([
+            verifier(old.public_key_to_jwk()?, "https://old.test"),
+            verifier(new.public_key_to_jwk()?, "https://new.test"),
+        ]);
+
+        let mut token = HeaderAndClaims::new_dynamic();
+        token.set_iss("https://old.test");
+        let (i, _) = verifier.verify::<Value>(&sign(&mut token, &old)?).await?;
+        assert_eq!(i, 0);
+        token.set_iss("https://new.test");
+        let (i, _) = verifier.verify::<Value>(&sign(&mut token, &new)?).await?;
+        assert_eq!(i, 1);
+
+        assert!(matches!(
+            verifier.verify::<Value>(&sign(&mut token, &old)?).await,
+            Err(Error::IssMismatch)
+        ));
+        let other = WithKid::new("other".into(), Ed25519PrivateKey::generate()?);
+        assert!(matches!(
+            verifier.verify::<Value>(&sign(&mut token, &other)?).await,
+            Err(Error::NoKey)
+        ));
+        Ok(())
+    }
+
+    #[cfg(all(feature = "remote-jwks", feature = "test-util"))]
+    #[tokio::test]
+    async fn remote_jwks() -> Result<()> {
+        let mut issuer = crate::test_util::MockIssuer::new()?;
+        issuer.serve()?;
+        let config: VerifierConfig = serde_json::from_value(json!({
+            "issuers": [issuer.issuer()],
+            "jwks_urls": ["http://127.0.0.1:1/jwks", issuer.jwks_url().unwrap()],
+        }))?;
+        let verifier = Verifier::from_config(&config)?;
+        let token = issuer.mint(|_| {})?;
+        verifier.verify::<Map<String, Value>>(&token).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! Webhook signatures.
+//!
+//! The sender signs a token with the time it was sent (`iat`) and the
+//! SHA-256 of the HTTP body ([`BODY_HASH_CLAIM`]), and sends it along with
+//! the body, e.g. in a `Webhook-Signature` header. The receiver checks the
+//! signature, that the body is the one that was signed, and that the token is
+//! recent, so that a captured request can't be replayed much later.
+//!
+//! ```
+//! # use jwtk::{ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey}, webhook::{self, WebhookVerifier}};
+//! let k = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+//! let body = br#"{"event":"invoice.paid"}"#;
+//! let signature = webhook::sign(body, &k)?;
+//!
+//! let verifier = WebhookVerifier::new(k);
+//! verifier.verify(&signature, body)?;
+//! assert!(verifier.verify(&signature, b"{}").is_err());
+//! # Ok::<_, jwtk::Error>(())
+//! ```
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use openssl::hash::{hash, MessageDigest};
+use serde_json::{Map, Value};
+
+#[cfg(not(feature = "verify-only"))]
+use crate::SigningKey;
+use crate::{
+    b64, verify_only_with_options, Error, HeaderAndClaims, Result, ValidationOptions,
+    VerificationKey,
+};
+
+/// `typ` of webhook signatures.
+pub const WEBHOOK_TYP: &str = "webhook+jwt";
+
+/// The claim with the base64url SHA-256 of the body.
+pub const BODY_HASH_CLAIM: &str = "body_sha256";
+
+fn body_hash(body: &[u8]) -> Result<String> {
+    Ok(b64::encode_url(hash(MessageDigest::sha256(), body)?))
+}
+
+/// Sign `body` with `k`.
+#[cfg(not(feature = "verify-only"))]
+pub fn sign(body: &[u8], k: &dyn SigningKey) -> Result<String> {
+    sign_with_claims(body, &mut HeaderAndClaims::new_dynamic(), k)
+}
+
+/// Sign `body` with `k`, with other claims, e.g. `iss` or an event id, in
+/// `token`. `typ`, `iat` and the [`BODY_HASH_CLAIM`] are set here.
+#[cfg(not(feature = "verify-only"))]
+pub fn sign_with_claims(
+    body: &[u8],
+    token: &mut HeaderAndClaims<Map<String, Value>>,
+    k: &dyn SigningKey,
+) -> Result<String> {
+    token.header_mut().typ = Some(WEBHOOK_TYP.into());
+    // Whole seconds, so that `iat` isn't rounded up past the time of
+    // verification.
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    token.claims_mut().iat = Some(Duration::from_secs(now.as_secs()));
+    token.insert(BODY_HASH_CLAIM, body_hash(body)?);
+    crate::sign(token, k)
+}
+
+/// Verifies webhook signatures made with [`sign`].
+pub struct WebhookVerifier<K> {
+    key: K,
+    options: ValidationOptions,
+    max_age: Duration,
+}
+
+impl<K: VerificationKey> WebhookVerifier<K> {
+    pub fn new(key: K) -> Self {
+        Self {
+            key,
+            options: ValidationOptions::new(),
+            max_age: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// How long after signing a webhook is accepted. Defaults to 5 minutes.
+    pub fn set_max_age(&mut self, max_age: Duration) {
+        self.max_age = max_age;
+    }
+
+    /// Options for the token, e.g. to require an `iss` or allow clock skew
+    /// with a leeway. `typ` is always required to be [`WEBHOOK_TYP`].
+    pub fn set_validation_options(&mut self, options: ValidationOptions) {
+        self.options = options;
+    }
+
+    /// Verify `signature` over `body`, and return its header and claims.
+    ///
+    /// Returns `Error::VerificationError` if `body` is not the signed body,
+    /// `Error::Expired` if the signature is older than the max age, and
+    /// `Error::Before` if it was made in the future.
+    pub fn verify(
+        &self,
+        signature: &str,
+        body: &[u8],
+    ) -> Result<HeaderAndClaims<Map<String, Value>>> {
+        let options = self.options.clone().require_typ(WEBHOOK_TYP);
+        let verified =
+            verify_only_with_options::<Map<String, Value>>(signature, &self.key, &options)?;
+        let claims = verified.claims();
+        match claims.extra.get(BODY_HASH_CLAIM) {
+            Some(Value::String(h)) if *h == body_hash(body)? => {}
+            Some(_) => return Err(Error::VerificationError),
+            None => return Err(Error::InvalidToken),
+        }
+
+        let iat = claims.iat.ok_or(Error::InvalidToken)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let leeway = options.leeway;
+        if now.saturating_add(leeway) < iat {
+            return Err(Error::Before);
+        }
+        if now > iat.saturating_add(self.max_age).saturating_add(leeway) {
+            return Err(Error::Expired);
+        }
+        Ok(verified)
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "ecdsa",
+    feature = "hmac",
+    not(feature = "verify-only")
+))]
+mod tests {
+    use crate::{
+        ecdsa::{EcdsaAlgorithm, EcdsaPrivateKey},
+        hmac::{HmacAlgorithm, HmacKey},
+    };
+
+    use super::*;
+
+    #[test]
+    fn webhook() -> Result<()> {
+        let k = HmacKey::generate(HmacAlgorithm::HS256)?;
+        let body = br#"{"event":"invoice.paid"}"#;
+        let mut token = HeaderAndClaims::new_dynamic();
+        token.set_iss("billing").set_jti("evt_1");
+        let signature = sign_with_claims(body, &mut token, &k)?;
+
+        let mut verifier = WebhookVerifier::new(k);
+        verifier.set_validation_options(ValidationOptions::new().require_iss("billing"));
+        let verified = verifier.verify(&signature, body)?;
+        assert_eq!(verified.claims().jti.as_deref(), Some("evt_1"));
+        assert!(matches!(
+            verifier.verify(&signature, b"{}"),
+            Err(Error::VerificationError)
+        ));
+
+        // Too old.
+        let old_iat = verified.claims().iat.unwrap() - Duration::from_secs(301);
+        token.claims_mut().iat = Some(old_iat);
+        token.insert(BODY_HASH_CLAIM, body_hash(body)?);
+        let old = crate::sign(&mut token, &verifier.key)?;
+        assert!(matches!(verifier.verify(&old, body), Err(Error::Expired)));
+        verifier.set_max_age(Duration::from_secs(600));
+        verifier.verify(&old, body)?;
+
+        // Not a webhook signature.
+        let mut plain = HeaderAndClaims::new_dynamic();
+        plain
+            .set_iat_now()
+            .insert(BODY_HASH_CLAIM, body_hash(body)?);
+        let other = crate::sign(&mut plain, &verifier.key)?;
+        assert!(matches!(
+            verifier.verify(&other, body),
+            Err(Error::TypMismatch)
+        ));
+
+        let es = EcdsaPrivateKey::generate(EcdsaAlgorithm::ES256)?;
+        assert!(verifier.verify(&sign(body, &es)?, body).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! X.509 helpers for keys that come with a certificate chain.
+
+use std::{
+    slice,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use foreign_types::ForeignTypeRef;
+use openssl::{
+    asn1::Asn1Object,
+    stack::Stack,
+    x509::{store::X509StoreBuilder, verify::X509VerifyParam, X509Ref, X509StoreContext, X509},
+};
+
+use crate::{Error, Result};
+
+/// The value of the extension `oid` of `cert`, if it has one.
+pub(crate) fn extension_data(cert: &X509Ref, oid: &str) -> Result<Option<Vec<u8>>> {
+    let oid = Asn1Object::from_str(oid)?;
+    unsafe {
+        let i = openssl_sys::X509_get_ext_by_OBJ(cert.as_ptr(), oid.as_ptr(), -1);
+        if i < 0 {
+            return Ok(None);
+        }
+        let data = openssl_sys::X509_EXTENSION_get_data(openssl_sys::X509_get_ext(cert.as_ptr(), i))
+            as *const openssl_sys::ASN1_STRING;
+        let len = openssl_sys::ASN1_STRING_length(data) as usize;
+        let ptr = openssl_sys::ASN1_STRING_get0_data(data);
+        Ok(Some(slice::from_raw_parts(ptr, len).to_vec()))
+    }
+}
+
+/// Verify that `leaf` chains up to one of `roots`, through `untrusted`
+/// intermediates, with all certificates valid `at`.
+pub(crate) fn verify_chain(
+    roots: &[X509],
+    leaf: &X509Ref,
+    untrusted: &[X509],
+    at: SystemTime,
+) -> Result<()> {
+    let mut store = X509StoreBuilder::new()?;
+    for root in roots {
+        store.add_cert(root.clone())?;
+    }
+    let mut param = X509VerifyParam::new()?;
+    let secs = at
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| Error::InvalidToken)?
+        .as_secs();
+    param.set_time(secs as _);
+    store.set_param(&param)?;
+    let store = store.build();
+    let mut chain = Stack::new()?;
+    for c in untrusted {
+        chain.push(c.clone())?;
+    }
+    let mut ctx = X509StoreContext::new()?;
+    if ctx.init(&store, leaf, &chain, |c| c.verify_cert())? {
+        Ok(())
+    } else {
+        Err(Error::VerificationError)
+    }
+}
+
+/// Certificates for tests.
+#[cfg(all(test, feature = "ecdsa", not(feature = "verify-only")))]
+pub(crate) mod testing {
+    use openssl::{
+        asn1::{Asn1Integer, Asn1OctetString, Asn1Time},
+        bn::BigNum,
+        hash::MessageDigest,
+        pkey::{PKey, Private},
+        x509::{extension::BasicConstraints, X509Extension, X509NameBuilder},
+    };
+
+    use super::*;
+    use crate::ecdsa::EcdsaPrivateKey;
+
+    pub(crate) fn pkey(k: &EcdsaPrivateKey) -> Result<PKey<Private>> {
+        Ok(PKey::private_key_from_pem(
+            k.private_key_to_pem_pkcs8()?.as_bytes(),
+        )?)
+    }
+
+    /// A certificate valid from now for a day, self-signed without `issuer`.
+    pub(crate) fn cert(
+        name: &str,
+        key: &PKey<Private>,
+        issuer: Option<(&X509, &PKey<Private>)>,
+        ca: bool,
+        extensions: Vec<X509Extension>,
+    ) -> Result<X509> {
+        let mut subject = X509NameBuilder::new()?;
+        subject.append_entry_by_text("CN", name)?;
+        let subject = subject.build();
+        let mut b = X509::builder()?;
+        b.set_version(2)?;
+        let serial = Asn1Integer::from_bn(BigNum::from_u32(1)?.as_ref())?;
+        b.set_serial_number(&serial)?;
+        b.set_subject_name(&subject)?;
+        b.set_issuer_name(issuer.map_or(&subject, |(c, _)| c.subject_name()))?;
+        b.set_pubkey(key)?;
+        let (not_before, not_after) = (Asn1Time::days_from_now(0)?, Asn1Time::days_from_now(1)?);
+        b.set_not_before(&not_before)?;
+        b.set_not_after(&not_after)?;
+        if ca {
+            b.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+        }
+        for e in extensions {
+            b.append_extension(e)?;
+        }
+        b.sign(issuer.map_or(key, |(_, k)| k), MessageDigest::sha256())?;
+        Ok(b.build())
+    }
+
+    /// A non-critical extension `oid` with the DER encoded `value`.
+    pub(crate) fn extension(oid: &str, value: &[u8]) -> Result<X509Extension> {
+        let value = Asn1OctetString::new_from_bytes(value)?;
+        let oid = Asn1Object::from_str(oid)?;
+        Ok(X509Extension::new_from_der(&oid, false, &value)?)
+    }
+}
@@ -0,0 +1,134 @@
+//! Interoperability with other JOSE implementations, see
+//! `tests/interop/README.md`.
+//!
+//! Ignored by default. With `JWTK_INTEROP_DIR` set:
+//!
+//! ```sh
+//! cargo test --test interop -- --ignored interop_sign
+//! cargo test --test interop -- --ignored interop_verify
+//! ```
+
+#![cfg(all(
+    feature = "rsa",
+    feature = "ecdsa",
+    feature = "eddsa",
+    feature = "hmac",
+    not(feature = "verify-only")
+))]
+
+use std::{env, fs, path::PathBuf};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jwtk::{
+    hmac::{HmacAlgorithm, HmacKey},
+    rsa::RsaAlgorithm,
+    sign, verify, HeaderAndClaims, SigningKey, SomePrivateKey, SomePublicKey, VerificationKey,
+};
+use serde_json::Value;
+
+/// Algorithms, and the files in `tests/interop/keys` of their keys.
+const ALGORITHMS: &[(&str, &str)] = &[
+    ("HS256", "hmac"),
+    ("HS384", "hmac"),
+    ("HS512", "hmac"),
+    ("RS256", "rsa"),
+    ("RS384", "rsa"),
+    ("RS512", "rsa"),
+    ("PS256", "rsa"),
+    ("PS384", "rsa"),
+    ("PS512", "rsa"),
+    ("ES256", "p256"),
+    ("ES384", "p384"),
+    ("ES512", "p521"),
+    ("ES256K", "secp256k1"),
+    ("EdDSA", "ed25519"),
+];
+
+/// 2100-01-01T00:00:00Z.
+const EXP: u64 = 4_102_444_800;
+
+fn dir() -> PathBuf {
+    env::var_os("JWTK_INTEROP_DIR")
+        .expect("JWTK_INTEROP_DIR is not set")
+        .into()
+}
+
+fn key_file(name: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/interop/keys")
+        .join(name);
+    fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+fn hmac_key(alg: &str) -> HmacKey {
+    let k = String::from_utf8(key_file("hmac.key")).unwrap();
+    let k = URL_SAFE_NO_PAD.decode(k.trim()).unwrap();
+    let alg = match alg {
+        "HS256" => HmacAlgorithm::HS256,
+        "HS384" => HmacAlgorithm::HS384,
+        _ => HmacAlgorithm::HS512,
+    };
+    HmacKey::from_bytes(&k, alg)
+}
+
+fn signing_key(alg: &str, file: &str) -> Box<dyn SigningKey> {
+    if file == "hmac" {
+        return Box::new(hmac_key(alg));
+    }
+    let rsa_alg = RsaAlgorithm::from_name(alg).unwrap_or(RsaAlgorithm::RS256);
+    let pem = key_file(&format!("{}.pem", file));
+    Box::new(SomePrivateKey::from_pem(&pem, rsa_alg).unwrap())
+}
+
+fn verification_key(alg: &str, file: &str) -> Box<dyn VerificationKey> {
+    if file == "hmac" {
+        return Box::new(hmac_key(alg));
+    }
+    let pem = key_file(&format!("{}.pub.pem", file));
+    Box::new(SomePublicKey::from_pem(&pem).unwrap())
+}
+
+/// Sign a token with every algorithm, into `$JWTK_INTEROP_DIR/jwtk/<alg>.jwt`.
+#[test]
+#[ignore]
+fn interop_sign() {
+    let out = dir().join("jwtk");
+    fs::create_dir_all(&out).unwrap();
+    for (alg, file) in ALGORITHMS {
+        let mut token = HeaderAndClaims::new_dynamic();
+        token.set_iss("interop").set_sub("jwtk").insert("exp", EXP);
+        let token = sign(&mut token, &*signing_key(alg, file)).unwrap();
+        fs::write(out.join(format!("{}.jwt", alg)), token).unwrap();
+    }
+}
+
+/// Verify the tokens of the other implementations, i.e. every
+/// `$JWTK_INTEROP_DIR/<implementation>/<alg>.jwt`.
+#[test]
+#[ignore]
+fn interop_verify() {
+    let mut verified = 0;
+    let mut failures = Vec::new();
+    for entry in fs::read_dir(dir()).unwrap() {
+        let entry = entry.unwrap();
+        let implementation = entry.file_name().to_string_lossy().into_owned();
+        if implementation == "jwtk" || !entry.path().is_dir() {
+            continue;
+        }
+        for (alg, file) in ALGORITHMS {
+            let path = entry.path().join(format!("{}.jwt", alg));
+            let token = match fs::read_to_string(&path) {
+                Ok(token) => token,
+                // Not all implementations support all algorithms.
+                Err(_) => continue,
+            };
+            match verify::<Value>(token.trim(), &*verification_key(alg, file)) {
+                Ok(t) if t.claims().iss.as_deref() == Some("interop") => verified += 1,
+                Ok(_) => failures.push(format!("{} {}: wrong iss", implementation, alg)),
+                Err(e) => failures.push(format!("{} {}: {}", implementation, alg, e)),
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{:#?}", failures);
+    assert!(verified > 0, "no tokens of other implementations");
+}
@@ -0,0 +1,5 @@
+module github.com/sopium/jwtk/tests/interop/go
+
+go 1.21
+
+require github.com/go-jose/go-jose/v4 v4.0.1
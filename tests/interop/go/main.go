@@ -0,0 +1,157 @@
+// Interop tests with go-jose, see ../README.md.
+//
+//	go run . sign
+//	go run . verify
+package main
+
+import (
+	"crypto/x509"
+	"encoding/base64"
+	"encoding/pem"
+	"fmt"
+	"os"
+	"path/filepath"
+	"strings"
+	"time"
+
+	"github.com/go-jose/go-jose/v4"
+	"github.com/go-jose/go-jose/v4/jwt"
+)
+
+// ES256K is not supported.
+var algorithms = []struct {
+	alg  jose.SignatureAlgorithm
+	file string
+}{
+	{jose.HS256, "hmac"},
+	{jose.HS384, "hmac"},
+	{jose.HS512, "hmac"},
+	{jose.RS256, "rsa"},
+	{jose.RS384, "rsa"},
+	{jose.RS512, "rsa"},
+	{jose.PS256, "rsa"},
+	{jose.PS384, "rsa"},
+	{jose.PS512, "rsa"},
+	{jose.ES256, "p256"},
+	{jose.ES384, "p384"},
+	{jose.ES512, "p521"},
+	{jose.EdDSA, "ed25519"},
+}
+
+func keyFile(name string) []byte {
+	b, err := os.ReadFile(filepath.Join("..", "keys", name))
+	if err != nil {
+		panic(err)
+	}
+	return b
+}
+
+func pemBlock(name string) []byte {
+	block, _ := pem.Decode(keyFile(name))
+	if block == nil {
+		panic("no PEM in " + name)
+	}
+	return block.Bytes
+}
+
+func hmacKey() []byte {
+	k, err := base64.RawURLEncoding.DecodeString(strings.TrimSpace(string(keyFile("hmac.key"))))
+	if err != nil {
+		panic(err)
+	}
+	return k
+}
+
+func signingKey(file string) interface{} {
+	if file == "hmac" {
+		return hmacKey()
+	}
+	k, err := x509.ParsePKCS8PrivateKey(pemBlock(file + ".pem"))
+	if err != nil {
+		panic(err)
+	}
+	return k
+}
+
+func verificationKey(file string) interface{} {
+	if file == "hmac" {
+		return hmacKey()
+	}
+	k, err := x509.ParsePKIXPublicKey(pemBlock(file + ".pub.pem"))
+	if err != nil {
+		panic(err)
+	}
+	return k
+}
+
+func sign(dir string) error {
+	out := filepath.Join(dir, "go-jose")
+	if err := os.MkdirAll(out, 0o755); err != nil {
+		return err
+	}
+	claims := map[string]interface{}{"iss": "interop", "sub": "go-jose", "exp": 4102444800}
+	for _, a := range algorithms {
+		signer, err := jose.NewSigner(jose.SigningKey{Algorithm: a.alg, Key: signingKey(a.file)}, nil)
+		if err != nil {
+			return err
+		}
+		token, err := jwt.Signed(signer).Claims(claims).Serialize()
+		if err != nil {
+			return err
+		}
+		if err := os.WriteFile(filepath.Join(out, string(a.alg)+".jwt"), []byte(token), 0o644); err != nil {
+			return err
+		}
+	}
+	return nil
+}
+
+func verify(dir string) error {
+	failed := false
+	for _, a := range algorithms {
+		b, err := os.ReadFile(filepath.Join(dir, "jwtk", string(a.alg)+".jwt"))
+		if err != nil {
+			return err
+		}
+		err = func() error {
+			token, err := jwt.ParseSigned(strings.TrimSpace(string(b)), []jose.SignatureAlgorithm{a.alg})
+			if err != nil {
+				return err
+			}
+			var claims jwt.Claims
+			if err := token.Claims(verificationKey(a.file), &claims); err != nil {
+				return err
+			}
+			return claims.Validate(jwt.Expected{Issuer: "interop", Time: time.Now()})
+		}()
+		if err != nil {
+			fmt.Fprintf(os.Stderr, "%s: %v\n", a.alg, err)
+			failed = true
+		}
+	}
+	if failed {
+		return fmt.Errorf("verification failed")
+	}
+	return nil
+}
+
+func main() {
+	dir := os.Getenv("JWTK_INTEROP_DIR")
+	if dir == "" || len(os.Args) != 2 {
+		fmt.Fprintln(os.Stderr, "usage: JWTK_INTEROP_DIR=<dir> go run . (sign | verify)")
+		os.Exit(2)
+	}
+	var err error
+	switch os.Args[1] {
+	case "sign":
+		err = sign(dir)
+	case "verify":
+		err = verify(dir)
+	default:
+		err = fmt.Errorf("unknown command %s", os.Args[1])
+	}
+	if err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+}
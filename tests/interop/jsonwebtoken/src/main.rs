@@ -0,0 +1,108 @@
+//! Interop tests with the jsonwebtoken crate, see ../README.md.
+//!
+//! ```sh
+//! cargo run -- sign
+//! cargo run -- verify
+//! ```
+
+use std::{env, fs, path::PathBuf, process::exit};
+
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde_json::{json, Value};
+
+// ES512 and ES256K are not supported.
+const ALGORITHMS: &[(Algorithm, &str)] = &[
+    (Algorithm::HS256, "hmac"),
+    (Algorithm::HS384, "hmac"),
+    (Algorithm::HS512, "hmac"),
+    (Algorithm::RS256, "rsa"),
+    (Algorithm::RS384, "rsa"),
+    (Algorithm::RS512, "rsa"),
+    (Algorithm::PS256, "rsa"),
+    (Algorithm::PS384, "rsa"),
+    (Algorithm::PS512, "rsa"),
+    (Algorithm::ES256, "p256"),
+    (Algorithm::ES384, "p384"),
+    (Algorithm::EdDSA, "ed25519"),
+];
+
+fn key_file(name: &str) -> Vec<u8> {
+    fs::read(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../keys")
+            .join(name),
+    )
+    .unwrap()
+}
+
+/// The HMAC key in standard base64, which is what jsonwebtoken takes.
+fn hmac_secret() -> String {
+    let k = String::from_utf8(key_file("hmac.key")).unwrap();
+    let mut k = k.trim().replace('-', "+").replace('_', "/");
+    while k.len() % 4 != 0 {
+        k.push('=');
+    }
+    k
+}
+
+fn encoding_key(file: &str) -> EncodingKey {
+    if file == "hmac" {
+        return EncodingKey::from_base64_secret(&hmac_secret()).unwrap();
+    }
+    let pem = key_file(&format!("{}.pem", file));
+    match file {
+        "rsa" => EncodingKey::from_rsa_pem(&pem),
+        "ed25519" => EncodingKey::from_ed_pem(&pem),
+        _ => EncodingKey::from_ec_pem(&pem),
+    }
+    .unwrap()
+}
+
+fn decoding_key(file: &str) -> DecodingKey {
+    if file == "hmac" {
+        return DecodingKey::from_base64_secret(&hmac_secret()).unwrap();
+    }
+    let pem = key_file(&format!("{}.pub.pem", file));
+    match file {
+        "rsa" => DecodingKey::from_rsa_pem(&pem),
+        "ed25519" => DecodingKey::from_ed_pem(&pem),
+        _ => DecodingKey::from_ec_pem(&pem),
+    }
+    .unwrap()
+}
+
+fn main() {
+    let dir = PathBuf::from(env::var_os("JWTK_INTEROP_DIR").expect("JWTK_INTEROP_DIR is not set"));
+    match env::args().nth(1).as_deref() {
+        Some("sign") => {
+            let out = dir.join("jsonwebtoken");
+            fs::create_dir_all(&out).unwrap();
+            let claims =
+                json!({ "iss": "interop", "sub": "jsonwebtoken", "exp": 4_102_444_800u64 });
+            for (alg, file) in ALGORITHMS {
+                let token = encode(&Header::new(*alg), &claims, &encoding_key(file)).unwrap();
+                fs::write(out.join(format!("{:?}.jwt", alg)), token).unwrap();
+            }
+        }
+        Some("verify") => {
+            let mut failed = false;
+            for (alg, file) in ALGORITHMS {
+                let path = dir.join("jwtk").join(format!("{:?}.jwt", alg));
+                let token = fs::read_to_string(&path).unwrap();
+                let mut validation = Validation::new(*alg);
+                validation.set_issuer(&["interop"]);
+                if let Err(e) = decode::<Value>(token.trim(), &decoding_key(file), &validation) {
+                    eprintln!("{:?}: {}", alg, e);
+                    failed = true;
+                }
+            }
+            if failed {
+                exit(1);
+            }
+        }
+        _ => {
+            eprintln!("usage: jwtk-interop-jsonwebtoken (sign | verify)");
+            exit(2);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// Interop tests with jose for Node.js, see ../README.md.
+//
+//     npm install
+//     node interop.js sign
+//     node interop.js verify
+
+import { mkdirSync, readFileSync, writeFileSync } from "node:fs";
+import { join } from "node:path";
+import { base64url, importPKCS8, importSPKI, jwtVerify, SignJWT } from "jose";
+
+const ALGORITHMS = {
+  HS256: "hmac",
+  HS384: "hmac",
+  HS512: "hmac",
+  RS256: "rsa",
+  RS384: "rsa",
+  RS512: "rsa",
+  PS256: "rsa",
+  PS384: "rsa",
+  PS512: "rsa",
+  ES256: "p256",
+  ES384: "p384",
+  ES512: "p521",
+  ES256K: "secp256k1",
+  EdDSA: "ed25519",
+};
+
+const dir = process.env.JWTK_INTEROP_DIR;
+if (!dir) {
+  throw new Error("JWTK_INTEROP_DIR is not set");
+}
+
+function keyFile(name) {
+  return readFileSync(new URL(`../keys/${name}`, import.meta.url), "utf8");
+}
+
+async function key(alg, file, isPrivate) {
+  if (file === "hmac") {
+    return base64url.decode(keyFile("hmac.key").trim());
+  }
+  return isPrivate
+    ? importPKCS8(keyFile(`${file}.pem`), alg)
+    : importSPKI(keyFile(`${file}.pub.pem`), alg);
+}
+
+async function sign() {
+  const out = join(dir, "node-jose");
+  mkdirSync(out, { recursive: true });
+  for (const [alg, file] of Object.entries(ALGORITHMS)) {
+    const token = await new SignJWT({ iss: "interop", sub: "node-jose", exp: 4102444800 })
+      .setProtectedHeader({ alg })
+      .sign(await key(alg, file, true));
+    writeFileSync(join(out, `${alg}.jwt`), token);
+  }
+}
+
+async function verify() {
+  let failed = false;
+  for (const [alg, file] of Object.entries(ALGORITHMS)) {
+    const token = readFileSync(join(dir, "jwtk", `${alg}.jwt`), "utf8").trim();
+    try {
+      await jwtVerify(token, await key(alg, file, false), {
+        algorithms: [alg],
+        issuer: "interop",
+      });
+    } catch (e) {
+      console.error(`${alg}: ${e.message}`);
+      failed = true;
+    }
+  }
+  if (failed) {
+    process.exit(1);
+  }
+}
+
+const command = { sign, verify }[process.argv[2]];
+if (!command) {
+  console.error("usage: node interop.js (sign | verify)");
+  process.exit(2);
+}
+await command();